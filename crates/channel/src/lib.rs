@@ -307,6 +307,28 @@ impl Channel<'_> {
         self.read_bytes(&mut buf)?;
         Ok(T::from_bytes(&buf)?)
     }
+    /// Return the number of bytes received from the peer and buffered, which can be read
+    /// without blocking.
+    ///
+    /// More bytes may have been sent by the peer and be waiting in the inner connection.
+    ///
+    /// # Example
+    /// ```
+    /// use swanky_channel::Channel;
+    /// let (n, _) = swanky_channel::local::local_channel_pair(
+    ///     |c| {
+    ///         c.read_bytes(&mut [0; 1])?;
+    ///         Ok(c.buffered_len())
+    ///     },
+    ///     |c| c.write_bytes(b"hello"),
+    /// )
+    /// .unwrap();
+    /// assert_eq!(n, 4);
+    /// ```
+    #[inline]
+    pub fn buffered_len(&self) -> usize {
+        self.read_buffer_len
+    }
     /// Serialize `t` and [`Self::write_bytes()`] it over the wire.
     ///
     /// # Example
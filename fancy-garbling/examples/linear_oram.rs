@@ -1,15 +1,14 @@
 //! An example that secretly retrieves an element from an ORAM in a binary garbled circuit
 //! using fancy-garbling.
 use fancy_garbling::{
-    twopac::semihonest::{Evaluator, Garbler},
-    util, AllWire, BinaryBundle, BinaryGadgets, Fancy, FancyArithmetic, FancyBinary, FancyInput,
-    FancyReveal,
+    errors::TwopacError,
+    twopac::semihonest::{Party, Role},
+    util, AllWire, BinaryBundle, BinaryGadgets, Fancy, FancyArithmetic, FancyBinary, FancyReveal,
 };
 
 use ocelot::{ot::AlszReceiver as OtReceiver, ot::AlszSender as OtSender};
 use scuttlebutt::{AbstractChannel, AesRng, Channel};
 
-use std::fmt::Debug;
 use std::{
     io::{BufReader, BufWriter},
     os::unix::net::UnixStream,
@@ -21,90 +20,78 @@ struct ORAMInputs<F> {
     ram: Vec<BinaryBundle<F>>,
     query: BinaryBundle<F>,
 }
-/// The garbler's main method:
-/// (1) The garbler is first created using the passed rng and value.
-/// (2) The garbler then exchanges their wires obliviously with the evaluator.
-/// (3) The garbler and the evaluator then run the garbled circuit.
-/// (4) The garbler and the evaluator open the result of the computation.
-fn gb_linear_oram<C>(rng: &mut AesRng, channel: &mut C, inputs: &[u128])
-where
-    C: AbstractChannel + std::clone::Clone,
-{
-    // (1)
-    let mut gb =
-        Garbler::<C, AesRng, OtSender, AllWire>::new(channel.clone(), rng.clone()).unwrap();
-    // The size of the RAM is assumed to be public. The garbler sends their number of
-    // of input wires. We note that every element of the RAM has a fixed size of 128 bits.
-    let _ = channel.write_usize(inputs.len());
-    // (2)
-    let circuit_wires = gb_set_fancy_inputs(&mut gb, inputs);
-    // (3)
-    let query =
-        fancy_linear_oram::<Garbler<C, AesRng, OtSender, AllWire>>(&mut gb, circuit_wires).unwrap();
-    // (4)
-    gb.outputs(query.wires()).unwrap();
-}
-
-/// The garbler's wire exchange method
-fn gb_set_fancy_inputs<F, E>(gb: &mut F, inputs: &[u128]) -> ORAMInputs<F::Item>
-where
-    F: FancyInput<Item = AllWire, Error = E>,
-    E: Debug,
-{
-    // The number of bits needed to represent a single input value
-    let nbits = 128;
-    // The garbler encodes their wires with the appropriate moduli per wire.
-    let ram: Vec<BinaryBundle<F::Item>> = gb.bin_encode_many(inputs, nbits).unwrap();
-    // The evaluator receives their input labels using Oblivious Transfer (OT)
-    let query: BinaryBundle<F::Item> = gb.bin_receive(nbits).unwrap();
-
-    ORAMInputs { ram, query }
-}
-
-/// The evaluator's main method:
-/// (1) The evaluator is first created using the passed rng and value.
-/// (2) The evaluator then exchanges their wires obliviously with the garbler.
-/// (3) The evaluator and the garbler then run the garbled circuit.
-/// (4) The evaluator and the garbler open the result of the computation.
-/// (5) The evaluator translates the binary output of the circuit into its decimal
-///     representation.
 
-fn ev_linear_oram<C>(rng: &mut AesRng, channel: &mut C, input: u128) -> u128
+/// A party in linear ORAM, either the garbler or the evaluator.
+type OramParty<C> = Party<C, AesRng, OtSender, OtReceiver, AllWire>;
+
+/// The parties' main method, shared by both roles:
+/// (1) The party is first created with its role using the passed rng.
+/// (2) The size of the RAM is assumed to be public. The parties exchange the sizes
+///     of their inputs, and the evaluator learns the size of the garbler's RAM. We
+///     note that every element of the RAM has a fixed size of 128 bits.
+/// (3) The party then exchanges their wires obliviously with the other party: the
+///     garbler owns the RAM and the evaluator owns the query.
+/// (4) The garbler and the evaluator then run the garbled circuit.
+/// (5) The garbler and the evaluator open the result of the computation. Only the
+///     evaluator learns the result, which is translated from binary to decimal. If the
+///     garbler sends malformed decoding information, the evaluator fails with
+///     `TwopacError::OutputDecodingFailed` instead of panicking.
+fn run_oram<C>(
+    role: Role,
+    rng: &mut AesRng,
+    channel: &mut C,
+    ram: &[u128],
+    query: u128,
+) -> Result<Option<u128>, TwopacError>
 where
     C: AbstractChannel + std::clone::Clone,
 {
     // (1)
-    let mut ev =
-        Evaluator::<C, AesRng, OtReceiver, AllWire>::new(channel.clone(), rng.clone()).unwrap();
-    let ram_size = channel.read_usize().unwrap();
+    let mut party = OramParty::<C>::new(role, channel.clone(), rng.clone())?;
     // (2)
-    let circuit_wires = ev_set_fancy_inputs(&mut ev, input, ram_size);
+    let their_ram_size = party.exchange_input_sizes(ram.len())?;
+    let ram_size = match role {
+        Role::Garbler => ram.len(),
+        Role::Evaluator => their_ram_size,
+    };
     // (3)
-    let query =
-        fancy_linear_oram::<Evaluator<C, AesRng, OtReceiver, AllWire>>(&mut ev, circuit_wires)
-            .unwrap();
+    let circuit_wires = set_fancy_inputs(&mut party, ram, ram_size, query)?;
     // (4)
-    let query_binary = ev
-        .outputs(query.wires())
-        .unwrap()
-        .expect("evaluator should produce outputs");
-
+    let query = fancy_linear_oram::<OramParty<C>>(&mut party, circuit_wires)?;
     // (5)
-    util::u128_from_bits(&query_binary)
+    match &mut party {
+        Party::Garbler(gb) => {
+            gb.outputs(query.wires())?;
+            Ok(None)
+        }
+        Party::Evaluator(ev) => {
+            let query_binary = ev.decode_outputs(query.wires())?;
+            Ok(Some(util::u128_from_bits(&query_binary)))
+        }
+    }
 }
-fn ev_set_fancy_inputs<F, E>(ev: &mut F, input: u128, ram_size: usize) -> ORAMInputs<F::Item>
+
+/// The parties' wire exchange method
+fn set_fancy_inputs<C>(
+    party: &mut OramParty<C>,
+    ram: &[u128],
+    ram_size: usize,
+    query: u128,
+) -> Result<ORAMInputs<AllWire>, TwopacError>
 where
-    F: FancyInput<Item = AllWire, Error = E>,
-    E: Debug,
+    C: AbstractChannel,
 {
     // The number of bits needed to represent a single input value
     let nbits = 128;
-    // The evaluator receives the garblers input labels.
-    let ram: Vec<BinaryBundle<F::Item>> = ev.bin_receive_many(ram_size, nbits).unwrap();
-    // The evaluator encodes their input labels.
-    let query: BinaryBundle<F::Item> = ev.bin_encode(input, nbits).unwrap();
-
-    ORAMInputs { ram, query }
+    // The garbler encodes their RAM while the evaluator receives the garbler's input labels.
+    let ram = party.bin_input_many(Role::Garbler, ram, ram_size, nbits)?;
+    // The evaluator encodes their query while the garbler provides the evaluator's input
+    // labels using Oblivious Transfer (OT).
+    let query = party
+        .bin_input_many(Role::Evaluator, &[query], 1, nbits)?
+        .remove(0);
+
+    Ok(ORAMInputs { ram, query })
 }
 
 /// The main fancy function which describes the garbled circuit for linear ORAM.
@@ -170,14 +157,29 @@ fn main() {
             let reader = BufReader::new(sender.try_clone().unwrap());
             let writer = BufWriter::new(sender);
             let mut channel = Channel::new(reader, writer);
-            gb_linear_oram(&mut rng_gb, &mut channel, &gb_ram);
+            // The evaluator reports the failure once the garbler aborts
+            if let Err(e) = run_oram(Role::Garbler, &mut rng_gb, &mut channel, &gb_ram, 0) {
+                eprintln!("The garbler aborted: {}", e);
+            }
         });
         let rng_ev = AesRng::new();
         let reader = BufReader::new(receiver.try_clone().unwrap());
         let writer = BufWriter::new(receiver);
         let mut channel = Channel::new(reader, writer);
 
-        let result = ev_linear_oram(&mut rng_ev.clone(), &mut channel, ev_index);
+        let result = match run_oram(
+            Role::Evaluator,
+            &mut rng_ev.clone(),
+            &mut channel,
+            &[],
+            ev_index,
+        ) {
+            Ok(result) => result.expect("the evaluator learns the result"),
+            Err(e) => {
+                eprintln!("The garbled circuit was aborted: {}", e);
+                std::process::exit(1);
+            }
+        };
         let resut_in_clear = ram_in_clear(ev_index as usize, &gb_ram);
         println!(
             "Garbled Circuit result is : RAM([{:?}], at index:{}) = {}",
@@ -9,7 +9,14 @@ use crate::{
 };
 use itertools::Itertools;
 use scuttlebutt::{AesRng, Block, Channel};
-use std::{collections::HashMap, marker::PhantomData, rc::Rc};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufReader, Read, Write},
+    marker::PhantomData,
+    rc::Rc,
+};
+use tempfile::NamedTempFile;
 
 /// Static evaluator for a circuit, created by the `garble` function.
 ///
@@ -17,7 +24,7 @@ use std::{collections::HashMap, marker::PhantomData, rc::Rc};
 #[derive(Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GarbledCircuit<W, C> {
-    blocks: Vec<Block>,
+    blocks: BlockStorage,
     _phantom_wire: PhantomData<W>,
     _phantom_circ: PhantomData<C>,
 }
@@ -26,7 +33,7 @@ impl<W, C> GarbledCircuit<W, C> {
     /// Create a new object from a vector of garbled gates and constant wires.
     pub fn new(blocks: Vec<Block>) -> Self {
         GarbledCircuit {
-            blocks,
+            blocks: BlockStorage::from(blocks),
             _phantom_wire: PhantomData,
             _phantom_circ: PhantomData,
         }
@@ -36,6 +43,18 @@ impl<W, C> GarbledCircuit<W, C> {
     pub fn size(&self) -> usize {
         self.blocks.len()
     }
+
+    /// The number of garbled rows and constant wires stored on disk rather than
+    /// in memory.
+    pub fn num_spilled(&self) -> usize {
+        self.blocks.nspilled
+    }
+
+    /// The garbled rows and constant wires of the garbled circuit, read back into
+    /// memory if they were spilled, e.g. to send them to the evaluator.
+    pub fn blocks(&self) -> std::io::Result<Vec<Block>> {
+        self.blocks.to_vec()
+    }
 }
 
 type Ev<Wire> = Evaluator<Channel<GarbledReader, GarbledWriter>, Wire>;
@@ -49,7 +68,7 @@ impl<Wire: WireLabel, Circuit: EvaluableCircuit<Ev<Wire>>> GarbledCircuit<Wire,
         garbler_inputs: &[Wire],
         evaluator_inputs: &[Wire],
     ) -> Result<Vec<u16>, EvaluatorError> {
-        let channel = Channel::new(GarbledReader::new(&self.blocks), GarbledWriter::new(None));
+        let channel = Channel::new(GarbledReader::new(&self.blocks)?, GarbledWriter::new(None));
         let mut evaluator = Evaluator::new(channel);
         let outputs = c.eval(&mut evaluator, garbler_inputs, evaluator_inputs)?;
         Ok(outputs.expect("evaluator outputs always are Some(u16)"))
@@ -60,10 +79,26 @@ impl<Wire: WireLabel, Circuit: EvaluableCircuit<Ev<Wire>>> GarbledCircuit<Wire,
 pub fn garble<Wire: WireLabel, Circuit: EvaluableCircuit<Gb<Wire>>>(
     c: &Circuit,
 ) -> Result<(Encoder<Wire>, GarbledCircuit<Wire, Circuit>), GarblerError> {
-    let channel = Channel::new(
-        GarbledReader::new(&[]),
-        GarbledWriter::new(Some(c.get_num_nonfree_gates())),
-    );
+    garble_with_writer(c, GarbledWriter::new(Some(c.get_num_nonfree_gates())))
+}
+
+/// Garble a circuit without streaming, keeping at most `budget` bytes of the
+/// garbled circuit in memory.
+///
+/// Once the budget is reached, the garbled rows are spilled to a temporary file,
+/// which is streamed back when evaluating the resulting `GarbledCircuit`.
+pub fn garble_with_memory_budget<Wire: WireLabel, Circuit: EvaluableCircuit<Gb<Wire>>>(
+    c: &Circuit,
+    budget: usize,
+) -> Result<(Encoder<Wire>, GarbledCircuit<Wire, Circuit>), GarblerError> {
+    garble_with_writer(c, GarbledWriter::with_memory_budget(budget))
+}
+
+fn garble_with_writer<Wire: WireLabel, Circuit: EvaluableCircuit<Gb<Wire>>>(
+    c: &Circuit,
+    writer: GarbledWriter,
+) -> Result<(Encoder<Wire>, GarbledCircuit<Wire, Circuit>), GarblerError> {
+    let channel = Channel::new(GarbledReader::empty(), writer);
     let channel_ = channel.clone();
 
     let rng = AesRng::new();
@@ -90,12 +125,14 @@ pub fn garble<Wire: WireLabel, Circuit: EvaluableCircuit<Gb<Wire>>>(
 
     let en = Encoder::new(gb_inps, ev_inps, garbler.get_deltas());
 
-    let gc = GarbledCircuit::new(
-        Rc::try_unwrap(channel.writer())
+    let gc = GarbledCircuit {
+        blocks: Rc::try_unwrap(channel.writer())
             .unwrap()
             .into_inner()
             .blocks,
-    );
+        _phantom_wire: PhantomData,
+        _phantom_circ: PhantomData,
+    };
 
     Ok((en, gc))
 }
@@ -173,26 +210,146 @@ impl<Wire: WireLabel> Encoder<Wire> {
 ////////////////////////////////////////////////////////////////////////////////
 // Reader and Writer impls for simple local structures to collect and release blocks
 
+/// Storage for the blocks of a garbled circuit.
+///
+/// Blocks are kept in memory until `budget` of them have been written, at which
+/// point they are appended to a temporary file. The spilled blocks always come
+/// before the blocks in memory.
+#[derive(Debug)]
+struct BlockStorage {
+    memory: Vec<Block>,
+    spill: Option<NamedTempFile>,
+    nspilled: usize,
+    budget: Option<usize>,
+}
+
+impl BlockStorage {
+    fn new(budget: Option<usize>) -> Self {
+        BlockStorage {
+            memory: Vec::new(),
+            spill: None,
+            nspilled: 0,
+            budget,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.nspilled + self.memory.len()
+    }
+
+    fn push(&mut self, block: Block) -> std::io::Result<()> {
+        self.memory.push(block);
+        if self
+            .budget
+            .is_some_and(|budget| self.memory.len() >= budget)
+        {
+            self.spill()?;
+        }
+        Ok(())
+    }
+
+    /// Move the blocks in memory to the temporary file.
+    fn spill(&mut self) -> std::io::Result<()> {
+        if self.spill.is_none() {
+            self.spill = Some(NamedTempFile::new()?);
+        }
+        let bytes = self
+            .memory
+            .iter()
+            .flat_map(|block| <[u8; 16]>::from(*block))
+            .collect::<Vec<u8>>();
+        self.spill.as_mut().unwrap().write_all(&bytes)?;
+        self.nspilled += self.memory.len();
+        self.memory.clear();
+        Ok(())
+    }
+
+    /// Read all the blocks back into memory.
+    fn to_vec(&self) -> std::io::Result<Vec<Block>> {
+        let mut reader = GarbledReader::new(self)?;
+        let mut blocks = Vec::with_capacity(self.len());
+        let mut bytes = [0u8; 16];
+        for _ in 0..self.len() {
+            reader.read_exact(&mut bytes)?;
+            blocks.push(Block::from(bytes));
+        }
+        Ok(blocks)
+    }
+}
+
+impl From<Vec<Block>> for BlockStorage {
+    fn from(blocks: Vec<Block>) -> Self {
+        BlockStorage {
+            memory: blocks,
+            ..BlockStorage::new(None)
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for BlockStorage {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.nspilled == 0 {
+            return self.memory.serialize(serializer);
+        }
+        self.to_vec()
+            .map_err(serde::ser::Error::custom)?
+            .serialize(serializer)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for BlockStorage {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::<Block>::deserialize(deserializer).map(BlockStorage::from)
+    }
+}
+
 /// Implementation of the `Read` trait for use by the `Evaluator`.
 #[derive(Debug)]
 pub struct GarbledReader {
+    spilled: Option<BufReader<File>>,
+    nspilled: usize,
     blocks: Vec<Block>,
     index: usize,
 }
 
 impl GarbledReader {
-    fn new(blocks: &[Block]) -> Self {
+    fn new(storage: &BlockStorage) -> std::io::Result<Self> {
+        let spilled = match storage.spill {
+            Some(ref file) => Some(BufReader::new(file.reopen()?)),
+            None => None,
+        };
+        Ok(Self {
+            spilled,
+            nspilled: storage.nspilled,
+            blocks: storage.memory.clone(),
+            index: 0,
+        })
+    }
+
+    fn empty() -> Self {
         Self {
-            blocks: blocks.to_vec(),
+            spilled: None,
+            nspilled: 0,
+            blocks: Vec::new(),
             index: 0,
         }
     }
 }
 
-impl std::io::Read for GarbledReader {
+impl Read for GarbledReader {
     fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
         assert_eq!(buf.len() % 16, 0);
         for data in buf.chunks_mut(16) {
+            // Stream the spilled blocks first
+            if self.nspilled > 0 {
+                if let Some(ref mut spilled) = self.spilled {
+                    spilled.read_exact(data)?;
+                    self.nspilled -= 1;
+                    continue;
+                }
+            }
             let block: [u8; 16] = self.blocks[self.index].into();
             for (a, b) in data.iter_mut().zip(block.iter()) {
                 *a = *b;
@@ -206,7 +363,7 @@ impl std::io::Read for GarbledReader {
 /// Implementation of the `Write` trait for use by `Garbler`.
 #[derive(Debug)]
 pub struct GarbledWriter {
-    blocks: Vec<Block>,
+    blocks: BlockStorage,
 }
 
 impl GarbledWriter {
@@ -217,11 +374,21 @@ impl GarbledWriter {
         } else {
             Vec::new()
         };
-        Self { blocks }
+        Self {
+            blocks: BlockStorage::from(blocks),
+        }
+    }
+
+    /// Make a new `GarbledWriter` which keeps at most `budget` bytes in memory,
+    /// spilling the rest to a temporary file.
+    pub fn with_memory_budget(budget: usize) -> Self {
+        Self {
+            blocks: BlockStorage::new(Some(std::cmp::max(budget / 16, 1))),
+        }
     }
 }
 
-impl std::io::Write for GarbledWriter {
+impl Write for GarbledWriter {
     fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
         for item in buf.chunks(16) {
             let bytes: [u8; 16] = match item.try_into() {
@@ -233,7 +400,7 @@ impl std::io::Write for GarbledWriter {
                     ));
                 }
             };
-            self.blocks.push(Block::from(bytes));
+            self.blocks.push(Block::from(bytes))?;
         }
         Ok(buf.len())
     }
@@ -0,0 +1,203 @@
+//! `ConstCache` memoizes the constants created by a fancy computation.
+
+use crate::{
+    fancy::{Fancy, FancyInput, FancyReveal},
+    FancyArithmetic, FancyBinary,
+};
+use std::collections::HashMap;
+
+/// Implements `Fancy`. Returns a cached wire when a constant with the same value
+/// and modulus was already created, instead of creating a new one.
+///
+/// Circuits which repeatedly build the same constant bundles (e.g.
+/// `bin_constant_bundle(0, 128)` in a loop) only create each distinct constant wire
+/// once. In a two-party computation, both parties must wrap their backend in a
+/// `ConstCache` so that they agree on which constants are created.
+pub struct ConstCache<F: Fancy> {
+    /// The underlying fancy object.
+    pub underlying: F,
+    cache: HashMap<(u16, u16), F::Item>,
+}
+
+impl<F: Fancy> ConstCache<F> {
+    /// Make a new `ConstCache`.
+    pub fn new(underlying: F) -> ConstCache<F> {
+        ConstCache {
+            underlying,
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Number of constants created in the underlying fancy object.
+    pub fn num_constants(&self) -> usize {
+        self.cache.len()
+    }
+}
+
+impl<F: Fancy + FancyInput<Item = <F as Fancy>::Item, Error = <F as Fancy>::Error>> FancyInput
+    for ConstCache<F>
+{
+    type Item = <F as Fancy>::Item;
+    type Error = <F as Fancy>::Error;
+
+    fn receive_many(&mut self, moduli: &[u16]) -> Result<Vec<Self::Item>, Self::Error> {
+        self.underlying.receive_many(moduli)
+    }
+
+    fn encode_many(
+        &mut self,
+        values: &[u16],
+        moduli: &[u16],
+    ) -> Result<Vec<Self::Item>, Self::Error> {
+        self.underlying.encode_many(values, moduli)
+    }
+}
+
+impl<F: FancyBinary> FancyBinary for ConstCache<F> {
+    fn xor(&mut self, x: &Self::Item, y: &Self::Item) -> Result<Self::Item, Self::Error> {
+        self.underlying.xor(x, y)
+    }
+
+    fn and(&mut self, x: &Self::Item, y: &Self::Item) -> Result<Self::Item, Self::Error> {
+        self.underlying.and(x, y)
+    }
+
+    fn negate(&mut self, x: &Self::Item) -> Result<Self::Item, Self::Error> {
+        self.underlying.negate(x)
+    }
+}
+
+impl<F: FancyArithmetic> FancyArithmetic for ConstCache<F> {
+    fn add(&mut self, x: &Self::Item, y: &Self::Item) -> Result<Self::Item, Self::Error> {
+        self.underlying.add(x, y)
+    }
+
+    fn sub(&mut self, x: &Self::Item, y: &Self::Item) -> Result<Self::Item, Self::Error> {
+        self.underlying.sub(x, y)
+    }
+
+    fn cmul(&mut self, x: &Self::Item, y: u16) -> Result<Self::Item, Self::Error> {
+        self.underlying.cmul(x, y)
+    }
+
+    fn mul(&mut self, x: &Self::Item, y: &Self::Item) -> Result<Self::Item, Self::Error> {
+        self.underlying.mul(x, y)
+    }
+
+    fn proj(
+        &mut self,
+        x: &Self::Item,
+        q: u16,
+        tt: Option<Vec<u16>>,
+    ) -> Result<Self::Item, Self::Error> {
+        self.underlying.proj(x, q, tt)
+    }
+}
+
+impl<F: Fancy> Fancy for ConstCache<F> {
+    type Item = F::Item;
+    type Error = F::Error;
+
+    fn constant(&mut self, val: u16, q: u16) -> Result<Self::Item, Self::Error> {
+        if let Some(wire) = self.cache.get(&(val, q)) {
+            return Ok(wire.clone());
+        }
+        let wire = self.underlying.constant(val, q)?;
+        self.cache.insert((val, q), wire.clone());
+        Ok(wire)
+    }
+
+    fn output(&mut self, x: &Self::Item) -> Result<Option<u16>, Self::Error> {
+        self.underlying.output(x)
+    }
+}
+
+impl<F: Fancy + FancyReveal> FancyReveal for ConstCache<F> {
+    fn reveal(&mut self, x: &Self::Item) -> Result<u16, Self::Error> {
+        self.underlying.reveal(x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{dummy::Dummy, util::RngExt, BinaryGadgets};
+    use rand::thread_rng;
+
+    // Counts the calls to `constant` on the underlying fancy object, by value and
+    // modulus
+    struct ConstCounter<F: Fancy> {
+        underlying: F,
+        counts: HashMap<(u16, u16), usize>,
+    }
+
+    impl<F: Fancy> Fancy for ConstCounter<F> {
+        type Item = F::Item;
+        type Error = F::Error;
+
+        fn constant(&mut self, val: u16, q: u16) -> Result<Self::Item, Self::Error> {
+            *self.counts.entry((val, q)).or_default() += 1;
+            self.underlying.constant(val, q)
+        }
+
+        fn output(&mut self, x: &Self::Item) -> Result<Option<u16>, Self::Error> {
+            self.underlying.output(x)
+        }
+    }
+
+    impl<F: FancyBinary> FancyBinary for ConstCounter<F> {
+        fn xor(&mut self, x: &Self::Item, y: &Self::Item) -> Result<Self::Item, Self::Error> {
+            self.underlying.xor(x, y)
+        }
+
+        fn and(&mut self, x: &Self::Item, y: &Self::Item) -> Result<Self::Item, Self::Error> {
+            self.underlying.and(x, y)
+        }
+
+        fn negate(&mut self, x: &Self::Item) -> Result<Self::Item, Self::Error> {
+            self.underlying.negate(x)
+        }
+    }
+
+    // Builds the same constant bundles repeatedly, returning the outputs
+    fn repeated_constants<F: FancyBinary>(f: &mut F, x: u128) -> (u128, u128) {
+        let a = f.bin_constant_bundle(x, 128).unwrap();
+        let mut acc = f.bin_constant_bundle(0, 128).unwrap();
+        for _ in 0..16 {
+            let zero = f.bin_constant_bundle(0, 128).unwrap();
+            let one = f.bin_constant_bundle(1, 128).unwrap();
+            acc = f.bin_xor(&acc, &zero).unwrap();
+            acc = f.bin_addition_no_carry(&acc, &one).unwrap();
+        }
+        let a = f.bin_output(&a).unwrap().unwrap();
+        let acc = f.bin_output(&acc).unwrap().unwrap();
+        (a, acc)
+    }
+
+    #[test]
+    fn repeated_constants_are_created_once() {
+        let mut rng = thread_rng();
+        let x = rng.gen_u128();
+
+        // Without the cache, the underlying party creates a constant per wire
+        let mut uncached = ConstCounter {
+            underlying: Dummy::new(),
+            counts: HashMap::new(),
+        };
+        let outputs = repeated_constants(&mut uncached, x);
+        assert_eq!(outputs, (x, 16));
+        assert!(uncached.counts.values().all(|&n| n > 1));
+
+        // With the cache, it only creates every distinct constant once, and all the
+        // bundles above only use the binary constants 0 and 1
+        let mut cached = ConstCache::new(ConstCounter {
+            underlying: Dummy::new(),
+            counts: HashMap::new(),
+        });
+        assert_eq!(repeated_constants(&mut cached, x), outputs);
+        let counts = &cached.underlying.counts;
+        assert_eq!(counts.len(), 2);
+        assert_eq!(counts.get(&(0, 2)), Some(&1));
+        assert_eq!(counts.get(&(1, 2)), Some(&1));
+    }
+}
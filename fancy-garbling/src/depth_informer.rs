@@ -88,6 +88,11 @@ impl DepthInformer {
             mul_depth: 0,
         }
     }
+
+    /// The multiplicative depth of the outputs computed so far.
+    pub fn mul_depth(&self) -> usize {
+        self.mul_depth
+    }
 }
 
 impl FancyInput for DepthInformer {
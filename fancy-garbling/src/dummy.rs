@@ -147,11 +147,17 @@ impl FancyReveal for Dummy {
 mod bundle {
     use super::*;
     use crate::{
-        fancy::{ArithmeticBundleGadgets, BinaryGadgets, Bundle, BundleGadgets, CrtGadgets},
+        depth_informer::{DepthInformer, DepthItem},
+        fancy::{
+            ArithmeticBundleGadgets, BinaryBundle, BinaryGadgets, BinaryGadgetsExt, Bundle,
+            BundleGadgets, CrtGadgets,
+        },
+        informer::Informer,
         util::{self, RngExt},
     };
     use itertools::Itertools;
     use rand::thread_rng;
+    use scuttlebutt::BloomFilter;
 
     const NITERS: usize = 1 << 10;
 
@@ -273,6 +279,30 @@ mod bundle {
         }
     }
 
+    #[test]
+    fn test_binary_full_multiplication_u16() {
+        let mut rng = thread_rng();
+        let nbits = 16;
+        for _ in 0..NITERS {
+            let x = rng.gen_u16();
+            let y = rng.gen_u16();
+            let mut d = Dummy::new();
+            let (full, lower);
+            {
+                let x = d.bin_encode(x as u128, nbits).unwrap();
+                let y = d.bin_encode(y as u128, nbits).unwrap();
+                let z = d.bin_mul(&x, &y).unwrap();
+                assert_eq!(z.size(), 2 * nbits);
+                full = d.bin_output(&z).unwrap().unwrap();
+                let z = d.bin_multiplication_lower_half(&x, &y).unwrap();
+                assert_eq!(z.size(), nbits);
+                lower = d.bin_output(&z).unwrap().unwrap();
+            }
+            assert_eq!(full, (x as u32 * y as u32) as u128, "x={} y={}", x, y);
+            assert_eq!(lower, x.wrapping_mul(y) as u128, "x={} y={}", x, y);
+        }
+    }
+
     #[test]
     fn test_binary_division() {
         let mut rng = thread_rng();
@@ -383,6 +413,126 @@ mod bundle {
         }
     }
 
+    // Sample a value close to either end of the range of `nbits`-bit integers
+    fn gen_near_boundary(rng: &mut rand::rngs::ThreadRng, nbits: usize) -> u128 {
+        let q = 1 << nbits;
+        let offset = rng.gen_u128() % 64;
+        if rng.gen_bool() {
+            offset
+        } else {
+            q - 1 - offset
+        }
+    }
+
+    #[test]
+    fn binary_saturating_add() {
+        let mut rng = thread_rng();
+        let nbits = 16;
+        for _ in 0..NITERS {
+            let x = gen_near_boundary(&mut rng, nbits);
+            let y = gen_near_boundary(&mut rng, nbits);
+            let should_be = (x as u16).saturating_add(y as u16);
+            let mut d = Dummy::new();
+            let out;
+            {
+                let x = d.bin_encode(x, nbits).unwrap();
+                let y = d.bin_encode(y, nbits).unwrap();
+                let z = d.bin_saturating_add(&x, &y).unwrap();
+                out = d.bin_output(&z).unwrap().unwrap();
+            }
+            assert_eq!(out, should_be as u128, "x={} y={}", x, y);
+        }
+    }
+
+    #[test]
+    fn binary_saturating_sub() {
+        let mut rng = thread_rng();
+        let nbits = 16;
+        for _ in 0..NITERS {
+            let x = gen_near_boundary(&mut rng, nbits);
+            let y = gen_near_boundary(&mut rng, nbits);
+            let should_be = (x as u16).saturating_sub(y as u16);
+            let mut d = Dummy::new();
+            let out;
+            {
+                let x = d.bin_encode(x, nbits).unwrap();
+                let y = d.bin_encode(y, nbits).unwrap();
+                let z = d.bin_saturating_sub(&x, &y).unwrap();
+                out = d.bin_output(&z).unwrap().unwrap();
+            }
+            assert_eq!(out, should_be as u128, "x={} y={}", x, y);
+        }
+    }
+
+    #[test]
+    fn binary_cassign_accumulator() {
+        let mut rng = thread_rng();
+        let nbits = 16;
+        let q = 1 << nbits;
+        let nsteps = 8;
+        for _ in 0..NITERS {
+            let init = rng.gen_u128() % q;
+            let xs = (0..nsteps).map(|_| rng.gen_u128() % q).collect_vec();
+            let conds = (0..nsteps).map(|_| rng.gen_bool()).collect_vec();
+            // Add `xs[i]` to the accumulator at every step `i` where `conds[i]` is set
+            let mut should_be = init;
+            for (x, cond) in xs.iter().zip(conds.iter()) {
+                if *cond {
+                    should_be = (should_be + x) % q;
+                }
+            }
+            let mut d = Dummy::new();
+            let out;
+            {
+                let mut acc = d.bin_encode(init, nbits).unwrap();
+                for (x, cond) in xs.iter().zip(conds.iter()) {
+                    let x = d.bin_encode(*x, nbits).unwrap();
+                    let cond = d.encode(*cond as u16, 2).unwrap();
+                    let sum = d.bin_addition_no_carry(&acc, &x).unwrap();
+                    d.bin_cassign(&mut acc, &sum, &cond).unwrap();
+                }
+                out = d.bin_output(&acc).unwrap().unwrap();
+            }
+            assert_eq!(out, should_be);
+        }
+    }
+
+    #[test]
+    fn binary_cassign_unequal_sizes() {
+        let mut d = Dummy::new();
+        let mut target = d.bin_encode(0, 16).unwrap();
+        let src = d.bin_encode(1, 8).unwrap();
+        let cond = d.encode(1, 2).unwrap();
+        assert!(matches!(
+            d.bin_cassign(&mut target, &src, &cond),
+            Err(DummyError::FancyError(FancyError::InvalidArgNum {
+                got: 8,
+                needed: 16
+            }))
+        ));
+    }
+
+    #[test]
+    fn encode_bytes() {
+        let mut rng = thread_rng();
+        for _ in 0..NITERS {
+            let bytes = (0..64).map(|_| rng.gen_u16() as u8).collect_vec();
+            let mut d = Dummy::new();
+            let x = d.encode_bytes(&bytes).unwrap();
+            assert_eq!(x.size(), 8 * bytes.len());
+            let bits = d.outputs(x.wires()).unwrap().unwrap();
+            let out = bits
+                .chunks(8)
+                .map(|bs| util::u128_from_bits(bs) as u8)
+                .collect_vec();
+            assert_eq!(out, bytes);
+            // Up to 16 bytes, the encoding agrees with `bin_encode`
+            let x = d.encode_bytes(&bytes[..16]).unwrap();
+            let y = u128::from_le_bytes(bytes[..16].try_into().unwrap());
+            assert_eq!(d.bin_output(&x).unwrap().unwrap(), y);
+        }
+    }
+
     #[test]
     fn binary_lt() {
         let mut rng = thread_rng();
@@ -404,6 +554,80 @@ mod bundle {
         }
     }
 
+    #[test]
+    fn binary_in_range() {
+        let mut rng = thread_rng();
+        let nbits = 16;
+        let q = 1 << nbits;
+        for _ in 0..NITERS {
+            let a = rng.gen_u128() % q;
+            let b = rng.gen_u128() % q;
+            let (lo, hi) = (a.min(b), a.max(b));
+            // Make sure the boundaries are tested as well
+            let x = match rng.gen_usize() % 4 {
+                0 => lo,
+                1 => hi,
+                _ => rng.gen_u128() % q,
+            };
+            let should_be = lo <= x && x <= hi;
+            let mut d = Dummy::new();
+            let out;
+            {
+                let x = d.bin_encode(x, nbits).unwrap();
+                let z = d.bin_in_range(&x, lo, hi).unwrap();
+                out = d.output(&z).unwrap().unwrap();
+            }
+            assert_eq!(out > 0, should_be, "x={} lo={} hi={}", x, lo, hi);
+        }
+    }
+
+    #[test]
+    fn binary_in_range_invalid_bounds() {
+        let mut d = Dummy::new();
+        let x = d.bin_encode(5, 8).unwrap();
+        // An empty range
+        assert!(matches!(
+            d.bin_in_range(&x, 7, 3),
+            Err(DummyError::FancyError(FancyError::InvalidArg(_)))
+        ));
+        // Bounds wider than `x`
+        assert!(matches!(
+            d.bin_in_range(&x, 0, 256),
+            Err(DummyError::FancyError(FancyError::InvalidArg(_)))
+        ));
+        assert!(matches!(
+            d.bin_in_range(&x, 300, 400),
+            Err(DummyError::FancyError(FancyError::InvalidArg(_)))
+        ));
+        // The widest bounds which fit are accepted
+        let z = d.bin_in_range(&x, 0, 255).unwrap();
+        assert_eq!(d.output(&z).unwrap(), Some(1));
+    }
+
+    #[test]
+    fn binary_cmp_reveal() {
+        let mut rng = thread_rng();
+        let nbits = 16;
+        let q = 1 << nbits;
+        for _ in 0..NITERS {
+            let x = rng.gen_u128() % q;
+            // Make sure equal operands are tested as well
+            let y = if rng.gen_bool() {
+                x
+            } else {
+                rng.gen_u128() % q
+            };
+            let mut d = Dummy::new();
+            let out;
+            {
+                let x = d.bin_encode(x, nbits).unwrap();
+                let y = d.bin_encode(y, nbits).unwrap();
+                out = d.fancy_cmp_reveal(&x, &y).unwrap();
+            }
+            assert_eq!(out, x.cmp(&y), "x={} y={}", x, y);
+        }
+    }
+
     #[test]
     fn binary_lt_signed() {
         let mut rng = thread_rng();
@@ -448,6 +672,531 @@ mod bundle {
         }
     }
 
+    #[test]
+    fn binary_mod_constant() {
+        let mut rng = thread_rng();
+        let moduli = [1, 2, 3, 7, 251, 65521, (1 << 61) - 1, u128::MAX];
+        for &m in moduli.iter() {
+            for _ in 0..16 {
+                let nbits = 1 + rng.gen_usize() % 128;
+                let x = rng.gen_u128() & (u128::MAX >> (128 - nbits));
+                let mut d = Dummy::new();
+                let out;
+                {
+                    let x = d.bin_encode(x, nbits).unwrap();
+                    let z = d.bin_mod_constant(&x, m).unwrap();
+                    out = d.bin_output(&z).unwrap().unwrap();
+                }
+                assert_eq!(out, x % m, "x={} m={} nbits={}", x, m, nbits);
+            }
+        }
+    }
+
+    #[test]
+    fn binary_reduce() {
+        let mut rng = thread_rng();
+        let nbits = 16;
+        let q = 1 << nbits;
+        for _ in 0..NITERS {
+            let n = 1 + rng.gen_usize() % 16;
+            let inps = (0..n).map(|_| rng.gen_u128() % q).collect_vec();
+            let should_be = inps.iter().fold(0, |acc, x| (acc + x) % q);
+            let mut d = Dummy::new();
+            let (out, direct);
+            {
+                let xs = inps
+                    .into_iter()
+                    .map(|x| d.bin_encode(x, nbits).unwrap())
+                    .collect_vec();
+                let z = d
+                    .bin_reduce(&xs, |d, x, y| d.bin_addition_no_carry(x, y))
+                    .unwrap();
+                out = d.bin_output(&z).unwrap().unwrap();
+                let z = xs.iter().skip(1).fold(xs[0].clone(), |acc, x| {
+                    d.bin_addition_no_carry(&acc, x).unwrap()
+                });
+                direct = d.bin_output(&z).unwrap().unwrap();
+            }
+            assert_eq!(out, should_be);
+            assert_eq!(out, direct);
+        }
+    }
+
+    #[test]
+    fn binary_argmax() {
+        let mut rng = thread_rng();
+        let nbits = 16;
+        for _ in 0..NITERS {
+            let n = 1 + rng.gen_usize() % 16;
+            // Use few distinct values so that there are ties
+            let inps = (0..n).map(|_| rng.gen_u128() % 8).collect_vec();
+            let max = *inps.iter().max().unwrap();
+            let should_be = inps.iter().position(|x| *x == max).unwrap() as u128;
+            let mut d = Dummy::new();
+            let out;
+            {
+                let xs = inps
+                    .into_iter()
+                    .map(|x| d.bin_encode(x, nbits).unwrap())
+                    .collect_vec();
+                let z = d.fancy_argmax(&xs).unwrap();
+                out = d.bin_output(&z).unwrap().unwrap();
+            }
+            assert_eq!(out, should_be);
+        }
+    }
+
+    #[test]
+    fn binary_gcd() {
+        fn gcd(a: u128, b: u128) -> u128 {
+            if b == 0 {
+                a
+            } else {
+                gcd(b, a % b)
+            }
+        }
+        let mut rng = thread_rng();
+        let nbits = 16;
+        for i in 0..NITERS {
+            let (a, b) = match i {
+                0 => (0, 0),
+                1 => (0, rng.gen_u16() as u128),
+                2 => (rng.gen_u16() as u128, 0),
+                _ => (rng.gen_u16() as u128, rng.gen_u16() as u128),
+            };
+            let mut d = Dummy::new();
+            let out;
+            {
+                let x = d.bin_encode(a, nbits).unwrap();
+                let y = d.bin_encode(b, nbits).unwrap();
+                let z = d.bin_gcd(&x, &y).unwrap();
+                out = d.bin_output(&z).unwrap().unwrap();
+            }
+            assert_eq!(out, gcd(a, b), "gcd({}, {})", a, b);
+        }
+    }
+
+    #[test]
+    fn binary_counting_bloom_lookup() {
+        let mut rng = thread_rng();
+        let nbits = 8;
+        let ncounters = 32;
+        let nhashes = 3;
+        let index_bits = 5;
+        for _ in 0..64 {
+            // Insert random items into a cleartext counting bloom filter, where each
+            // item is identified by the positions its hash functions select.
+            let items = (0..8)
+                .map(|_| {
+                    (0..nhashes)
+                        .map(|_| rng.gen_usize() % ncounters)
+                        .collect_vec()
+                })
+                .collect_vec();
+            let mut counters = vec![0u128; ncounters];
+            for _ in 0..20 {
+                let item = &items[rng.gen_usize() % items.len()];
+                for &p in item.iter() {
+                    counters[p] += 1;
+                }
+            }
+            let item = &items[rng.gen_usize() % items.len()];
+            let should_be = item.iter().map(|&p| counters[p]).min().unwrap();
+
+            let mut d = Dummy::new();
+            let out;
+            {
+                let cs = counters
+                    .iter()
+                    .map(|&c| d.bin_encode(c, nbits).unwrap())
+                    .collect_vec();
+                let ps = item
+                    .iter()
+                    .map(|&p| d.bin_encode(p as u128, index_bits).unwrap())
+                    .collect_vec();
+                let z = d.bin_counting_bloom_lookup(&cs, &ps).unwrap();
+                out = d.bin_output(&z).unwrap().unwrap();
+            }
+            assert_eq!(out, should_be);
+        }
+    }
+
+    #[test]
+    fn binary_counting_bloom_lookup_invalid() {
+        let mut d = Dummy::new();
+        let cs = (0..5).map(|_| d.bin_encode(0, 8).unwrap()).collect_vec();
+        // Two bits only index four of the five counters
+        let ps = vec![d.bin_encode(0, 2).unwrap()];
+        assert!(matches!(
+            d.bin_counting_bloom_lookup(&cs, &ps),
+            Err(DummyError::FancyError(FancyError::InvalidArgNum {
+                got: 5,
+                needed: 4
+            }))
+        ));
+        // The counters must all have the same size
+        let mut cs = cs;
+        cs[3] = d.bin_encode(0, 4).unwrap();
+        let ps = vec![d.bin_encode(0, 3).unwrap()];
+        assert!(matches!(
+            d.bin_counting_bloom_lookup(&cs, &ps),
+            Err(DummyError::FancyError(FancyError::InvalidArgNum {
+                got: 4,
+                needed: 8
+            }))
+        ));
+    }
+
+    #[test]
+    fn binary_dict_lookup() {
+        let nbits = 16;
+        let dict = [(3u128, 30u128), (17, 170), (42, 420), (1000, 10000)];
+        let default = 0xffff;
+        // A present key returns its value, an absent one returns the default
+        for (query, should_be) in [(42, 420), (3, 30), (7, default)] {
+            let mut d = Dummy::new();
+            let out;
+            {
+                let keys = dict
+                    .iter()
+                    .map(|(k, _)| d.bin_encode(*k, nbits).unwrap())
+                    .collect_vec();
+                let values = dict
+                    .iter()
+                    .map(|(_, v)| d.bin_encode(*v, nbits).unwrap())
+                    .collect_vec();
+                let q = d.bin_encode(query, nbits).unwrap();
+                let def = d.bin_encode(default, nbits).unwrap();
+                let z = d.fancy_dict_lookup(&keys, &values, &q, &def).unwrap();
+                out = d.bin_output(&z).unwrap().unwrap();
+            }
+            assert_eq!(out, should_be);
+        }
+    }
+
+    #[test]
+    fn binary_popcount() {
+        let mut rng = thread_rng();
+        for _ in 0..NITERS {
+            let nbits = rng.gen_usize() % 128;
+            let x = rng.gen_u128() & ((1 << nbits) - 1);
+            let mut d = Dummy::new();
+            let out;
+            {
+                let x = d.bin_encode(x, nbits).unwrap();
+                let z = d.bin_popcount(x.wires()).unwrap();
+                out = d.bin_output(&z).unwrap().unwrap();
+            }
+            assert_eq!(out, x.count_ones() as u128, "x={} nbits={}", x, nbits);
+        }
+    }
+
+    #[test]
+    fn binary_popcount_low_depth() {
+        let mut rng = thread_rng();
+        for _ in 0..NITERS {
+            let nbits = rng.gen_usize() % 128;
+            let x = rng.gen_u128() & ((1 << nbits) - 1);
+            let mut d = Dummy::new();
+            let (out, expected);
+            {
+                let x = d.bin_encode(x, nbits).unwrap();
+                let z = d.bin_popcount_low_depth(x.wires()).unwrap();
+                out = d.bin_output(&z).unwrap().unwrap();
+                let z = d.bin_popcount(x.wires()).unwrap();
+                expected = d.bin_output(&z).unwrap().unwrap();
+            }
+            assert_eq!(out, expected, "x={} nbits={}", x, nbits);
+            assert_eq!(out, x.count_ones() as u128, "x={} nbits={}", x, nbits);
+        }
+    }
+
+    #[test]
+    fn binary_popcount_low_depth_is_shallower() {
+        let depth = |f: &dyn Fn(&mut DepthInformer, &BinaryBundle<DepthItem>)| {
+            let mut informer = DepthInformer::new();
+            let x = informer.bin_encode(0, 100).unwrap();
+            f(&mut informer, &x);
+            informer.mul_depth()
+        };
+        let low_depth = depth(&|f, x| {
+            let z = f.bin_popcount_low_depth(x.wires()).unwrap();
+            f.bin_output(&z).unwrap();
+        });
+        let standard = depth(&|f, x| {
+            let z = f.bin_popcount(x.wires()).unwrap();
+            f.bin_output(&z).unwrap();
+        });
+        // The count of 100 bits has 7 bits, the last one of degree 64
+        assert_eq!(low_depth, 6);
+        assert!(low_depth < standard, "{} >= {}", low_depth, standard);
+    }
+
+    #[test]
+    fn binary_lowest_set_bit() {
+        let mut rng = thread_rng();
+        let xs = std::iter::once(0).chain((0..NITERS).map(|_| rng.gen_u128() as u32));
+        for x in xs {
+            let mut d = Dummy::new();
+            let out;
+            {
+                let x = d.bin_encode(x as u128, 32).unwrap();
+                let z = d.bin_lowest_set_bit(&x).unwrap();
+                out = d.bin_output(&z).unwrap().unwrap();
+            }
+            assert_eq!(out, (x & x.wrapping_neg()) as u128, "x={}", x);
+        }
+    }
+
+    #[test]
+    fn binary_ctz() {
+        let mut rng = thread_rng();
+        let xs = std::iter::once(0).chain((0..NITERS).map(|_| {
+            // Shift the random values so that counts other than 0 are likely
+            (rng.gen_u128() as u32) << (rng.gen_usize() % 32)
+        }));
+        for x in xs {
+            let mut d = Dummy::new();
+            let out;
+            {
+                let x = d.bin_encode(x as u128, 32).unwrap();
+                let z = d.bin_ctz(&x).unwrap();
+                out = d.bin_output(&z).unwrap().unwrap();
+            }
+            assert_eq!(out, x.trailing_zeros() as u128, "x={}", x);
+        }
+    }
+
+    #[test]
+    fn binary_popcount_is() {
+        let mut rng = thread_rng();
+        for _ in 0..NITERS {
+            let nbits = rng.gen_usize() % 128;
+            let x = rng.gen_u128() & ((1 << nbits) - 1);
+            // Pick the actual weight half of the time
+            let k = if rng.gen_bool() {
+                x.count_ones() as usize
+            } else {
+                rng.gen_usize() % (nbits + 2)
+            };
+            let mut d = Dummy::new();
+            let out;
+            {
+                let x = d.bin_encode(x, nbits).unwrap();
+                let z = d.bin_popcount_is(x.wires(), k).unwrap();
+                out = d.output(&z).unwrap().unwrap();
+            }
+            let should_be = x.count_ones() as usize == k;
+            assert_eq!(out, should_be as u16, "x={} nbits={} k={}", x, nbits, k);
+        }
+    }
+
+    #[test]
+    fn binary_hamming_distance() {
+        let mut rng = thread_rng();
+        for _ in 0..NITERS {
+            let nbits = 1 + rng.gen_usize() % 127;
+            let q = 1 << nbits;
+            let x = rng.gen_u128() % q;
+            let y = rng.gen_u128() % q;
+            let mut d = Dummy::new();
+            let out;
+            {
+                let x = d.bin_encode(x, nbits).unwrap();
+                let y = d.bin_encode(y, nbits).unwrap();
+                let z = d.bin_hamming_distance(&x, &y).unwrap();
+                out = d.bin_output(&z).unwrap().unwrap();
+            }
+            assert_eq!(out, (x ^ y).count_ones() as u128, "x={} y={}", x, y);
+        }
+    }
+
+    #[test]
+    fn binary_jaccard() {
+        let mut rng = thread_rng();
+        for _ in 0..16 {
+            // Two overlapping sets, inserted in Bloom filters with the same parameters
+            let nitems = 1 + rng.gen_usize() % 64;
+            let nshared = rng.gen_usize() % (nitems + 1);
+            let items = (0..2 * nitems - nshared)
+                .map(|_| rng.gen_u128().to_le_bytes())
+                .collect::<Vec<_>>();
+            let mut a = BloomFilter::new(2 * nitems, 0.1);
+            let mut b = a.clone();
+            items[..nitems].iter().for_each(|item| a.insert(item));
+            items[nitems - nshared..]
+                .iter()
+                .for_each(|item| b.insert(item));
+
+            let a_bits = (0..a.len()).map(|i| a.get(i) as u16).collect_vec();
+            let b_bits = (0..b.len()).map(|i| b.get(i) as u16).collect_vec();
+            let shared = a_bits.iter().zip(&b_bits).filter(|(x, y)| **x & **y == 1);
+            let total = a_bits.iter().zip(&b_bits).filter(|(x, y)| **x | **y == 1);
+            let should_be = shared.count() as f64 / total.count() as f64;
+
+            let mut d = Dummy::new();
+            let out;
+            let precision;
+            {
+                let a_bits = d.encode_many(&a_bits, &vec![2; a_bits.len()]).unwrap();
+                let b_bits = d.encode_many(&b_bits, &vec![2; b_bits.len()]).unwrap();
+                let z = d.fancy_jaccard(&a_bits, &b_bits).unwrap();
+                precision = z.precision();
+                out = d.bin_output(z.bundle()).unwrap().unwrap();
+            }
+            let out = util::fixed_to_f64(out, precision);
+            assert!(
+                (out - should_be).abs() < 1.0 / (1 << precision) as f64,
+                "out={} should_be={}",
+                out,
+                should_be
+            );
+        }
+    }
+
+    #[test]
+    fn binary_rotl_secret() {
+        let mut rng = thread_rng();
+        for _ in 0..NITERS {
+            let nbits = 1 + rng.gen_usize() % 64;
+            let amount_bits = 1 + rng.gen_usize() % 8;
+            let x = rng.gen_u128() % (1 << nbits);
+            let amount = rng.gen_u128() % (1 << amount_bits);
+            let c = (amount % nbits as u128) as usize;
+            let should_be = if c == 0 {
+                x
+            } else {
+                ((x << c) | (x >> (nbits - c))) & ((1 << nbits) - 1)
+            };
+            let mut d = Dummy::new();
+            let out;
+            {
+                let x = d.bin_encode(x, nbits).unwrap();
+                let amount = d.bin_encode(amount, amount_bits).unwrap();
+                let z = d.bin_rotl_secret(&x, &amount).unwrap();
+                out = d.bin_output(&z).unwrap().unwrap();
+            }
+            assert_eq!(out, should_be, "x={} amount={} nbits={}", x, amount, nbits);
+        }
+    }
+
+    #[test]
+    fn test_bin_to_crt() {
+        let mut rng = thread_rng();
+        for _ in 0..NITERS {
+            let q = rng.gen_usable_composite_modulus();
+            let nbits = 1 + rng.gen_usize() % 64;
+            let x = rng.gen_u128() % (1 << nbits);
+            let mut d = Dummy::new();
+            let out;
+            {
+                let x = d.bin_encode(x, nbits).unwrap();
+                let z = d.bin_to_crt(&x, q).unwrap();
+                out = d.crt_output(&z).unwrap().unwrap();
+            }
+            assert_eq!(out, x % q, "x={} q={}", x, q);
+        }
+    }
+
+    #[test]
+    fn test_crt_to_bin() {
+        let mut rng = thread_rng();
+        for _ in 0..NITERS {
+            let q = rng.gen_usable_composite_modulus();
+            let nbits = (128 - q.leading_zeros()) as usize;
+            let x = rng.gen_u128() % q;
+            let mut d = Dummy::new();
+            let (out, roundtrip);
+            {
+                let x = d.crt_encode(x, q).unwrap();
+                let z = d.crt_to_bin(&x, nbits).unwrap();
+                out = d.bin_output(&z).unwrap().unwrap();
+                let y = d.bin_to_crt(&z, q).unwrap();
+                roundtrip = d.crt_output(&y).unwrap().unwrap();
+            }
+            assert_eq!(out, x, "q={}", q);
+            assert_eq!(roundtrip, x, "q={}", q);
+        }
+    }
+
+    #[test]
+    fn test_crt_to_bin_oversized_modulus() {
+        let mut d = Dummy::new();
+        let q = util::modulus_with_width(127);
+        assert!(q >= 1 << 127);
+        let x = d.crt_encode(0, q).unwrap();
+        assert!(matches!(
+            d.crt_to_bin(&x, 128),
+            Err(DummyError::FancyError(FancyError::InvalidArg(_)))
+        ));
+    }
+
+    #[test]
+    fn binary_constant_ops() {
+        let mut rng = thread_rng();
+        for _ in 0..NITERS {
+            let nbits = 1 + rng.gen_usize() % 128;
+            let mask = u128::MAX >> (128 - nbits);
+            let x = rng.gen_u128() & mask;
+            let c = rng.gen_u128();
+            let mut d = Dummy::new();
+            let xb = d.bin_encode(x, nbits).unwrap();
+            let cb = d.bin_constant_bundle(c & mask, nbits).unwrap();
+            for (z, generic, should_be) in [
+                (
+                    d.bin_xor_constant(&xb, c).unwrap(),
+                    d.bin_xor(&xb, &cb).unwrap(),
+                    x ^ c,
+                ),
+                (
+                    d.bin_and_constant(&xb, c).unwrap(),
+                    d.bin_and(&xb, &cb).unwrap(),
+                    x & c,
+                ),
+                (
+                    d.bin_or_constant(&xb, c).unwrap(),
+                    d.bin_or(&xb, &cb).unwrap(),
+                    x | c,
+                ),
+            ] {
+                let out = d.bin_output(&z).unwrap().unwrap();
+                assert_eq!(out, should_be & mask);
+                assert_eq!(out, d.bin_output(&generic).unwrap().unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn binary_constant_ops_gate_counts() {
+        let nbits = 64;
+        // Half of the bits of the constant are set
+        let c = 0x00ff_00ff_00ff_00ff;
+        let count = |f: &dyn Fn(&mut Informer<Dummy>, &BinaryBundle<DummyVal>)| {
+            let mut informer = Informer::new(Dummy::new());
+            let x = informer.bin_encode(0, nbits).unwrap();
+            f(&mut informer, &x);
+            informer.stats()
+        };
+        let xor_constant = count(&|f, x| {
+            f.bin_xor_constant(x, c).unwrap();
+        });
+        let xor_generic = count(&|f, x| {
+            let c = f.bin_constant_bundle(c, nbits).unwrap();
+            f.bin_xor(x, &c).unwrap();
+        });
+        assert_eq!(xor_constant.num_adds(), nbits / 2);
+        assert_eq!(xor_generic.num_adds(), nbits);
+        let and_constant = count(&|f, x| {
+            f.bin_and_constant(x, c).unwrap();
+        });
+        let and_generic = count(&|f, x| {
+            let c = f.bin_constant_bundle(c, nbits).unwrap();
+            f.bin_and(x, &c).unwrap();
+        });
+        assert_eq!(and_constant.num_muls(), 0);
+        assert_eq!(and_generic.num_muls(), nbits);
+    }
+
     #[test] // bundle relu
     fn test_relu() {
         let mut rng = thread_rng();
@@ -565,6 +1314,31 @@ mod bundle {
         }
     }
 
+    #[test]
+    fn binary_is_zero() {
+        let mut rng = thread_rng();
+        for _ in 0..NITERS {
+            let nbits = rng.gen_usize() % 127 + 1;
+            let q = 1 << nbits;
+            let x = if rng.gen_bool() {
+                0
+            } else {
+                rng.gen_u128() % q
+            };
+            let mut d = Dummy::new();
+            let (zero, nonzero);
+            {
+                let x = d.bin_encode(x, nbits).unwrap();
+                let z = d.bin_is_zero(&x).unwrap();
+                zero = d.output(&z).unwrap().unwrap();
+                let z = d.bin_is_nonzero(&x).unwrap();
+                nonzero = d.output(&z).unwrap().unwrap();
+            }
+            assert_eq!(zero, (x == 0) as u16);
+            assert_eq!(nonzero, (x != 0) as u16);
+        }
+    }
+
     #[test]
     fn binary_proj_eq() {
         let mut rng = thread_rng();
@@ -629,6 +1403,65 @@ mod bundle {
         }
     }
 
+    #[test]
+    fn binary_interleave() {
+        let mut rng = thread_rng();
+        for _ in 0..NITERS {
+            let nbits = 64;
+            let q = 1 << nbits;
+            let (x, y) = (rng.gen_u128() % q, rng.gen_u128() % q);
+            let mut d = Dummy::new();
+            let (z, x_, y_);
+            {
+                let x = d.bin_encode(x, nbits).unwrap();
+                let y = d.bin_encode(y, nbits).unwrap();
+                let interleaved = d.bin_interleave(&x, &y).unwrap();
+                let (xs, ys) = d.bin_deinterleave(&interleaved).unwrap();
+                z = d.outputs(interleaved.wires()).unwrap().unwrap();
+                x_ = d.bin_output(&xs).unwrap().unwrap();
+                y_ = d.bin_output(&ys).unwrap().unwrap();
+            }
+            let (xbits, ybits) = (util::u128_to_bits(x, nbits), util::u128_to_bits(y, nbits));
+            let should_be = xbits
+                .into_iter()
+                .zip(ybits)
+                .flat_map(|(x, y)| [x, y])
+                .collect_vec();
+            assert_eq!(z, should_be);
+            assert_eq!((x_, y_), (x, y));
+        }
+        // Rearranging the wires costs no gates
+        let mut informer = Informer::new(Dummy::new());
+        let x = informer.bin_encode(0, 64).unwrap();
+        let y = informer.bin_encode(0, 64).unwrap();
+        let z = informer.bin_interleave(&x, &y).unwrap();
+        informer.bin_deinterleave(&z).unwrap();
+        assert_eq!(informer.stats().num_muls(), 0);
+        assert_eq!(informer.stats().num_adds(), 0);
+    }
+
+    #[test]
+    fn binary_interleave_invalid_sizes() {
+        let mut d = Dummy::new();
+        let x = d.bin_encode(0, 8).unwrap();
+        let y = d.bin_encode(0, 4).unwrap();
+        assert!(matches!(
+            d.bin_interleave(&x, &y),
+            Err(DummyError::FancyError(FancyError::InvalidArgNum {
+                got: 4,
+                needed: 8
+            }))
+        ));
+        let z = d.bin_encode(0, 7).unwrap();
+        assert!(matches!(
+            d.bin_deinterleave(&z),
+            Err(DummyError::FancyError(FancyError::InvalidArgNum {
+                got: 7,
+                ..
+            }))
+        ));
+    }
+
     #[test]
     fn test_mixed_radix_addition_msb_only() {
         let mut rng = thread_rng();
@@ -376,6 +376,11 @@ pub enum TwopacError {
     EvaluatorError(EvaluatorError),
     /// Processing the garbled circuit produced an error.
     FancyError(FancyError),
+    /// The parties declared inconsistent parameters.
+    ParameterMismatch(String),
+    /// The evaluator could not decode the output at this index, e.g. because the
+    /// garbler sent malformed output decoding information.
+    OutputDecodingFailed(usize),
 }
 
 impl std::error::Error for TwopacError {}
@@ -418,6 +423,8 @@ impl std::fmt::Display for TwopacError {
             TwopacError::EvaluatorError(e) => write!(f, "evaluator error: {}", e),
             TwopacError::GarblerError(e) => write!(f, "garbler error: {}", e),
             TwopacError::FancyError(e) => write!(f, "fancy error: {}", e),
+            TwopacError::ParameterMismatch(s) => write!(f, "parameter mismatch: {}", s),
+            TwopacError::OutputDecodingFailed(i) => write!(f, "failed to decode output {}", i),
         }
     }
 }
@@ -11,7 +11,7 @@ mod bundle;
 mod crt;
 mod input;
 mod reveal;
-pub use binary::{BinaryBundle, BinaryGadgets};
+pub use binary::{BinaryBundle, BinaryGadgets, BinaryGadgetsExt, FixedBundle};
 pub use bundle::{ArithmeticBundleGadgets, BinaryBundleGadgets, Bundle, BundleGadgets};
 pub use crt::{CrtBundle, CrtGadgets};
 pub use input::FancyInput;
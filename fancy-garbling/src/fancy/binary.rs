@@ -7,7 +7,14 @@ use crate::{
     util, FancyBinary,
 };
 use itertools::Itertools;
-use std::ops::{Deref, DerefMut};
+use std::{
+    cmp::Reverse,
+    collections::BinaryHeap,
+    ops::{Deref, DerefMut},
+};
+
+/// The number of fractional bits of the similarity computed by `fancy_jaccard`.
+const JACCARD_PRECISION: usize = 16;
 
 /// Bundle which is explicitly binary representation.
 #[derive(Clone)]
@@ -46,6 +53,37 @@ impl<W: Clone + HasModulus> From<Bundle<W>> for BinaryBundle<W> {
     }
 }
 
+/// Binary bundle representing an unsigned fixed-point number, i.e. the integer value
+/// of the bundle divided by `2^precision`.
+#[derive(Clone)]
+pub struct FixedBundle<W> {
+    bundle: BinaryBundle<W>,
+    precision: usize,
+}
+
+impl<W: Clone + HasModulus> FixedBundle<W> {
+    /// Create a new fixed-point bundle from a binary bundle with `precision`
+    /// fractional bits.
+    pub fn new(bundle: BinaryBundle<W>, precision: usize) -> FixedBundle<W> {
+        FixedBundle { bundle, precision }
+    }
+
+    /// The number of fractional bits.
+    pub fn precision(&self) -> usize {
+        self.precision
+    }
+
+    /// The underlying binary bundle.
+    pub fn bundle(&self) -> &BinaryBundle<W> {
+        &self.bundle
+    }
+
+    /// Extract the underlying binary bundle from this fixed-point bundle.
+    pub fn extract(self) -> BinaryBundle<W> {
+        self.bundle
+    }
+}
+
 impl<F: FancyBinary> BinaryGadgets for F {}
 
 /// Extension trait for `Fancy` providing gadgets that operate over bundles of mod2 wires.
@@ -206,7 +244,10 @@ pub trait BinaryGadgets: FancyBinary + BundleGadgets {
         Ok(sum)
     }
 
-    /// Full multiplier
+    /// Full multiplier.
+    ///
+    /// Returns all the output bits, ie a number with twice the number of bits
+    /// as the inputs (cf. `bin_multiplication_lower_half` for the truncated product).
     fn bin_mul(
         &mut self,
         xs: &BinaryBundle<Self::Item>,
@@ -266,6 +307,190 @@ pub trait BinaryGadgets: FancyBinary + BundleGadgets {
         Ok(qs)
     }
 
+    /// Compute the Hamming distance between `x` and `y`, i.e. the number of bits
+    /// in which they differ, with as many bits as `bin_popcount` returns.
+    fn bin_hamming_distance(
+        &mut self,
+        x: &BinaryBundle<Self::Item>,
+        y: &BinaryBundle<Self::Item>,
+    ) -> Result<BinaryBundle<Self::Item>, Self::Error> {
+        if x.moduli() != y.moduli() {
+            return Err(Self::Error::from(FancyError::UnequalModuli));
+        }
+        let z = self.bin_xor(x, y)?;
+        self.bin_popcount(z.wires())
+    }
+
+    /// Count the number of set bits in `xs`.
+    ///
+    /// The bits are summed with a tree of adders, so the result has just enough bits
+    /// to hold `xs.len()`, i.e. `ceil(log2(xs.len())) + 1` bits.
+    fn bin_popcount(&mut self, xs: &[Self::Item]) -> Result<BinaryBundle<Self::Item>, Self::Error> {
+        if xs.is_empty() {
+            return self.bin_constant_bundle(0, 1);
+        }
+        let zero = self.constant(0, 2)?;
+        let mut counts = xs
+            .iter()
+            .map(|x| BinaryBundle::new(vec![x.clone()]))
+            .collect_vec();
+        while counts.len() > 1 {
+            let mut next = Vec::with_capacity(counts.len().div_ceil(2));
+            for pair in counts.chunks(2) {
+                if let [x, y] = pair {
+                    // The counts only differ in size when `y` is the last one of an
+                    // odd level, in which case it is padded with zeros
+                    let mut y = y.clone();
+                    while y.size() < x.size() {
+                        y.push(zero.clone());
+                    }
+                    let (mut z, carry) = self.bin_addition(x, &y)?;
+                    z.push(carry);
+                    next.push(z);
+                } else {
+                    next.push(pair[0].clone());
+                }
+            }
+            counts = next;
+        }
+        Ok(counts.pop().unwrap())
+    }
+
+    /// Count the number of set bits in `xs`, like `bin_popcount`, with a lower
+    /// multiplicative depth.
+    ///
+    /// The bits of each weight are summed with carry-save adders, taking the
+    /// shallowest bits first, until a single bit is left, the carries moving on to
+    /// the next weight. Since XORs are free in depth, only carries deepen the
+    /// circuit, by one per weight: the count of `n` bits has AND depth
+    /// `floor(log2(n))`, the least any circuit can have, where the ripple-carry
+    /// adders of `bin_popcount` may add a level. It also needs `n - n.count_ones()`
+    /// AND gates, about half of those of `bin_popcount`. The result has just
+    /// enough bits to hold `xs.len()`.
+    fn bin_popcount_low_depth(
+        &mut self,
+        xs: &[Self::Item],
+    ) -> Result<BinaryBundle<Self::Item>, Self::Error> {
+        let nbits = (usize::BITS - xs.len().leading_zeros()).max(1) as usize;
+        // The bits left to sum at each weight, with their depth relative to `xs`
+        let mut columns = vec![vec![]; nbits];
+        columns[0] = xs.iter().map(|x| (0, x.clone())).collect_vec();
+        let mut out = Vec::with_capacity(nbits);
+        for i in 0..nbits {
+            let mut bits = Vec::new();
+            // Shallowest bits first, then in order of creation
+            let mut heap = BinaryHeap::new();
+            for (depth, x) in std::mem::take(&mut columns[i]) {
+                heap.push(Reverse((depth, bits.len())));
+                bits.push(Some(x));
+            }
+            while heap.len() > 1 {
+                let mut depth = 0;
+                let mut adds = Vec::with_capacity(3);
+                while adds.len() < 3 {
+                    let Some(Reverse((d, j))) = heap.pop() else {
+                        break;
+                    };
+                    depth = depth.max(d);
+                    adds.push(bits[j].take().unwrap());
+                }
+                // The count fits in `nbits` bits, so nothing carries out of the last
+                // weight
+                let needs_carry = i + 1 < nbits;
+                let (sum, carry) = match adds.as_slice() {
+                    [a, b] => {
+                        let carry = if needs_carry {
+                            Some(self.and(a, b)?)
+                        } else {
+                            None
+                        };
+                        (self.xor(a, b)?, carry)
+                    }
+                    [a, b, c] => {
+                        let ac = self.xor(a, c)?;
+                        let carry = if needs_carry {
+                            let bc = self.xor(b, c)?;
+                            let and = self.and(&ac, &bc)?;
+                            Some(self.xor(&and, c)?)
+                        } else {
+                            None
+                        };
+                        (self.xor(&ac, b)?, carry)
+                    }
+                    _ => unreachable!(),
+                };
+                heap.push(Reverse((depth, bits.len())));
+                bits.push(Some(sum));
+                if let Some(carry) = carry {
+                    columns[i + 1].push((depth + 1, carry));
+                }
+            }
+            out.push(match heap.pop() {
+                Some(Reverse((_, j))) => bits[j].take().unwrap(),
+                None => self.constant(0, 2)?,
+            });
+        }
+        Ok(BinaryBundle::new(out))
+    }
+
+    /// Compute whether exactly `k` of the bits in `xs` are set, without revealing
+    /// their actual number.
+    fn bin_popcount_is(&mut self, xs: &[Self::Item], k: usize) -> Result<Self::Item, Self::Error> {
+        if k > xs.len() {
+            return self.constant(0, 2);
+        }
+        let count = self.bin_popcount(xs)?;
+        // `k` fits in the width of the count, which can hold `xs.len()`
+        let diff = self.bin_xor_constant(&count, k as u128)?;
+        self.bin_is_zero(&diff)
+    }
+
+    /// Estimate the Jaccard similarity `|A ∩ B| / |A ∪ B|` of two sets from the bins
+    /// of their Bloom filters `a_bits` and `b_bits`, as the number of bins set in both
+    /// filters divided by the number of bins set in either.
+    ///
+    /// The result is rounded down to 16 fractional bits, and is zero when both filters
+    /// are empty.
+    fn fancy_jaccard(
+        &mut self,
+        a_bits: &[Self::Item],
+        b_bits: &[Self::Item],
+    ) -> Result<FixedBundle<Self::Item>, Self::Error> {
+        if a_bits.len() != b_bits.len() {
+            return Err(Self::Error::from(FancyError::InvalidArgNum {
+                got: b_bits.len(),
+                needed: a_bits.len(),
+            }));
+        }
+        let (shared, total): (Vec<Self::Item>, Vec<Self::Item>) = a_bits
+            .iter()
+            .zip(b_bits.iter())
+            .map(|(a, b)| Ok((self.and(a, b)?, self.or(a, b)?)))
+            .collect::<Result<Vec<_>, Self::Error>>()?
+            .into_iter()
+            .unzip();
+        let shared = self.bin_popcount(&shared)?;
+        let total = self.bin_popcount(&total)?;
+
+        // Compute `(shared * 2^precision) / total`
+        let nbits = total.size() + JACCARD_PRECISION;
+        let zero = self.constant(0, 2)?;
+        let mut numerator = vec![zero.clone(); JACCARD_PRECISION];
+        numerator.extend(shared.wires().iter().cloned());
+        let mut denominator = total.wires().to_vec();
+        denominator.resize(nbits, zero);
+        let quotient = self.bin_div(
+            &BinaryBundle::new(numerator),
+            &BinaryBundle::new(denominator),
+        )?;
+
+        // The similarity is at most one, so only the bits up to `2^precision` are kept
+        Ok(FixedBundle::new(
+            BinaryBundle::new(quotient.wires()[..=JACCARD_PRECISION].to_vec()),
+            JACCARD_PRECISION,
+        ))
+    }
+
     /// Compute the twos complement of the input bundle (which must be base 2).
     fn bin_twos_complement(
         &mut self,
@@ -281,6 +506,39 @@ pub trait BinaryGadgets: FancyBinary + BundleGadgets {
         self.bin_addition_no_carry(&not_xs, &one)
     }
 
+    /// Isolate the least significant set bit of `x`, i.e. compute `x & -x`,
+    /// which is zero if `x` is zero.
+    fn bin_lowest_set_bit(
+        &mut self,
+        x: &BinaryBundle<Self::Item>,
+    ) -> Result<BinaryBundle<Self::Item>, Self::Error> {
+        let neg_x = self.bin_twos_complement(x)?;
+        self.bin_and(x, &neg_x)
+    }
+
+    /// Count the trailing zeros of `x`, i.e. the index of its least significant
+    /// set bit, or its size if `x` is zero.
+    ///
+    /// The i-th bit of the prefix OR of `x` is zero iff its bits up to `i` are,
+    /// so the count is the number of unset bits in the prefix OR, with as many
+    /// bits as `bin_popcount` returns.
+    fn bin_ctz(
+        &mut self,
+        x: &BinaryBundle<Self::Item>,
+    ) -> Result<BinaryBundle<Self::Item>, Self::Error> {
+        let mut zeros = Vec::with_capacity(x.size());
+        let mut prefix: Option<Self::Item> = None;
+        for w in x.wires() {
+            let acc = match prefix {
+                Some(acc) => self.or(&acc, w)?,
+                None => w.clone(),
+            };
+            zeros.push(self.negate(&acc)?);
+            prefix = Some(acc);
+        }
+        self.bin_popcount(&zeros)
+    }
+
     /// Subtract two binary bundles. Returns the result and whether it underflowed.
     ///
     /// Due to the way that `twos_complement(0) = 0`, underflow indicates `y != 0 && x >= y`.
@@ -293,6 +551,41 @@ pub trait BinaryGadgets: FancyBinary + BundleGadgets {
         self.bin_addition(xs, &neg_ys)
     }
 
+    /// Binary addition, clamping the result to all ones on overflow.
+    fn bin_saturating_add(
+        &mut self,
+        xs: &BinaryBundle<Self::Item>,
+        ys: &BinaryBundle<Self::Item>,
+    ) -> Result<BinaryBundle<Self::Item>, Self::Error> {
+        let (zs, carry) = self.bin_addition(xs, ys)?;
+        // Setting every bit when there is a carry clamps the result to all ones
+        zs.wires()
+            .iter()
+            .map(|z| self.or(z, &carry))
+            .collect::<Result<Vec<Self::Item>, Self::Error>>()
+            .map(BinaryBundle::new)
+    }
+
+    /// Binary subtraction, clamping the result to zero on underflow.
+    fn bin_saturating_sub(
+        &mut self,
+        xs: &BinaryBundle<Self::Item>,
+        ys: &BinaryBundle<Self::Item>,
+    ) -> Result<BinaryBundle<Self::Item>, Self::Error> {
+        // The carry of `bin_subtraction` indicates `ys != 0 && xs >= ys`, so there is
+        // a borrow exactly when it is unset and `ys != 0`.
+        let (zs, carry) = self.bin_subtraction(xs, ys)?;
+        let ys_nonzero = self.or_many(ys.wires())?;
+        let no_carry = self.negate(&carry)?;
+        let borrow = self.and(&no_carry, &ys_nonzero)?;
+        let no_borrow = self.negate(&borrow)?;
+        zs.wires()
+            .iter()
+            .map(|z| self.and(z, &no_borrow))
+            .collect::<Result<Vec<Self::Item>, Self::Error>>()
+            .map(BinaryBundle::new)
+    }
+
     /// If `x=0` return `c1` as a bundle of constant bits, else return `c2`.
     fn bin_multiplex_constant_bits(
         &mut self,
@@ -332,6 +625,30 @@ pub trait BinaryGadgets: FancyBinary + BundleGadgets {
             .map(BinaryBundle::new)
     }
 
+    /// Conditional assignment gadget for binary bundles, setting `target` to `src`
+    /// if `cond=1` and leaving it unchanged otherwise.
+    ///
+    /// This is `bin_multiplex(cond, target, src)` updating `target` in place, e.g. for
+    /// the state of an accumulator. Both bundles must have the same size, otherwise
+    /// `FancyError::InvalidArgNum` is returned.
+    fn bin_cassign(
+        &mut self,
+        target: &mut BinaryBundle<Self::Item>,
+        src: &BinaryBundle<Self::Item>,
+        cond: &Self::Item,
+    ) -> Result<(), Self::Error> {
+        if target.size() != src.size() {
+            return Err(Self::Error::from(FancyError::InvalidArgNum {
+                got: src.size(),
+                needed: target.size(),
+            }));
+        }
+        for (twire, swire) in target.wires_mut().iter_mut().zip(src.wires().iter()) {
+            *twire = self.mux(cond, twire, swire)?;
+        }
+        Ok(())
+    }
+
     /// Write the constant in binary and that gives you the shift amounts, Eg.. 7x is 4x+2x+x.
     fn bin_cmul(
         &mut self,
@@ -430,10 +747,119 @@ pub trait BinaryGadgets: FancyBinary + BundleGadgets {
         self.negate(&z)
     }
 
+    /// Returns 1 if `lo <= x <= hi`, for constants `lo` and `hi` which fit in
+    /// the width of `x`.
+    ///
+    /// Fails with `FancyError::InvalidArg` if `lo > hi`, or if `hi` does not fit
+    /// in the width of `x`.
+    fn bin_in_range(
+        &mut self,
+        x: &BinaryBundle<Self::Item>,
+        lo: u128,
+        hi: u128,
+    ) -> Result<Self::Item, Self::Error> {
+        let n = x.size();
+        if lo > hi {
+            return Err(Self::Error::from(FancyError::InvalidArg(format!(
+                "the range [{}, {}] is empty",
+                lo, hi
+            ))));
+        }
+        if n < 128 && hi >> n != 0 {
+            return Err(Self::Error::from(FancyError::InvalidArg(format!(
+                "the bound {} does not fit in a {}-bit bundle",
+                hi, n
+            ))));
+        }
+        let lo = self.bin_constant_bundle(lo, x.size())?;
+        let hi = self.bin_constant_bundle(hi, x.size())?;
+        let geq_lo = self.bin_geq(x, &lo)?;
+        let gt_hi = self.bin_lt(&hi, x)?;
+        let leq_hi = self.negate(&gt_hi)?;
+        self.and(&geq_lo, &leq_hi)
+    }
+
+    /// Reduce `items` to a single bundle with the associative operation `f`.
+    ///
+    /// The items are combined pairwise in a balanced tree, so that the result has
+    /// depth logarithmic in `items.len()` rather than linear as with a fold.
+    fn bin_reduce(
+        &mut self,
+        items: &[BinaryBundle<Self::Item>],
+        f: impl Fn(
+            &mut Self,
+            &BinaryBundle<Self::Item>,
+            &BinaryBundle<Self::Item>,
+        ) -> Result<BinaryBundle<Self::Item>, Self::Error>,
+    ) -> Result<BinaryBundle<Self::Item>, Self::Error> {
+        if items.is_empty() {
+            return Err(Self::Error::from(FancyError::InvalidArgNum {
+                got: items.len(),
+                needed: 1,
+            }));
+        }
+        let mut level = items.to_vec();
+        while level.len() > 1 {
+            level = level
+                .chunks(2)
+                .map(|pair| match pair {
+                    [x, y] => f(self, x, y),
+                    // An odd item out is carried over to the next level
+                    [x] => Ok(x.clone()),
+                    _ => unreachable!(),
+                })
+                .collect::<Result<Vec<_>, Self::Error>>()?;
+        }
+        Ok(level.remove(0))
+    }
+
+    /// Compute `x mod m` for a public modulus `m`, e.g. to hash into a prime field.
+    ///
+    /// `x` must have at most 128 bits and `m` must be nonzero. The result has the
+    /// width of `x`, so it should be truncated by the caller if a narrower bundle
+    /// is needed. The reduction subtracts `m * 2^k` whenever it is at most the
+    /// remainder, for every `k` such that `m * 2^k` fits in the width of `x`,
+    /// which costs one subtraction and one multiplex per bit of `x` beyond the
+    /// bits of `m`.
+    fn bin_mod_constant(
+        &mut self,
+        x: &BinaryBundle<Self::Item>,
+        m: u128,
+    ) -> Result<BinaryBundle<Self::Item>, Self::Error> {
+        let n = x.size();
+        if m == 0 || n > 128 {
+            return Err(Self::Error::from(FancyError::InvalidArg(format!(
+                "cannot reduce a {}-bit bundle modulo {}",
+                n, m
+            ))));
+        }
+        let mbits = (u128::BITS - m.leading_zeros()) as usize;
+        let mut r = x.clone();
+        for k in (0..(n + 1).saturating_sub(mbits)).rev() {
+            let c = self.bin_constant_bundle(m << k, n)?;
+            // `c` is nonzero, so the carry indicates `r >= c`
+            let (diff, geq) = self.bin_subtraction(&r, &c)?;
+            self.bin_cassign(&mut r, &diff, &geq)?;
+        }
+        Ok(r)
+    }
+
     /// Compute the maximum bundle in `xs`.
     fn bin_max(
         &mut self,
         xs: &[BinaryBundle<Self::Item>],
+    ) -> Result<BinaryBundle<Self::Item>, Self::Error> {
+        self.bin_reduce(xs, |f, x, y| {
+            let pos = f.bin_lt(x, y)?;
+            f.bin_multiplex(&pos, x, y)
+        })
+    }
+
+    /// Compute the index of the maximum bundle in `xs`, as a bundle wide enough to
+    /// hold `xs.len() - 1` (and at least one bit). Ties break to the lowest index.
+    fn fancy_argmax(
+        &mut self,
+        xs: &[BinaryBundle<Self::Item>],
     ) -> Result<BinaryBundle<Self::Item>, Self::Error> {
         if xs.is_empty() {
             return Err(Self::Error::from(FancyError::InvalidArgNum {
@@ -441,22 +867,102 @@ pub trait BinaryGadgets: FancyBinary + BundleGadgets {
                 needed: 1,
             }));
         }
-        xs.iter().skip(1).fold(Ok(xs[0].clone()), |x, y| {
-            x.map(|x| {
-                let pos = self.bin_lt(&x, y)?;
-                let neg = self.negate(&pos)?;
-                x.wires()
-                    .iter()
-                    .zip(y.wires().iter())
-                    .map(|(x, y)| {
-                        let xp = self.and(x, &neg)?;
-                        let yp = self.and(y, &pos)?;
-                        self.xor(&xp, &yp)
-                    })
-                    .collect::<Result<Vec<Self::Item>, Self::Error>>()
-                    .map(BinaryBundle::new)
-            })?
-        })
+        let nbits = std::cmp::max(1, (usize::BITS - (xs.len() - 1).leading_zeros()) as usize);
+        let mut max = xs[0].clone();
+        let mut index = self.bin_constant_bundle(0, nbits)?;
+        for (i, x) in xs.iter().enumerate().skip(1) {
+            // Only replace the maximum by a strictly greater value, to keep the lowest index
+            let greater = self.bin_lt(&max, x)?;
+            let i = self.bin_constant_bundle(i as u128, nbits)?;
+            self.bin_cassign(&mut max, x, &greater)?;
+            self.bin_cassign(&mut index, &i, &greater)?;
+        }
+        Ok(index)
+    }
+
+    /// Look up an item in a garbled counting bloom filter.
+    ///
+    /// `counters` holds the filter's counters and `positions` holds the (secret) indices
+    /// of the counters selected by the item's hash functions. Each position is read
+    /// obliviously with a linear scan over `counters`, and the minimum of the selected
+    /// counters is returned, which is an upper bound on the item's multiplicity.
+    ///
+    /// The counters must all have the same size, and every position must be wide
+    /// enough to index all of them, otherwise `FancyError::InvalidArgNum` is returned.
+    fn bin_counting_bloom_lookup(
+        &mut self,
+        counters: &[BinaryBundle<Self::Item>],
+        positions: &[BinaryBundle<Self::Item>],
+    ) -> Result<BinaryBundle<Self::Item>, Self::Error> {
+        if counters.is_empty() || positions.is_empty() {
+            return Err(Self::Error::from(FancyError::InvalidArgNum {
+                got: std::cmp::min(counters.len(), positions.len()),
+                needed: 1,
+            }));
+        }
+        let nbits = counters[0].size();
+        if let Some(counter) = counters.iter().find(|counter| counter.size() != nbits) {
+            return Err(Self::Error::from(FancyError::InvalidArgNum {
+                got: counter.size(),
+                needed: nbits,
+            }));
+        }
+        // A position of `n` bits only indexes the first `2^n` counters
+        if let Some(position) = positions.iter().find(|position| {
+            position.size() < usize::BITS as usize && counters.len() > 1 << position.size()
+        }) {
+            return Err(Self::Error::from(FancyError::InvalidArgNum {
+                got: counters.len(),
+                needed: 1 << position.size(),
+            }));
+        }
+        let zero = self.bin_constant_bundle(0, nbits)?;
+        let mut selected = Vec::with_capacity(positions.len());
+        for position in positions.iter() {
+            let mut acc = zero.clone();
+            for (i, counter) in counters.iter().enumerate() {
+                let index = self.bin_constant_bundle(i as u128, position.size())?;
+                let hit = self.bin_eq_bundles(position, &index)?;
+                let mux = self.bin_multiplex(&hit, &zero, counter)?;
+                acc = self.bin_xor(&acc, &mux)?;
+            }
+            selected.push(acc);
+        }
+        selected
+            .iter()
+            .skip(1)
+            .fold(Ok(selected[0].clone()), |x, y| {
+                let x = x?;
+                let y_lt_x = self.bin_lt(y, &x)?;
+                self.bin_multiplex(&y_lt_x, &x, y)
+            })
+    }
+
+    /// Look up `query` in a dictionary of `keys` and their `values`.
+    ///
+    /// Returns the value whose key equals `query`, or `default` if there is none. Each
+    /// entry is read obliviously with an equality test gating a multiplex, so the cost
+    /// is linear in the size of the dictionary. If several keys match, the last one wins.
+    fn fancy_dict_lookup(
+        &mut self,
+        keys: &[BinaryBundle<Self::Item>],
+        values: &[BinaryBundle<Self::Item>],
+        query: &BinaryBundle<Self::Item>,
+        default: &BinaryBundle<Self::Item>,
+    ) -> Result<BinaryBundle<Self::Item>, Self::Error> {
+        if keys.len() != values.len() {
+            return Err(Self::Error::from(FancyError::InvalidArgNum {
+                got: values.len(),
+                needed: keys.len(),
+            }));
+        }
+        keys.iter()
+            .zip(values.iter())
+            .fold(Ok(default.clone()), |acc, (key, value)| {
+                let acc = acc?;
+                let hit = self.bin_eq_bundles(key, query)?;
+                self.bin_multiplex(&hit, &acc, value)
+            })
     }
 
     /// Demux a binary bundle into a unary vector.
@@ -529,6 +1035,163 @@ pub trait BinaryGadgets: FancyBinary + BundleGadgets {
 
         Ok(BinaryBundle::new(wires))
     }
+
+    /// Interleave the bits of `x` and `y` into `x0, y0, x1, y1, ...`.
+    ///
+    /// This only rearranges the wires, so it costs no gates. Returns
+    /// `FancyError::InvalidArgNum` if `x` and `y` do not have the same size.
+    fn bin_interleave(
+        &mut self,
+        x: &BinaryBundle<Self::Item>,
+        y: &BinaryBundle<Self::Item>,
+    ) -> Result<BinaryBundle<Self::Item>, Self::Error> {
+        if x.size() != y.size() {
+            return Err(Self::Error::from(FancyError::InvalidArgNum {
+                got: y.size(),
+                needed: x.size(),
+            }));
+        }
+        let wires = x
+            .wires()
+            .iter()
+            .zip(y.wires().iter())
+            .flat_map(|(x, y)| [x.clone(), y.clone()])
+            .collect();
+        Ok(BinaryBundle::new(wires))
+    }
+
+    /// Split the bits of `z` into its even and odd bits, reversing
+    /// `bin_interleave`.
+    ///
+    /// This only rearranges the wires, so it costs no gates. Returns
+    /// `FancyError::InvalidArgNum` if `z` has an odd size.
+    fn bin_deinterleave(
+        &mut self,
+        z: &BinaryBundle<Self::Item>,
+    ) -> Result<(BinaryBundle<Self::Item>, BinaryBundle<Self::Item>), Self::Error> {
+        if z.size() % 2 != 0 {
+            return Err(Self::Error::from(FancyError::InvalidArgNum {
+                got: z.size(),
+                needed: z.size() + 1,
+            }));
+        }
+        let (x, y) = z
+            .wires()
+            .chunks(2)
+            .map(|pair| (pair[0].clone(), pair[1].clone()))
+            .unzip();
+        Ok((BinaryBundle::new(x), BinaryBundle::new(y)))
+    }
+
+    /// Rotate `x` left by the secret amount `amount`, modulo the bitlength of `x`.
+    ///
+    /// This is a logarithmic barrel shifter: for each bit `j` of `amount`, `x` is
+    /// conditionally rotated by `2^j`. This costs one multiplexer per bit of `x` for
+    /// each bit of `amount`, i.e. `O(n log n)` multiplexers for an `n`-bit `x` and a
+    /// `log n`-bit `amount`.
+    fn bin_rotl_secret(
+        &mut self,
+        x: &BinaryBundle<Self::Item>,
+        amount: &BinaryBundle<Self::Item>,
+    ) -> Result<BinaryBundle<Self::Item>, Self::Error> {
+        let n = x.size();
+        let mut acc = x.clone();
+        if n == 0 {
+            return Ok(acc);
+        }
+        // The rotation by `2^j` modulo `n` for the current bit `j` of `amount`
+        let mut c = 1 % n;
+        for b in amount.wires().iter() {
+            if c != 0 {
+                let rotated = (0..n)
+                    .map(|i| acc.wires()[(i + n - c) % n].clone())
+                    .collect_vec();
+                self.bin_cassign(&mut acc, &BinaryBundle::new(rotated), b)?;
+            }
+            c = (2 * c) % n;
+        }
+        Ok(acc)
+    }
+
+    /// Compute the greatest common divisor of `a` and `b` with the binary (Stein's)
+    /// algorithm, where `gcd(0, b) = b`.
+    ///
+    /// Each iteration halves `a` or `b` if it is even, or subtracts the smaller one
+    /// from the larger one and halves the result if both are odd, counting the
+    /// factors of two they have in common. This shortens `a` or `b` by at least one
+    /// bit until one of them is zero, so for `n`-bit inputs the loop always runs
+    /// `2n` iterations, the later ones leaving the values unchanged: the number of
+    /// gates does not depend on the inputs. The result is the remaining nonzero
+    /// value shifted back by the common factors of two.
+    fn bin_gcd(
+        &mut self,
+        a: &BinaryBundle<Self::Item>,
+        b: &BinaryBundle<Self::Item>,
+    ) -> Result<BinaryBundle<Self::Item>, Self::Error> {
+        if a.size() != b.size() {
+            return Err(Self::Error::from(FancyError::InvalidArgNum {
+                got: b.size(),
+                needed: a.size(),
+            }));
+        }
+        let n = a.size();
+        let zero = self.constant(0, 2)?;
+        let halve = |x: &BinaryBundle<Self::Item>| {
+            BinaryBundle::new(
+                x.wires()
+                    .iter()
+                    .skip(1)
+                    .cloned()
+                    .chain(std::iter::once(zero.clone()))
+                    .collect_vec(),
+            )
+        };
+        // The common factors of two, at most `n`
+        let shift_bits = (usize::BITS - n.leading_zeros()) as usize;
+        let mut shift = self.bin_constant_bundle(0, shift_bits)?;
+
+        let (mut a, mut b) = (a.clone(), b.clone());
+        for _ in 0..2 * n {
+            let a_zero = self.bin_is_zero(&a)?;
+            let b_zero = self.bin_is_zero(&b)?;
+            let done = self.or(&a_zero, &b_zero)?;
+            let active = self.negate(&done)?;
+
+            let (a_odd, b_odd) = (a.wires()[0].clone(), b.wires()[0].clone());
+            let a_even = self.negate(&a_odd)?;
+            let b_even = self.negate(&b_odd)?;
+            let both_odd = self.and(&a_odd, &b_odd)?;
+            let a_lt_b = self.bin_lt(&a, &b)?;
+            let b_sub = self.and(&both_odd, &a_lt_b)?;
+            let a_sub = self.xor(&both_odd, &b_sub)?;
+
+            let (a_minus_b, _) = self.bin_subtraction(&a, &b)?;
+            let (b_minus_a, _) = self.bin_subtraction(&b, &a)?;
+            let a_next = self.bin_multiplex(&a_sub, &a, &a_minus_b)?;
+            let b_next = self.bin_multiplex(&b_sub, &b, &b_minus_a)?;
+            let halve_a = self.or(&a_even, &a_sub)?;
+            let halve_a = self.and(&halve_a, &active)?;
+            let halve_b = self.or(&b_even, &b_sub)?;
+            let halve_b = self.and(&halve_b, &active)?;
+            self.bin_cassign(&mut a, &halve(&a_next), &halve_a)?;
+            self.bin_cassign(&mut b, &halve(&b_next), &halve_b)?;
+
+            let both_even = self.and(&a_even, &b_even)?;
+            let both_even = self.and(&both_even, &active)?;
+            let mut inc = vec![zero.clone(); shift_bits];
+            inc[0] = both_even;
+            shift = self.bin_addition_no_carry(&shift, &BinaryBundle::new(inc))?;
+        }
+
+        // One of `a` and `b` is zero
+        let mut result = self.bin_or(&a, &b)?;
+        for (j, bit) in shift.wires().iter().enumerate() {
+            let shifted = self.shift(&result, 1 << j).map(BinaryBundle)?;
+            self.bin_cassign(&mut result, &shifted, bit)?;
+        }
+        Ok(result)
+    }
+
     /// Compute `x == y` for binary bundles.
     fn bin_eq_bundles(
         &mut self,
@@ -549,4 +1212,85 @@ pub trait BinaryGadgets: FancyBinary + BundleGadgets {
         // indicating equality
         self.and_many(&zs)
     }
+
+    /// Compute `x == 0` for a binary bundle, as the NOR of its bits.
+    fn bin_is_zero(&mut self, x: &BinaryBundle<Self::Item>) -> Result<Self::Item, Self::Error> {
+        let nonzero = self.bin_is_nonzero(x)?;
+        self.negate(&nonzero)
+    }
+
+    /// Compute `x != 0` for a binary bundle, as the OR of its bits.
+    fn bin_is_nonzero(&mut self, x: &BinaryBundle<Self::Item>) -> Result<Self::Item, Self::Error> {
+        self.or_many(x.wires())
+    }
+}
+
+impl<F: FancyBinary> BinaryGadgetsExt for F {}
+
+/// Extension trait for `Fancy` providing binary gadgets against public constants.
+///
+/// The constant is applied bit by bit, so that no constant bundle is built: XOR
+/// only negates the bits of `x` where `c` is set, while AND and OR either forward
+/// a bit of `x` or replace it by a constant wire. None of them uses an AND gate.
+/// The bits of `c` beyond the width of `x` are ignored.
+pub trait BinaryGadgetsExt: FancyBinary {
+    /// Compute `x ^ c` for a constant `c`.
+    fn bin_xor_constant(
+        &mut self,
+        x: &BinaryBundle<Self::Item>,
+        c: u128,
+    ) -> Result<BinaryBundle<Self::Item>, Self::Error> {
+        x.wires()
+            .iter()
+            .enumerate()
+            .map(|(i, x)| {
+                if i < 128 && (c >> i) & 1 == 1 {
+                    self.negate(x)
+                } else {
+                    Ok(x.clone())
+                }
+            })
+            .collect::<Result<Vec<Self::Item>, Self::Error>>()
+            .map(BinaryBundle::new)
+    }
+
+    /// Compute `x & c` for a constant `c`.
+    fn bin_and_constant(
+        &mut self,
+        x: &BinaryBundle<Self::Item>,
+        c: u128,
+    ) -> Result<BinaryBundle<Self::Item>, Self::Error> {
+        x.wires()
+            .iter()
+            .enumerate()
+            .map(|(i, x)| {
+                if i < 128 && (c >> i) & 1 == 1 {
+                    Ok(x.clone())
+                } else {
+                    self.constant(0, 2)
+                }
+            })
+            .collect::<Result<Vec<Self::Item>, Self::Error>>()
+            .map(BinaryBundle::new)
+    }
+
+    /// Compute `x | c` for a constant `c`.
+    fn bin_or_constant(
+        &mut self,
+        x: &BinaryBundle<Self::Item>,
+        c: u128,
+    ) -> Result<BinaryBundle<Self::Item>, Self::Error> {
+        x.wires()
+            .iter()
+            .enumerate()
+            .map(|(i, x)| {
+                if i < 128 && (c >> i) & 1 == 1 {
+                    self.constant(1, 2)
+                } else {
+                    Ok(x.clone())
+                }
+            })
+            .collect::<Result<Vec<Self::Item>, Self::Error>>()
+            .map(BinaryBundle::new)
+    }
 }
@@ -26,6 +26,11 @@ impl<W: Clone + HasModulus> Bundle<W> {
         &self.0
     }
 
+    /// Extract the wires from this bundle, to update them in place.
+    pub fn wires_mut(&mut self) -> &mut [W] {
+        &mut self.0
+    }
+
     /// Get the number of wires in this bundle.
     pub fn size(&self) -> usize {
         self.0.len()
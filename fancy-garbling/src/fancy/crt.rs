@@ -3,7 +3,10 @@
 use super::{bundle::ArithmeticBundleGadgets, HasModulus};
 use crate::{
     errors::FancyError,
-    fancy::bundle::{Bundle, BundleGadgets},
+    fancy::{
+        binary::{BinaryBundle, BinaryGadgets},
+        bundle::{Bundle, BundleGadgets},
+    },
     util, FancyArithmetic, FancyBinary,
 };
 use itertools::Itertools;
@@ -48,7 +51,7 @@ impl<F: FancyArithmetic + FancyBinary> CrtGadgets for F {}
 
 /// Extension trait for `Fancy` providing advanced CRT gadgets based on bundles of wires.
 pub trait CrtGadgets:
-    FancyArithmetic + FancyBinary + ArithmeticBundleGadgets + BundleGadgets
+    FancyArithmetic + FancyBinary + ArithmeticBundleGadgets + BinaryGadgets + BundleGadgets
 {
     /// Creates a bundle of constant wires for the CRT representation of `x` under
     /// composite modulus `q`.
@@ -324,6 +327,96 @@ pub trait CrtGadgets:
         })
     }
 
+    /// Convert the binary bundle `x` to a CRT bundle under composite modulus `q`.
+    ///
+    /// `q` must be a product of distinct supported primes (cf. `util::factor`).
+    /// The output holds `x mod q`, so the conversion is exact only if `q` exceeds
+    /// the largest value `x` can take, e.g. `q >= 2^n` for an `n`-bit bundle.
+    /// Each residue is a sum of projections of the bits of `x`, so no
+    /// multiplication is needed.
+    fn bin_to_crt(
+        &mut self,
+        x: &BinaryBundle<Self::Item>,
+        q: u128,
+    ) -> Result<CrtBundle<Self::Item>, Self::Error> {
+        util::factor(q)
+            .into_iter()
+            .map(|p| {
+                // The i-th bit contributes `2^i mod p` to the residue
+                let mut c = 1;
+                let mut z = self.constant(0, p)?;
+                for bit in x.wires() {
+                    let term = self.proj(bit, p, Some(vec![0, c]))?;
+                    z = self.add(&z, &term)?;
+                    c = ((c as u32 * 2) % p as u32) as u16;
+                }
+                Ok(z)
+            })
+            .collect::<Result<Vec<Self::Item>, Self::Error>>()
+            .map(CrtBundle::new)
+    }
+
+    /// Convert the CRT bundle `x` to a binary bundle of `nbits` bits.
+    ///
+    /// The composite modulus `q` of `x` must be below `2^127`, otherwise this fails
+    /// with `FancyError::InvalidArg`. The output holds `x`
+    /// reduced modulo `2^nbits`, so the conversion is exact only if `2^nbits >= q`.
+    /// Each residue is mapped to the binary representation of its term in the CRT
+    /// reconstruction with projections, and the terms are summed modulo `q` with
+    /// one binary addition and conditional subtraction per residue.
+    fn crt_to_bin(
+        &mut self,
+        x: &CrtBundle<Self::Item>,
+        nbits: usize,
+    ) -> Result<BinaryBundle<Self::Item>, Self::Error> {
+        let q = x
+            .moduli()
+            .iter()
+            .try_fold(1u128, |q, &p| q.checked_mul(p as u128))
+            .filter(|&q| q < 1 << 127)
+            .ok_or_else(|| {
+                Self::Error::from(FancyError::InvalidArg(
+                    "the composite modulus must be below 2^127!".to_string(),
+                ))
+            })?;
+        // The sum of two values below `q` must fit
+        let width = (129 - q.leading_zeros()) as usize;
+        let mut acc: Option<BinaryBundle<Self::Item>> = None;
+        for w in x.wires() {
+            let p = w.modulus();
+            // The CRT reconstruction of `x` is the sum of `v * (q / p) * k mod q`,
+            // `k` being the inverse of `q / p` mod `p`, for each residue `v`
+            let qp = q / p as u128;
+            let k = util::inv((qp % p as u128) as i128, p as i128) as u128;
+            let terms = (0..p as u128)
+                .map(|v| qp * (v * k % p as u128))
+                .collect::<Vec<u128>>();
+            let term = (0..width)
+                .map(|i| {
+                    let tab = terms.iter().map(|t| ((t >> i) & 1) as u16).collect();
+                    self.proj(w, 2, Some(tab))
+                })
+                .collect::<Result<Vec<Self::Item>, Self::Error>>()
+                .map(BinaryBundle::new)?;
+            acc = Some(match acc {
+                None => term,
+                Some(acc) => {
+                    let (sum, _) = self.bin_addition(&acc, &term)?;
+                    let qs = self.bin_constant_bundle(q, width)?;
+                    let geq = self.bin_geq(&sum, &qs)?;
+                    let (reduced, _) = self.bin_subtraction(&sum, &qs)?;
+                    self.bin_multiplex(&geq, &sum, &reduced)?
+                }
+            });
+        }
+        let mut zs = acc.map_or(vec![], |acc| acc.wires().to_vec());
+        zs.truncate(nbits);
+        while zs.len() < nbits {
+            zs.push(self.constant(0, 2)?);
+        }
+        Ok(BinaryBundle::new(zs))
+    }
+
     /// Convert the xs bundle to PMR representation. Useful for extracting out of CRT.
     fn crt_to_pmr(
         &mut self,
@@ -211,4 +211,25 @@ pub trait FancyInput {
             .collect_vec();
         Ok(buns)
     }
+
+    /// Encode an arbitrary-length byte string as a binary bundle of
+    /// `8 * bytes.len()` wires.
+    ///
+    /// Bits are ordered least significant first: bit `j` of `bytes[i]` is wire
+    /// `8 * i + j`. For up to 16 bytes, this is the bundle `bin_encode` produces
+    /// for `u128::from_le_bytes` of the zero-padded bytes.
+    fn encode_bytes(&mut self, bytes: &[u8]) -> Result<BinaryBundle<Self::Item>, Self::Error> {
+        let xs = bytes
+            .iter()
+            .flat_map(|byte| (0..8).map(move |j| u16::from((byte >> j) & 1)))
+            .collect_vec();
+        self.encode_many(&xs, &vec![2; xs.len()])
+            .map(BinaryBundle::new)
+    }
+
+    /// Receive a byte string of `nbytes` bytes encoded by `encode_bytes`.
+    fn receive_bytes(&mut self, nbytes: usize) -> Result<BinaryBundle<Self::Item>, Self::Error> {
+        self.receive_many(&vec![2; 8 * nbytes])
+            .map(BinaryBundle::new)
+    }
 }
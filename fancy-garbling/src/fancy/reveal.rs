@@ -1,5 +1,6 @@
 use super::*;
 use crate::util;
+use std::cmp::Ordering;
 
 /// Trait to describe Fancy objects which can reveal outputs to both parties. For many
 /// simple Fancy objects in this library such as Dummy, this is simply output. For Garbler
@@ -57,6 +58,36 @@ pub trait FancyReveal: Fancy {
         Ok(util::u128_from_bits(&bits))
     }
 
+    /// Reveal the bits of a binary bundle to all parties, least significant first.
+    ///
+    /// Unlike `reveal_bundle`, implementations may pack all the revealed bits into as
+    /// few channel messages as possible, which matters for wide bundles such as 512-bit
+    /// payloads. By default, this reveals each wire in turn.
+    fn bin_reveal_packed(&mut self, x: &BinaryBundle<Self::Item>) -> Result<Vec<u16>, Self::Error> {
+        self.reveal_many(x.wires())
+    }
+
+    /// Compare two binary bundles, only revealing their ordering to all parties.
+    fn fancy_cmp_reveal(
+        &mut self,
+        a: &BinaryBundle<Self::Item>,
+        b: &BinaryBundle<Self::Item>,
+    ) -> Result<Ordering, Self::Error>
+    where
+        Self: FancyBinary + Sized,
+    {
+        let lt = self.bin_lt(a, b)?;
+        let eq = self.bin_eq_bundles(a, b)?;
+        let (lt, eq) = (self.reveal(&lt)?, self.reveal(&eq)?);
+        Ok(if eq == 1 {
+            Ordering::Equal
+        } else if lt == 1 {
+            Ordering::Less
+        } else {
+            Ordering::Greater
+        })
+    }
+
     /// Reveal many binary bundles to all parties.
     fn bin_reveal_many(
         &mut self,
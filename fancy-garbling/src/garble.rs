@@ -284,11 +284,12 @@ mod streaming {
     use crate::{
         dummy::{Dummy, DummyVal},
         util::RngExt,
-        AllWire, Evaluator, FancyArithmetic, FancyInput, Garbler, WireLabel,
+        AllWire, BinaryBundle, Evaluator, Fancy, FancyArithmetic, FancyInput, FancyReveal, Garbler,
+        WireLabel, WireMod2,
     };
     use itertools::Itertools;
     use rand::thread_rng;
-    use scuttlebutt::{unix_channel_pair, AesRng, UnixChannel};
+    use scuttlebutt::{track_unix_channel_pair, unix_channel_pair, AesRng, UnixChannel};
 
     // helper - checks that Streaming evaluation of a fancy function equals Dummy
     // evaluation of the same function
@@ -355,6 +356,77 @@ mod streaming {
         }
     }
 
+    #[test]
+    fn output_labels() {
+        let mut rng = thread_rng();
+        for _ in 0..16 {
+            let q = rng.gen_modulus();
+            let x = rng.gen_u16() % q;
+            let y = rng.gen_u16() % q;
+            let (sender, receiver) = unix_channel_pair();
+            let (tx, rx) = std::sync::mpsc::channel();
+
+            std::thread::spawn(move || {
+                let mut gb = Garbler::<UnixChannel, AesRng, AllWire>::new(sender, AesRng::new());
+                let (gb_inp, ev_inp) = gb.encode_many_wires(&[x, y], &[q, q]).unwrap();
+                for w in ev_inp.iter() {
+                    gb.send_wire(w).unwrap();
+                }
+                let z = gb.add(&gb_inp[0], &gb_inp[1]).unwrap();
+                gb.outputs(&[z.clone()]).unwrap();
+                // Hand the output decoding information to the external verifier
+                tx.send((z, gb.delta(q))).unwrap();
+            });
+
+            let mut ev = Evaluator::<UnixChannel, AllWire>::new(receiver);
+            let ev_inp = [ev.read_wire(q).unwrap(), ev.read_wire(q).unwrap()];
+            let z = ev.add(&ev_inp[0], &ev_inp[1]).unwrap();
+            let labels = ev.output_labels(&[z]).unwrap();
+
+            let (zero, delta) = rx.recv().unwrap();
+            let expected = zero.plus(&delta.cmul((x + y) % q));
+            assert_eq!(labels, vec![expected.as_block()]);
+        }
+    }
+
+    #[test]
+    fn bin_reveal_packed() {
+        let nbits = 512;
+        let mut rng = thread_rng();
+        let bits = (0..nbits).map(|_| rng.gen_u16() % 2).collect_vec();
+        let (sender, receiver) = track_unix_channel_pair();
+
+        let bits_ = bits.clone();
+        let handle = std::thread::spawn(move || {
+            let mut gb = Garbler::<_, AesRng, WireMod2>::new(sender, AesRng::new());
+            let (gb_inp, ev_inp) = gb.encode_many_wires(&bits_, &vec![2; nbits]).unwrap();
+            for w in ev_inp.iter() {
+                gb.send_wire(w).unwrap();
+            }
+            let x = BinaryBundle::new(gb_inp);
+            (
+                gb.reveal_bundle(&x).unwrap(),
+                gb.bin_reveal_packed(&x).unwrap(),
+            )
+        });
+
+        let mut ev = Evaluator::<_, WireMod2>::new(receiver);
+        let x = BinaryBundle::new((0..nbits).map(|_| ev.read_wire(2).unwrap()).collect());
+        let written = ev.channel.bytes_written();
+        let naive = ev.reveal_bundle(&x).unwrap();
+        let naive_written = ev.channel.bytes_written() - written;
+        let packed = ev.bin_reveal_packed(&x).unwrap();
+        let packed_written = ev.channel.bytes_written() - written - naive_written;
+        let (gb_naive, gb_packed) = handle.join().unwrap();
+
+        assert_eq!(naive, bits);
+        assert_eq!(packed, bits);
+        assert_eq!((gb_naive, gb_packed), (naive, packed));
+        // One bit per wire instead of a `u16`
+        assert_eq!(packed_written, nbits / 8);
+        assert!(packed_written < naive_written);
+    }
+
     #[test]
     fn subtraction() {
         fn fancy_subtraction<F: FancyArithmetic>(b: &mut F, xs: &[F::Item]) -> Option<u16> {
@@ -3,7 +3,7 @@ use std::marker::PhantomData;
 use crate::{
     check_binary,
     errors::{EvaluatorError, FancyError},
-    fancy::{Fancy, FancyReveal},
+    fancy::{BinaryBundle, Fancy, FancyReveal},
     hash_wires,
     util::{output_tweak, tweak, tweak2},
     wire::WireLabel,
@@ -56,6 +56,21 @@ impl<C: AbstractChannel, Wire: WireLabel> Evaluator<C, Wire> {
         Ok(Wire::from_block(block, modulus))
     }
 
+    /// Output the wires `xs` and capture their labels instead of the decoded values.
+    ///
+    /// The output ciphertexts are consumed and checked as in `Fancy::output`, so this
+    /// stays in sync with a garbler calling `outputs`. An external verifier knowing
+    /// the garbler's zero label `Z` and offset `D` of an output wire can check that
+    /// its label is `Z + k * D` for the decoded value `k`.
+    pub fn output_labels(&mut self, xs: &[Wire]) -> Result<Vec<Block>, EvaluatorError> {
+        xs.iter()
+            .map(|x| {
+                self.output(x)?;
+                Ok(x.as_block())
+            })
+            .collect()
+    }
+
     /// Evaluates an 'and' gate given two inputs wires and two half-gates from the garbler.
     ///
     /// Outputs C = A & B
@@ -113,6 +128,21 @@ impl<C: AbstractChannel, Wire: WireLabel> FancyReveal for Evaluator<C, Wire> {
         self.channel.flush()?;
         Ok(val)
     }
+
+    fn bin_reveal_packed(&mut self, x: &BinaryBundle<Wire>) -> Result<Vec<u16>, EvaluatorError> {
+        let mut bits = Vec::with_capacity(x.size());
+        for w in x.wires() {
+            check_binary!(w);
+            bits.push(self.output(w)?.expect("Evaluator always outputs Some(u16)"));
+        }
+        let mut bytes = vec![0u8; bits.len().div_ceil(8)];
+        for (i, bit) in bits.iter().enumerate() {
+            bytes[i / 8] |= (*bit as u8) << (i % 8);
+        }
+        self.channel.write_bytes(&bytes)?;
+        self.channel.flush()?;
+        Ok(bits)
+    }
 }
 
 impl<C: AbstractChannel> FancyBinary for Evaluator<C, AllWire> {
@@ -203,6 +203,19 @@ impl<C: AbstractChannel, RNG: RngCore + CryptoRng, Wire: WireLabel> FancyReveal
         let val = self.channel.read_u16()?;
         Ok(val)
     }
+
+    fn bin_reveal_packed(&mut self, x: &BinaryBundle<Wire>) -> Result<Vec<u16>, GarblerError> {
+        for w in x.wires() {
+            check_binary!(w);
+            self.output(w)?;
+        }
+        self.channel.flush()?;
+        // The evaluator sends back all the bits at once, packed eight to a byte
+        let bytes = self.channel.read_vec(x.size().div_ceil(8))?;
+        Ok((0..x.size())
+            .map(|i| u16::from((bytes[i / 8] >> (i % 8)) & 1))
+            .collect())
+    }
 }
 
 impl<C: AbstractChannel, RNG: RngCore + CryptoRng> FancyBinary for Garbler<C, RNG, WireMod2> {
@@ -6,6 +6,7 @@
 
 pub mod circuit;
 pub mod classic;
+pub mod const_cache;
 pub mod depth_informer;
 pub mod dummy;
 pub mod errors;
@@ -13,6 +14,7 @@ mod fancy;
 mod garble;
 pub mod informer;
 mod parser;
+pub mod recording_dummy;
 pub mod twopac;
 pub mod util;
 mod wire;
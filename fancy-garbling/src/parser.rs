@@ -163,9 +163,11 @@ impl BinaryCircuit {
 mod tests {
     use crate::{
         circuit::{eval_plain, BinaryCircuit as Circuit},
-        classic::garble,
+        classic::{garble, garble_with_memory_budget},
+        util::RngExt,
         WireMod2,
     };
+    use rand::thread_rng;
 
     #[test]
     fn test_parser() {
@@ -210,4 +212,43 @@ mod tests {
         let ev = en.encode_evaluator_inputs(&vec![0u16; 128]);
         gc.eval(&circ, &gb, &ev).unwrap();
     }
+
+    #[test]
+    fn test_gc_eval_spilled() {
+        let mut rng = thread_rng();
+        let circ = Circuit::parse(std::io::Cursor::<&'static [u8]>::new(include_bytes!(
+            "../circuits/AES-non-expanded.txt"
+        )))
+        .unwrap();
+        let (en, gc) = garble::<WireMod2, _>(&circ).unwrap();
+        // Only keep 64 blocks in memory
+        let (en_, gc_) = garble_with_memory_budget::<WireMod2, _>(&circ, 64 * 16).unwrap();
+        assert_eq!(gc.num_spilled(), 0);
+        assert_eq!(gc_.size(), gc.size());
+        assert!(gc_.num_spilled() > gc_.size() - 64);
+        for _ in 0..4 {
+            let pt = (0..128)
+                .map(|_| rng.gen_bool() as u16)
+                .collect::<Vec<u16>>();
+            let key = (0..128)
+                .map(|_| rng.gen_bool() as u16)
+                .collect::<Vec<u16>>();
+            let output = gc
+                .eval(
+                    &circ,
+                    &en.encode_garbler_inputs(&pt),
+                    &en.encode_evaluator_inputs(&key),
+                )
+                .unwrap();
+            let output_ = gc_
+                .eval(
+                    &circ,
+                    &en_.encode_garbler_inputs(&pt),
+                    &en_.encode_evaluator_inputs(&key),
+                )
+                .unwrap();
+            assert_eq!(output_, output);
+            assert_eq!(output, eval_plain(&circ, &pt, &key).unwrap());
+        }
+    }
 }
@@ -0,0 +1,407 @@
+//! Dummy implementation of `Fancy` which records a trace of every wire value.
+//!
+//! Useful for debugging a computation producing a wrong answer: the traces of a
+//! correct and a faulty run can be diffed to find the first diverging wire.
+
+use crate::{
+    dummy::{Dummy, DummyVal},
+    errors::DummyError,
+    fancy::{Fancy, FancyInput, FancyReveal, HasModulus},
+    FancyArithmetic, FancyBinary,
+};
+use std::{
+    fmt::{self, Display, Formatter},
+    io::{self, BufRead, Write},
+    str::FromStr,
+};
+
+/// The operation which produced a wire.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TraceGate {
+    /// An encoded input.
+    Input,
+    /// A constant.
+    Constant,
+    /// An addition.
+    Add,
+    /// A subtraction.
+    Sub,
+    /// A multiplication by a constant.
+    Cmul,
+    /// A multiplication.
+    Mul,
+    /// A projection.
+    Proj,
+    /// A binary XOR.
+    Xor,
+    /// A binary AND.
+    And,
+    /// A binary negation.
+    Negate,
+}
+
+impl Display for TraceGate {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let s = match self {
+            TraceGate::Input => "input",
+            TraceGate::Constant => "constant",
+            TraceGate::Add => "add",
+            TraceGate::Sub => "sub",
+            TraceGate::Cmul => "cmul",
+            TraceGate::Mul => "mul",
+            TraceGate::Proj => "proj",
+            TraceGate::Xor => "xor",
+            TraceGate::And => "and",
+            TraceGate::Negate => "negate",
+        };
+        s.fmt(f)
+    }
+}
+
+impl FromStr for TraceGate {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "input" => Ok(TraceGate::Input),
+            "constant" => Ok(TraceGate::Constant),
+            "add" => Ok(TraceGate::Add),
+            "sub" => Ok(TraceGate::Sub),
+            "cmul" => Ok(TraceGate::Cmul),
+            "mul" => Ok(TraceGate::Mul),
+            "proj" => Ok(TraceGate::Proj),
+            "xor" => Ok(TraceGate::Xor),
+            "and" => Ok(TraceGate::And),
+            "negate" => Ok(TraceGate::Negate),
+            _ => Err(invalid_data(format!("unknown gate \"{}\"", s))),
+        }
+    }
+}
+
+/// The value of a single wire in a trace.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TraceEntry {
+    /// The operation which produced the wire.
+    pub gate: TraceGate,
+    /// The value of the wire.
+    pub val: u16,
+    /// The modulus of the wire.
+    pub modulus: u16,
+}
+
+impl Display for TraceEntry {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{} {} {}", self.gate, self.val, self.modulus)
+    }
+}
+
+/// The values of all wires produced by a computation, in the order they were
+/// produced.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct WireTrace {
+    entries: Vec<TraceEntry>,
+}
+
+/// A wire on which two traces disagree, as found by `WireTrace::diff`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TraceDiff {
+    /// The index of the wire in the traces.
+    pub index: usize,
+    /// The wire in the first trace, or `None` if that trace is shorter.
+    pub left: Option<TraceEntry>,
+    /// The wire in the second trace, or `None` if that trace is shorter.
+    pub right: Option<TraceEntry>,
+}
+
+impl Display for TraceDiff {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let show = |entry: &Option<TraceEntry>| match entry {
+            Some(entry) => entry.to_string(),
+            None => "<missing>".to_string(),
+        };
+        write!(
+            f,
+            "wire {}: {} != {}",
+            self.index,
+            show(&self.left),
+            show(&self.right)
+        )
+    }
+}
+
+impl WireTrace {
+    /// Get the recorded wires.
+    pub fn entries(&self) -> &[TraceEntry] {
+        &self.entries
+    }
+
+    /// Get the number of recorded wires.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check whether no wire was recorded.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Compare two traces wire by wire, returning every wire on which they
+    /// disagree.
+    pub fn diff(&self, other: &WireTrace) -> Vec<TraceDiff> {
+        (0..self.len().max(other.len()))
+            .map(|index| TraceDiff {
+                index,
+                left: self.entries.get(index).copied(),
+                right: other.entries.get(index).copied(),
+            })
+            .filter(|d| d.left != d.right)
+            .collect()
+    }
+
+    /// Get the first wire on which two traces disagree, if any.
+    pub fn first_divergence(&self, other: &WireTrace) -> Option<TraceDiff> {
+        self.diff(other).into_iter().next()
+    }
+
+    /// Write the trace with one wire per line, as `<gate> <val> <modulus>`.
+    pub fn write_to(&self, mut writer: impl Write) -> io::Result<()> {
+        for entry in self.entries.iter() {
+            writeln!(writer, "{}", entry)?;
+        }
+        Ok(())
+    }
+
+    /// Read a trace written by `write_to`.
+    pub fn read_from(reader: impl BufRead) -> io::Result<WireTrace> {
+        let mut entries = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            let fields = line.split_whitespace().collect::<Vec<&str>>();
+            if fields.len() != 3 {
+                return Err(invalid_data(format!("malformed trace line \"{}\"", line)));
+            }
+            let parse = |s: &str| {
+                s.parse::<u16>()
+                    .map_err(|e| invalid_data(format!("invalid number \"{}\": {}", s, e)))
+            };
+            entries.push(TraceEntry {
+                gate: fields[0].parse()?,
+                val: parse(fields[1])?,
+                modulus: parse(fields[2])?,
+            });
+        }
+        Ok(WireTrace { entries })
+    }
+}
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+/// Performs the fancy computation over `u16` like `Dummy`, recording the value
+/// of every wire produced along the way.
+pub struct RecordingDummy {
+    dummy: Dummy,
+    trace: WireTrace,
+}
+
+impl RecordingDummy {
+    /// Create a new RecordingDummy with an empty trace.
+    pub fn new() -> RecordingDummy {
+        RecordingDummy {
+            dummy: Dummy::new(),
+            trace: WireTrace::default(),
+        }
+    }
+
+    /// Get the trace recorded so far.
+    pub fn trace(&self) -> &WireTrace {
+        &self.trace
+    }
+
+    /// Consume the RecordingDummy, returning its trace.
+    pub fn into_trace(self) -> WireTrace {
+        self.trace
+    }
+
+    fn record(&mut self, gate: TraceGate, x: DummyVal) -> DummyVal {
+        self.trace.entries.push(TraceEntry {
+            gate,
+            val: x.val(),
+            modulus: x.modulus(),
+        });
+        x
+    }
+}
+
+impl Default for RecordingDummy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FancyInput for RecordingDummy {
+    type Item = DummyVal;
+    type Error = DummyError;
+
+    fn encode_many(&mut self, xs: &[u16], moduli: &[u16]) -> Result<Vec<DummyVal>, DummyError> {
+        let xs = self.dummy.encode_many(xs, moduli)?;
+        Ok(xs
+            .into_iter()
+            .map(|x| self.record(TraceGate::Input, x))
+            .collect())
+    }
+
+    fn receive_many(&mut self, moduli: &[u16]) -> Result<Vec<DummyVal>, DummyError> {
+        self.dummy.receive_many(moduli)
+    }
+}
+
+impl FancyBinary for RecordingDummy {
+    fn xor(&mut self, x: &DummyVal, y: &DummyVal) -> Result<DummyVal, Self::Error> {
+        let z = self.dummy.xor(x, y)?;
+        Ok(self.record(TraceGate::Xor, z))
+    }
+
+    fn and(&mut self, x: &DummyVal, y: &DummyVal) -> Result<DummyVal, Self::Error> {
+        let z = self.dummy.and(x, y)?;
+        Ok(self.record(TraceGate::And, z))
+    }
+
+    fn negate(&mut self, x: &DummyVal) -> Result<DummyVal, Self::Error> {
+        let z = self.dummy.negate(x)?;
+        Ok(self.record(TraceGate::Negate, z))
+    }
+}
+
+impl FancyArithmetic for RecordingDummy {
+    fn add(&mut self, x: &DummyVal, y: &DummyVal) -> Result<DummyVal, Self::Error> {
+        let z = self.dummy.add(x, y)?;
+        Ok(self.record(TraceGate::Add, z))
+    }
+
+    fn sub(&mut self, x: &DummyVal, y: &DummyVal) -> Result<DummyVal, Self::Error> {
+        let z = self.dummy.sub(x, y)?;
+        Ok(self.record(TraceGate::Sub, z))
+    }
+
+    fn cmul(&mut self, x: &DummyVal, c: u16) -> Result<DummyVal, Self::Error> {
+        let z = self.dummy.cmul(x, c)?;
+        Ok(self.record(TraceGate::Cmul, z))
+    }
+
+    fn mul(&mut self, x: &DummyVal, y: &DummyVal) -> Result<DummyVal, Self::Error> {
+        let z = self.dummy.mul(x, y)?;
+        Ok(self.record(TraceGate::Mul, z))
+    }
+
+    fn proj(
+        &mut self,
+        x: &DummyVal,
+        modulus: u16,
+        tt: Option<Vec<u16>>,
+    ) -> Result<DummyVal, Self::Error> {
+        let z = self.dummy.proj(x, modulus, tt)?;
+        Ok(self.record(TraceGate::Proj, z))
+    }
+}
+
+impl Fancy for RecordingDummy {
+    type Item = DummyVal;
+    type Error = DummyError;
+
+    fn constant(&mut self, val: u16, modulus: u16) -> Result<DummyVal, Self::Error> {
+        let x = self.dummy.constant(val, modulus)?;
+        Ok(self.record(TraceGate::Constant, x))
+    }
+
+    fn output(&mut self, x: &DummyVal) -> Result<Option<u16>, Self::Error> {
+        self.dummy.output(x)
+    }
+}
+
+impl FancyReveal for RecordingDummy {
+    fn reveal(&mut self, x: &DummyVal) -> Result<u16, DummyError> {
+        self.dummy.reveal(x)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        fancy::{BinaryBundle, BinaryGadgets},
+        util::RngExt,
+    };
+    use rand::thread_rng;
+
+    const NBITS: usize = 16;
+
+    // Compute `x + y`, where the buggy version computes the first carry with
+    // an XOR instead of an AND
+    fn add(
+        f: &mut RecordingDummy,
+        x: &BinaryBundle<DummyVal>,
+        y: &BinaryBundle<DummyVal>,
+        buggy: bool,
+    ) -> BinaryBundle<DummyVal> {
+        let xs = x.wires();
+        let ys = y.wires();
+        let mut zs = vec![f.xor(&xs[0], &ys[0]).unwrap()];
+        let mut c = if buggy {
+            f.xor(&xs[0], &ys[0]).unwrap()
+        } else {
+            f.and(&xs[0], &ys[0]).unwrap()
+        };
+        for i in 1..xs.len() {
+            let (z, c_) = f.adder(&xs[i], &ys[i], Some(&c)).unwrap();
+            zs.push(z);
+            c = c_;
+        }
+        BinaryBundle::new(zs)
+    }
+
+    fn run(x: u128, y: u128, buggy: bool) -> (u128, WireTrace) {
+        let mut f = RecordingDummy::new();
+        let x = f.bin_encode(x, NBITS).unwrap();
+        let y = f.bin_encode(y, NBITS).unwrap();
+        let z = add(&mut f, &x, &y, buggy);
+        let out = f.bin_output(&z).unwrap().unwrap();
+        (out, f.into_trace())
+    }
+
+    #[test]
+    fn test_trace_diff_finds_bug() {
+        let mut rng = thread_rng();
+        let q = 1 << NBITS;
+        // Make sure the first carry is set, so that the bug is observable
+        let x = (rng.gen_u128() % q) | 1;
+        let y = (rng.gen_u128() % q) | 1;
+
+        let (out, trace) = run(x, y, false);
+        let (out_buggy, trace_buggy) = run(x, y, true);
+        assert_eq!(out, (x + y) % q);
+        assert_ne!(out_buggy, out, "The bug should change the output");
+
+        assert!(trace.diff(&trace).is_empty(), "A trace should equal itself");
+        let divergence = trace
+            .first_divergence(&trace_buggy)
+            .expect("The traces should diverge");
+        // The inputs and the first output bit are computed correctly
+        assert_eq!(divergence.index, 2 * NBITS + 1);
+        assert_eq!(divergence.left.unwrap().gate, TraceGate::And);
+        assert_eq!(divergence.right.unwrap().gate, TraceGate::Xor);
+    }
+
+    #[test]
+    fn test_trace_roundtrip() {
+        let mut rng = thread_rng();
+        let q = 1 << NBITS;
+        let (_, trace) = run(rng.gen_u128() % q, rng.gen_u128() % q, false);
+        let mut bytes = Vec::new();
+        trace.write_to(&mut bytes).unwrap();
+        let trace_ = WireTrace::read_from(bytes.as_slice()).unwrap();
+        assert_eq!(trace, trace_);
+        assert!(WireTrace::read_from("xor 1".as_bytes()).is_err());
+    }
+}
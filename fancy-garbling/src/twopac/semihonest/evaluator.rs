@@ -1,6 +1,8 @@
 use crate::{
-    errors::TwopacError, wire::WireLabel, AllWire, ArithmeticWire, Evaluator as Ev, Fancy,
-    FancyArithmetic, FancyBinary, FancyInput, FancyReveal, WireMod2,
+    errors::{EvaluatorError, TwopacError},
+    wire::WireLabel,
+    AllWire, ArithmeticWire, BinaryBundle, Evaluator as Ev, Fancy, FancyArithmetic, FancyBinary,
+    FancyInput, FancyReveal, WireMod2,
 };
 use ocelot::ot::Receiver as OtReceiver;
 use rand::{CryptoRng, Rng};
@@ -34,6 +36,36 @@ impl<
         &mut self.evaluator.channel
     }
 
+    /// Get a reference to the internal OT receiver.
+    pub fn ot_mut(&mut self) -> &mut OT {
+        &mut self.ot
+    }
+
+    /// Output the wires `xs` and capture their labels for external verification.
+    pub fn output_labels(&mut self, xs: &[Wire]) -> Result<Vec<Block>, TwopacError> {
+        self.evaluator.output_labels(xs).map_err(TwopacError::from)
+    }
+
+    /// Output the wires `xs` and decode their values, failing with
+    /// `TwopacError::OutputDecodingFailed` at the first output whose decoding
+    /// information does not match its label.
+    ///
+    /// Unlike `outputs`, this never leaves the caller to unwrap a missing value, so
+    /// that a cheating or buggy garbler can be handled gracefully. The parties are
+    /// out of sync after a failure and the protocol must be aborted.
+    pub fn decode_outputs(&mut self, xs: &[Wire]) -> Result<Vec<u16>, TwopacError> {
+        xs.iter()
+            .enumerate()
+            .map(|(i, x)| match self.evaluator.output(x) {
+                Ok(Some(value)) => Ok(value),
+                Ok(None) | Err(EvaluatorError::DecodingFailed) => {
+                    Err(TwopacError::OutputDecodingFailed(i))
+                }
+                Err(e) => Err(TwopacError::from(e)),
+            })
+            .collect()
+    }
+
     fn run_ot(&mut self, inputs: &[bool]) -> Result<Vec<Block>, TwopacError> {
         self.ot
             .receive(&mut self.evaluator.channel, inputs, &mut self.rng)
@@ -166,6 +198,12 @@ impl<C: AbstractChannel, RNG: CryptoRng + Rng, OT, Wire: WireLabel> FancyReveal
     fn reveal(&mut self, x: &Self::Item) -> Result<u16, Self::Error> {
         self.evaluator.reveal(x).map_err(Self::Error::from)
     }
+
+    fn bin_reveal_packed(&mut self, x: &BinaryBundle<Self::Item>) -> Result<Vec<u16>, Self::Error> {
+        self.evaluator
+            .bin_reveal_packed(x)
+            .map_err(Self::Error::from)
+    }
 }
 
 impl<C: AbstractChannel, RNG, OT, Wire> SemiHonest for Evaluator<C, RNG, OT, Wire> {}
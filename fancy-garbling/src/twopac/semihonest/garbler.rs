@@ -1,6 +1,6 @@
 use crate::{
-    errors::TwopacError, wire::WireLabel, AllWire, ArithmeticWire, Fancy, FancyArithmetic,
-    FancyBinary, FancyInput, FancyReveal, Garbler as Gb, WireMod2,
+    errors::TwopacError, wire::WireLabel, AllWire, ArithmeticWire, BinaryBundle, Fancy,
+    FancyArithmetic, FancyBinary, FancyInput, FancyReveal, Garbler as Gb, WireMod2,
 };
 use ocelot::ot::Sender as OtSender;
 use rand::{CryptoRng, Rng, SeedableRng};
@@ -46,6 +46,11 @@ impl<
         &mut self.garbler.channel
     }
 
+    /// Get a reference to the internal OT sender.
+    pub fn ot_mut(&mut self) -> &mut OT {
+        &mut self.ot
+    }
+
     fn _evaluator_input(&mut self, delta: &Wire, q: u16) -> (Wire, Vec<(Block, Block)>) {
         let len = f32::from(q).log(2.0).ceil() as u16;
         let mut wire = Wire::zero(q);
@@ -186,6 +191,10 @@ impl<C: AbstractChannel, RNG: CryptoRng + Rng, OT, Wire: WireLabel> FancyReveal
     fn reveal(&mut self, x: &Self::Item) -> Result<u16, Self::Error> {
         self.garbler.reveal(x).map_err(Self::Error::from)
     }
+
+    fn bin_reveal_packed(&mut self, x: &BinaryBundle<Self::Item>) -> Result<Vec<u16>, Self::Error> {
+        self.garbler.bin_reveal_packed(x).map_err(Self::Error::from)
+    }
 }
 
 impl<C, RNG, OT, Wire> SemiHonest for Garbler<C, RNG, OT, Wire> {}
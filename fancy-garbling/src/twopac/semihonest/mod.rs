@@ -2,23 +2,28 @@
 
 mod evaluator;
 mod garbler;
+mod party;
+mod pregarbled;
 
 pub use evaluator::Evaluator;
 pub use garbler::Garbler;
+pub use party::{Party, Role};
+pub use pregarbled::{PregarbledEvaluator, PregarbledGarbler};
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{
-        circuit::{eval_plain, BinaryCircuit, CircuitInfo, EvaluableCircuit},
+        circuit::{eval_plain, BinaryCircuit, CircuitBuilder, CircuitInfo, EvaluableCircuit},
         dummy::Dummy,
+        errors::TwopacError,
         util::RngExt,
-        AllWire, CrtBundle, CrtGadgets, FancyArithmetic, FancyBinary, FancyInput, WireLabel,
-        WireMod2,
+        AllWire, BinaryBundle, BinaryGadgets, CrtBundle, CrtGadgets, Fancy, FancyArithmetic,
+        FancyBinary, FancyInput, WireLabel, WireMod2,
     };
     use itertools::Itertools;
     use ocelot::ot::{ChouOrlandiReceiver, ChouOrlandiSender};
-    use scuttlebutt::{unix_channel_pair, AesRng, UnixChannel};
+    use scuttlebutt::{unix_channel_pair, AbstractChannel, AesRng, UnixChannel};
 
     fn addition<F: FancyArithmetic>(
         f: &mut F,
@@ -162,4 +167,181 @@ mod tests {
         .unwrap();
         test_circuit::<_, WireMod2>(circ);
     }
+
+    type PARTY<Wire> = Party<UnixChannel, AesRng, ChouOrlandiSender, ChouOrlandiReceiver, Wire>;
+
+    // Linear ORAM written once for both roles: the garbler owns the RAM and the
+    // evaluator owns the query. The size of the RAM is public, and both parties
+    // check that they agree on it.
+    fn run_oram(
+        role: Role,
+        channel: UnixChannel,
+        ram: &[u128],
+        ram_size: usize,
+        query: u128,
+    ) -> Result<Option<u128>, TwopacError> {
+        let nbits = 16;
+        let mut party = PARTY::<WireMod2>::new(role, channel, AesRng::new())?;
+        party.check_input_sizes(ram_size)?;
+        let ram = party.bin_input_many(Role::Garbler, ram, ram_size, nbits)?;
+        let index = party
+            .bin_input_many(Role::Evaluator, &[query], 1, nbits)?
+            .remove(0);
+
+        let zero = party.bin_constant_bundle(0, nbits)?;
+        let mut result: BinaryBundle<_> = zero.clone();
+        for (i, item) in ram.iter().enumerate() {
+            let current_index = party.bin_constant_bundle(i as u128, nbits)?;
+            let mux_bit = party.bin_eq_bundles(&index, &current_index)?;
+            let mux = party.bin_multiplex(&mux_bit, &zero, item)?;
+            result = party.bin_addition_no_carry(&result, &mux)?;
+        }
+        party.bin_output(&result)
+    }
+
+    #[test]
+    fn test_party_linear_oram() {
+        let mut rng = rand::thread_rng();
+        let ram = (0..16).map(|_| rng.gen_u16() as u128).collect_vec();
+        let query = rng.gen_usize() % ram.len();
+
+        let (sender, receiver) = unix_channel_pair();
+        let ram_ = ram.clone();
+        let handle =
+            std::thread::spawn(move || run_oram(Role::Garbler, sender, &ram_, ram_.len(), 0));
+        let result = run_oram(Role::Evaluator, receiver, &[], ram.len(), query as u128);
+        assert_eq!(handle.join().unwrap().unwrap(), None);
+        assert_eq!(result.unwrap(), Some(ram[query]));
+    }
+
+    #[test]
+    fn test_party_input_size_mismatch() {
+        let ram = (0..16).collect_vec();
+        let (sender, receiver) = unix_channel_pair();
+        let ram_ = ram.clone();
+        let handle =
+            std::thread::spawn(move || run_oram(Role::Garbler, sender, &ram_, ram_.len(), 0));
+        // The evaluator expects a smaller RAM, both parties fail before any input
+        let result = run_oram(Role::Evaluator, receiver, &[], ram.len() / 2, 0);
+        assert!(matches!(result, Err(TwopacError::ParameterMismatch(_))));
+        assert!(matches!(
+            handle.join().unwrap(),
+            Err(TwopacError::ParameterMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn test_decode_outputs_corrupted() {
+        let (sender, receiver) = unix_channel_pair();
+        let handle = std::thread::spawn(move || {
+            let mut gb = Garbler::<UnixChannel, AesRng, ChouOrlandiSender, WireMod2>::new(
+                sender,
+                AesRng::new(),
+            )
+            .unwrap();
+            let xs = gb.encode_many(&[1, 0], &[2, 2]).unwrap();
+            gb.output(&xs[0]).unwrap();
+            // Send random decoding information for the second output
+            let channel = gb.get_channel();
+            channel.write_block(&rand::random()).unwrap();
+            channel.write_block(&rand::random()).unwrap();
+            channel.flush().unwrap();
+        });
+        let mut ev = Evaluator::<UnixChannel, AesRng, ChouOrlandiReceiver, WireMod2>::new(
+            receiver,
+            AesRng::new(),
+        )
+        .unwrap();
+        let xs = ev.receive_many(&[2, 2]).unwrap();
+        let result = ev.decode_outputs(&xs);
+        assert!(matches!(result, Err(TwopacError::OutputDecodingFailed(1))));
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_encode_bytes() {
+        let mut rng = rand::thread_rng();
+        let bytes = (0..64).map(|_| rng.gen_u16() as u8).collect_vec();
+        let bytes_ = bytes.clone();
+        let (sender, receiver) = unix_channel_pair();
+        let handle = std::thread::spawn(move || {
+            let mut gb = Garbler::<UnixChannel, AesRng, ChouOrlandiSender, WireMod2>::new(
+                sender,
+                AesRng::new(),
+            )
+            .unwrap();
+            let x = gb.encode_bytes(&bytes_).unwrap();
+            gb.outputs(x.wires()).unwrap();
+        });
+        let mut ev = Evaluator::<UnixChannel, AesRng, ChouOrlandiReceiver, WireMod2>::new(
+            receiver,
+            AesRng::new(),
+        )
+        .unwrap();
+        let x = ev.receive_bytes(bytes.len()).unwrap();
+        let bits = ev.outputs(x.wires()).unwrap().unwrap();
+        let out = bits
+            .chunks(8)
+            .map(|bs| crate::util::u128_from_bits(bs) as u8)
+            .collect_vec();
+        assert_eq!(out, bytes);
+        handle.join().unwrap();
+    }
+
+    // Linear ORAM as a circuit, over a RAM of `ram_size` elements owned by the
+    // garbler and a query owned by the evaluator.
+    fn oram_circuit(ram_size: usize, nbits: usize) -> BinaryCircuit {
+        let mut b = CircuitBuilder::<BinaryCircuit>::new();
+        let ram = (0..ram_size)
+            .map(|_| b.bin_garbler_input(nbits))
+            .collect_vec();
+        let index = b.bin_evaluator_input(nbits);
+        let zero = b.bin_constant_bundle(0, nbits).unwrap();
+        let mut result = zero.clone();
+        for (i, item) in ram.iter().enumerate() {
+            let current_index = b.bin_constant_bundle(i as u128, nbits).unwrap();
+            let mux_bit = b.bin_eq_bundles(&index, &current_index).unwrap();
+            let mux = b.bin_multiplex(&mux_bit, &zero, item).unwrap();
+            result = b.bin_addition_no_carry(&result, &mux).unwrap();
+        }
+        b.bin_outputs(&[result]).unwrap();
+        b.finish()
+    }
+
+    #[test]
+    fn test_pregarbled_linear_oram() {
+        let mut rng = rand::thread_rng();
+        let nbits = 16;
+        let ram = (0..16).map(|_| rng.gen_u16() as u128).collect_vec();
+        let queries = (0..3).map(|_| rng.gen_usize() % ram.len()).collect_vec();
+        let circuit = oram_circuit(ram.len(), nbits);
+        let ram_bits = ram
+            .iter()
+            .flat_map(|x| crate::util::u128_to_bits(*x, nbits))
+            .collect_vec();
+
+        let (sender, receiver) = unix_channel_pair();
+        let circuit_ = circuit.clone();
+        let handle = std::thread::spawn(move || {
+            let mut gb =
+                PregarbledGarbler::<_, _, ChouOrlandiSender>::new(sender, AesRng::new(), circuit_)
+                    .unwrap();
+            // Garble ahead for two of the queries, the third one is garbled online
+            gb.garble_ahead(2).unwrap();
+            for _ in 0..3 {
+                gb.serve(&ram_bits).unwrap();
+            }
+            assert_eq!(gb.ngarbled(), 0);
+        });
+        let mut ev =
+            PregarbledEvaluator::<_, _, ChouOrlandiReceiver>::new(receiver, AesRng::new(), circuit)
+                .unwrap();
+        for query in queries {
+            let outputs = ev
+                .query(&crate::util::u128_to_bits(query as u128, nbits))
+                .unwrap();
+            assert_eq!(crate::util::u128_from_bits(&outputs), ram[query]);
+        }
+        handle.join().unwrap();
+    }
 }
@@ -0,0 +1,294 @@
+use super::{Evaluator, Garbler};
+use crate::{
+    errors::{FancyError, TwopacError},
+    util,
+    wire::WireLabel,
+    BinaryBundle, Fancy, FancyArithmetic, FancyBinary, FancyInput, FancyReveal,
+};
+use itertools::Itertools;
+use ocelot::ot::{Receiver as OtReceiver, Sender as OtSender};
+use rand::{CryptoRng, Rng, SeedableRng};
+use scuttlebutt::{AbstractChannel, Block, SemiHonest};
+
+/// The role played by a party in semi-honest two-party computation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Role {
+    /// The party garbling the circuit.
+    Garbler,
+    /// The party evaluating the garbled circuit.
+    Evaluator,
+}
+
+/// Semi-honest party which is either a `Garbler` or an `Evaluator`.
+///
+/// This allows writing a single function parameterized over a `Role` instead of one
+/// function per party: every `Fancy` operation is dispatched to the right backend.
+pub enum Party<C, RNG, OTS, OTR, Wire> {
+    /// A party acting as the garbler.
+    Garbler(Garbler<C, RNG, OTS, Wire>),
+    /// A party acting as the evaluator.
+    Evaluator(Evaluator<C, RNG, OTR, Wire>),
+}
+
+impl<
+        C: AbstractChannel,
+        RNG: CryptoRng + Rng + SeedableRng<Seed = Block>,
+        OTS: OtSender<Msg = Block> + SemiHonest,
+        OTR: OtReceiver<Msg = Block> + SemiHonest,
+        Wire: WireLabel,
+    > Party<C, RNG, OTS, OTR, Wire>
+{
+    /// Make a new `Party` playing the given `role`.
+    pub fn new(role: Role, channel: C, rng: RNG) -> Result<Self, TwopacError> {
+        match role {
+            Role::Garbler => Ok(Party::Garbler(Garbler::new(channel, rng)?)),
+            Role::Evaluator => Ok(Party::Evaluator(Evaluator::new(channel, rng)?)),
+        }
+    }
+
+    /// Get a reference to the internal channel.
+    pub fn get_channel(&mut self) -> &mut C {
+        match self {
+            Party::Garbler(gb) => gb.get_channel(),
+            Party::Evaluator(ev) => ev.get_channel(),
+        }
+    }
+
+    /// Exchange the number of inputs declared by each party, returning the one
+    /// declared by the other party.
+    ///
+    /// This should be called before inputting values whose number is only known
+    /// to one of the parties, so that the other party does not desync.
+    pub fn exchange_input_sizes(&mut self, ninputs: usize) -> Result<usize, TwopacError> {
+        let role = self.role();
+        let channel = self.get_channel();
+        // The garbler writes first, so the exchange does not rely on buffering
+        let theirs = match role {
+            Role::Garbler => {
+                channel.write_usize(ninputs)?;
+                channel.flush()?;
+                channel.read_usize()?
+            }
+            Role::Evaluator => {
+                let theirs = channel.read_usize()?;
+                channel.write_usize(ninputs)?;
+                channel.flush()?;
+                theirs
+            }
+        };
+        Ok(theirs)
+    }
+
+    /// Check that both parties declare the same number of inputs `ninputs`,
+    /// failing with `TwopacError::ParameterMismatch` on both sides otherwise.
+    pub fn check_input_sizes(&mut self, ninputs: usize) -> Result<(), TwopacError> {
+        let theirs = self.exchange_input_sizes(ninputs)?;
+        if theirs != ninputs {
+            return Err(TwopacError::ParameterMismatch(format!(
+                "the {:?} declared {} inputs, but the other party declared {}",
+                self.role(),
+                ninputs,
+                theirs
+            )));
+        }
+        Ok(())
+    }
+
+    /// Input values owned by the party playing `owner`.
+    ///
+    /// The owner encodes `values`, while the other party receives the corresponding
+    /// wires and ignores `values`.
+    pub fn input_many(
+        &mut self,
+        owner: Role,
+        values: &[u16],
+        moduli: &[u16],
+    ) -> Result<Vec<Wire>, TwopacError> {
+        if owner == self.role() {
+            self.encode_many(values, moduli)
+        } else {
+            self.receive_many(moduli)
+        }
+    }
+
+    /// Input `ninputs` binary values of `nbits` bits owned by the party playing `owner`.
+    ///
+    /// `values` is only read by the owner, in which case it must contain `ninputs` values.
+    pub fn bin_input_many(
+        &mut self,
+        owner: Role,
+        values: &[u128],
+        ninputs: usize,
+        nbits: usize,
+    ) -> Result<Vec<BinaryBundle<Wire>>, TwopacError> {
+        let xs = if owner == self.role() {
+            if values.len() != ninputs {
+                return Err(TwopacError::from(FancyError::InvalidArgNum {
+                    got: values.len(),
+                    needed: ninputs,
+                }));
+            }
+            values
+                .iter()
+                .flat_map(|x| util::u128_to_bits(*x, nbits))
+                .collect_vec()
+        } else {
+            Vec::new()
+        };
+        let mut wires = self.input_many(owner, &xs, &vec![2; ninputs * nbits])?;
+        Ok((0..ninputs)
+            .map(|_| BinaryBundle::new(wires.drain(0..nbits).collect_vec()))
+            .collect())
+    }
+}
+
+impl<C, RNG, OTS, OTR, Wire> Party<C, RNG, OTS, OTR, Wire> {
+    /// The role played by this party.
+    pub fn role(&self) -> Role {
+        match self {
+            Party::Garbler(_) => Role::Garbler,
+            Party::Evaluator(_) => Role::Evaluator,
+        }
+    }
+}
+
+impl<C, RNG, OTS, OTR, Wire> FancyInput for Party<C, RNG, OTS, OTR, Wire>
+where
+    Garbler<C, RNG, OTS, Wire>: FancyInput<Item = Wire, Error = TwopacError>,
+    Evaluator<C, RNG, OTR, Wire>: FancyInput<Item = Wire, Error = TwopacError>,
+    Wire: WireLabel,
+{
+    type Item = Wire;
+    type Error = TwopacError;
+
+    fn encode_many(&mut self, values: &[u16], moduli: &[u16]) -> Result<Vec<Wire>, TwopacError> {
+        match self {
+            Party::Garbler(gb) => gb.encode_many(values, moduli),
+            Party::Evaluator(ev) => ev.encode_many(values, moduli),
+        }
+    }
+
+    fn receive_many(&mut self, moduli: &[u16]) -> Result<Vec<Wire>, TwopacError> {
+        match self {
+            Party::Garbler(gb) => gb.receive_many(moduli),
+            Party::Evaluator(ev) => ev.receive_many(moduli),
+        }
+    }
+}
+
+impl<C, RNG, OTS, OTR, Wire> Fancy for Party<C, RNG, OTS, OTR, Wire>
+where
+    Garbler<C, RNG, OTS, Wire>: Fancy<Item = Wire, Error = TwopacError>,
+    Evaluator<C, RNG, OTR, Wire>: Fancy<Item = Wire, Error = TwopacError>,
+    Wire: WireLabel,
+{
+    type Item = Wire;
+    type Error = TwopacError;
+
+    fn constant(&mut self, x: u16, q: u16) -> Result<Wire, TwopacError> {
+        match self {
+            Party::Garbler(gb) => gb.constant(x, q),
+            Party::Evaluator(ev) => ev.constant(x, q),
+        }
+    }
+
+    fn output(&mut self, x: &Wire) -> Result<Option<u16>, TwopacError> {
+        match self {
+            Party::Garbler(gb) => gb.output(x),
+            Party::Evaluator(ev) => ev.output(x),
+        }
+    }
+}
+
+impl<C, RNG, OTS, OTR, Wire> FancyBinary for Party<C, RNG, OTS, OTR, Wire>
+where
+    Garbler<C, RNG, OTS, Wire>: FancyBinary + Fancy<Item = Wire, Error = TwopacError>,
+    Evaluator<C, RNG, OTR, Wire>: FancyBinary + Fancy<Item = Wire, Error = TwopacError>,
+    Wire: WireLabel,
+{
+    fn xor(&mut self, x: &Wire, y: &Wire) -> Result<Wire, TwopacError> {
+        match self {
+            Party::Garbler(gb) => gb.xor(x, y),
+            Party::Evaluator(ev) => ev.xor(x, y),
+        }
+    }
+
+    fn and(&mut self, x: &Wire, y: &Wire) -> Result<Wire, TwopacError> {
+        match self {
+            Party::Garbler(gb) => gb.and(x, y),
+            Party::Evaluator(ev) => ev.and(x, y),
+        }
+    }
+
+    fn negate(&mut self, x: &Wire) -> Result<Wire, TwopacError> {
+        match self {
+            Party::Garbler(gb) => gb.negate(x),
+            Party::Evaluator(ev) => ev.negate(x),
+        }
+    }
+}
+
+impl<C, RNG, OTS, OTR, Wire> FancyArithmetic for Party<C, RNG, OTS, OTR, Wire>
+where
+    Garbler<C, RNG, OTS, Wire>: FancyArithmetic + Fancy<Item = Wire, Error = TwopacError>,
+    Evaluator<C, RNG, OTR, Wire>: FancyArithmetic + Fancy<Item = Wire, Error = TwopacError>,
+    Wire: WireLabel,
+{
+    fn add(&mut self, x: &Wire, y: &Wire) -> Result<Wire, TwopacError> {
+        match self {
+            Party::Garbler(gb) => gb.add(x, y),
+            Party::Evaluator(ev) => ev.add(x, y),
+        }
+    }
+
+    fn sub(&mut self, x: &Wire, y: &Wire) -> Result<Wire, TwopacError> {
+        match self {
+            Party::Garbler(gb) => gb.sub(x, y),
+            Party::Evaluator(ev) => ev.sub(x, y),
+        }
+    }
+
+    fn cmul(&mut self, x: &Wire, c: u16) -> Result<Wire, TwopacError> {
+        match self {
+            Party::Garbler(gb) => gb.cmul(x, c),
+            Party::Evaluator(ev) => ev.cmul(x, c),
+        }
+    }
+
+    fn mul(&mut self, x: &Wire, y: &Wire) -> Result<Wire, TwopacError> {
+        match self {
+            Party::Garbler(gb) => gb.mul(x, y),
+            Party::Evaluator(ev) => ev.mul(x, y),
+        }
+    }
+
+    fn proj(&mut self, x: &Wire, q: u16, tt: Option<Vec<u16>>) -> Result<Wire, TwopacError> {
+        match self {
+            Party::Garbler(gb) => gb.proj(x, q, tt),
+            Party::Evaluator(ev) => ev.proj(x, q, tt),
+        }
+    }
+}
+
+impl<C, RNG, OTS, OTR, Wire> FancyReveal for Party<C, RNG, OTS, OTR, Wire>
+where
+    Garbler<C, RNG, OTS, Wire>: FancyReveal + Fancy<Item = Wire, Error = TwopacError>,
+    Evaluator<C, RNG, OTR, Wire>: FancyReveal + Fancy<Item = Wire, Error = TwopacError>,
+    Wire: WireLabel,
+{
+    fn reveal(&mut self, x: &Wire) -> Result<u16, TwopacError> {
+        match self {
+            Party::Garbler(gb) => gb.reveal(x),
+            Party::Evaluator(ev) => ev.reveal(x),
+        }
+    }
+
+    fn bin_reveal_packed(&mut self, x: &BinaryBundle<Wire>) -> Result<Vec<u16>, TwopacError> {
+        match self {
+            Party::Garbler(gb) => gb.bin_reveal_packed(x),
+            Party::Evaluator(ev) => ev.bin_reveal_packed(x),
+        }
+    }
+}
+
+impl<C, RNG, OTS, OTR, Wire> SemiHonest for Party<C, RNG, OTS, OTR, Wire> {}
@@ -0,0 +1,179 @@
+//! Semi-honest two-party computation of a fixed circuit on many queries, with
+//! the circuit garbled ahead of time.
+//!
+//! A garbled circuit must never be evaluated on more than one evaluator input:
+//! the evaluator would then hold both labels of any input wire whose value
+//! differs between the queries, whose XOR is the garbler's global offset
+//! `delta`. Knowing `delta`, the evaluator can compute both labels of every wire
+//! and decode all of the garbler's inputs. The same holds if the garbler's input
+//! labels are reused. The security model of free-XOR garbling therefore does not
+//! permit any input reuse.
+//!
+//! What can be saved is the latency of garbling: the `PregarbledGarbler` garbles
+//! fresh copies of the circuit offline with `garble_ahead`, e.g. while the
+//! server is idle, and each query then consumes one copy. Only the garbled tables
+//! are sent and the evaluator's input labels obtained by OT in the online phase.
+//! Each copy is garbled with fresh randomness and dropped once it has been
+//! served.
+
+use crate::{
+    circuit::{BinaryCircuit, CircuitType},
+    classic::{self, Encoder, GarbledCircuit},
+    errors::{FancyError, TwopacError},
+    WireLabel, WireMod2,
+};
+use ocelot::ot::{Receiver as OtReceiver, Sender as OtSender};
+use rand::{CryptoRng, Rng};
+use scuttlebutt::{AbstractChannel, Block, SemiHonest};
+use std::collections::VecDeque;
+
+/// A copy of the circuit garbled ahead of time, along with the labels needed
+/// to encode its inputs.
+type Garbling = (Encoder<WireMod2>, GarbledCircuit<WireMod2, BinaryCircuit>);
+
+/// Semi-honest garbler answering queries on a fixed binary circuit with copies of
+/// the circuit garbled ahead of time.
+pub struct PregarbledGarbler<C, RNG, OT> {
+    channel: C,
+    ot: OT,
+    rng: RNG,
+    circuit: BinaryCircuit,
+    garblings: VecDeque<Garbling>,
+}
+
+impl<C: AbstractChannel, RNG: CryptoRng + Rng, OT: OtSender<Msg = Block> + SemiHonest>
+    PregarbledGarbler<C, RNG, OT>
+{
+    /// Make a new `PregarbledGarbler` for `circuit`, whose inputs must all be
+    /// binary.
+    pub fn new(mut channel: C, mut rng: RNG, circuit: BinaryCircuit) -> Result<Self, TwopacError> {
+        check_binary_inputs(&circuit)?;
+        let ot = OT::init(&mut channel, &mut rng)?;
+        Ok(Self {
+            channel,
+            ot,
+            rng,
+            circuit,
+            garblings: VecDeque::new(),
+        })
+    }
+
+    /// Garble `n` fresh copies of the circuit, to be consumed by the next queries.
+    ///
+    /// This does not communicate with the evaluator.
+    pub fn garble_ahead(&mut self, n: usize) -> Result<(), TwopacError> {
+        for _ in 0..n {
+            self.garblings.push_back(classic::garble(&self.circuit)?);
+        }
+        Ok(())
+    }
+
+    /// The number of copies of the circuit garbled ahead of time and not yet
+    /// consumed.
+    pub fn ngarbled(&self) -> usize {
+        self.garblings.len()
+    }
+
+    /// Answer one query of the evaluator on the garbler's `inputs`.
+    ///
+    /// This consumes a copy of the circuit garbled ahead of time, or garbles one
+    /// if none is left. The garbled tables and the labels of `inputs` are sent to
+    /// the evaluator, which obtains the labels of its own inputs by OT.
+    pub fn serve(&mut self, inputs: &[u16]) -> Result<(), TwopacError> {
+        if inputs.len() != self.circuit.num_garbler_inputs() {
+            return Err(TwopacError::from(FancyError::InvalidArgNum {
+                got: inputs.len(),
+                needed: self.circuit.num_garbler_inputs(),
+            }));
+        }
+        let (encoder, garbled) = match self.garblings.pop_front() {
+            Some(garbling) => garbling,
+            None => classic::garble(&self.circuit)?,
+        };
+        let blocks = garbled.blocks()?;
+        self.channel.write_usize(blocks.len())?;
+        for block in blocks.iter() {
+            self.channel.write_block(block)?;
+        }
+        for wire in encoder.encode_garbler_inputs(inputs) {
+            self.channel.write_block(&wire.as_block())?;
+        }
+        let labels = (0..encoder.num_evaluator_inputs())
+            .map(|i| {
+                (
+                    encoder.encode_evaluator_input(0, i).as_block(),
+                    encoder.encode_evaluator_input(1, i).as_block(),
+                )
+            })
+            .collect::<Vec<(Block, Block)>>();
+        self.channel.flush()?;
+        self.ot.send(&mut self.channel, &labels, &mut self.rng)?;
+        Ok(())
+    }
+}
+
+/// Semi-honest evaluator querying a `PregarbledGarbler` on a fixed binary circuit.
+pub struct PregarbledEvaluator<C, RNG, OT> {
+    channel: C,
+    ot: OT,
+    rng: RNG,
+    circuit: BinaryCircuit,
+}
+
+impl<C: AbstractChannel, RNG: CryptoRng + Rng, OT: OtReceiver<Msg = Block> + SemiHonest>
+    PregarbledEvaluator<C, RNG, OT>
+{
+    /// Make a new `PregarbledEvaluator` for `circuit`, which must be the garbler's
+    /// circuit.
+    pub fn new(mut channel: C, mut rng: RNG, circuit: BinaryCircuit) -> Result<Self, TwopacError> {
+        check_binary_inputs(&circuit)?;
+        let ot = OT::init(&mut channel, &mut rng)?;
+        Ok(Self {
+            channel,
+            ot,
+            rng,
+            circuit,
+        })
+    }
+
+    /// Evaluate a fresh garbled copy of the circuit on the evaluator's `inputs`,
+    /// returning the outputs of the circuit.
+    pub fn query(&mut self, inputs: &[u16]) -> Result<Vec<u16>, TwopacError> {
+        if inputs.len() != self.circuit.num_evaluator_inputs() {
+            return Err(TwopacError::from(FancyError::InvalidArgNum {
+                got: inputs.len(),
+                needed: self.circuit.num_evaluator_inputs(),
+            }));
+        }
+        let nblocks = self.channel.read_usize()?;
+        let garbled =
+            GarbledCircuit::<WireMod2, BinaryCircuit>::new(self.channel.read_blocks(nblocks)?);
+        let garbler_inputs = (0..self.circuit.num_garbler_inputs())
+            .map(|_| Ok(WireMod2::from_block(self.channel.read_block()?, 2)))
+            .collect::<Result<Vec<WireMod2>, TwopacError>>()?;
+        let choices = inputs.iter().map(|x| *x != 0).collect::<Vec<bool>>();
+        let evaluator_inputs = self
+            .ot
+            .receive(&mut self.channel, &choices, &mut self.rng)?
+            .into_iter()
+            .map(|block| WireMod2::from_block(block, 2))
+            .collect::<Vec<WireMod2>>();
+        Ok(garbled.eval(&self.circuit, &garbler_inputs, &evaluator_inputs)?)
+    }
+}
+
+impl<C, RNG, OT> SemiHonest for PregarbledGarbler<C, RNG, OT> {}
+impl<C, RNG, OT> SemiHonest for PregarbledEvaluator<C, RNG, OT> {}
+
+// Check that all inputs of `circuit` are binary, which is assumed when encoding
+// them.
+fn check_binary_inputs(circuit: &BinaryCircuit) -> Result<(), TwopacError> {
+    let binary = (0..circuit.num_garbler_inputs()).all(|i| circuit.garbler_input_mod(i) == 2)
+        && (0..circuit.num_evaluator_inputs()).all(|i| circuit.evaluator_input_mod(i) == 2);
+    if !binary {
+        return Err(TwopacError::from(FancyError::InvalidArg(
+            "pregarbled circuits only support binary inputs".to_string(),
+        )));
+    }
+    Ok(())
+}
@@ -175,6 +175,37 @@ pub fn u128_from_bits(bs: &[u16]) -> u128 {
     x
 }
 
+/// Format the "bits" as u16, least significant first, as an unsigned decimal
+/// integer. Unlike `u128_from_bits`, this supports any number of bits, e.g. to
+/// print outputs wider than a u128 when debugging. Assumes each "bit" is 0 or 1.
+pub fn decimal_string_from_bits(bs: &[u16]) -> String {
+    // Little-endian limbs in base 10^9
+    const BASE: u64 = 1_000_000_000;
+    let mut limbs: Vec<u64> = vec![0];
+    for &b in bs.iter().rev() {
+        let mut carry = b as u64;
+        for limb in limbs.iter_mut() {
+            let y = *limb * 2 + carry;
+            *limb = y % BASE;
+            carry = y / BASE;
+        }
+        if carry > 0 {
+            limbs.push(carry);
+        }
+    }
+    let mut s = limbs.last().unwrap().to_string();
+    for limb in limbs.iter().rev().skip(1) {
+        s.push_str(&format!("{:09}", limb));
+    }
+    s
+}
+
+/// Convert the integer value of a fixed-point number with `precision` fractional bits
+/// (cf. `FixedBundle`) into a float.
+pub fn fixed_to_f64(x: u128, precision: usize) -> f64 {
+    x as f64 / 2f64.powi(precision as i32)
+}
+
 ////////////////////////////////////////////////////////////////////////////////
 // primes & crt
 
@@ -482,6 +513,37 @@ mod tests {
         }
     }
 
+    #[test]
+    fn decimal_string() {
+        let mut rng = thread_rng();
+        for _ in 0..128 {
+            let x = rng.gen_u128();
+            assert_eq!(
+                decimal_string_from_bits(&u128_to_bits(x, 128)),
+                x.to_string()
+            );
+            let x = x % 1000;
+            assert_eq!(
+                decimal_string_from_bits(&u128_to_bits(x, 10)),
+                x.to_string()
+            );
+        }
+        assert_eq!(decimal_string_from_bits(&[]), "0");
+        assert_eq!(decimal_string_from_bits(&[0; 200]), "0");
+        // 2^200 - 1
+        assert_eq!(
+            decimal_string_from_bits(&[1; 200]),
+            "1606938044258990275541962092341162602522202993782792835301375"
+        );
+        // 2^256
+        let mut bs = vec![0; 257];
+        bs[256] = 1;
+        assert_eq!(
+            decimal_string_from_bits(&bs),
+            "115792089237316195423570985008687907853269984665640564039457584007913129639936"
+        );
+    }
+
     #[test]
     fn base_q_conversion() {
         let mut rng = thread_rng();
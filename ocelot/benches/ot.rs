@@ -131,6 +131,50 @@ fn bench_ot(c: &mut Criterion) {
     });
 }
 
+fn _bench_parallel_ot(batches: &[Vec<(Block, Block)>], choices: &[Vec<bool>], nthreads: usize) {
+    let (sender, receiver) = UnixStream::pair().unwrap();
+    std::thread::scope(|s| {
+        s.spawn(|| {
+            let mut rng = AesRng::new();
+            let reader = BufReader::new(sender.try_clone().unwrap());
+            let writer = BufWriter::new(sender);
+            ot::parallel::ot_many_parallel_send::<ot::KosSender, _, _, _>(
+                reader, writer, batches, nthreads, &mut rng,
+            )
+            .unwrap();
+        });
+        let mut rng = AesRng::new();
+        let reader = BufReader::new(receiver.try_clone().unwrap());
+        let writer = BufWriter::new(receiver);
+        ot::parallel::ot_many_parallel_receive::<ot::KosReceiver, _, _, _>(
+            reader, writer, choices, nthreads, &mut rng,
+        )
+        .unwrap();
+    });
+}
+
+fn bench_parallel_otext(c: &mut Criterion) {
+    // The same OTs as `bench_otext`, split into independent batches
+    const NBATCHES: usize = 8;
+    let batches = (0..NBATCHES)
+        .map(|_| {
+            rand_block_vec(T / NBATCHES)
+                .into_iter()
+                .zip(rand_block_vec(T / NBATCHES))
+                .collect::<Vec<(Block, Block)>>()
+        })
+        .collect::<Vec<_>>();
+    let choices = (0..NBATCHES)
+        .map(|_| rand_bool_vec(T / NBATCHES))
+        .collect::<Vec<_>>();
+    for nthreads in [1, 4] {
+        c.bench_function(
+            &format!("parallel::KosOT ({} threads)", nthreads),
+            |bench| bench.iter(|| _bench_parallel_ot(&batches, &choices, nthreads)),
+        );
+    }
+}
+
 fn bench_otext(c: &mut Criterion) {
     c.bench_function("ot::AlszOT", move |bench| {
         let m0s = rand_block_vec(T);
@@ -181,7 +225,7 @@ fn bench_random_otext(c: &mut Criterion) {
 criterion_group! {
     name = ot;
     config = Criterion::default().warm_up_time(Duration::from_millis(100)).sample_size(10);
-    targets = bench_ot, bench_otext, bench_correlated_otext, bench_random_otext
+    targets = bench_ot, bench_otext, bench_parallel_otext, bench_correlated_otext, bench_random_otext
 }
 
 criterion_main!(ot);
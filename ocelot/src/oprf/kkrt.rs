@@ -15,7 +15,10 @@ use rand::{CryptoRng, Rng, RngCore, SeedableRng};
 use scuttlebutt::{
     cointoss, utils as scutils, AbstractChannel, AesRng, Block, Block512, SemiHonest,
 };
-use std::marker::PhantomData;
+use std::{
+    collections::{HashMap, HashSet},
+    marker::PhantomData,
+};
 
 /// KKRT oblivious PRF sender.
 pub struct Sender<OT: OtReceiver + SemiHonest> {
@@ -120,6 +123,7 @@ pub struct Receiver<OT: OtSender + SemiHonest> {
     _ot: PhantomData<OT>,
     code: PseudorandomCode,
     rngs: Vec<(AesRng, AesRng)>,
+    cache: Option<HashMap<Block, Block512>>,
 }
 
 impl<OT: OtSender<Msg = Block> + SemiHonest> ObliviousPrf for Receiver<OT> {
@@ -154,6 +158,7 @@ impl<OT: OtSender<Msg = Block> + SemiHonest> OprfReceiver for Receiver<OT> {
             _ot: PhantomData::<OT>,
             code,
             rngs,
+            cache: None,
         })
     }
 
@@ -163,6 +168,72 @@ impl<OT: OtSender<Msg = Block> + SemiHonest> OprfReceiver for Receiver<OT> {
         inputs: &[Self::Input],
         rng: &mut RNG,
     ) -> Result<Vec<Self::Output>, Error> {
+        if self.cache.is_none() {
+            return self.receive_uncached(channel, inputs, rng);
+        }
+        let misses = self.cache_misses(inputs);
+        if !misses.is_empty() {
+            let outputs = self.receive_uncached(channel, &misses, rng)?;
+            let cache = self.cache.as_mut().unwrap();
+            cache.extend(misses.into_iter().zip(outputs));
+        }
+        let cache = self.cache.as_ref().unwrap();
+        Ok(inputs.iter().map(|input| cache[input]).collect())
+    }
+}
+
+impl<OT: OtSender<Msg = Block> + SemiHonest> Receiver<OT> {
+    /// Enable caching the outputs of the OPRF, so that inputs which were already
+    /// queried are answered locally instead of being queried again.
+    ///
+    /// When caching is enabled, `receive` only queries the distinct inputs which
+    /// are not cached, in order, so the sender must `send` exactly
+    /// `num_cache_misses(inputs)` instances. Since every instance is evaluated under
+    /// its own seed, the sender must also keep the seeds the cached outputs were
+    /// computed under. The cache must be invalidated with `invalidate_cache` when
+    /// the sender rotates its key.
+    ///
+    /// Cached outputs must be protected like any other OPRF output: anyone learning
+    /// them can link them to the sender's evaluations, and the cache itself reveals
+    /// which inputs were queried.
+    pub fn enable_cache(&mut self) {
+        if self.cache.is_none() {
+            self.cache = Some(HashMap::new());
+        }
+    }
+
+    /// Drop all the cached outputs, e.g. when the sender rotated its key.
+    pub fn invalidate_cache(&mut self) {
+        if let Some(cache) = self.cache.as_mut() {
+            cache.clear();
+        }
+    }
+
+    /// The number of OPRF instances `receive` runs on `inputs`.
+    pub fn num_cache_misses(&self, inputs: &[Block]) -> usize {
+        match self.cache {
+            Some(_) => self.cache_misses(inputs).len(),
+            None => inputs.len(),
+        }
+    }
+
+    // The distinct inputs which are not cached, in order.
+    fn cache_misses(&self, inputs: &[Block]) -> Vec<Block> {
+        let cache = self.cache.as_ref().unwrap();
+        let mut seen = HashSet::new();
+        inputs
+            .iter()
+            .filter(|input| !cache.contains_key(input) && seen.insert(**input))
+            .copied()
+            .collect()
+    }
+
+    fn receive_uncached<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        inputs: &[Block],
+        rng: &mut RNG,
+    ) -> Result<Vec<Block512>, Error> {
         let m = inputs.len();
         // Round up if necessary so that `m mod 16 ≡ 0`.
         let nrows = if m % 16 != 0 { m + (16 - m % 16) } else { m };
@@ -209,7 +280,7 @@ impl<OT: OtSender<Msg = Block> + SemiHonest> SemiHonest for Receiver<OT> {}
 mod tests {
     use super::*;
     use crate::oprf;
-    use scuttlebutt::{AesRng, Channel};
+    use scuttlebutt::{assert_comm_within, track_unix_channel_pair, AesRng, Channel};
     use std::{
         io::{BufReader, BufWriter},
         os::unix::net::UnixStream,
@@ -269,4 +340,43 @@ mod tests {
         _test_oprf(11);
         _test_oprf(64);
     }
+
+    #[test]
+    fn test_oprf_cache() {
+        let n = 16;
+        let selections = rand_block_vec(n);
+        let selections_ = selections.clone();
+        let (mut sender, receiver) = track_unix_channel_pair();
+        let handle = std::thread::spawn(move || {
+            let mut rng = AesRng::new();
+            let mut oprf = oprf::KkrtSender::init(&mut sender, &mut rng).unwrap();
+            let seeds = oprf.send(&mut sender, n, &mut rng).unwrap();
+            selections_
+                .iter()
+                .zip(seeds.into_iter())
+                .map(|(inp, seed)| oprf.compute(seed, *inp))
+                .collect::<Vec<Block512>>()
+        });
+        let mut rng = AesRng::new();
+        let mut channel = receiver;
+        let mut oprf = oprf::KkrtReceiver::init(&mut channel, &mut rng).unwrap();
+        oprf.enable_cache();
+        assert_eq!(oprf.num_cache_misses(&selections), n);
+        let outputs = oprf.receive(&mut channel, &selections, &mut rng).unwrap();
+        let results = handle.join().unwrap();
+        assert_eq!(outputs, results);
+
+        // All the queries hit the cache, so nothing is sent or received
+        assert_eq!(oprf.num_cache_misses(&selections), 0);
+        let mut selections_rev = selections.clone();
+        selections_rev.reverse();
+        let outputs_rev = assert_comm_within!(channel, 0, 0, {
+            oprf.receive(&mut channel, &selections_rev, &mut rng)
+                .unwrap()
+        });
+        assert!(outputs_rev.into_iter().eq(outputs.into_iter().rev()));
+
+        oprf.invalidate_cache();
+        assert_eq!(oprf.num_cache_misses(&selections), n);
+    }
 }
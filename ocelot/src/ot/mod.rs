@@ -9,6 +9,9 @@
 //! * `alsz`: Asharov-Lindell-Schneider-Zohner semi-honest OT extension (+ correlated and random OT).
 //! * `kos`: Keller-Orsini-Scholl malicious OT extension (+ correlated and random OT).
 //!
+//! The `parallel` module runs independent batches of OTs across threads, and
+//! the `precomputed` module consumes random OTs generated in an offline phase.
+//!
 
 pub mod alsz;
 pub mod chou_orlandi;
@@ -17,6 +20,8 @@ pub mod explicit_round;
 pub mod kos;
 pub mod kos_delta;
 pub mod naor_pinkas;
+pub mod parallel;
+pub mod precomputed;
 
 use crate::errors::Error;
 use curve25519_dalek::RistrettoPoint;
@@ -0,0 +1,175 @@
+//! Running independent batches of oblivious transfers in parallel.
+//!
+//! Each batch runs its own instance of the OT protocol, from initialization
+//! onwards, on a dedicated sub-channel of a `MuxChannel` and with its own RNG
+//! forked from the caller's. The batches are spread across `nthreads` worker
+//! threads, worker `w` running batches `w`, `w + nthreads`, ... in order. Both
+//! parties must therefore use the same number of batches and threads.
+
+use crate::{
+    errors::Error,
+    ot::{Receiver as OtReceiver, Sender as OtSender},
+};
+use rand::{CryptoRng, Rng, SeedableRng};
+use scuttlebutt::{AesRng, Block, MuxChannel};
+use std::{
+    io::{Read, Write},
+    thread,
+};
+
+/// Run the sender side of one OT instance per batch in `batches`, using up to
+/// `nthreads` threads.
+pub fn ot_many_parallel_send<OT, R, W, RNG>(
+    reader: R,
+    writer: W,
+    batches: &[Vec<(OT::Msg, OT::Msg)>],
+    nthreads: usize,
+    rng: &mut RNG,
+) -> Result<(), Error>
+where
+    OT: OtSender,
+    OT::Msg: Sync,
+    R: Read + Send,
+    W: Write + Send,
+    RNG: CryptoRng + Rng,
+{
+    let mux = MuxChannel::new(reader, writer, batches.len());
+    let seeds = batches.iter().map(|_| rng.gen()).collect::<Vec<Block>>();
+    let nthreads = nthreads.max(1);
+    thread::scope(|s| {
+        let workers = (0..nthreads.min(batches.len()))
+            .map(|w| {
+                let (mux, seeds) = (&mux, &seeds);
+                s.spawn(move || -> Result<(), Error> {
+                    for i in (w..batches.len()).step_by(nthreads) {
+                        let mut channel = mux.subchannel(i);
+                        let mut rng = AesRng::from_seed(seeds[i]);
+                        let mut ot = OT::init(&mut channel, &mut rng)?;
+                        ot.send(&mut channel, &batches[i], &mut rng)?;
+                    }
+                    Ok(())
+                })
+            })
+            .collect::<Vec<_>>();
+        workers
+            .into_iter()
+            .try_for_each(|worker| worker.join().unwrap())
+    })
+}
+
+/// Run the receiver side of one OT instance per batch of choice bits in
+/// `batches`, using up to `nthreads` threads. Returns the received messages of
+/// each batch, in order.
+pub fn ot_many_parallel_receive<OT, R, W, RNG>(
+    reader: R,
+    writer: W,
+    batches: &[Vec<bool>],
+    nthreads: usize,
+    rng: &mut RNG,
+) -> Result<Vec<Vec<OT::Msg>>, Error>
+where
+    OT: OtReceiver,
+    OT::Msg: Send,
+    R: Read + Send,
+    W: Write + Send,
+    RNG: CryptoRng + Rng,
+{
+    let mux = MuxChannel::new(reader, writer, batches.len());
+    let seeds = batches.iter().map(|_| rng.gen()).collect::<Vec<Block>>();
+    let nthreads = nthreads.max(1);
+    let mut results = thread::scope(|s| {
+        let workers = (0..nthreads.min(batches.len()))
+            .map(|w| {
+                let (mux, seeds) = (&mux, &seeds);
+                s.spawn(move || -> Result<Vec<(usize, Vec<OT::Msg>)>, Error> {
+                    let mut results = Vec::new();
+                    for i in (w..batches.len()).step_by(nthreads) {
+                        let mut channel = mux.subchannel(i);
+                        let mut rng = AesRng::from_seed(seeds[i]);
+                        let mut ot = OT::init(&mut channel, &mut rng)?;
+                        results.push((i, ot.receive(&mut channel, &batches[i], &mut rng)?));
+                    }
+                    Ok(results)
+                })
+            })
+            .collect::<Vec<_>>();
+        workers
+            .into_iter()
+            .map(|worker| worker.join().unwrap())
+            .collect::<Result<Vec<_>, Error>>()
+    })?
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>();
+    results.sort_by_key(|(i, _)| *i);
+    Ok(results.into_iter().map(|(_, msgs)| msgs).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ot::{KosReceiver, KosSender};
+    use std::{
+        io::{BufReader, BufWriter},
+        os::unix::net::UnixStream,
+    };
+
+    const NBATCHES: usize = 8;
+    const BATCH_SIZE: usize = 1 << 12;
+
+    fn rand_batches(rng: &mut AesRng) -> (Vec<Vec<(Block, Block)>>, Vec<Vec<bool>>) {
+        let messages = (0..NBATCHES)
+            .map(|_| (0..BATCH_SIZE).map(|_| (rng.gen(), rng.gen())).collect())
+            .collect();
+        let choices = (0..NBATCHES)
+            .map(|_| (0..BATCH_SIZE).map(|_| rng.gen()).collect())
+            .collect();
+        (messages, choices)
+    }
+
+    // Run the batches on `nthreads` threads with fixed seeds, returning the
+    // receiver's output
+    fn run(
+        messages: &[Vec<(Block, Block)>],
+        choices: &[Vec<bool>],
+        nthreads: usize,
+    ) -> Vec<Vec<Block>> {
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        thread::scope(|s| {
+            s.spawn(|| {
+                let mut rng = AesRng::from_seed(Block::from(1));
+                let reader = BufReader::new(sender.try_clone().unwrap());
+                let writer = BufWriter::new(sender);
+                ot_many_parallel_send::<KosSender, _, _, _>(
+                    reader, writer, messages, nthreads, &mut rng,
+                )
+                .unwrap();
+            });
+            let mut rng = AesRng::from_seed(Block::from(2));
+            let reader = BufReader::new(receiver.try_clone().unwrap());
+            let writer = BufWriter::new(receiver);
+            ot_many_parallel_receive::<KosReceiver, _, _, _>(
+                reader, writer, choices, nthreads, &mut rng,
+            )
+            .unwrap()
+        })
+    }
+
+    #[test]
+    fn test_ot_many_parallel() {
+        let mut rng = AesRng::new();
+        let (messages, choices) = rand_batches(&mut rng);
+        let sequential = run(&messages, &choices, 1);
+        for (batch, (ms, bs)) in sequential.iter().zip(messages.iter().zip(choices.iter())) {
+            for (m, ((m0, m1), b)) in batch.iter().zip(ms.iter().zip(bs.iter())) {
+                assert_eq!(*m, if *b { *m1 } else { *m0 });
+            }
+        }
+        // With fixed seeds, the outputs do not depend on how the batches are
+        // spread across threads, including with fewer batches than threads
+        for nthreads in [2, 3, NBATCHES, 2 * NBATCHES] {
+            let parallel = run(&messages, &choices, nthreads);
+            assert_eq!(sequential, parallel, "nthreads={}", nthreads);
+        }
+    }
+}
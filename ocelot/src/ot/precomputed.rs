@@ -0,0 +1,262 @@
+//! Oblivious transfer from random OT correlations precomputed in an offline
+//! phase.
+//!
+//! Random OTs are generated ahead of time (e.g. with `alsz`), and consumed
+//! online using Beaver's derandomization: the receiver sends the XOR of its
+//! choice bit with its random choice bit, and the sender masks its messages
+//! with the random messages, swapped accordingly. This only needs one message
+//! per party and no public-key or OT extension work online.
+//!
+//! `PrecomputedOtSender` and `PrecomputedOtReceiver` wrap an OT protocol which
+//! is used once the precomputed correlations are exhausted. Both parties must
+//! hold the same number of correlations, which is the case when they were
+//! generated together.
+
+use crate::{
+    errors::Error,
+    ot::{RandomReceiver, RandomSender, Receiver as OtReceiver, Sender as OtSender},
+};
+use rand::{CryptoRng, Rng};
+use scuttlebutt::{AbstractChannel, Block, SemiHonest};
+use std::collections::VecDeque;
+
+/// Random OT correlations held by one of the parties.
+#[derive(Clone, Debug)]
+pub enum PrecomputedOts {
+    /// The sender's pairs of random messages.
+    Sender(Vec<(Block, Block)>),
+    /// The receiver's random choice bits and the messages it received.
+    Receiver(Vec<(bool, Block)>),
+}
+
+impl PrecomputedOts {
+    /// Generate `count` correlations as the sender, using the random OT `ot`.
+    pub fn generate_sender<OT, C, RNG>(
+        ot: &mut OT,
+        channel: &mut C,
+        count: usize,
+        rng: &mut RNG,
+    ) -> Result<Self, Error>
+    where
+        OT: RandomSender<Msg = Block>,
+        C: AbstractChannel,
+        RNG: CryptoRng + Rng,
+    {
+        Ok(PrecomputedOts::Sender(ot.send_random(channel, count, rng)?))
+    }
+
+    /// Generate `count` correlations as the receiver, using the random OT `ot`.
+    pub fn generate_receiver<OT, C, RNG>(
+        ot: &mut OT,
+        channel: &mut C,
+        count: usize,
+        rng: &mut RNG,
+    ) -> Result<Self, Error>
+    where
+        OT: RandomReceiver<Msg = Block>,
+        C: AbstractChannel,
+        RNG: CryptoRng + Rng,
+    {
+        let choices = (0..count).map(|_| rng.gen()).collect::<Vec<bool>>();
+        let msgs = ot.receive_random(channel, &choices, rng)?;
+        Ok(PrecomputedOts::Receiver(
+            choices.into_iter().zip(msgs).collect(),
+        ))
+    }
+
+    /// The number of correlations.
+    pub fn len(&self) -> usize {
+        match self {
+            PrecomputedOts::Sender(pairs) => pairs.len(),
+            PrecomputedOts::Receiver(msgs) => msgs.len(),
+        }
+    }
+
+    /// Return whether there are no correlations.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Oblivious transfer sender consuming precomputed correlations before
+/// falling back to `OT`.
+pub struct PrecomputedOtSender<OT> {
+    ot: OT,
+    correlations: VecDeque<(Block, Block)>,
+}
+
+impl<OT> PrecomputedOtSender<OT> {
+    /// Add sender correlations to be consumed by the next transfers.
+    pub fn extend(&mut self, precomputed: PrecomputedOts) -> Result<(), Error> {
+        match precomputed {
+            PrecomputedOts::Sender(pairs) => {
+                self.correlations.extend(pairs);
+                Ok(())
+            }
+            PrecomputedOts::Receiver(_) => Err(Error::Other(
+                "the OT sender was given receiver correlations".to_string(),
+            )),
+        }
+    }
+
+    /// The number of correlations left.
+    pub fn remaining(&self) -> usize {
+        self.correlations.len()
+    }
+}
+
+impl<OT: OtSender<Msg = Block>> OtSender for PrecomputedOtSender<OT> {
+    type Msg = Block;
+
+    fn init<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<Self, Error> {
+        let ot = OT::init(channel, rng)?;
+        Ok(Self {
+            ot,
+            correlations: VecDeque::new(),
+        })
+    }
+
+    fn send<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        inputs: &[(Block, Block)],
+        rng: &mut RNG,
+    ) -> Result<(), Error> {
+        let n = inputs.len().min(self.correlations.len());
+        if n > 0 {
+            let flips = (0..n)
+                .map(|_| channel.read_bool())
+                .collect::<Result<Vec<bool>, _>>()?;
+            for ((m0, m1), flip) in inputs[..n].iter().zip(flips) {
+                let (r0, r1) = self.correlations.pop_front().unwrap();
+                let (r0, r1) = if flip { (r1, r0) } else { (r0, r1) };
+                channel.write_block(&(*m0 ^ r0))?;
+                channel.write_block(&(*m1 ^ r1))?;
+            }
+            channel.flush()?;
+        }
+        if n < inputs.len() {
+            self.ot.send(channel, &inputs[n..], rng)?;
+        }
+        Ok(())
+    }
+}
+
+impl<OT: SemiHonest> SemiHonest for PrecomputedOtSender<OT> {}
+
+/// Oblivious transfer receiver consuming precomputed correlations before
+/// falling back to `OT`.
+pub struct PrecomputedOtReceiver<OT> {
+    ot: OT,
+    correlations: VecDeque<(bool, Block)>,
+}
+
+impl<OT> PrecomputedOtReceiver<OT> {
+    /// Add receiver correlations to be consumed by the next transfers.
+    pub fn extend(&mut self, precomputed: PrecomputedOts) -> Result<(), Error> {
+        match precomputed {
+            PrecomputedOts::Receiver(msgs) => {
+                self.correlations.extend(msgs);
+                Ok(())
+            }
+            PrecomputedOts::Sender(_) => Err(Error::Other(
+                "the OT receiver was given sender correlations".to_string(),
+            )),
+        }
+    }
+
+    /// The number of correlations left.
+    pub fn remaining(&self) -> usize {
+        self.correlations.len()
+    }
+}
+
+impl<OT: OtReceiver<Msg = Block>> OtReceiver for PrecomputedOtReceiver<OT> {
+    type Msg = Block;
+
+    fn init<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<Self, Error> {
+        let ot = OT::init(channel, rng)?;
+        Ok(Self {
+            ot,
+            correlations: VecDeque::new(),
+        })
+    }
+
+    fn receive<C: AbstractChannel, RNG: CryptoRng + Rng>(
+        &mut self,
+        channel: &mut C,
+        inputs: &[bool],
+        rng: &mut RNG,
+    ) -> Result<Vec<Block>, Error> {
+        let n = inputs.len().min(self.correlations.len());
+        let mut out = Vec::with_capacity(inputs.len());
+        if n > 0 {
+            let correlations = self.correlations.drain(..n).collect::<Vec<_>>();
+            for (b, (c, _)) in inputs[..n].iter().zip(correlations.iter()) {
+                channel.write_bool(b ^ c)?;
+            }
+            channel.flush()?;
+            for (b, (_, r)) in inputs[..n].iter().zip(correlations.iter()) {
+                let e0 = channel.read_block()?;
+                let e1 = channel.read_block()?;
+                out.push(if *b { e1 } else { e0 } ^ *r);
+            }
+        }
+        if n < inputs.len() {
+            out.extend(self.ot.receive(channel, &inputs[n..], rng)?);
+        }
+        Ok(out)
+    }
+}
+
+impl<OT: SemiHonest> SemiHonest for PrecomputedOtReceiver<OT> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ot::{AlszReceiver, AlszSender};
+    use scuttlebutt::{unix_channel_pair, AesRng};
+
+    const NOTS: usize = 1 << 10;
+
+    #[test]
+    fn test_precomputed_ot() {
+        let mut rng = AesRng::new();
+        let msgs = (0..NOTS)
+            .map(|_| (rng.gen(), rng.gen()))
+            .collect::<Vec<(Block, Block)>>();
+        let choices = (0..NOTS).map(|_| rng.gen()).collect::<Vec<bool>>();
+        let (mut sender, mut receiver) = unix_channel_pair();
+        let msgs_ = msgs.clone();
+        let handle = std::thread::spawn(move || {
+            let mut rng = AesRng::new();
+            // Only precompute half of the OTs, the other half falls back to ALSZ
+            let mut alsz = AlszSender::init(&mut sender, &mut rng).unwrap();
+            let precomputed =
+                PrecomputedOts::generate_sender(&mut alsz, &mut sender, NOTS / 2, &mut rng)
+                    .unwrap();
+            let mut ot = PrecomputedOtSender::<AlszSender>::init(&mut sender, &mut rng).unwrap();
+            ot.extend(precomputed).unwrap();
+            ot.send(&mut sender, &msgs_, &mut rng).unwrap();
+            assert_eq!(ot.remaining(), 0);
+        });
+        let mut alsz = AlszReceiver::init(&mut receiver, &mut rng).unwrap();
+        let precomputed =
+            PrecomputedOts::generate_receiver(&mut alsz, &mut receiver, NOTS / 2, &mut rng)
+                .unwrap();
+        let mut ot = PrecomputedOtReceiver::<AlszReceiver>::init(&mut receiver, &mut rng).unwrap();
+        assert!(ot.extend(PrecomputedOts::Sender(vec![])).is_err());
+        ot.extend(precomputed).unwrap();
+        let results = ot.receive(&mut receiver, &choices, &mut rng).unwrap();
+        handle.join().unwrap();
+        for ((m0, m1), (b, m)) in msgs.iter().zip(choices.iter().zip(results.iter())) {
+            assert_eq!(*m, if *b { *m1 } else { *m0 });
+        }
+    }
+}
@@ -25,6 +25,7 @@ pub fn psty_cardinality(set_a: &[Vec<u8>], set_b: &[Vec<u8>]) -> u128 {
             )
             .unwrap();
             gb_psi.gb.outputs(res.wires()).unwrap();
+            channel.close().unwrap();
         });
         let mut rng = AesRng::new();
         let mut channel = setup_channel(receiver);
@@ -42,6 +43,7 @@ pub fn psty_cardinality(set_a: &[Vec<u8>], set_b: &[Vec<u8>]) -> u128 {
             .outputs(res.wires())
             .unwrap()
             .expect("evaluator should produce outputs");
+        channel.close().unwrap();
         binary_to_u128(res_out)
     })
 }
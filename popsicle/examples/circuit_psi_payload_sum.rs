@@ -35,6 +35,7 @@ pub fn psty_payload_sum(
             )
             .unwrap();
             gb_psi.gb.outputs(res.wires()).unwrap();
+            channel.close().unwrap();
         });
         let mut rng = AesRng::new();
         let mut channel = setup_channel(receiver);
@@ -57,6 +58,7 @@ pub fn psty_payload_sum(
             .outputs(res.wires())
             .unwrap()
             .expect("evaluator should produce outputs");
+        channel.close().unwrap();
         binary_to_u128(res_out)
     })
 }
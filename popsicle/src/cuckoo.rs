@@ -33,14 +33,41 @@ pub(crate) struct CuckooHash {
     pub(crate) items: Vec<Option<CuckooItem>>,
     pub(crate) nbins: usize,
     pub(crate) nhashes: usize,
+    // The items which could not be placed in a bin.
+    pub(crate) stash: Vec<CuckooItem>,
+    max_evictions: usize,
+    stash_size: usize,
 }
 
-/// The number of times to loop when trying to place an entry in a bin.
+/// The default number of times to loop when trying to place an entry in a bin.
 const NITERS: usize = 1000;
 
-fn compute_nbins(n: usize, nhashes: usize) -> Result<usize, Error> {
+/// The parameters of a cuckoo hash table, beyond its number of hash functions.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CuckooParams {
+    /// The maximum number of evictions when placing an entry, after which the
+    /// evicted entry is put in the stash.
+    pub max_evictions: usize,
+    /// The maximum number of entries in the stash. Without a stash, an entry
+    /// which cannot be placed makes the table full.
+    pub stash_size: usize,
+}
+
+impl Default for CuckooParams {
+    /// Parameters with `NITERS` evictions and no stash.
+    fn default() -> Self {
+        CuckooParams {
+            max_evictions: NITERS,
+            stash_size: 0,
+        }
+    }
+}
+
+pub(crate) fn compute_nbins(n: usize, nhashes: usize) -> Result<usize, Error> {
     // Numbers taken from <https://thomaschneider.de/papers/PSZ18.pdf>, §3.2.2.
-    if nhashes == 3 {
+    if nhashes == 2 {
+        Ok((2.4 * (n as f64)).ceil() as usize)
+    } else if nhashes == 3 {
         if n < 1 << 27 {
             Ok((1.27 * (n as f64)).ceil() as usize) // good up to set size 2^26
         } else {
@@ -81,12 +108,25 @@ impl CuckooHash {
     /// lower-order-bits of the values in `inputs` are zero-ed out, as those
     /// bits will be used to store the hash index.
     pub fn new(inputs: &[Block], nhashes: usize) -> Result<CuckooHash, Error> {
+        CuckooHash::with_params(inputs, nhashes, CuckooParams::default())
+    }
+
+    /// Build a new cuckoo hash table with `nhashes` hash functions and the given
+    /// parameters, hashing `inputs` in (cf. `CuckooHash::new`).
+    pub fn with_params(
+        inputs: &[Block],
+        nhashes: usize,
+        params: CuckooParams,
+    ) -> Result<CuckooHash, Error> {
         let nbins = compute_nbins(inputs.len(), nhashes)?;
 
         let mut tbl = CuckooHash {
             items: vec![None; nbins],
             nbins,
             nhashes,
+            stash: Vec::new(),
+            max_evictions: params.max_evictions,
+            stash_size: params.stash_size,
         };
 
         // Fill table with `inputs`.
@@ -99,13 +139,17 @@ impl CuckooHash {
 
     /// Place `input`, alongside the input index `idx` it corresponds to, in the
     /// hash table.
+    ///
+    /// If the entry cannot be placed within `max_evictions` evictions, the last
+    /// evicted entry is put in the stash. Fails with `Error::CuckooHashFull` if
+    /// there is no stash, and with `Error::StashOverflow` if the stash is full.
     pub fn hash(&mut self, input: Block, idx: usize) -> Result<(), Error> {
         let mut item = CuckooItem {
             entry: input,
             input_index: idx,
             hash_index: 0,
         };
-        for _ in 0..NITERS {
+        for _ in 0..=self.max_evictions {
             let i = CuckooHash::bin(item.entry, item.hash_index, self.nbins);
             let opt_item = self.items[i].replace(item);
             if let Some(x) = opt_item {
@@ -118,7 +162,14 @@ impl CuckooHash {
                 return Ok(());
             }
         }
-        Err(Error::CuckooHashFull)
+        if self.stash_size == 0 {
+            return Err(Error::CuckooHashFull);
+        }
+        if self.stash.len() == self.stash_size {
+            return Err(Error::StashOverflow);
+        }
+        self.stash.push(item);
+        Ok(())
     }
 
     /// Output the bin number for a given hash output `hash` and hash index `hidx`.
@@ -192,4 +243,54 @@ mod tests {
             }
         }
     }
+
+    // Identical entries all map to the same `NHASHES` bins, so at most `NHASHES`
+    // of them can be placed in the table
+    fn colliding_inputs(n: usize) -> Vec<Block> {
+        let mut rng = AesRng::new();
+        let entry = rng.gen::<Block>();
+        let mut inputs = vec![entry; n];
+        // Pad with random entries so the table has more than `NHASHES` bins
+        inputs.extend((0..SETSIZE).map(|_| rng.gen::<Block>()));
+        inputs
+    }
+
+    #[test]
+    fn colliding_inputs_are_stashed() {
+        let inputs = colliding_inputs(NHASHES + 2);
+        let params = CuckooParams {
+            max_evictions: 64,
+            stash_size: 4,
+        };
+        let cuckoo = CuckooHash::with_params(&inputs, NHASHES, params).unwrap();
+        let nplaced = cuckoo.items.iter().filter(|item| item.is_some()).count();
+        assert!(cuckoo.stash.len() >= 2);
+        assert_eq!(nplaced + cuckoo.stash.len(), inputs.len());
+        let mut indices = cuckoo
+            .items
+            .iter()
+            .flatten()
+            .chain(cuckoo.stash.iter())
+            .map(|item| item.input_index)
+            .collect::<Vec<usize>>();
+        indices.sort_unstable();
+        assert_eq!(indices, (0..inputs.len()).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    fn colliding_inputs_overflow_stash() {
+        let inputs = colliding_inputs(NHASHES + 2);
+        let params = CuckooParams {
+            max_evictions: 64,
+            stash_size: 1,
+        };
+        assert!(matches!(
+            CuckooHash::with_params(&inputs, NHASHES, params),
+            Err(Error::StashOverflow)
+        ));
+        assert!(matches!(
+            CuckooHash::new(&inputs, NHASHES),
+            Err(Error::CuckooHashFull)
+        ));
+    }
 }
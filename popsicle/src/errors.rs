@@ -34,6 +34,39 @@ pub enum Error {
         /// length of the set of primary keys
         nprimarykeys: usize,
     },
+    /// A payload could not be encoded or decoded.
+    PayloadEncodingError(String),
+    /// The parties' base PSI hashing parameters do not match.
+    ParameterInconsistency,
+    /// The differential privacy parameter is not a positive finite number, or
+    /// cannot be sampled from exactly.
+    InvalidDpEpsilon(f64),
+    /// A one-time payload mask was used for more than one record.
+    MaskReuse,
+    /// The cuckoo hash stash is full.
+    StashOverflow,
+    /// The primary key at this index is not greater than the previous one,
+    /// while the keys must be sorted without duplicates.
+    UnsortedInput(usize),
+    /// A primary key does not have `PRIMARY_KEY_SIZE` bytes.
+    InvalidPrimaryKeyLength(usize),
+    /// The security parameters are outside of the supported ranges.
+    InvalidSecurityParams {
+        /// The statistical security parameter
+        stat_sec: usize,
+        /// The computational security parameter
+        comp_sec: usize,
+    },
+    /// A key does not satisfy its format (cf. `keys::KeyFormat`).
+    InvalidKey {
+        /// The key, before normalization
+        key: String,
+        /// Why the key is invalid
+        reason: String,
+    },
+    /// The protocol was cancelled through its handle (cf.
+    /// `circuit_psi::progress::ProtocolHandle`).
+    Cancelled,
 }
 
 impl From<aes_gcm::Error> for Error {
@@ -98,6 +131,33 @@ impl std::fmt::Display for Error {
                 "The set of payloads (len: {}) is not equal to the set of primary keys (len: {})!",
                 npayloads, nprimarykeys
             ),
+            Error::PayloadEncodingError(s) => write!(f, "payload encoding error: {}", s),
+            Error::ParameterInconsistency => {
+                write!(f, "The parties' hashing parameters are inconsistent!")
+            }
+            Error::InvalidDpEpsilon(epsilon) => {
+                write!(f, "Invalid differential privacy parameter {}", epsilon)
+            }
+            Error::MaskReuse => write!(f, "A payload mask was reused!"),
+            Error::StashOverflow => write!(f, "cuckoo hash error: stash is full"),
+            Error::UnsortedInput(index) => write!(
+                f,
+                "primary key {} is out of order or duplicated, the keys must be sorted",
+                index
+            ),
+            Error::InvalidPrimaryKeyLength(len) => write!(
+                f,
+                "primary key of {} bytes, expected {} bytes",
+                len,
+                crate::psi::circuit_psi::PRIMARY_KEY_SIZE
+            ),
+            Error::InvalidSecurityParams { stat_sec, comp_sec } => write!(
+                f,
+                "Invalid security parameters (statistical: {}, computational: {})",
+                stat_sec, comp_sec
+            ),
+            Error::InvalidKey { key, reason } => write!(f, "invalid key {:?}: {}", key, reason),
+            Error::Cancelled => write!(f, "The protocol was cancelled"),
         }
     }
 }
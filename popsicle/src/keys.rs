@@ -0,0 +1,267 @@
+//! Validating and normalizing structured primary keys, e.g. social security
+//! numbers, before they are used in a PSI protocol.
+//!
+//! The protocols compare primary keys byte for byte, so both parties must
+//! normalize their keys in exactly the same way, or equivalent keys written
+//! differently (e.g. `123-45-6789` and `123456789`) silently fail to match.
+//! Both parties should therefore use the same `KeyFormat`, which they can check
+//! with `KeyFormat::check_agreement`.
+
+use crate::{
+    errors::Error,
+    psi::circuit_psi::{PrimaryKey, PRIMARY_KEY_SIZE},
+};
+use scuttlebutt::AbstractChannel;
+
+/// A normalization step applied to a key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum KeyTransform {
+    /// Remove the leading and trailing whitespace.
+    Trim,
+    /// Remove every occurrence of the character, e.g. the dashes of a social
+    /// security number.
+    Strip(char),
+    /// Convert ASCII letters to uppercase.
+    Uppercase,
+}
+
+impl KeyTransform {
+    fn apply(&self, key: &str) -> String {
+        match self {
+            KeyTransform::Trim => key.trim().to_string(),
+            KeyTransform::Strip(c) => key.chars().filter(|x| x != c).collect(),
+            KeyTransform::Uppercase => key.to_ascii_uppercase(),
+        }
+    }
+}
+
+/// The characters allowed in a normalized key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyCharset {
+    /// ASCII digits. The keys are encoded as their value (cf.
+    /// `KeyFormat::primary_key`).
+    Digits,
+    /// ASCII letters and digits.
+    Alphanumeric,
+    /// Any character.
+    Any,
+}
+
+impl KeyCharset {
+    fn contains(&self, c: char) -> bool {
+        match self {
+            KeyCharset::Digits => c.is_ascii_digit(),
+            KeyCharset::Alphanumeric => c.is_ascii_alphanumeric(),
+            KeyCharset::Any => true,
+        }
+    }
+}
+
+/// The format of a party's keys: the transforms normalizing them, in order, and
+/// the constraints the normalized keys must satisfy.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct KeyFormat {
+    /// The transforms applied to the keys, in order
+    pub transforms: Vec<KeyTransform>,
+    /// The characters allowed in a normalized key
+    pub charset: KeyCharset,
+    /// The number of characters of a normalized key, if it is fixed
+    pub length: Option<usize>,
+}
+
+impl KeyFormat {
+    /// The format of US social security numbers: nine digits, possibly
+    /// separated by dashes or spaces, e.g. `123-45-6789`.
+    pub fn ssn() -> Self {
+        KeyFormat {
+            transforms: vec![
+                KeyTransform::Trim,
+                KeyTransform::Strip('-'),
+                KeyTransform::Strip(' '),
+            ],
+            charset: KeyCharset::Digits,
+            length: Some(9),
+        }
+    }
+
+    /// Normalizes `key`, failing with `Error::InvalidKey` if the normalized key
+    /// does not satisfy the format.
+    pub fn normalize(&self, key: &str) -> Result<String, Error> {
+        let normalized = self
+            .transforms
+            .iter()
+            .fold(key.to_string(), |key, transform| transform.apply(&key));
+        let invalid = |reason: String| Error::InvalidKey {
+            key: key.to_string(),
+            reason,
+        };
+        if normalized.is_empty() {
+            return Err(invalid("the key is empty".to_string()));
+        }
+        if let Some(c) = normalized.chars().find(|c| !self.charset.contains(*c)) {
+            return Err(invalid(format!("unexpected character {:?}", c)));
+        }
+        if let Some(length) = self.length {
+            let nchars = normalized.chars().count();
+            if nchars != length {
+                return Err(invalid(format!(
+                    "{} characters, expected {}",
+                    nchars, length
+                )));
+            }
+        }
+        Ok(normalized)
+    }
+
+    /// Normalizes `key` and encodes it as a primary key of `PRIMARY_KEY_SIZE`
+    /// bytes.
+    ///
+    /// Keys of digits are encoded as their little-endian value, as numeric ids
+    /// are by `psty_payload::parse_files`. Other keys are encoded
+    /// as their bytes padded with zeros, and must fit in `PRIMARY_KEY_SIZE`
+    /// bytes.
+    pub fn primary_key(&self, key: &str) -> Result<PrimaryKey, Error> {
+        let normalized = self.normalize(key)?;
+        let invalid = |reason: String| Error::InvalidKey {
+            key: key.to_string(),
+            reason,
+        };
+        let bytes = match self.charset {
+            KeyCharset::Digits => normalized
+                .parse::<u64>()
+                .map_err(|e| invalid(e.to_string()))?
+                .to_le_bytes()
+                .to_vec(),
+            _ => normalized.into_bytes(),
+        };
+        if bytes.len() > PRIMARY_KEY_SIZE {
+            return Err(invalid(format!(
+                "{} bytes, at most {} are supported",
+                bytes.len(),
+                PRIMARY_KEY_SIZE
+            )));
+        }
+        let mut primary_key = bytes;
+        primary_key.resize(PRIMARY_KEY_SIZE, 0);
+        Ok(primary_key)
+    }
+
+    /// Normalizes and encodes all of `keys` (cf. `KeyFormat::primary_key`),
+    /// failing on the first invalid key.
+    pub fn primary_keys<S: AsRef<str>>(&self, keys: &[S]) -> Result<Vec<PrimaryKey>, Error> {
+        keys.iter()
+            .map(|key| self.primary_key(key.as_ref()))
+            .collect()
+    }
+
+    /// Checks that the other party normalizes its keys with the same format,
+    /// failing with `Error::PsiProtocolError` otherwise. Both parties must call
+    /// this method.
+    pub fn check_agreement<C: AbstractChannel>(&self, channel: &mut C) -> Result<(), Error> {
+        let description = format!("{:?}", self).into_bytes();
+        channel.write_lenprefixed(&description)?;
+        channel.flush()?;
+        let other = channel.read_lenprefixed()?;
+        if other != description {
+            return Err(Error::PsiProtocolError(format!(
+                "the parties' key formats differ: {} and {}",
+                String::from_utf8_lossy(&description),
+                String::from_utf8_lossy(&other)
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::reference_intersection;
+    use scuttlebutt::Channel;
+    use std::{
+        io::{BufReader, BufWriter},
+        os::unix::net::UnixStream,
+    };
+
+    #[test]
+    fn ssn_normalization() {
+        let format = KeyFormat::ssn();
+        for key in ["123-45-6789", "123456789", " 123 45 6789\n", "123-45 6789"] {
+            assert_eq!(format.normalize(key).unwrap(), "123456789");
+        }
+        assert_eq!(
+            format.primary_key("123-45-6789").unwrap(),
+            123456789u64.to_le_bytes().to_vec()
+        );
+    }
+
+    #[test]
+    fn ssn_invalid() {
+        let format = KeyFormat::ssn();
+        for key in [
+            "",
+            "12-345-678",
+            "123-45-67890",
+            "123-45-678O",
+            "123.45.6789",
+        ] {
+            match format.normalize(key) {
+                Err(Error::InvalidKey { key: invalid, .. }) => assert_eq!(invalid, key),
+                other => panic!("{:?} should be invalid, got {:?}", key, other),
+            }
+        }
+        assert!(format.primary_keys(&["123-45-6789", "n/a"]).is_err());
+    }
+
+    #[test]
+    fn ssn_equivalent_keys_match() {
+        let format = KeyFormat::ssn();
+        let a = format
+            .primary_keys(&["123-45-6789", "987-65-4321", "555-12-3456"])
+            .unwrap();
+        let b = format
+            .primary_keys(&["987654321 ", "111-22-3333", " 123 45 6789"])
+            .unwrap();
+        assert_eq!(
+            reference_intersection(&a, &b),
+            vec![a[0].clone(), a[1].clone()]
+        );
+    }
+
+    #[test]
+    fn alphanumeric_keys() {
+        let format = KeyFormat {
+            transforms: vec![KeyTransform::Trim, KeyTransform::Uppercase],
+            charset: KeyCharset::Alphanumeric,
+            length: None,
+        };
+        assert_eq!(
+            format.primary_key(" ab12 ").unwrap(),
+            b"AB12\0\0\0\0".to_vec()
+        );
+        assert!(format.primary_key("abcdefghi").is_err());
+        assert!(format.primary_key("ab-12").is_err());
+    }
+
+    #[test]
+    fn format_agreement() {
+        let check = |format_a: KeyFormat, format_b: KeyFormat| {
+            let (sender, receiver) = UnixStream::pair().unwrap();
+            let handle = std::thread::spawn(move || {
+                let reader = BufReader::new(sender.try_clone().unwrap());
+                let writer = BufWriter::new(sender);
+                let mut channel = Channel::new(reader, writer);
+                format_a.check_agreement(&mut channel).is_ok()
+            });
+            let reader = BufReader::new(receiver.try_clone().unwrap());
+            let writer = BufWriter::new(receiver);
+            let mut channel = Channel::new(reader, writer);
+            let ok = format_b.check_agreement(&mut channel).is_ok();
+            (handle.join().unwrap(), ok)
+        };
+        assert_eq!(check(KeyFormat::ssn(), KeyFormat::ssn()), (true, true));
+        let mut format = KeyFormat::ssn();
+        format.transforms.pop();
+        assert_eq!(check(KeyFormat::ssn(), format), (false, false));
+    }
+}
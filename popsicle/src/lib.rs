@@ -6,7 +6,10 @@
 
 mod cuckoo;
 pub mod errors;
+pub mod keys;
 mod psi;
+pub mod secure_channel;
+pub mod stream;
 pub mod utils;
 
-pub use crate::{errors::Error, psi::*};
+pub use crate::{errors::Error, psi::*, utils::reference_intersection};
@@ -3,12 +3,46 @@
 use crate::{circuit_psi::*, errors::Error};
 use fancy_garbling::{FancyInput, WireMod2};
 use rand::{CryptoRng, RngCore, SeedableRng};
-use scuttlebutt::AbstractChannel;
+use scuttlebutt::{AbstractChannel, Block};
+use sha2::{Digest, Sha256};
 use std::fmt::Debug;
 
-// The number of hash functions that will be used to attempt to
-// place any item in a cuckoo bin
-const NHASHES: usize = 3;
+pub use crate::cuckoo::CuckooParams;
+
+/// The maximum number of times the receiver resamples its hashing key when its
+/// primary keys cannot all be placed in its cuckoo table, after which the base
+/// PSI fails. Random primary keys almost never need a single resampling, so
+/// reaching this bound means that the primary keys collide under every key,
+/// e.g. because some of them are duplicated.
+pub const MAX_REHASHES: usize = 16;
+
+/// The cuckoo hashing strategy of the base PSI, i.e. the number of hash
+/// functions giving the candidate bins of every primary key.
+///
+/// The receiver places each of its primary keys in one of its candidate bins,
+/// while the sender places each of its primary keys in all of them. Two choices
+/// thus halve the sender's entries relative to three, but the receiver's table
+/// needs about `2.4n` bins for `n` primary keys instead of `1.27n` (cf.
+/// `cuckoo::compute_nbins`), which makes the circuit larger. Both parties must
+/// use the same strategy.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CuckooStrategy {
+    /// Two candidate bins per primary key.
+    TwoChoice,
+    /// Three candidate bins per primary key.
+    #[default]
+    ThreeChoice,
+}
+
+impl CuckooStrategy {
+    /// The number of hash functions, i.e. of candidate bins per primary key.
+    pub const fn nhashes(self) -> usize {
+        match self {
+            CuckooStrategy::TwoChoice => 2,
+            CuckooStrategy::ThreeChoice => 3,
+        }
+    }
+}
 
 /// The `OpprfReceiver` which implement BasePsi
 pub mod receiver;
@@ -29,7 +63,22 @@ pub mod sender;
 /// performed in the garbled circuit with no a-priori pre-processing computation).
 pub trait BasePsi {
     /// Initializes the BasePsi party
-    fn init<C, RNG>(channel: &mut C, rng: &mut RNG, has_payload: bool) -> Result<Self, Error>
+    ///
+    /// If `public_seed` is set, the public randomness of the protocol (e.g. the
+    /// cuckoo hashing key) is derived from it, which allows for reproducible and
+    /// publicly verifiable runs. Both parties must then supply the same seed.
+    ///
+    /// Fails with `Error::InvalidSecurityParams` if `security` is not within
+    /// the supported ranges. Both parties must use the same parameters and the
+    /// same cuckoo hashing `strategy`.
+    fn init<C, RNG>(
+        channel: &mut C,
+        rng: &mut RNG,
+        has_payload: bool,
+        public_seed: Option<Block>,
+        security: SecurityParams,
+        strategy: CuckooStrategy,
+    ) -> Result<Self, Error>
     where
         Self: Sized,
         C: AbstractChannel,
@@ -47,6 +96,44 @@ pub trait BasePsi {
     where
         C: AbstractChannel,
         RNG: RngCore + CryptoRng + SeedableRng;
+    /// Sets the parameters of the party's cuckoo hash table, if it uses one
+    ///
+    /// With a stash, the primary keys which cannot be placed in a bin are put in
+    /// extra bins after the table, which spares resampling the hashing key.
+    fn set_cuckoo_params(&mut self, params: CuckooParams);
+    /// The number of bins of the parties' hashing tables, which both parties
+    /// know once their inputs are hashed
+    fn nbins(&self) -> usize;
+    /// The statistics of the party's hashing table once its inputs are hashed
+    fn diagnostics(&self) -> PsiDiagnostics;
+    /// A digest of the public parameters of the party's hashing table, i.e. the
+    /// hashing key, the number of bins, the cuckoo strategy, the security
+    /// parameters and the public seed
+    ///
+    /// Both parties must compute the same digest once their inputs are hashed. It
+    /// does not cover which of a party's inputs are placed in which bin, as that
+    /// assignment is private and differs between the cuckoo and simple tables.
+    fn params_digest(&self) -> [u8; 32];
+    /// Parties check that they hash their inputs with the same public parameters
+    ///
+    /// Each party sends the digest of its parameters and compares it with the other
+    /// party's, returning `Error::ParameterInconsistency` on mismatch. This catches
+    /// misconfigured parties, e.g. with different strategies or public seeds, but
+    /// not a party which deviates from the protocol.
+    fn verify_params<C>(&self, channel: &mut C) -> Result<(), Error>
+    where
+        C: AbstractChannel,
+    {
+        let digest = self.params_digest();
+        channel.write_bytes(&digest)?;
+        channel.flush()?;
+        let mut other = [0u8; 32];
+        channel.read_bytes(&mut other)?;
+        if digest != other {
+            return Err(Error::ParameterInconsistency);
+        }
+        Ok(())
+    }
     /// Parties call an OPPRF on their inputs
     ///
     /// This allows them to agree on a hidden representation of
@@ -69,12 +156,29 @@ pub trait BasePsi {
         Error: From<E>;
     /// A wrapper that calls the different pieces of the BasePsi in order
     /// to the necessary hidden inputs that the CircuitPsi can operate on.
+    ///
+    /// If `verify` is set, the parties check that they hashed their inputs with
+    /// the same public parameters before calling the OPPRF. This check is always performed when
+    /// a `public_seed` is set, so that parties using different seeds are detected.
+    ///
+    /// If `diagnostics` is set, the statistics of the party's hashing table are
+    /// returned along with the circuit inputs.
+    ///
+    /// The `cuckoo_params` only affect the party doing cuckoo hashing, i.e. the
+    /// receiver.
+    #[allow(clippy::too_many_arguments)]
     fn base_psi<F, E, C, RNG>(
         gc_party: &mut F,
         primary_keys: &[PrimaryKey],
         payloads: Option<&[Payload]>,
         channel: &mut C,
         rng: &mut RNG,
+        verify: bool,
+        public_seed: Option<Block>,
+        security: SecurityParams,
+        strategy: CuckooStrategy,
+        cuckoo_params: CuckooParams,
+        diagnostics: bool,
     ) -> Result<CircuitInputs<F::Item>, Error>
     where
         Self: Sized,
@@ -85,14 +189,87 @@ pub trait BasePsi {
         RNG: RngCore + CryptoRng + SeedableRng,
     {
         let has_payloads = payloads.is_some();
+        // Count the bytes sent in each phase
+        #[cfg(feature = "tracing")]
+        let mut channel = scuttlebutt::TrackChannel::new(channel);
+        #[cfg(feature = "tracing")]
+        let channel = &mut channel;
 
-        let mut party = Self::init(channel, rng, has_payloads)?;
-        party.hash_data(primary_keys, payloads, channel, rng)?;
+        let mut party = {
+            trace_phase!("init", set_size = primary_keys.len(), has_payloads);
+            let mut party =
+                Self::init(channel, rng, has_payloads, public_seed, security, strategy)?;
+            party.set_cuckoo_params(cuckoo_params);
+            trace_event!(
+                bytes_read = channel.bytes_read(),
+                bytes_written = channel.bytes_written()
+            );
+            party
+        };
+        {
+            trace_phase!("hashing", set_size = primary_keys.len());
+            party.hash_data(primary_keys, payloads, channel, rng)?;
+            if verify || public_seed.is_some() {
+                party.verify_params(channel)?;
+            }
+            trace_event!(
+                nbins = party.nbins(),
+                bytes_read = channel.bytes_read(),
+                bytes_written = channel.bytes_written()
+            );
+        }
+        let diagnostics = diagnostics.then(|| party.diagnostics());
+        // The receiver's set is empty, so is the intersection: there is
+        // nothing to send through the OPPRF
+        if party.nbins() == 0 {
+            return Ok(CircuitInputs {
+                sender_primary_keys: vec![],
+                receiver_primary_keys: vec![],
+                sender_payloads_masked: vec![],
+                receiver_payloads: vec![],
+                masks: vec![],
+                input_indices: vec![],
+                hash_size: security.hash_size(0),
+                diagnostics,
+            });
+        }
 
-        channel.flush()?;
-        party.opprf_exchange(channel, rng)?;
-        channel.flush()?;
+        {
+            trace_phase!("opprf", nbins = party.nbins());
+            channel.flush()?;
+            party.opprf_exchange(channel, rng)?;
+            channel.flush()?;
+            trace_event!(
+                bytes_read = channel.bytes_read(),
+                bytes_written = channel.bytes_written()
+            );
+        }
+
+        trace_phase!("encoding", nbins = party.nbins());
+        let mut circuit_inputs = party.encode_circuit_inputs(gc_party)?;
+        circuit_inputs.diagnostics = diagnostics;
+        Ok(circuit_inputs)
+    }
+}
 
-        party.encode_circuit_inputs(gc_party)
+/// Hashes the public parameters of a hashing table, which both parties agree on
+fn hash_table_params(
+    key: &Block,
+    nbins: usize,
+    npayload_bins: usize,
+    public_seed: Option<&Block>,
+    security: &SecurityParams,
+    strategy: CuckooStrategy,
+) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    hasher.update(key.as_ref());
+    hasher.update((nbins as u64).to_le_bytes());
+    hasher.update((npayload_bins as u64).to_le_bytes());
+    hasher.update((strategy.nhashes() as u64).to_le_bytes());
+    hasher.update((security.stat_sec as u64).to_le_bytes());
+    hasher.update((security.comp_sec as u64).to_le_bytes());
+    if let Some(seed) = public_seed {
+        hasher.update(seed.as_ref());
     }
+    hasher.finalize().into()
 }
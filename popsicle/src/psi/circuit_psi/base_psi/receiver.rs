@@ -1,11 +1,11 @@
 use crate::{
     circuit_psi::{base_psi::*, utils::*, *},
-    cuckoo::CuckooHash,
+    cuckoo::{CuckooHash, CuckooItem},
     utils::*,
 };
 
 use ocelot::oprf::KmprtReceiver;
-use scuttlebutt::{Block, Block512};
+use scuttlebutt::{AesRng, Block, Block512};
 
 /// A strut defining the receiver in the base circuit PSI computation.
 /// A receiver here refers to the party which queries the OPPRF in this
@@ -13,6 +13,16 @@ use scuttlebutt::{Block, Block512};
 pub struct OpprfReceiver {
     /// The hashing key
     pub key: Block,
+    /// The public seed the public randomness is derived from, if any
+    pub public_seed: Option<Block>,
+    /// The security parameters of the protocol
+    pub security: SecurityParams,
+    /// The cuckoo hashing strategy of the protocol
+    pub strategy: CuckooStrategy,
+    /// The parameters of the cuckoo hash table
+    pub cuckoo_params: CuckooParams,
+    /// The rng derived from the public seed, used to sample the hashing keys
+    public_rng: Option<AesRng>,
     /// The opprf for primary keys
     pub opprf_primary_keys: KmprtReceiver,
     /// The opprf for payloads
@@ -43,6 +53,15 @@ pub struct ReceiverState {
     pub opprf_payloads_in: Vec<Block512>,
     /// The opprf output for payloads
     pub opprf_payloads_out: Vec<Block512>,
+    /// The index of the input placed in each cuckoo bin, if any, followed by
+    /// the stash bins
+    pub input_indices: Vec<Option<usize>>,
+    /// The number of stash bins appended after the cuckoo bins
+    pub nstash: usize,
+    /// The number of inputs placed in the stash
+    pub stash_size: usize,
+    /// The number of times the hashing key was resampled
+    pub nrehashes: usize,
 }
 
 impl BasePsi for OpprfReceiver {
@@ -50,15 +69,24 @@ impl BasePsi for OpprfReceiver {
     ///
     /// If the payloads are not needed for the computation, `payload_existence`
     /// should be set to false.
-    fn init<C, RNG>(channel: &mut C, rng: &mut RNG, has_payload: bool) -> Result<Self, Error>
+    fn init<C, RNG>(
+        channel: &mut C,
+        rng: &mut RNG,
+        has_payload: bool,
+        public_seed: Option<Block>,
+        security: SecurityParams,
+        strategy: CuckooStrategy,
+    ) -> Result<Self, Error>
     where
         C: AbstractChannel,
         RNG: RngCore + CryptoRng + SeedableRng,
     {
+        security.validate()?;
         // The key used during hashing is known to both
         // parties and allows them to hash the same inputs
         // to the same outputs.
-        let key = rng.gen();
+        let mut public_rng = public_seed.map(AesRng::from_seed);
+        let key = sample_key(&mut public_rng, rng);
         channel.write_block(&key)?;
         channel.flush()?;
 
@@ -73,12 +101,21 @@ impl BasePsi for OpprfReceiver {
 
         Ok(Self {
             key,
+            public_seed,
+            security,
+            strategy,
+            cuckoo_params: CuckooParams::default(),
+            public_rng,
             opprf_primary_keys,
             opprf_payload,
             state: Default::default(),
         })
     }
     /// Hash the data using cuckoo hashing
+    ///
+    /// Fails with `Error::StashOverflow` if the stash overflows, and with
+    /// `Error::CuckooHashFull` if the primary keys still cannot be placed after
+    /// `MAX_REHASHES` resamplings of the hashing key, e.g. on duplicate keys.
     fn hash_data<C, RNG>(
         &mut self,
         primary_keys: &[PrimaryKey],
@@ -90,38 +127,115 @@ impl BasePsi for OpprfReceiver {
         C: AbstractChannel,
         RNG: RngCore + CryptoRng + SeedableRng,
     {
+        // Every stash bin has its own hash index, which must fit in the lower-order
+        // byte of the entries
+        let nhashes = self.strategy.nhashes();
+        if nhashes + self.cuckoo_params.stash_size > 256 {
+            return Err(Error::PsiProtocolError(format!(
+                "the stash size {} exceeds {}",
+                self.cuckoo_params.stash_size,
+                256 - nhashes
+            )));
+        }
         let mut hashed_inputs = compress_and_hash_inputs(primary_keys, self.key);
 
-        // refresh the key until the cuckoo hash is not full
+        // refresh the key until the cuckoo hash is not full. The stash is sized
+        // so that it only overflows on colliding primary keys, which no key
+        // separates, and the rehashes are bounded for the same reason.
+        let mut nrehashes = 0;
         let cuckoo = loop {
-            match CuckooHash::new(&hashed_inputs, NHASHES) {
+            match CuckooHash::with_params(&hashed_inputs, nhashes, self.cuckoo_params) {
                 Ok(res) => break res,
+                Err(e @ Error::StashOverflow) => return Err(e),
+                Err(e) if nrehashes == MAX_REHASHES => return Err(e),
                 Err(_e) => {
-                    self.key = rng.gen();
+                    nrehashes += 1;
+                    self.key = sample_key(&mut self.public_rng, rng);
                     hashed_inputs = compress_and_hash_inputs(primary_keys, self.key);
                 }
             }
         };
 
+        // The stash bins are always all sent, so that the sender does not learn
+        // how many inputs were stashed
+        let nstash = if cuckoo.nbins == 0 {
+            0
+        } else {
+            self.cuckoo_params.stash_size
+        };
         channel.write_block(&self.key)?;
         channel.write_usize(cuckoo.nbins)?; // The number of bins is sent out to the sender
+        channel.write_usize(nstash)?;
         channel.flush()?;
 
-        let opprf_primary_keys_in = cuckoo_place_ids(&cuckoo.items, rng);
+        // The stashed inputs are placed in the bins after the table, each tagged
+        // with the hash index of its stash bin
+        let stash_size = cuckoo.stash.len();
+        let mut items = cuckoo.items;
+        items.extend((0..nstash).map(|s| {
+            cuckoo.stash.get(s).map(|item| CuckooItem {
+                hash_index: nhashes + s,
+                ..item.clone()
+            })
+        }));
+
+        let opprf_primary_keys_in = cuckoo_place_ids(&items, rng);
 
         let mut opprf_payloads_in = vec![];
         if payloads.is_some() {
-            opprf_payloads_in = cuckoo_place_payloads(&cuckoo.items, payloads.unwrap(), rng);
+            opprf_payloads_in = cuckoo_place_payloads(&items, payloads.unwrap(), rng);
         }
+        let input_indices = items
+            .iter()
+            .map(|opt_item| opt_item.as_ref().map(|item| item.input_index))
+            .collect();
         self.state = ReceiverState {
             opprf_primary_keys_in,
             opprf_primary_keys_out: vec![],
             opprf_payloads_in,
             opprf_payloads_out: vec![],
+            input_indices,
+            nstash,
+            stash_size,
+            nrehashes,
         };
         Ok(())
     }
 
+    fn set_cuckoo_params(&mut self, params: CuckooParams) {
+        self.cuckoo_params = params;
+    }
+
+    fn nbins(&self) -> usize {
+        self.state.opprf_primary_keys_in.len()
+    }
+
+    fn diagnostics(&self) -> PsiDiagnostics {
+        // The loads are those of the cuckoo bins, the stash bins being accounted
+        // for by the stash size
+        let ncuckoo = self.state.input_indices.len() - self.state.nstash;
+        let nplaced = self.state.input_indices[..ncuckoo].iter().flatten().count();
+        PsiDiagnostics::from_loads(
+            nplaced + self.state.stash_size,
+            self.state.input_indices[..ncuckoo]
+                .iter()
+                .map(|index| index.is_some() as usize),
+            self.state.stash_size,
+            self.state.nrehashes,
+        )
+    }
+
+    fn params_digest(&self) -> [u8; 32] {
+        hash_table_params(
+            &self.key,
+            self.state.opprf_primary_keys_in.len(),
+            self.state.opprf_payloads_in.len(),
+            self.public_seed.as_ref(),
+            &self.security,
+            self.strategy,
+        )
+    }
+
     fn opprf_exchange<C, RNG>(&mut self, channel: &mut C, rng: &mut RNG) -> Result<(), Error>
     where
         C: AbstractChannel,
@@ -156,18 +270,17 @@ impl BasePsi for OpprfReceiver {
         // bytes, turning it into bits, and then multiplying it by the
         // number of primary keys the parties are intersecting.
         // Note that PSTY expects parties to have the same set sizes.
-        let primary_keys_binary_len = PRIMARY_KEY_SIZE * 8 * self.state.opprf_primary_keys_in.len();
+        let nbins = self.state.opprf_primary_keys_in.len();
+        let hash_size = self.security.hash_size(nbins);
+        let primary_keys_binary_len = hash_size * 8 * nbins;
 
         // First receive encoded inputs from the `OpprfSender`
         let sender_primary_keys: Vec<F::Item> =
             bin_receive_many_block512(gc_party, primary_keys_binary_len)?;
 
         // Then send encoded inputs
-        let receiver_primary_keys: Vec<F::Item> = bin_encode_many_block512(
-            gc_party,
-            &self.state.opprf_primary_keys_out,
-            PRIMARY_KEY_SIZE,
-        )?;
+        let receiver_primary_keys: Vec<F::Item> =
+            bin_encode_many_block512(gc_party, &self.state.opprf_primary_keys_out, hash_size)?;
 
         let mut result = CircuitInputs {
             sender_primary_keys,
@@ -175,6 +288,9 @@ impl BasePsi for OpprfReceiver {
             sender_payloads_masked: vec![],
             receiver_payloads: vec![],
             masks: vec![],
+            input_indices: self.state.input_indices.clone(),
+            hash_size,
+            diagnostics: None,
         };
         // If payloads exist, then encode them
         if !&self.state.opprf_payloads_in.is_empty() {
@@ -200,3 +316,12 @@ impl BasePsi for OpprfReceiver {
         Ok(result)
     }
 }
+
+/// Samples a hashing key from the public rng if there is one, and from the
+/// party's own rng otherwise
+fn sample_key<RNG: RngCore + CryptoRng>(public_rng: &mut Option<AesRng>, rng: &mut RNG) -> Block {
+    match public_rng {
+        Some(public_rng) => public_rng.gen(),
+        None => rng.gen(),
+    }
+}
@@ -5,7 +5,7 @@ use crate::{
 };
 
 use ocelot::oprf::KmprtSender;
-use scuttlebutt::{Block, Block512};
+use scuttlebutt::{AesRng, Block, Block512};
 
 /// A struct defining the sender in the base circuit PSI computation.
 /// A sender here refers to the party which programs the OPPRF in this
@@ -13,6 +13,18 @@ use scuttlebutt::{Block, Block512};
 pub struct OpprfSender {
     /// The hashing key
     pub key: Block,
+    /// The public seed the public randomness is derived from, if any
+    pub public_seed: Option<Block>,
+    /// The security parameters of the protocol
+    pub security: SecurityParams,
+    /// The cuckoo hashing strategy of the protocol
+    pub strategy: CuckooStrategy,
+    /// The rng derived from the public seed, used to check the receiver's hashing
+    /// keys
+    public_rng: Option<AesRng>,
+    /// The key the programmed outputs of the opprf are derived from, if it is
+    /// fixed (cf. `OpprfSender::init_with_key`)
+    pub oprf_key: Option<Block>,
     /// The number of hashing bins
     pub nbins: Option<usize>,
     /// The opprf for set primary keys
@@ -44,6 +56,45 @@ pub struct SenderState {
     pub opprf_payloads_in: Vec<Vec<Block512>>,
     /// The opprf programmed outputs for the payloads
     pub opprf_payloads_out: Vec<Block512>,
+    /// The number of inputs hashed into the bins
+    pub nitems: usize,
+    /// The number of stash bins appended after the simple hashing bins
+    pub nstash: usize,
+}
+
+impl OpprfSender {
+    /// Initialize the `OpprfSender` like `BasePsi::init`, but with the
+    /// programmed outputs of the primary keys' opprf derived from `key` rather
+    /// than sampled afresh.
+    ///
+    /// This is meant for systems that deliberately fix or rotate the sender's
+    /// key to control linkability across sessions. Reusing a key enables
+    /// linking repeated queries: in every run with the same key, the same
+    /// hashing key (e.g. the same `public_seed`) and the same number of bins,
+    /// the receiver gets the same opprf output for a primary key the sender
+    /// holds, and can thus tell which of its primary keys the sender held in
+    /// earlier runs. A fresh key should be used whenever sessions must remain
+    /// unlinkable.
+    ///
+    /// The payloads' opprf outputs mask the sender's payloads, so they are still
+    /// sampled afresh in every run.
+    pub fn init_with_key<C, RNG>(
+        channel: &mut C,
+        rng: &mut RNG,
+        key: Block,
+        has_payload: bool,
+        public_seed: Option<Block>,
+        security: SecurityParams,
+        strategy: CuckooStrategy,
+    ) -> Result<Self, Error>
+    where
+        C: AbstractChannel,
+        RNG: RngCore + CryptoRng + SeedableRng,
+    {
+        let mut sender = Self::init(channel, rng, has_payload, public_seed, security, strategy)?;
+        sender.oprf_key = Some(key);
+        Ok(sender)
+    }
 }
 
 impl BasePsi for OpprfSender {
@@ -51,15 +102,30 @@ impl BasePsi for OpprfSender {
     ///
     /// If the payloads are not needed for the computation, `payload_existence`
     /// should be set to false.
-    fn init<C, RNG>(channel: &mut C, rng: &mut RNG, has_payload: bool) -> Result<Self, Error>
+    fn init<C, RNG>(
+        channel: &mut C,
+        rng: &mut RNG,
+        has_payload: bool,
+        public_seed: Option<Block>,
+        security: SecurityParams,
+        strategy: CuckooStrategy,
+    ) -> Result<Self, Error>
     where
         C: AbstractChannel,
         RNG: RngCore + CryptoRng + SeedableRng,
     {
+        security.validate()?;
         // The key used during hashing is known to both
         // parties and allows them to hash the same inputs
-        // to the same outputs.
+        // to the same outputs. With a public seed, it is derived
+        // from the seed, so the receiver's key is checked against it.
+        let mut public_rng = public_seed.map(AesRng::from_seed);
         let key = channel.read_block()?;
+        if let Some(public_rng) = &mut public_rng {
+            if key != public_rng.gen::<Block>() {
+                return Err(Error::ParameterInconsistency);
+            }
+        }
         let opprf_primary_keys = KmprtSender::init(channel, rng)?;
         let mut opprf_payload = None;
         if has_payload {
@@ -68,6 +134,11 @@ impl BasePsi for OpprfSender {
 
         Ok(Self {
             key,
+            public_seed,
+            security,
+            strategy,
+            public_rng,
+            oprf_key: None,
             nbins: None,
             opprf_primary_keys,
             opprf_payload,
@@ -87,30 +158,75 @@ impl BasePsi for OpprfSender {
         RNG: RngCore + CryptoRng + SeedableRng,
     {
         // refresh key if cuckoo hash is full
-        self.key = channel.read_block()?;
+        let key = channel.read_block()?;
+        // With a public seed, the receiver resamples its key from the seed, at most
+        // `MAX_REHASHES` times
+        if let Some(public_rng) = &mut self.public_rng {
+            let mut nrehashes = 0;
+            while key != self.key {
+                if nrehashes == MAX_REHASHES {
+                    return Err(Error::ParameterInconsistency);
+                }
+                self.key = public_rng.gen();
+                nrehashes += 1;
+            }
+        }
+        self.key = key;
 
         // Receive cuckoo hash info from sender
         // The receiver determines the number of bins
         // to be used by the sender.
         let nbins = channel.read_usize()?;
+        // The receiver's stash bins come after its cuckoo bins, each with its own
+        // hash index
+        let nstash = channel.read_usize()?;
+        let nhashes = self.strategy.nhashes();
+        if nhashes + nstash > 256 {
+            return Err(Error::PsiProtocolError(format!(
+                "the stash size {} exceeds {}",
+                nstash,
+                256 - nhashes
+            )));
+        }
+        let ntotal = nbins + nstash;
 
-        self.nbins = Some(nbins);
+        self.nbins = Some(ntotal);
 
-        let hashes = utils::compress_and_hash_inputs(primary_keys, self.key);
+        // If the receiver's set is empty, there are no bins to place the inputs in
+        let hashes = if nbins == 0 {
+            vec![]
+        } else {
+            utils::compress_and_hash_inputs(primary_keys, self.key)
+        };
 
-        let mut opprf_primary_keys_in = vec![Vec::new(); nbins];
-        let opprf_primary_keys_out = (0..nbins).map(|_| rng.gen::<Block512>()).collect();
+        let mut opprf_primary_keys_in = vec![Vec::new(); ntotal];
+        let opprf_primary_keys_out = match self.oprf_key {
+            Some(key) => {
+                let mut key_rng = AesRng::from_seed(key);
+                (0..ntotal).map(|_| key_rng.gen::<Block512>()).collect()
+            }
+            None => (0..ntotal).map(|_| rng.gen::<Block512>()).collect(),
+        };
 
         let mut opprf_payloads_in = vec![];
         let mut opprf_payloads_out = vec![];
         if payloads.is_some() {
-            opprf_payloads_in = vec![Vec::new(); nbins];
-            opprf_payloads_out = (0..nbins).map(|_| rng.gen::<Block512>()).collect();
+            opprf_payloads_in = vec![Vec::new(); ntotal];
+            opprf_payloads_out = (0..ntotal).map(|_| rng.gen::<Block512>()).collect();
         }
 
         for (i, x) in hashes.iter().enumerate() {
-            let mut bins = Vec::with_capacity(NHASHES);
-            for h in 0..NHASHES {
+            // Any input may have been stashed by the receiver, so every input is
+            // placed in every stash bin
+            for s in 0..nstash {
+                let bin = nbins + s;
+                opprf_primary_keys_in[bin].push(*x ^ Block::from((nhashes + s) as u128));
+                if payloads.is_some() {
+                    opprf_payloads_in[bin].push(payloads.unwrap()[i] ^ opprf_payloads_out[bin]);
+                }
+            }
+            let mut bins = Vec::with_capacity(nhashes);
+            for h in 0..nhashes {
                 // First find the bin where the item will be placed
                 let bin = CuckooHash::bin(*x, h, nbins);
                 // Then place the item in that bin while keeping track
@@ -139,11 +255,45 @@ impl BasePsi for OpprfSender {
             opprf_primary_keys_out,
             opprf_payloads_in,
             opprf_payloads_out,
+            nitems: hashes.len(),
+            nstash,
         };
 
         Ok(())
     }
 
+    /// The sender does simple hashing, so it has no cuckoo hash table to set up
+    fn set_cuckoo_params(&mut self, _params: CuckooParams) {}
+
+    fn nbins(&self) -> usize {
+        self.state.opprf_primary_keys_in.len()
+    }
+
+    fn diagnostics(&self) -> PsiDiagnostics {
+        // Simple hashing places every input in a bin, without a stash or rehashing.
+        // The stash bins mirror the receiver's stash and are left out.
+        let nbins = self.state.opprf_primary_keys_in.len() - self.state.nstash;
+        PsiDiagnostics::from_loads(
+            self.state.nitems,
+            self.state.opprf_primary_keys_in[..nbins]
+                .iter()
+                .map(|bin| bin.len()),
+            0,
+            0,
+        )
+    }
+
+    fn params_digest(&self) -> [u8; 32] {
+        hash_table_params(
+            &self.key,
+            self.state.opprf_primary_keys_in.len(),
+            self.state.opprf_payloads_in.len(),
+            self.public_seed.as_ref(),
+            &self.security,
+            self.strategy,
+        )
+    }
+
     fn opprf_exchange<C, RNG>(&mut self, channel: &mut C, rng: &mut RNG) -> Result<(), Error>
     where
         C: AbstractChannel,
@@ -181,11 +331,16 @@ impl BasePsi for OpprfSender {
         E: Debug,
         Error: From<E>,
     {
-        let sender_primary_keys = bin_encode_many_block512(
-            gc_party,
-            &self.state.opprf_primary_keys_out,
-            PRIMARY_KEY_SIZE,
-        )?;
+        // Reusing a mask would break the one-time pad hiding the payloads
+        if cfg!(any(debug_assertions, feature = "mask-reuse-check")) {
+            check_masks_unique(&self.state.opprf_payloads_out, PAYLOAD_SIZE)?;
+        }
+
+        let hash_size = self
+            .security
+            .hash_size(self.state.opprf_primary_keys_out.len());
+        let sender_primary_keys =
+            bin_encode_many_block512(gc_party, &self.state.opprf_primary_keys_out, hash_size)?;
 
         let receiver_primary_keys = bin_receive_many_block512(gc_party, sender_primary_keys.len())?;
 
@@ -195,6 +350,9 @@ impl BasePsi for OpprfSender {
             sender_payloads_masked: vec![],
             receiver_payloads: vec![],
             masks: vec![],
+            input_indices: vec![],
+            hash_size,
+            diagnostics: None,
         };
 
         // If payloads exist, then encode them
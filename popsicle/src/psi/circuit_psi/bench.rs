@@ -0,0 +1,180 @@
+//! A benchmarking mode for circuit PSI, enabled by the `bench` feature.
+//!
+//! A benchmark run seeds the party's RNGs from a fixed seed, so that two runs on
+//! the same inputs and seeds exchange exactly the same messages, optionally pins
+//! the party's thread to a CPU core to reduce scheduling noise, and records the
+//! time spent in each phase of the protocol into a `PhaseTimings`. The phases
+//! are the ones traced when the `tracing` feature is enabled, preceded by a
+//! `"setup"` phase for the creation of the party.
+use crate::{
+    errors::Error,
+    psi::circuit_psi::{evaluator::PsiEvaluator, garbler::PsiGarbler},
+};
+use scuttlebutt::{AbstractChannel, AesRng, Block};
+use std::{
+    cell::RefCell,
+    time::{Duration, Instant},
+};
+
+thread_local! {
+    // The timings recorded on the current thread, if any (cf. `record_timings`)
+    static TIMINGS: RefCell<Option<PhaseTimings>> = const { RefCell::new(None) };
+}
+
+/// The time spent in each phase of a circuit PSI run.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PhaseTimings {
+    /// The name and duration of every phase, in the order the phases ended
+    pub phases: Vec<(&'static str, Duration)>,
+}
+
+impl PhaseTimings {
+    /// The names of the phases, in the order they ended.
+    pub fn names(&self) -> Vec<&'static str> {
+        self.phases.iter().map(|(name, _)| *name).collect()
+    }
+
+    /// The total time spent in `phase`, or `None` if it was never entered.
+    pub fn get(&self, phase: &str) -> Option<Duration> {
+        self.phases
+            .iter()
+            .filter(|(name, _)| *name == phase)
+            .map(|(_, duration)| *duration)
+            .reduce(|a, b| a + b)
+    }
+
+    /// The total time spent in all phases.
+    pub fn total(&self) -> Duration {
+        self.phases.iter().map(|(_, duration)| *duration).sum()
+    }
+}
+
+/// Times a phase of the protocol from its creation until it is dropped, if the
+/// current thread is recording timings (cf. `record_timings`).
+pub struct PhaseTimer {
+    phase: &'static str,
+    start: Instant,
+}
+
+impl PhaseTimer {
+    /// Start timing `phase`.
+    pub fn start(phase: &'static str) -> Self {
+        PhaseTimer {
+            phase,
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Drop for PhaseTimer {
+    fn drop(&mut self) {
+        let elapsed = self.start.elapsed();
+        TIMINGS.with(|timings| {
+            if let Some(timings) = timings.borrow_mut().as_mut() {
+                timings.phases.push((self.phase, elapsed));
+            }
+        });
+    }
+}
+
+/// Run `f`, returning its result along with the timings of the phases entered
+/// by `f` on the current thread.
+pub fn record_timings<T>(f: impl FnOnce() -> T) -> (T, PhaseTimings) {
+    let previous = TIMINGS.with(|timings| timings.replace(Some(PhaseTimings::default())));
+    let result = f();
+    let recorded = TIMINGS.with(|timings| timings.replace(previous));
+    (result, recorded.unwrap_or_default())
+}
+
+/// Pin the current thread to the CPU `core`.
+///
+/// Pinning is only supported on Linux, and fails with `Error::IoError` on other
+/// platforms.
+pub fn pin_thread(core: usize) -> Result<(), Error> {
+    #[cfg(target_os = "linux")]
+    {
+        let mut cpu_set = nix::sched::CpuSet::new();
+        cpu_set.set(core).map_err(std::io::Error::from)?;
+        nix::sched::sched_setaffinity(nix::unistd::Pid::from_raw(0), &cpu_set)
+            .map_err(std::io::Error::from)?;
+        Ok(())
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        let _ = core;
+        Err(Error::IoError(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "thread pinning is only supported on Linux",
+        )))
+    }
+}
+
+/// The configuration of one party in a benchmark run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BenchConfig {
+    /// The seed of the party's RNGs. Runs with the same seeds and inputs exchange
+    /// the same messages.
+    pub seed: Block,
+    /// The CPU core the party's thread is pinned to, if any (cf. `pin_thread`)
+    pub core: Option<usize>,
+}
+
+impl BenchConfig {
+    /// A configuration seeding the party's RNGs with `seed`, without pinning its
+    /// thread.
+    pub fn new(seed: Block) -> Self {
+        BenchConfig { seed, core: None }
+    }
+}
+
+/// Creates a `PsiGarbler` on `channel` according to `config` and runs `f` on it,
+/// returning the result of `f` and the timings of the phases of the protocol.
+///
+/// `f` may configure the garbler before running the protocol. The party is
+/// created on the current thread, which is pinned first if `config.core` is set.
+pub fn bench_garbler<C, B, T, F>(
+    channel: &mut C,
+    config: &BenchConfig,
+    f: F,
+) -> Result<(T, PhaseTimings), Error>
+where
+    C: AbstractChannel + Clone,
+    F: FnOnce(&mut PsiGarbler<C, AesRng, B>) -> Result<T, Error>,
+{
+    if let Some(core) = config.core {
+        pin_thread(core)?;
+    }
+    let (result, timings) = record_timings(|| {
+        let mut gb = {
+            let _timer = PhaseTimer::start("setup");
+            PsiGarbler::new(channel, config.seed)?
+        };
+        f(&mut gb)
+    });
+    Ok((result?, timings))
+}
+
+/// Creates a `PsiEvaluator` on `channel` according to `config` and runs `f` on
+/// it, returning the result of `f` and the timings of the phases of the protocol
+/// (cf. `bench_garbler`).
+pub fn bench_evaluator<C, B, T, F>(
+    channel: &mut C,
+    config: &BenchConfig,
+    f: F,
+) -> Result<(T, PhaseTimings), Error>
+where
+    C: AbstractChannel + Clone,
+    F: FnOnce(&mut PsiEvaluator<C, AesRng, B>) -> Result<T, Error>,
+{
+    if let Some(core) = config.core {
+        pin_thread(core)?;
+    }
+    let (result, timings) = record_timings(|| {
+        let mut ev = {
+            let _timer = PhaseTimer::start("setup");
+            PsiEvaluator::new(channel, config.seed)?
+        };
+        f(&mut ev)
+    });
+    Ok((result?, timings))
+}
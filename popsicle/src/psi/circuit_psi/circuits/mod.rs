@@ -2,12 +2,7 @@
 use crate::{circuit_psi::*, errors::Error};
 use fancy_garbling::{BinaryBundle, BinaryGadgets, Fancy, FancyBinary, FancyReveal};
 use itertools::Itertools;
-use std::fmt::Debug;
-
-// How many bytes of the hash to use for the equality tests. This affects
-// correctness, with a lower value increasing the likelihood of a false
-// positive.
-const HASH_SIZE: usize = 8;
+use std::{cmp::Ordering, fmt::Debug};
 
 /// Fancy function to compute the intersection of two sets
 /// and return a bit vector indicating the presence or abscence of
@@ -15,17 +10,20 @@ const HASH_SIZE: usize = 8;
 /// The sender and receiver slices are assumed to be of the same size
 /// and ordered in such a way that if elements are shared between them
 /// then they will be in the same position.
+/// Elements are compared on `hash_size` bytes (cf. `SecurityParams::hash_size`),
+/// a lower value increasing the likelihood of a false positive.
 pub fn fancy_intersection_bit_vector<F>(
     f: &mut F,
     sender_inputs: &[F::Item],
     receiver_inputs: &[F::Item],
+    hash_size: usize,
 ) -> Result<Vec<F::Item>, F::Error>
 where
     F: FancyReveal + Fancy + FancyBinary,
 {
     sender_inputs
-        .chunks(HASH_SIZE * 8)
-        .zip_eq(receiver_inputs.chunks(HASH_SIZE * 8))
+        .chunks(hash_size * 8)
+        .zip_eq(receiver_inputs.chunks(hash_size * 8))
         .map(|(xs, ys)| {
             f.bin_eq_bundles(
                 &BinaryBundle::new(xs.to_vec()),
@@ -35,6 +33,191 @@ where
         .collect()
 }
 
+/// Fancy function which complements the intersection bit vector, so that the
+/// i-th bit indicates whether the primary key in the i-th bin is *not* in the
+/// intersection. Negating a wire is free.
+pub fn fancy_complement_bit_vector<F>(
+    f: &mut F,
+    intersect_bitvec: &[F::Item],
+) -> Result<Vec<F::Item>, F::Error>
+where
+    F: Fancy + FancyBinary,
+{
+    intersect_bitvec.iter().map(|bit| f.negate(bit)).collect()
+}
+
+/// Fancy function to compute the fuzzy intersection of two sets, where the
+/// i-th bit of the returned vector indicates whether the receiver's i-th key is
+/// within Hamming distance `max_distance` of any of the sender's keys.
+///
+/// Every pair of keys is compared, so the circuit has
+/// `O(sender_keys.len() * receiver_keys.len() * key_bits)` AND gates.
+pub fn fancy_fuzzy_intersection_bit_vector<F>(
+    f: &mut F,
+    sender_keys: &[BinaryBundle<F::Item>],
+    receiver_keys: &[BinaryBundle<F::Item>],
+    max_distance: usize,
+) -> Result<Vec<F::Item>, F::Error>
+where
+    F: Fancy + FancyBinary,
+{
+    receiver_keys
+        .iter()
+        .map(|y| {
+            let matches = sender_keys
+                .iter()
+                .map(|x| {
+                    let distance = f.bin_hamming_distance(x, y)?;
+                    // The distance is at most the number of bits of the keys,
+                    // which also fits in the distance's width
+                    let max_distance = max_distance.min(y.size()) as u128;
+                    let max_distance = f.bin_constant_bundle(max_distance, distance.size())?;
+                    let too_far = f.bin_lt(&max_distance, &distance)?;
+                    f.negate(&too_far)
+                })
+                .collect::<Result<Vec<F::Item>, F::Error>>()?;
+            if matches.is_empty() {
+                f.constant(0, 2)
+            } else {
+                f.or_many(&matches)
+            }
+        })
+        .collect()
+}
+
+/// Fancy function to compute the intersection of two sorted sets by merging
+/// them, and return a bit vector along with the merged keys, where a bit is set
+/// if the key at the same position is in the intersection.
+///
+/// Both `sender_keys` and `receiver_keys` must be sorted in strictly increasing
+/// order. The sender's keys followed by the receiver's keys in reverse order form a
+/// bitonic sequence, which is merged with `O(n log n)` comparisons for `n` keys
+/// in total (rounded up to a power of two with dummy keys), without any hashing.
+/// Since both sets have no duplicates, a key is in the intersection exactly when
+/// the next merged key is equal to it.
+pub fn fancy_sorted_intersection<F>(
+    f: &mut F,
+    sender_keys: &[BinaryBundle<F::Item>],
+    receiver_keys: &[BinaryBundle<F::Item>],
+) -> Result<(Vec<F::Item>, Vec<BinaryBundle<F::Item>>), F::Error>
+where
+    F: Fancy + FancyBinary,
+{
+    let nbits = sender_keys
+        .first()
+        .or(receiver_keys.first())
+        .map_or(0, |x| x.size());
+    let n = (sender_keys.len() + receiver_keys.len()).next_power_of_two();
+    // Keys are extended with a most significant bit set only for dummy keys,
+    // which therefore come after every real key
+    let zero = f.constant(0, 2)?;
+    let real = |x: &BinaryBundle<F::Item>| {
+        BinaryBundle::new(x.wires().iter().chain([&zero]).cloned().collect())
+    };
+    let ndummies = n - sender_keys.len() - receiver_keys.len();
+    let dummy = f.bin_constant_bundle((1 << (nbits + 1)) - 1, nbits + 1)?;
+    let mut xs: Vec<BinaryBundle<F::Item>> = sender_keys
+        .iter()
+        .map(real)
+        .chain(std::iter::repeat(dummy).take(ndummies))
+        .chain(receiver_keys.iter().rev().map(real))
+        .collect();
+
+    // Bitonic merge
+    let mut stride = n / 2;
+    while stride > 0 {
+        for i in (0..n).filter(|i| i & stride == 0) {
+            fancy_compare_swap(f, &mut xs, i, i | stride)?;
+        }
+        stride /= 2;
+    }
+
+    let mut bits = xs
+        .windows(2)
+        .map(|pair| {
+            let eq = f.bin_eq_bundles(&pair[0], &pair[1])?;
+            let not_dummy = f.negate(&pair[0].wires()[nbits])?;
+            f.and(&eq, &not_dummy)
+        })
+        .collect::<Result<Vec<F::Item>, F::Error>>()?;
+    bits.push(f.constant(0, 2)?);
+    let keys = xs
+        .into_iter()
+        .map(|x| BinaryBundle::new(x.wires()[..nbits].to_vec()))
+        .collect();
+    Ok((bits, keys))
+}
+
+// Obliviously swap `xs[i]` and `xs[j]` if needed so that `xs[i] <= xs[j]`.
+fn fancy_compare_swap<F>(
+    f: &mut F,
+    xs: &mut [BinaryBundle<F::Item>],
+    i: usize,
+    j: usize,
+) -> Result<(), F::Error>
+where
+    F: Fancy + FancyBinary,
+{
+    let (x, y) = (&xs[i], &xs[j]);
+    let swap = f.bin_lt(y, x)?;
+    let diff = f.bin_xor(x, y)?;
+    let diff = diff
+        .wires()
+        .iter()
+        .map(|d| f.and(&swap, d))
+        .collect::<Result<Vec<F::Item>, F::Error>>()
+        .map(BinaryBundle::new)?;
+    let lo = f.bin_xor(x, &diff)?;
+    let hi = f.bin_xor(y, &diff)?;
+    xs[i] = lo;
+    xs[j] = hi;
+    Ok(())
+}
+
+/// Sorts `xs` in increasing order with a bitonic sorting network, whose length
+/// must be a power of two.
+fn fancy_bitonic_sort<F>(f: &mut F, xs: &mut [BinaryBundle<F::Item>]) -> Result<(), F::Error>
+where
+    F: Fancy + FancyBinary,
+{
+    let n = xs.len();
+    debug_assert!(n.is_power_of_two());
+    let mut size = 2;
+    while size <= n {
+        let mut stride = size / 2;
+        while stride > 0 {
+            for i in (0..n).filter(|i| i & stride == 0) {
+                if i & size == 0 {
+                    fancy_compare_swap(f, xs, i, i | stride)?;
+                } else {
+                    fancy_compare_swap(f, xs, i | stride, i)?;
+                }
+            }
+            stride /= 2;
+        }
+        size *= 2;
+    }
+    Ok(())
+}
+
+/// Sorts the bitonic sequence `xs` in increasing order with a bitonic merging
+/// network, whose length must be a power of two.
+fn fancy_bitonic_merge<F>(f: &mut F, xs: &mut [BinaryBundle<F::Item>]) -> Result<(), F::Error>
+where
+    F: Fancy + FancyBinary,
+{
+    let n = xs.len();
+    debug_assert!(n.is_power_of_two());
+    let mut stride = n / 2;
+    while stride > 0 {
+        for i in (0..n).filter(|i| i & stride == 0) {
+            fancy_compare_swap(f, xs, i, i | stride)?;
+        }
+        stride /= 2;
+    }
+    Ok(())
+}
+
 /// Fancy function that turns a slice of binary wires into a vector of BinaryBundle
 /// by grouping wires together according to the size of the element being bundled.
 pub fn wires_to_bundle<F>(x: &[F::Item], size: usize) -> Vec<BinaryBundle<F::Item>>
@@ -83,6 +266,90 @@ where
     Ok(acc)
 }
 
+/// Fancy function which computes the cardinality of the intersection with
+/// differential privacy noise added to it.
+///
+/// The bits of the intersection are counted with the popcount suited to `hint`
+/// (cf. `fancy_cardinality_with_hint`), and the count is extended with zeros
+/// to `DP_NOISE_BITS` bits. `noise_shares` are the parties' noise
+/// contributions as two's complement bundles of that width (cf.
+/// `PsiGarbler::dp_noise_shares`). Since each party only knows its own share,
+/// the other party's share alone hides the cardinality from it. The output
+/// should be decoded as a signed integer, since the noise may make it negative.
+pub fn fancy_noisy_cardinality<F, E>(
+    f: &mut F,
+    intersect_bitvec: &[<F as Fancy>::Item],
+    noise_shares: &[BinaryBundle<<F as Fancy>::Item>],
+    hint: NetworkHint,
+) -> Result<BinaryBundle<<F as Fancy>::Item>, Error>
+where
+    F: FancyBinary + Fancy<Item = WireMod2, Error = E>,
+    E: Debug,
+    Error: From<E>,
+{
+    let mut acc = fancy_cardinality_with_hint(f, intersect_bitvec, hint)?;
+    let zero = f.constant(0, 2)?;
+    while acc.size() < DP_NOISE_BITS {
+        acc.push(zero.clone());
+    }
+    for noise in noise_shares {
+        acc = f.bin_addition_no_carry(&acc, noise)?;
+    }
+    Ok(acc)
+}
+
+/// Fancy function which computes the cardinality of the intersection with the
+/// popcount suited to `hint`: `bin_popcount_low_depth` for
+/// `NetworkHint::Latency` and `bin_popcount` for `NetworkHint::Bandwidth`.
+///
+/// Unlike `fancy_cardinality`, the count only has enough bits to hold the number
+/// of bins.
+pub fn fancy_cardinality_with_hint<F>(
+    f: &mut F,
+    intersect_bitvec: &[F::Item],
+    hint: NetworkHint,
+) -> Result<BinaryBundle<F::Item>, F::Error>
+where
+    F: Fancy + FancyBinary,
+{
+    match hint {
+        NetworkHint::Bandwidth => f.bin_popcount(intersect_bitvec),
+        NetworkHint::Latency => f.bin_popcount_low_depth(intersect_bitvec),
+    }
+}
+
+/// Fancy function which applies randomized response to the intersection bit
+/// vector, flipping each bit with probability `threshold / 2^DP_FLIP_BITS`.
+///
+/// `flip_shares` are the parties' contributions, one `DP_FLIP_BITS`-bit bundle
+/// per bit of `intersect_bitvec` each (cf. `PsiGarbler::dp_flip_shares`). A bit is
+/// flipped when the sum of its shares is below `threshold`: since the sum is
+/// uniform as long as one party's share is, neither party knows which bits were
+/// flipped.
+pub fn fancy_randomized_response<F, E>(
+    f: &mut F,
+    intersect_bitvec: &[<F as Fancy>::Item],
+    flip_shares: &[Vec<BinaryBundle<<F as Fancy>::Item>>],
+    threshold: u128,
+) -> Result<Vec<<F as Fancy>::Item>, Error>
+where
+    F: FancyBinary + Fancy<Item = WireMod2, Error = E>,
+    E: Debug,
+    Error: From<E>,
+{
+    let threshold = f.bin_constant_bundle(threshold, utils::DP_FLIP_BITS)?;
+    let mut res = Vec::with_capacity(intersect_bitvec.len());
+    for (i, bit) in intersect_bitvec.iter().enumerate() {
+        let mut acc = f.bin_constant_bundle(0, utils::DP_FLIP_BITS)?;
+        for shares in flip_shares {
+            acc = f.bin_addition_no_carry(&acc, &shares[i])?;
+        }
+        let flip = f.bin_lt(&acc, &threshold)?;
+        res.push(f.xor(bit, &flip)?);
+    }
+    Ok(res)
+}
+
 /// Fancy function which computes the payload sum of the intersection
 /// where associated payloads with elements of the intersection are summed
 /// together and returned
@@ -108,3 +375,502 @@ where
     }
     Ok(acc)
 }
+
+/// Fancy function which computes the maximum of the parties' payloads associated
+/// with each element of the intersection.
+///
+/// The payloads are compared as unsigned integers. The i-th output bundle is the
+/// maximum of `payload_a[i]` and `payload_b[i]` if the i-th bit of the intersection
+/// bit vector is set, and 0 otherwise.
+pub fn fancy_payload_max<F, E>(
+    f: &mut F,
+    intersect_bitvec: &[<F as Fancy>::Item],
+    payload_a: &[BinaryBundle<<F as Fancy>::Item>],
+    payload_b: &[BinaryBundle<<F as Fancy>::Item>],
+) -> Result<Vec<BinaryBundle<<F as Fancy>::Item>>, Error>
+where
+    F: FancyBinary + Fancy<Item = WireMod2, Error = E>,
+    E: Debug,
+    Error: From<E>,
+{
+    let zero = f.bin_constant_bundle(0, PAYLOAD_SIZE * 8)?;
+    let mut res = Vec::with_capacity(intersect_bitvec.len());
+    for (i, bit) in intersect_bitvec.iter().enumerate() {
+        let max = f.bin_max(&[payload_a[i].clone(), payload_b[i].clone()])?;
+        res.push(f.bin_multiplex(bit, &zero, &max)?);
+    }
+    Ok(res)
+}
+
+/// Fancy function which computes `op` on the parties' payloads associated with
+/// each element of the intersection.
+///
+/// `payload_a` holds the garbler's payloads and `payload_b` the evaluator's. The
+/// i-th output bundle is `op` applied to `payload_a[i]` and `payload_b[i]` if the
+/// i-th bit of the intersection bit vector is set, and 0 otherwise.
+pub fn fancy_payload_combine<F, E>(
+    f: &mut F,
+    intersect_bitvec: &[<F as Fancy>::Item],
+    payload_a: &[BinaryBundle<<F as Fancy>::Item>],
+    payload_b: &[BinaryBundle<<F as Fancy>::Item>],
+    op: PayloadOp,
+) -> Result<Vec<BinaryBundle<<F as Fancy>::Item>>, Error>
+where
+    F: FancyBinary + Fancy<Item = WireMod2, Error = E>,
+    E: Debug,
+    Error: From<E>,
+{
+    let zero = f.bin_constant_bundle(0, PAYLOAD_SIZE * 8)?;
+    let mut res = Vec::with_capacity(intersect_bitvec.len());
+    for (i, bit) in intersect_bitvec.iter().enumerate() {
+        let combined = match op {
+            PayloadOp::Sum => f.bin_addition_no_carry(&payload_a[i], &payload_b[i])?,
+            PayloadOp::Difference => f.bin_subtraction(&payload_b[i], &payload_a[i])?.0,
+            PayloadOp::Product => f.bin_multiplication_lower_half(&payload_a[i], &payload_b[i])?,
+        };
+        res.push(f.bin_multiplex(bit, &zero, &combined)?);
+    }
+    Ok(res)
+}
+
+/// Fancy function which selects the payloads associated with each element of the
+/// intersection.
+///
+/// The i-th output bundle is `payload[i]` if the i-th bit of the intersection bit
+/// vector is set, and 0 otherwise, so that revealing it does not reveal anything
+/// about the payloads outside of the intersection.
+pub fn fancy_payload_select<F, E>(
+    f: &mut F,
+    intersect_bitvec: &[<F as Fancy>::Item],
+    payload: &[BinaryBundle<<F as Fancy>::Item>],
+) -> Result<Vec<BinaryBundle<<F as Fancy>::Item>>, Error>
+where
+    F: FancyBinary + Fancy<Item = WireMod2, Error = E>,
+    E: Debug,
+    Error: From<E>,
+{
+    let zero = f.bin_constant_bundle(0, PAYLOAD_SIZE * 8)?;
+    let mut res = Vec::with_capacity(intersect_bitvec.len());
+    for (i, bit) in intersect_bitvec.iter().enumerate() {
+        res.push(f.bin_multiplex(bit, &zero, &payload[i])?);
+    }
+    Ok(res)
+}
+
+/// The number of bits of the bin indices of `fancy_top_k` for `nbins` bins.
+pub fn top_k_index_bits(nbins: usize) -> usize {
+    (usize::BITS - nbins.saturating_sub(1).leading_zeros()).max(1) as usize
+}
+
+/// Fancy function which selects the `k` largest payloads associated with the
+/// elements of the intersection, e.g. the highest-value matched records.
+///
+/// Each payload is turned into a record holding, from the least significant
+/// bit, its bin index over `top_k_index_bits` bits if `with_index` is set, the
+/// payload, and whether its bin is in the intersection. Records outside of the
+/// intersection are zeroed, so that records compare as their payloads, ties
+/// being broken by bin index, and after every record in the intersection.
+///
+/// Rather than sorting all `n` records, the records are split into blocks of
+/// `k` (rounded up to a power of two), each sorted with a bitonic sorting
+/// network, and the blocks are merged one after the other while keeping only
+/// the largest `k` records. This costs `O(n log^2 k)` comparisons of records
+/// instead of the `O(n log^2 n)` of a full sort, i.e. the circuit grows with
+/// `n` times `log^2 k`.
+///
+/// Returns `min(k, n)` records in decreasing order, the records of bins outside
+/// of the intersection being zero.
+pub fn fancy_top_k<F>(
+    f: &mut F,
+    intersect_bitvec: &[F::Item],
+    payload: &[BinaryBundle<F::Item>],
+    k: usize,
+    with_index: bool,
+) -> Result<Vec<BinaryBundle<F::Item>>, F::Error>
+where
+    F: Fancy + FancyBinary,
+{
+    let n = intersect_bitvec.len();
+    if n == 0 || k == 0 {
+        return Ok(vec![]);
+    }
+    let index_bits = if with_index { top_k_index_bits(n) } else { 0 };
+    let nbits = index_bits + PAYLOAD_SIZE * 8 + 1;
+    let zero = f.bin_constant_bundle(0, nbits)?;
+    let mut records = Vec::with_capacity(n);
+    for (i, (bit, x)) in intersect_bitvec.iter().zip_eq(payload).enumerate() {
+        let index = f.bin_constant_bundle(i as u128, index_bits)?;
+        let record = BinaryBundle::new(
+            index
+                .wires()
+                .iter()
+                .chain(x.wires())
+                .chain([bit])
+                .cloned()
+                .collect(),
+        );
+        records.push(f.bin_multiplex(bit, &zero, &record)?);
+    }
+
+    let block = k.next_power_of_two();
+    records.resize(n.div_ceil(block) * block, zero);
+    let mut blocks = records.chunks_exact_mut(block);
+    let top = blocks.next().unwrap();
+    fancy_bitonic_sort(f, top)?;
+    let mut top = top.to_vec();
+    for other in blocks {
+        fancy_bitonic_sort(f, other)?;
+        // The pairwise maxima of an increasing and a decreasing sequence are a
+        // bitonic sequence holding the largest half of both
+        for (x, y) in top.iter_mut().zip(other.iter().rev()) {
+            *x = f.bin_max(&[x.clone(), y.clone()])?;
+        }
+        fancy_bitonic_merge(f, &mut top)?;
+    }
+    top.reverse();
+    top.truncate(k.min(n));
+    Ok(top)
+}
+
+/// Fancy function which sums a payload column over the intersection, wrapping
+/// around at the width of the column.
+///
+/// `column` holds one party's column bundles aligned with the intersection bit
+/// vector, as split by `PayloadSchema::split_columns`.
+pub fn fancy_column_sum<F, E>(
+    f: &mut F,
+    intersect_bitvec: &[<F as Fancy>::Item],
+    column: &[BinaryBundle<<F as Fancy>::Item>],
+) -> Result<BinaryBundle<<F as Fancy>::Item>, Error>
+where
+    F: FancyBinary + Fancy<Item = WireMod2, Error = E>,
+    E: Debug,
+    Error: From<E>,
+{
+    let nbits = column.first().map_or(0, |x| x.size());
+    let mut acc = f.bin_constant_bundle(0, nbits)?;
+    let zero = f.bin_constant_bundle(0, nbits)?;
+    for (bit, x) in intersect_bitvec.iter().zip(column.iter()) {
+        let mux = f.bin_multiplex(bit, &zero, x)?;
+        acc = f.bin_addition_no_carry(&acc, &mux)?;
+    }
+    Ok(acc)
+}
+
+/// Fancy function which counts the distinct values of a payload column over the
+/// intersection.
+///
+/// `column` holds one party's column bundles aligned with the intersection bit
+/// vector, as split by `PayloadSchema::split_columns`. The values are extended
+/// with a most significant bit set for the bins outside of the intersection, so
+/// that they come after every value in the intersection, and sorted with a
+/// bitonic sorting network. A value is then counted when it differs from the
+/// previous one. For `n` bins (rounded up to a power of two) the sort costs
+/// `O(n log^2 n)` comparisons, i.e. `O(n log^2 n * w)` AND gates for columns of
+/// `w` bits, which dominates the cost of the intersection itself.
+pub fn fancy_column_count_distinct<F, E>(
+    f: &mut F,
+    intersect_bitvec: &[<F as Fancy>::Item],
+    column: &[BinaryBundle<<F as Fancy>::Item>],
+) -> Result<BinaryBundle<<F as Fancy>::Item>, Error>
+where
+    F: FancyBinary + Fancy<Item = WireMod2, Error = E>,
+    E: Debug,
+    Error: From<E>,
+{
+    let nbits = column.first().map_or(0, |x| x.size());
+    let n = column.len().next_power_of_two();
+    let one = f.constant(1, 2)?;
+    let dummy = BinaryBundle::new(vec![one; nbits + 1]);
+    let mut xs = Vec::with_capacity(n);
+    for (bit, x) in intersect_bitvec.iter().zip_eq(column.iter()) {
+        let outside = f.negate(bit)?;
+        xs.push(BinaryBundle::new(
+            x.wires().iter().chain([&outside]).cloned().collect(),
+        ));
+    }
+    xs.resize(n, dummy);
+    fancy_bitonic_sort(f, &mut xs)?;
+
+    // A value in the intersection is new if it differs from the previous one
+    let mut new_values = Vec::with_capacity(n);
+    for (i, x) in xs.iter().enumerate() {
+        let inside = f.negate(&x.wires()[nbits])?;
+        if i == 0 {
+            new_values.push(inside);
+        } else {
+            let eq = f.bin_eq_bundles(x, &xs[i - 1])?;
+            let neq = f.negate(&eq)?;
+            new_values.push(f.and(&inside, &neq)?);
+        }
+    }
+    Ok(f.bin_popcount(&new_values)?)
+}
+
+/// Fancy function which computes, for each of the evaluator's `nrecords` records,
+/// whether it is in the intersection of any of several key columns.
+///
+/// `bit_vectors[c]` is the intersection bit vector of the `c`-th key column, and
+/// `tags[c]` holds for each of its bins the index of the evaluator's record
+/// placed in the bin, or all ones for an empty bin (cf. `key_column_tag_bits`).
+/// Since the tags are private to the evaluator, the bits are routed to the
+/// records by sorting the pairs of tags and bits with a bitonic sorting network:
+/// each record is placed in exactly one bin per column, so that the `k` bits of
+/// record `i` then come at positions `k * i` to `k * i + k - 1` for `k` columns,
+/// and are ORed. For `m` bins over all columns (rounded up to a power of two)
+/// this costs `O(m log^2 m * w)` AND gates for tags of `w` bits.
+pub fn fancy_any_column_bit_vector<F, E>(
+    f: &mut F,
+    bit_vectors: &[Vec<<F as Fancy>::Item>],
+    tags: &[Vec<BinaryBundle<<F as Fancy>::Item>>],
+    nrecords: usize,
+) -> Result<Vec<<F as Fancy>::Item>, Error>
+where
+    F: FancyBinary + Fancy<Item = WireMod2, Error = E>,
+    E: Debug,
+    Error: From<E>,
+{
+    let ncolumns = bit_vectors.len();
+    let tag_bits = key_column_tag_bits(nrecords);
+    let mut xs = Vec::new();
+    for (bits, tags) in bit_vectors.iter().zip_eq(tags.iter()) {
+        for (bit, tag) in bits.iter().zip_eq(tags.iter()) {
+            // The bit is the least significant one, so that the pairs are sorted
+            // by tag first
+            xs.push(BinaryBundle::new(
+                [bit].into_iter().chain(tag.wires()).cloned().collect(),
+            ));
+        }
+    }
+    let one = f.constant(1, 2)?;
+    let dummy = BinaryBundle::new(vec![one; tag_bits + 1]);
+    xs.resize(xs.len().next_power_of_two(), dummy);
+    fancy_bitonic_sort(f, &mut xs)?;
+
+    let mut res = Vec::with_capacity(nrecords);
+    for record in xs.chunks(ncolumns).take(nrecords) {
+        let mut acc = record[0].wires()[0].clone();
+        for x in record.iter().skip(1) {
+            acc = f.or(&acc, &x.wires()[0])?;
+        }
+        res.push(acc);
+    }
+    Ok(res)
+}
+
+/// The number of bits of the tags of `fancy_any_column_bit_vector` for
+/// `nrecords` records, so that all ones is not a record index.
+pub fn key_column_tag_bits(nrecords: usize) -> usize {
+    (usize::BITS - nrecords.leading_zeros()).max(1) as usize
+}
+
+/// Fancy function which reveals to both parties how the garbler's set size
+/// compares to the evaluator's, and nothing else about them.
+///
+/// The sizes are compared with two `bin_lt` comparisons, and only their two
+/// result bits are revealed.
+pub fn fancy_compare_set_sizes<F, E>(
+    f: &mut F,
+    garbler_size: &BinaryBundle<<F as Fancy>::Item>,
+    evaluator_size: &BinaryBundle<<F as Fancy>::Item>,
+) -> Result<Ordering, Error>
+where
+    F: FancyBinary + FancyReveal + Fancy<Item = WireMod2, Error = E>,
+    E: Debug,
+    Error: From<E>,
+{
+    let less = f.bin_lt(garbler_size, evaluator_size)?;
+    let greater = f.bin_lt(evaluator_size, garbler_size)?;
+    let bits = f.reveal_many(&[less, greater])?;
+    Ok(match (bits[0], bits[1]) {
+        (1, _) => Ordering::Less,
+        (_, 1) => Ordering::Greater,
+        _ => Ordering::Equal,
+    })
+}
+
+/// Fancy function which computes the inclusive prefix sums of `items`, e.g. for
+/// windowed aggregations over sorted intersection results: the i-th output is
+/// the sum of the first `i + 1` items.
+///
+/// The items are zero-extended by `ceil(log2(n))` bits for `n` items, so that
+/// the sums never overflow. They are computed with a work-efficient (Blelloch)
+/// scan over the items padded to a power of two: an up-sweep computes the sums
+/// of subtrees, and a down-sweep turns them into the exclusive prefix sums, the
+/// inclusive sum of an item being the exclusive sum of the next one. This costs
+/// `2n` additions, with a depth of `2 log n` additions instead of the `n` of a
+/// sequential sum.
+pub fn fancy_prefix_sum<F>(
+    f: &mut F,
+    items: &[BinaryBundle<F::Item>],
+) -> Result<Vec<BinaryBundle<F::Item>>, F::Error>
+where
+    F: Fancy + FancyBinary,
+{
+    let len = items.len();
+    if len == 0 {
+        return Ok(vec![]);
+    }
+    let n = len.next_power_of_two();
+    let nbits = items.iter().map(|x| x.size()).max().unwrap_or(0) + n.trailing_zeros() as usize;
+    let zero = f.constant(0, 2)?;
+    let extend = |x: &BinaryBundle<F::Item>| {
+        let mut wires = x.wires().to_vec();
+        wires.resize(nbits, zero.clone());
+        BinaryBundle::new(wires)
+    };
+    let mut xs: Vec<BinaryBundle<F::Item>> = items.iter().map(extend).collect();
+    xs.resize(n, BinaryBundle::new(vec![zero.clone(); nbits]));
+
+    // Up-sweep: each node holds the sum of its subtree
+    let mut stride = 1;
+    while stride < n {
+        for i in (0..n).step_by(2 * stride) {
+            xs[i + 2 * stride - 1] =
+                f.bin_addition_no_carry(&xs[i + stride - 1], &xs[i + 2 * stride - 1])?;
+        }
+        stride *= 2;
+    }
+    let total = xs[n - 1].clone();
+
+    // Down-sweep: each node gets the sum of the items before its subtree
+    xs[n - 1] = BinaryBundle::new(vec![zero; nbits]);
+    while stride > 1 {
+        stride /= 2;
+        for i in (0..n).step_by(2 * stride) {
+            let left = xs[i + stride - 1].clone();
+            xs[i + stride - 1] = xs[i + 2 * stride - 1].clone();
+            xs[i + 2 * stride - 1] = f.bin_addition_no_carry(&left, &xs[i + 2 * stride - 1])?;
+        }
+    }
+    xs.push(total);
+    xs.truncate(len + 1);
+    Ok(xs.split_off(1))
+}
+
+/// Fancy function which obliviously shuffles `items`, so that their order does
+/// not depend on their original order.
+///
+/// Each item is tagged with the XOR of both parties' tags for it, which is
+/// uniformly random as long as one of the parties' tags is, and the items are
+/// sorted by tag with a bitonic sorting network. No party learns the
+/// permutation, and ties between tags, which occur with probability about
+/// `n^2 / 2^(w + 1)` for `n` items and tags of `w` bits, only leave the tied
+/// items in their original order. This costs `O(n log^2 n)` comparisons of the
+/// tagged items.
+pub fn fancy_shuffle<F>(
+    f: &mut F,
+    items: &[BinaryBundle<F::Item>],
+    garbler_tags: &[BinaryBundle<F::Item>],
+    evaluator_tags: &[BinaryBundle<F::Item>],
+) -> Result<Vec<BinaryBundle<F::Item>>, F::Error>
+where
+    F: Fancy + FancyBinary,
+{
+    let len = items.len();
+    if len == 0 {
+        return Ok(vec![]);
+    }
+    let nbits = items[0].size();
+    let tag_bits = garbler_tags[0].size();
+    let zero = f.constant(0, 2)?;
+    let one = f.constant(1, 2)?;
+    // The tag comes before the item, and a most significant bit set for the
+    // padding keeps it after every item
+    let mut xs = Vec::with_capacity(len.next_power_of_two());
+    for ((x, a), b) in items.iter().zip_eq(garbler_tags).zip_eq(evaluator_tags) {
+        let tag = f.bin_xor(a, b)?;
+        xs.push(BinaryBundle::new(
+            x.wires()
+                .iter()
+                .chain(tag.wires())
+                .chain([&zero])
+                .cloned()
+                .collect(),
+        ));
+    }
+    xs.resize(
+        len.next_power_of_two(),
+        BinaryBundle::new(vec![one; nbits + tag_bits + 1]),
+    );
+    fancy_bitonic_sort(f, &mut xs)?;
+    Ok(xs
+        .into_iter()
+        .take(len)
+        .map(|x| BinaryBundle::new(x.wires()[..nbits].to_vec()))
+        .collect())
+}
+
+/// Fancy function which computes the union of the parties' keys, obliviously
+/// shuffled (cf. `fancy_shuffle`).
+///
+/// The keys of both parties are sorted together with a bitonic sorting network,
+/// so that a key both parties have ends up next to its copy, which is then
+/// marked as a duplicate. The keys and their duplicate bits are then shuffled
+/// with the parties' tags, one per key of either party, so that revealing them
+/// only reveals the union and its size. This costs `O(n log^2 n)` comparisons
+/// of keys for `n` keys in total, and as many of the tagged keys.
+///
+/// Returns the shuffled keys along with, for each of them, whether it is a
+/// duplicate of another key.
+pub fn fancy_shuffled_union<F>(
+    f: &mut F,
+    sender_keys: &[BinaryBundle<F::Item>],
+    receiver_keys: &[BinaryBundle<F::Item>],
+    garbler_tags: &[BinaryBundle<F::Item>],
+    evaluator_tags: &[BinaryBundle<F::Item>],
+) -> Result<(Vec<BinaryBundle<F::Item>>, Vec<F::Item>), F::Error>
+where
+    F: Fancy + FancyBinary,
+{
+    let len = sender_keys.len() + receiver_keys.len();
+    if len == 0 {
+        return Ok((vec![], vec![]));
+    }
+    let nbits = sender_keys
+        .iter()
+        .chain(receiver_keys)
+        .next()
+        .unwrap()
+        .size();
+    let zero = f.constant(0, 2)?;
+    let one = f.constant(1, 2)?;
+    // A most significant bit set for the padding keeps it after every key
+    let mut xs = Vec::with_capacity(len.next_power_of_two());
+    for x in sender_keys.iter().chain(receiver_keys) {
+        xs.push(BinaryBundle::new(
+            x.wires().iter().chain([&zero]).cloned().collect(),
+        ));
+    }
+    xs.resize(
+        len.next_power_of_two(),
+        BinaryBundle::new(vec![one; nbits + 1]),
+    );
+    fancy_bitonic_sort(f, &mut xs)?;
+    xs.truncate(len);
+
+    let mut items = Vec::with_capacity(len);
+    for i in 0..len {
+        let duplicate = if i == 0 {
+            zero.clone()
+        } else {
+            f.bin_eq_bundles(&xs[i], &xs[i - 1])?
+        };
+        items.push(BinaryBundle::new(
+            xs[i].wires()[..nbits]
+                .iter()
+                .chain([&duplicate])
+                .cloned()
+                .collect(),
+        ));
+    }
+    let shuffled = fancy_shuffle(f, &items, garbler_tags, evaluator_tags)?;
+    Ok(shuffled
+        .into_iter()
+        .map(|x| {
+            let mut wires = x.wires().to_vec();
+            let duplicate = wires.pop().unwrap();
+            (BinaryBundle::new(wires), duplicate)
+        })
+        .unzip())
+}
@@ -3,16 +3,22 @@ use crate::{
     errors::Error,
     psi::circuit_psi::{circuits::*, *},
 };
-use fancy_garbling::{twopac::semihonest::Evaluator, WireMod2};
-use ocelot::ot::AlszReceiver as OtReceiver;
+use fancy_garbling::{
+    twopac::semihonest::Evaluator, BinaryBundle, BinaryGadgets, Fancy, FancyInput, WireMod2,
+};
+use ocelot::ot::{precomputed::PrecomputedOtReceiver, AlszReceiver};
 use scuttlebutt::{AbstractChannel, Block, SemiHonest};
-use std::marker::PhantomData;
+use std::{cmp::Ordering, marker::PhantomData};
 
 use self::receiver::OpprfReceiver;
 
 /// An Evaluating party for Circuit PSI that uses OPPRF Base PSI
 pub type OpprfPsiEvaluator<C, RNG> = PsiEvaluator<C, RNG, OpprfReceiver>;
 
+/// The OT receiver of the garbled circuit, consuming precomputed correlations
+/// before running OT extension
+type OtReceiver = PrecomputedOtReceiver<AlszReceiver>;
+
 /// A struct defining the Evaluating party in Circuit Psi
 pub struct PsiEvaluator<C, RNG, B> {
     /// The actual evaluator being called during the garbled circuit
@@ -21,6 +27,35 @@ pub struct PsiEvaluator<C, RNG, B> {
     pub channel: C,
     /// The evaluator's dedicated rng
     pub rng: RNG,
+    /// Whether the parties check that they use the same base PSI hashing parameters
+    pub verify: bool,
+    /// A public seed (e.g. from a randomness beacon) the parties derive their
+    /// public randomness from. Both parties must set the same seed.
+    pub public_seed: Option<Block>,
+    /// The security parameters of the protocol. Both parties must use the
+    /// same parameters.
+    pub security: SecurityParams,
+    /// The cuckoo hashing strategy of the base PSI. Both parties must use the
+    /// same strategy.
+    pub cuckoo_strategy: CuckooStrategy,
+    /// The parameters of the evaluator's cuckoo hash table in the base PSI,
+    /// e.g. its stash size. Only the evaluator sets them, as only it does
+    /// cuckoo hashing.
+    pub cuckoo_params: CuckooParams,
+    /// If set, the intersection bit vector is output in randomized response with
+    /// this parameter `epsilon`, each bit being flipped with probability
+    /// `1 / (1 + exp(epsilon))` (cf. `utils::dp_flip_threshold`). This makes the
+    /// presence of every primary key `epsilon`-differentially private. Both
+    /// parties must set the same `epsilon`.
+    pub dp_output: Option<f64>,
+    /// If set, the cardinality revealed by `CircuitPsiExt::intersect_cardinality` has
+    /// discrete Laplace noise with this parameter `epsilon` added to it by each
+    /// party, which makes it `epsilon`-differentially private. Both parties must
+    /// set the same `epsilon`.
+    pub dp_cardinality: Option<f64>,
+    /// Whether the statistics of the party's hashing table are returned with the
+    /// intersection (cf. `PsiDiagnostics`)
+    pub diagnostics: bool,
     /// A witness for the Base PSI protocol
     _base_psi: PhantomData<B>,
 }
@@ -42,9 +77,61 @@ where
             )?,
             channel: channel.clone(),
             rng: RNG::from_seed(seed),
+            verify: false,
+            public_seed: None,
+            security: SecurityParams::default(),
+            cuckoo_strategy: CuckooStrategy::default(),
+            cuckoo_params: CuckooParams::default(),
+            dp_output: None,
+            dp_cardinality: None,
+            diagnostics: false,
             _base_psi: PhantomData,
         })
     }
+
+    /// Flushes the channel and waits for the other party to reach the same point
+    /// of the protocol.
+    pub fn barrier(&mut self) -> Result<(), Error> {
+        utils::barrier(&mut self.channel)
+    }
+
+    /// Samples the party's discrete Laplace noise share with parameter `epsilon` and
+    /// inputs both parties' shares, to be used with `fancy_noisy_cardinality`.
+    ///
+    /// Both parties must call this with the same `epsilon`. The shares are returned
+    /// in the garbler, evaluator order.
+    pub fn dp_noise_shares(&mut self, epsilon: f64) -> Result<Vec<BinaryBundle<WireMod2>>, Error> {
+        let noise = utils::sample_discrete_laplace(epsilon, &mut self.rng)?;
+        let garbler_share = self.ev.bin_receive(DP_NOISE_BITS)?;
+        let evaluator_share = self.ev.bin_encode(noise as u64 as u128, DP_NOISE_BITS)?;
+        Ok(vec![garbler_share, evaluator_share])
+    }
+
+    /// Samples the party's uniform shares of the randomized response flips of
+    /// `nbits` bits and inputs both parties' shares, to be used with
+    /// `fancy_randomized_response`.
+    ///
+    /// Both parties must call this with the same `nbits`. The shares are returned
+    /// in the garbler, evaluator order.
+    pub fn dp_flip_shares(
+        &mut self,
+        nbits: usize,
+    ) -> Result<Vec<Vec<BinaryBundle<WireMod2>>>, Error> {
+        let shares: Vec<u128> = (0..nbits).map(|_| self.rng.gen::<u32>() as u128).collect();
+        let garbler_shares = self.ev.bin_receive_many(nbits, utils::DP_FLIP_BITS)?;
+        let evaluator_shares = self.ev.bin_encode_many(&shares, utils::DP_FLIP_BITS)?;
+        Ok(vec![garbler_shares, evaluator_shares])
+    }
+
+    /// Compares the evaluator's set size `my_size` with the garbler's, returning
+    /// how `my_size` compares to the other party's size (cf.
+    /// `PsiGarbler::compare_set_sizes`).
+    pub fn compare_set_sizes(&mut self, my_size: usize) -> Result<Ordering, Error> {
+        let garbler_size = self.ev.bin_receive(SET_SIZE_BITS)?;
+        let evaluator_size = self.ev.bin_encode(my_size as u128, SET_SIZE_BITS)?;
+        let ordering = fancy_compare_set_sizes(&mut self.ev, &garbler_size, &evaluator_size)?;
+        Ok(ordering.reverse())
+    }
 }
 
 impl<C, RNG, B> SemiHonest for PsiEvaluator<C, RNG, B> {}
@@ -59,20 +146,25 @@ where
     ///
     /// (0) Check that the set of primary keys has the same size as the set of payloads
     /// if the latter is not empty.
-    /// (1) Call the Base Psi to create the circuit's input.
+    /// (1) Call the Base Psi to create the circuit's input with the `security`
+    /// parameters, checking that the parties use the same hashing parameters if
+    /// `verify` or `public_seed` is set.
     /// The Base Psi effectively constructs the intersection in a hidden form
     /// that only the garbled circuit can read and operate on.
-    /// (2) Turns the circuit inputs into bundles that are easier to operate on in swanky's
+    /// (2) Synchronize both parties before garbling the circuit.
+    /// (3) Turns the circuit inputs into bundles that are easier to operate on in swanky's
     /// fancy garbling.
-    /// (3) Takes the output of the Base Psi and turns it into a garbled intersection bit
+    /// (4) Takes the output of the Base Psi and turns it into a garbled intersection bit
     /// vector which indicates the presence or abscence of a primary key.
-    /// (4) Computes the user defined circuit on the parties' inputs.
+    /// If `dp_output` is set, each bit is then flipped with the configured probability.
+    /// (5) Computes the user defined circuit on the parties' inputs.
     fn intersect_with_payloads(
         &mut self,
         primary_keys: &[PrimaryKey],
         payloads: Option<&[Payload]>,
     ) -> Result<Intersection, Error> {
         // (0)
+        let dp_threshold = self.dp_output.map(utils::dp_flip_threshold).transpose()?;
         if payloads.is_some() && primary_keys.len() != payloads.unwrap().len() {
             return Err(Error::PayloadSetNotComplete {
                 npayloads: payloads.unwrap().len(),
@@ -86,18 +178,43 @@ where
             payloads,
             &mut self.channel,
             &mut self.rng,
+            self.verify,
+            self.public_seed,
+            self.security,
+            self.cuckoo_strategy,
+            self.cuckoo_params,
+            self.diagnostics,
         )?;
         // (2)
+        self.barrier()?;
+        trace_phase!(
+            "garbling",
+            nwires = circuit_inputs.sender_primary_keys.len()
+        );
+        // (3)
         let primary_keys =
             bundle_primary_keys::<Evaluator<C, RNG, OtReceiver, WireMod2>, _>(&circuit_inputs)?;
         let (sender_payloads, receiver_payloads) = bundle_payloads(&mut self.ev, &circuit_inputs)?;
 
-        // (3)
+        // (4)
         let existence_bit_vector = fancy_intersection_bit_vector(
             &mut self.ev,
             &circuit_inputs.sender_primary_keys,
             &circuit_inputs.receiver_primary_keys,
+            circuit_inputs.hash_size,
         )?;
+        let existence_bit_vector = match dp_threshold {
+            Some(threshold) => {
+                let flip_shares = self.dp_flip_shares(existence_bit_vector.len())?;
+                fancy_randomized_response(
+                    &mut self.ev,
+                    &existence_bit_vector,
+                    &flip_shares,
+                    threshold,
+                )?
+            }
+            None => existence_bit_vector,
+        };
 
         let intersection_results = Intersection {
             intersection: PrivateIntersection {
@@ -108,6 +225,8 @@ where
                 sender_payloads,
                 receiver_payloads,
             },
+            input_indices: circuit_inputs.input_indices,
+            diagnostics: circuit_inputs.diagnostics,
         };
         Ok(intersection_results)
     }
@@ -115,3 +234,397 @@ where
         self.intersect_with_payloads(primary_keys, None)
     }
 }
+
+impl<C, RNG, B> CircuitPsiExt for PsiEvaluator<C, RNG, B>
+where
+    C: AbstractChannel + Clone,
+    RNG: RngCore + CryptoRng + Rng + SeedableRng<Seed = Block>,
+    B: BasePsi,
+{
+    /// Reveals the intersection bit vector and maps every bin in the intersection
+    /// back to the evaluator's row placed in it.
+    fn intersect_with_rows<T: Clone>(
+        &mut self,
+        rows: &[(PrimaryKey, T)],
+    ) -> Result<Vec<(PrimaryKey, T)>, Error> {
+        let primary_keys: Vec<PrimaryKey> = rows.iter().map(|(key, _)| key.clone()).collect();
+        let intersection_results = self.intersect(&primary_keys)?;
+        let existence_bit_vector = &intersection_results.intersection.existence_bit_vector;
+        trace_phase!("reveal", nbits = existence_bit_vector.len());
+        let opened_bits = self
+            .ev
+            .outputs(existence_bit_vector)?
+            .expect("evaluator should produce outputs");
+
+        let mut input_indices = opened_bits
+            .into_iter()
+            .zip(intersection_results.input_indices)
+            .filter_map(|(bit, index)| if bit == 1 { index } else { None })
+            .collect::<Vec<usize>>();
+        // Return the rows in the order they were given
+        input_indices.sort_unstable();
+        Ok(input_indices.into_iter().map(|i| rows[i].clone()).collect())
+    }
+    fn set_difference(&mut self, primary_keys: &[PrimaryKey]) -> Result<Vec<PrimaryKey>, Error> {
+        let intersection_results = self.intersect(primary_keys)?;
+        let difference_bit_vector = fancy_complement_bit_vector(
+            &mut self.ev,
+            &intersection_results.intersection.existence_bit_vector,
+        )?;
+        trace_phase!("reveal", nbits = difference_bit_vector.len());
+        let opened_bits = self
+            .ev
+            .outputs(&difference_bit_vector)?
+            .expect("evaluator should produce outputs");
+
+        // Empty bins are not in the intersection either, but hold no key
+        let mut input_indices = opened_bits
+            .into_iter()
+            .zip(intersection_results.input_indices)
+            .filter_map(|(bit, index)| if bit == 1 { index } else { None })
+            .collect::<Vec<usize>>();
+        input_indices.sort_unstable();
+        Ok(input_indices
+            .into_iter()
+            .map(|i| primary_keys[i].clone())
+            .collect())
+    }
+    /// The evaluator's shares are the sender's payloads masked with the
+    /// garbler's shares.
+    fn intersect_with_payload_shares(
+        &mut self,
+        primary_keys: &[PrimaryKey],
+        payloads: Option<&[Payload]>,
+    ) -> Result<(Intersection, Vec<Payload>), Error> {
+        let intersection_results = self.intersect_with_payloads(primary_keys, payloads)?;
+        let sender_payloads = &intersection_results.payloads.sender_payloads;
+        let share_wires = self
+            .ev
+            .bin_receive_many(sender_payloads.len(), PAYLOAD_SIZE * 8)?;
+        let mut shares = Vec::with_capacity(sender_payloads.len());
+        for (payload, share) in sender_payloads.iter().zip(share_wires.iter()) {
+            let masked = self.ev.bin_xor(payload, share)?;
+            shares.push(
+                self.ev
+                    .bin_output(&masked)?
+                    .expect("evaluator should produce outputs"),
+            );
+        }
+        Ok((
+            intersection_results,
+            utils::int_vec_block512(shares, PAYLOAD_SIZE),
+        ))
+    }
+    fn intersect_fuzzy(
+        &mut self,
+        primary_keys: &[PrimaryKey],
+        max_distance: usize,
+    ) -> Result<Intersection, Error> {
+        let nsender = self.channel.read_usize()?;
+        self.channel.write_usize(primary_keys.len())?;
+        self.channel.flush()?;
+        let sender_keys = self.ev.bin_receive_many(nsender, PRIMARY_KEY_SIZE * 8)?;
+        let receiver_keys = self
+            .ev
+            .bin_encode_many(&fuzzy_key_values(primary_keys), PRIMARY_KEY_SIZE * 8)?;
+        let existence_bit_vector = fancy_fuzzy_intersection_bit_vector(
+            &mut self.ev,
+            &sender_keys,
+            &receiver_keys,
+            max_distance,
+        )?;
+        Ok(Intersection {
+            intersection: PrivateIntersection {
+                existence_bit_vector,
+                primary_keys: receiver_keys,
+            },
+            payloads: Default::default(),
+            input_indices: (0..primary_keys.len()).map(Some).collect(),
+            diagnostics: None,
+        })
+    }
+    fn intersect_sorted(&mut self, primary_keys: &[PrimaryKey]) -> Result<Intersection, Error> {
+        let values = sorted_key_values(primary_keys)?;
+        let nsender = self.channel.read_usize()?;
+        self.channel.write_usize(primary_keys.len())?;
+        self.channel.flush()?;
+        let sender_keys = self.ev.bin_receive_many(nsender, PRIMARY_KEY_SIZE * 8)?;
+        let receiver_keys = self.ev.bin_encode_many(&values, PRIMARY_KEY_SIZE * 8)?;
+        let (existence_bit_vector, primary_keys) =
+            fancy_sorted_intersection(&mut self.ev, &sender_keys, &receiver_keys)?;
+        Ok(Intersection {
+            intersection: PrivateIntersection {
+                existence_bit_vector,
+                primary_keys,
+            },
+            payloads: Default::default(),
+            input_indices: vec![],
+            diagnostics: None,
+        })
+    }
+    fn intersect_prescreened(
+        &mut self,
+        primary_keys: &[PrimaryKey],
+        rate: f64,
+    ) -> Result<Intersection, Error> {
+        let candidates: Vec<usize> = {
+            let nsender = self.channel.read_usize()?;
+            let (nbins, nhashes) = prescreen_parameters(nsender, rate)?;
+            trace_phase!("prescreen", nbins);
+            let bytes = self.channel.read_lenprefixed()?;
+            if bytes.len() != nbins.div_ceil(8) {
+                return Err(Error::PsiProtocolError(format!(
+                    "prescreen filter of {} bytes, expected {} bytes",
+                    bytes.len(),
+                    nbins.div_ceil(8)
+                )));
+            }
+            let filter = BloomFilter::from_bytes(&bytes, nbins, nhashes);
+            (0..primary_keys.len())
+                .filter(|i| filter.contains(&primary_keys[*i]))
+                .collect()
+        };
+        let candidate_keys: Vec<PrimaryKey> = candidates
+            .iter()
+            .map(|i| primary_keys[*i].clone())
+            .collect();
+        let mut intersection_results = self.intersect(&candidate_keys)?;
+        for index in intersection_results.input_indices.iter_mut().flatten() {
+            *index = candidates[*index];
+        }
+        Ok(intersection_results)
+    }
+    fn intersect_online(
+        &mut self,
+        primary_keys: &[PrimaryKey],
+        precomputed: PrecomputedOts,
+    ) -> Result<Intersection, Error> {
+        self.ev.ot_mut().extend(precomputed)?;
+        self.intersect(primary_keys)
+    }
+    fn intersect_cardinality(
+        &mut self,
+        primary_keys: &[PrimaryKey],
+        hint: NetworkHint,
+    ) -> Result<Option<i64>, Error> {
+        let intersection = self.intersect(primary_keys)?;
+        let existence_bit_vector = &intersection.intersection.existence_bit_vector;
+        let count = match self.dp_cardinality {
+            Some(epsilon) => {
+                let noise_shares = self.dp_noise_shares(epsilon)?;
+                fancy_noisy_cardinality(&mut self.ev, existence_bit_vector, &noise_shares, hint)?
+            }
+            None => fancy_cardinality_with_hint(&mut self.ev, existence_bit_vector, hint)?,
+        };
+        let count = self
+            .ev
+            .bin_output(&count)?
+            .expect("evaluator should produce outputs");
+        // The noisy count is a two's complement integer
+        Ok(Some(count as u64 as i64))
+    }
+    fn intersect_count_distinct_column(
+        &mut self,
+        primary_keys: &[PrimaryKey],
+        payloads: &[Payload],
+        schema: &PayloadSchema,
+        column: usize,
+    ) -> Result<Option<u128>, Error> {
+        check_column(schema, column)?;
+        let intersection_results = self.intersect_with_payloads(primary_keys, Some(payloads))?;
+        let columns = schema.split_columns(&intersection_results.payloads.sender_payloads)?;
+        let count = fancy_column_count_distinct(
+            &mut self.ev,
+            &intersection_results.intersection.existence_bit_vector,
+            &columns[column],
+        )?;
+        let count = self
+            .ev
+            .bin_output(&count)?
+            .expect("evaluator should produce outputs");
+        Ok(Some(count))
+    }
+    fn intersect_payload_max(
+        &mut self,
+        primary_keys: &[PrimaryKey],
+        payloads: &[Payload],
+    ) -> Result<Vec<u128>, Error> {
+        let intersection_results = self.intersect_with_payloads(primary_keys, Some(payloads))?;
+        let maxima = fancy_payload_max(
+            &mut self.ev,
+            &intersection_results.intersection.existence_bit_vector,
+            &intersection_results.payloads.sender_payloads,
+            &intersection_results.payloads.receiver_payloads,
+        )?;
+        let maxima = self
+            .ev
+            .bin_outputs(&maxima)?
+            .expect("evaluator should produce outputs");
+
+        // Map every bin back to the evaluator's primary key placed in it
+        let mut res = vec![0; primary_keys.len()];
+        for (max, index) in maxima.into_iter().zip(intersection_results.input_indices) {
+            if let Some(i) = index {
+                res[i] = max;
+            }
+        }
+        Ok(res)
+    }
+    fn intersect_top_k(
+        &mut self,
+        primary_keys: &[PrimaryKey],
+        payloads: &[Payload],
+        k: usize,
+        with_keys: bool,
+    ) -> Result<Vec<(u128, Option<PrimaryKey>)>, Error> {
+        let intersection_results = self.intersect_with_payloads(primary_keys, Some(payloads))?;
+        let nbins = intersection_results.input_indices.len();
+        let records = fancy_top_k(
+            &mut self.ev,
+            &intersection_results.intersection.existence_bit_vector,
+            &intersection_results.payloads.sender_payloads,
+            k,
+            with_keys,
+        )?;
+        trace_phase!("reveal", nbits = records.len());
+        let records = self
+            .ev
+            .bin_outputs(&records)?
+            .expect("evaluator should produce outputs");
+
+        // Records outside of the intersection are zero, and only selected when
+        // it holds fewer than `k` records
+        let index_bits = if with_keys {
+            top_k_index_bits(nbins)
+        } else {
+            0
+        };
+        let payload_bits = PAYLOAD_SIZE * 8;
+        Ok(records
+            .into_iter()
+            .filter(|record| (record >> (index_bits + payload_bits)) & 1 == 1)
+            .map(|record| {
+                let value = (record >> index_bits) & ((1 << payload_bits) - 1);
+                let key = with_keys.then(|| {
+                    let bin = (record & ((1 << index_bits) - 1)) as usize;
+                    intersection_results.input_indices[bin].map(|i| primary_keys[i].clone())
+                });
+                (value, key.flatten())
+            })
+            .collect())
+    }
+    fn intersect_with_both_payloads(
+        &mut self,
+        primary_keys: &[PrimaryKey],
+        payloads: &[Payload],
+        op: PayloadOp,
+    ) -> Result<Vec<Option<u128>>, Error> {
+        let intersection_results = self.intersect_with_payloads(primary_keys, Some(payloads))?;
+        let existence_bit_vector = &intersection_results.intersection.existence_bit_vector;
+        let combined = fancy_payload_combine(
+            &mut self.ev,
+            existence_bit_vector,
+            &intersection_results.payloads.sender_payloads,
+            &intersection_results.payloads.receiver_payloads,
+            op,
+        )?;
+        trace_phase!("reveal", nbits = existence_bit_vector.len());
+        let opened_bits = self
+            .ev
+            .outputs(existence_bit_vector)?
+            .expect("evaluator should produce outputs");
+        let combined = self
+            .ev
+            .bin_outputs(&combined)?
+            .expect("evaluator should produce outputs");
+
+        // Map every bin back to the evaluator's primary key placed in it
+        let mut res = vec![None; primary_keys.len()];
+        for ((bit, value), index) in opened_bits
+            .into_iter()
+            .zip(combined)
+            .zip(intersection_results.input_indices)
+        {
+            if let (1, Some(i)) = (bit, index) {
+                res[i] = Some(value);
+            }
+        }
+        Ok(res)
+    }
+    fn intersect_join<T: Clone, U: Clone + Into<Payload>>(
+        &mut self,
+        local: &[(PrimaryKey, T)],
+        payloads: &[U],
+    ) -> Result<Vec<(PrimaryKey, T, Payload)>, Error> {
+        let primary_keys: Vec<PrimaryKey> = local.iter().map(|(key, _)| key.clone()).collect();
+        let payloads: Vec<Payload> = payloads.iter().cloned().map(Into::into).collect();
+        let intersection_results = self.intersect_with_payloads(&primary_keys, Some(&payloads))?;
+        let existence_bit_vector = &intersection_results.intersection.existence_bit_vector;
+        let sender_payloads = fancy_payload_select(
+            &mut self.ev,
+            existence_bit_vector,
+            &intersection_results.payloads.sender_payloads,
+        )?;
+        trace_phase!("reveal", nbits = existence_bit_vector.len());
+        let opened_bits = self
+            .ev
+            .outputs(existence_bit_vector)?
+            .expect("evaluator should produce outputs");
+        let sender_payloads = self
+            .ev
+            .bin_outputs(&sender_payloads)?
+            .expect("evaluator should produce outputs");
+
+        let mut matches = opened_bits
+            .into_iter()
+            .zip(sender_payloads)
+            .zip(intersection_results.input_indices)
+            .filter_map(|((bit, payload), index)| match (bit, index) {
+                (1, Some(i)) => Some((i, payload)),
+                _ => None,
+            })
+            .collect::<Vec<(usize, u128)>>();
+        // Return the rows in the order they were given
+        matches.sort_unstable_by_key(|(i, _)| *i);
+        let recovered = utils::int_vec_block512(
+            matches.iter().map(|(_, payload)| *payload).collect(),
+            PAYLOAD_SIZE,
+        );
+        Ok(matches
+            .into_iter()
+            .zip(recovered)
+            .map(|((i, _), payload)| (local[i].0.clone(), local[i].1.clone(), payload))
+            .collect())
+    }
+    fn intersect_any_column(&mut self, columns: &[Vec<PrimaryKey>]) -> Result<Vec<usize>, Error> {
+        let nrecords = check_key_columns(columns)?;
+        self.channel.write_usize(nrecords)?;
+        self.channel.flush()?;
+        let tag_bits = key_column_tag_bits(nrecords);
+        // Empty bins are tagged with all ones, which comes after every record
+        let empty = (1 << tag_bits) - 1;
+        let mut bit_vectors = Vec::with_capacity(columns.len());
+        let mut tags = Vec::with_capacity(columns.len());
+        for column in columns {
+            let intersection_results = self.intersect(column)?;
+            let tag_values: Vec<u128> = intersection_results
+                .input_indices
+                .iter()
+                .map(|index| index.map_or(empty, |i| i as u128))
+                .collect();
+            tags.push(self.ev.bin_encode_many(&tag_values, tag_bits)?);
+            bit_vectors.push(intersection_results.intersection.existence_bit_vector);
+        }
+        let bits = fancy_any_column_bit_vector(&mut self.ev, &bit_vectors, &tags, nrecords)?;
+        trace_phase!("reveal", nbits = bits.len());
+        let opened_bits = self
+            .ev
+            .outputs(&bits)?
+            .expect("evaluator should produce outputs");
+        Ok(opened_bits
+            .into_iter()
+            .enumerate()
+            .filter_map(|(i, bit)| (bit == 1).then_some(i))
+            .collect())
+    }
+}
@@ -3,16 +3,22 @@ use crate::{
     errors::Error,
     psi::circuit_psi::{circuits::*, *},
 };
-use fancy_garbling::{twopac::semihonest::Garbler, WireMod2};
-use ocelot::ot::AlszSender as OtSender;
+use fancy_garbling::{
+    twopac::semihonest::Garbler, BinaryBundle, BinaryGadgets, Fancy, FancyInput, WireMod2,
+};
+use ocelot::ot::{precomputed::PrecomputedOtSender, AlszSender};
 use scuttlebutt::{AbstractChannel, Block, SemiHonest};
-use std::marker::PhantomData;
+use std::{cmp::Ordering, marker::PhantomData};
 
 use self::sender::OpprfSender;
 
 /// A Garbling party for Circuit PSI that uses OPPRF Base PSI
 pub type OpprfPsiGarbler<C, RNG> = PsiGarbler<C, RNG, OpprfSender>;
 
+/// The OT sender of the garbled circuit, consuming precomputed correlations
+/// before running OT extension
+type OtSender = PrecomputedOtSender<AlszSender>;
+
 /// A struct defining the Garbling party in Circuit Psi
 pub struct PsiGarbler<C, RNG, B> {
     /// The actual garbler being called during the garbled circuit
@@ -21,6 +27,31 @@ pub struct PsiGarbler<C, RNG, B> {
     pub channel: C,
     /// The garbler's dedicated rng
     pub rng: RNG,
+    /// Whether the parties check that they use the same base PSI hashing parameters
+    pub verify: bool,
+    /// A public seed (e.g. from a randomness beacon) the parties derive their
+    /// public randomness from. Both parties must set the same seed.
+    pub public_seed: Option<Block>,
+    /// The security parameters of the protocol. Both parties must use the
+    /// same parameters.
+    pub security: SecurityParams,
+    /// The cuckoo hashing strategy of the base PSI. Both parties must use the
+    /// same strategy.
+    pub cuckoo_strategy: CuckooStrategy,
+    /// If set, the intersection bit vector is output in randomized response with
+    /// this parameter `epsilon`, each bit being flipped with probability
+    /// `1 / (1 + exp(epsilon))` (cf. `utils::dp_flip_threshold`). This makes the
+    /// presence of every primary key `epsilon`-differentially private. Both
+    /// parties must set the same `epsilon`.
+    pub dp_output: Option<f64>,
+    /// If set, the cardinality revealed by `CircuitPsiExt::intersect_cardinality` has
+    /// discrete Laplace noise with this parameter `epsilon` added to it by each
+    /// party, which makes it `epsilon`-differentially private. Both parties must
+    /// set the same `epsilon`.
+    pub dp_cardinality: Option<f64>,
+    /// Whether the statistics of the party's hashing table are returned with the
+    /// intersection (cf. `PsiDiagnostics`)
+    pub diagnostics: bool,
     /// A witness for the Base PSI protocol
     _base_psi: PhantomData<B>,
 }
@@ -39,9 +70,63 @@ where
             gb: Garbler::<C, RNG, OtSender, WireMod2>::new(channel.clone(), RNG::from_seed(seed))?,
             channel: channel.clone(),
             rng: RNG::from_seed(seed),
+            verify: false,
+            public_seed: None,
+            security: SecurityParams::default(),
+            cuckoo_strategy: CuckooStrategy::default(),
+            dp_output: None,
+            dp_cardinality: None,
+            diagnostics: false,
             _base_psi: PhantomData,
         })
     }
+
+    /// Flushes the channel and waits for the other party to reach the same point
+    /// of the protocol.
+    pub fn barrier(&mut self) -> Result<(), Error> {
+        utils::barrier(&mut self.channel)
+    }
+
+    /// Samples the party's discrete Laplace noise share with parameter `epsilon` and
+    /// inputs both parties' shares, to be used with `fancy_noisy_cardinality`.
+    ///
+    /// Both parties must call this with the same `epsilon`. The shares are returned
+    /// in the garbler, evaluator order.
+    pub fn dp_noise_shares(&mut self, epsilon: f64) -> Result<Vec<BinaryBundle<WireMod2>>, Error> {
+        let noise = utils::sample_discrete_laplace(epsilon, &mut self.rng)?;
+        let garbler_share = self.gb.bin_encode(noise as u64 as u128, DP_NOISE_BITS)?;
+        let evaluator_share = self.gb.bin_receive(DP_NOISE_BITS)?;
+        Ok(vec![garbler_share, evaluator_share])
+    }
+
+    /// Samples the party's uniform shares of the randomized response flips of
+    /// `nbits` bits and inputs both parties' shares, to be used with
+    /// `fancy_randomized_response`.
+    ///
+    /// Both parties must call this with the same `nbits`. The shares are returned
+    /// in the garbler, evaluator order.
+    pub fn dp_flip_shares(
+        &mut self,
+        nbits: usize,
+    ) -> Result<Vec<Vec<BinaryBundle<WireMod2>>>, Error> {
+        let shares: Vec<u128> = (0..nbits).map(|_| self.rng.gen::<u32>() as u128).collect();
+        let garbler_shares = self.gb.bin_encode_many(&shares, utils::DP_FLIP_BITS)?;
+        let evaluator_shares = self.gb.bin_receive_many(nbits, utils::DP_FLIP_BITS)?;
+        Ok(vec![garbler_shares, evaluator_shares])
+    }
+
+    /// Compares the garbler's set size `my_size` with the evaluator's, e.g. to
+    /// choose between symmetric and asymmetric PSI, returning how `my_size`
+    /// compares to the other party's size.
+    ///
+    /// The sizes are compared in a garbled circuit, so that each party only
+    /// learns their ordering (cf. `fancy_compare_set_sizes`). Both parties must
+    /// call this at the same point of the protocol.
+    pub fn compare_set_sizes(&mut self, my_size: usize) -> Result<Ordering, Error> {
+        let garbler_size = self.gb.bin_encode(my_size as u128, SET_SIZE_BITS)?;
+        let evaluator_size = self.gb.bin_receive(SET_SIZE_BITS)?;
+        fancy_compare_set_sizes(&mut self.gb, &garbler_size, &evaluator_size)
+    }
 }
 
 impl<C, RNG, B> SemiHonest for PsiGarbler<C, RNG, B> {}
@@ -56,20 +141,25 @@ where
     ///
     /// (0) Check that the set of primary keys has the same size as the set of payloads
     /// if the latter is not empty.
-    /// (1) Call the Base Psi to create the circuit's input.
+    /// (1) Call the Base Psi to create the circuit's input with the `security`
+    /// parameters, checking that the parties use the same hashing parameters if
+    /// `verify` or `public_seed` is set.
     /// The Base Psi effectively constructs the intersection in a hidden form
     /// that only the garbled circuit can read and operate on.
-    /// (2) Turns the circuit inputs into bundles that are easier to operate on in swanky's
+    /// (2) Synchronize both parties before garbling the circuit.
+    /// (3) Turns the circuit inputs into bundles that are easier to operate on in swanky's
     /// fancy garbling.
-    /// (3) Takes the output of the Base Psi and turns it into a garbled intersection bit
+    /// (4) Takes the output of the Base Psi and turns it into a garbled intersection bit
     /// vector which indicates the presence or abscence of a primary key.
-    /// (4) Computes the user defined circuit on the parties' inputs.
+    /// If `dp_output` is set, each bit is then flipped with the configured probability.
+    /// (5) Computes the user defined circuit on the parties' inputs.
     fn intersect_with_payloads(
         &mut self,
         primary_keys: &[PrimaryKey],
         payloads: Option<&[Payload]>,
     ) -> Result<Intersection, Error> {
         // (0)
+        let dp_threshold = self.dp_output.map(utils::dp_flip_threshold).transpose()?;
         if payloads.is_some() && primary_keys.len() != payloads.unwrap().len() {
             return Err(Error::PayloadSetNotComplete {
                 npayloads: payloads.unwrap().len(),
@@ -83,18 +173,43 @@ where
             payloads,
             &mut self.channel,
             &mut self.rng,
+            self.verify,
+            self.public_seed,
+            self.security,
+            self.cuckoo_strategy,
+            CuckooParams::default(),
+            self.diagnostics,
         )?;
         // (2)
+        self.barrier()?;
+        trace_phase!(
+            "garbling",
+            nwires = circuit_inputs.sender_primary_keys.len()
+        );
+        // (3)
         let primary_keys =
             bundle_primary_keys::<Garbler<C, RNG, OtSender, WireMod2>, _>(&circuit_inputs)?;
         let (sender_payloads, receiver_payloads) = bundle_payloads(&mut self.gb, &circuit_inputs)?;
 
-        // (3)
+        // (4)
         let existence_bit_vector = fancy_intersection_bit_vector(
             &mut self.gb,
             &circuit_inputs.sender_primary_keys,
             &circuit_inputs.receiver_primary_keys,
+            circuit_inputs.hash_size,
         )?;
+        let existence_bit_vector = match dp_threshold {
+            Some(threshold) => {
+                let flip_shares = self.dp_flip_shares(existence_bit_vector.len())?;
+                fancy_randomized_response(
+                    &mut self.gb,
+                    &existence_bit_vector,
+                    &flip_shares,
+                    threshold,
+                )?
+            }
+            None => existence_bit_vector,
+        };
         let intersection_results = Intersection {
             intersection: PrivateIntersection {
                 existence_bit_vector,
@@ -104,6 +219,8 @@ where
                 sender_payloads,
                 receiver_payloads,
             },
+            input_indices: circuit_inputs.input_indices,
+            diagnostics: circuit_inputs.diagnostics,
         };
         Ok(intersection_results)
     }
@@ -111,3 +228,258 @@ where
         self.intersect_with_payloads(primary_keys, None)
     }
 }
+
+impl<C, RNG, B> CircuitPsiExt for PsiGarbler<C, RNG, B>
+where
+    C: AbstractChannel + Clone,
+    RNG: RngCore + CryptoRng + Rng + SeedableRng<Seed = Block>,
+    B: BasePsi,
+{
+    /// Reveals the intersection bit vector to the evaluator, the garbler
+    /// does not learn which of its rows are in the intersection.
+    fn intersect_with_rows<T: Clone>(
+        &mut self,
+        rows: &[(PrimaryKey, T)],
+    ) -> Result<Vec<(PrimaryKey, T)>, Error> {
+        let primary_keys: Vec<PrimaryKey> = rows.iter().map(|(key, _)| key.clone()).collect();
+        let intersection_results = self.intersect(&primary_keys)?;
+        let existence_bit_vector = &intersection_results.intersection.existence_bit_vector;
+        trace_phase!("reveal", nbits = existence_bit_vector.len());
+        self.gb.outputs(existence_bit_vector)?;
+        Ok(vec![])
+    }
+    fn set_difference(&mut self, primary_keys: &[PrimaryKey]) -> Result<Vec<PrimaryKey>, Error> {
+        let intersection_results = self.intersect(primary_keys)?;
+        let difference_bit_vector = fancy_complement_bit_vector(
+            &mut self.gb,
+            &intersection_results.intersection.existence_bit_vector,
+        )?;
+        trace_phase!("reveal", nbits = difference_bit_vector.len());
+        self.gb.outputs(&difference_bit_vector)?;
+        Ok(vec![])
+    }
+    /// The garbler's shares are uniformly random, and the evaluator learns the
+    /// sender's payloads masked with them.
+    fn intersect_with_payload_shares(
+        &mut self,
+        primary_keys: &[PrimaryKey],
+        payloads: Option<&[Payload]>,
+    ) -> Result<(Intersection, Vec<Payload>), Error> {
+        let intersection_results = self.intersect_with_payloads(primary_keys, payloads)?;
+        let sender_payloads = &intersection_results.payloads.sender_payloads;
+        let shares: Vec<u128> = (0..sender_payloads.len())
+            .map(|_| self.rng.gen::<u64>() as u128)
+            .collect();
+        let share_wires = self.gb.bin_encode_many(&shares, PAYLOAD_SIZE * 8)?;
+        for (payload, share) in sender_payloads.iter().zip(share_wires.iter()) {
+            let masked = self.gb.bin_xor(payload, share)?;
+            self.gb.bin_output(&masked)?;
+        }
+        Ok((
+            intersection_results,
+            utils::int_vec_block512(shares, PAYLOAD_SIZE),
+        ))
+    }
+    fn intersect_fuzzy(
+        &mut self,
+        primary_keys: &[PrimaryKey],
+        max_distance: usize,
+    ) -> Result<Intersection, Error> {
+        self.channel.write_usize(primary_keys.len())?;
+        self.channel.flush()?;
+        let nreceiver = self.channel.read_usize()?;
+        let sender_keys = self
+            .gb
+            .bin_encode_many(&fuzzy_key_values(primary_keys), PRIMARY_KEY_SIZE * 8)?;
+        let receiver_keys = self.gb.bin_receive_many(nreceiver, PRIMARY_KEY_SIZE * 8)?;
+        let existence_bit_vector = fancy_fuzzy_intersection_bit_vector(
+            &mut self.gb,
+            &sender_keys,
+            &receiver_keys,
+            max_distance,
+        )?;
+        Ok(Intersection {
+            intersection: PrivateIntersection {
+                existence_bit_vector,
+                primary_keys: receiver_keys,
+            },
+            payloads: Default::default(),
+            // The bits are those of the evaluator's keys
+            input_indices: vec![None; nreceiver],
+            diagnostics: None,
+        })
+    }
+    fn intersect_sorted(&mut self, primary_keys: &[PrimaryKey]) -> Result<Intersection, Error> {
+        let values = sorted_key_values(primary_keys)?;
+        self.channel.write_usize(primary_keys.len())?;
+        self.channel.flush()?;
+        let nreceiver = self.channel.read_usize()?;
+        let sender_keys = self.gb.bin_encode_many(&values, PRIMARY_KEY_SIZE * 8)?;
+        let receiver_keys = self.gb.bin_receive_many(nreceiver, PRIMARY_KEY_SIZE * 8)?;
+        let (existence_bit_vector, primary_keys) =
+            fancy_sorted_intersection(&mut self.gb, &sender_keys, &receiver_keys)?;
+        Ok(Intersection {
+            intersection: PrivateIntersection {
+                existence_bit_vector,
+                primary_keys,
+            },
+            payloads: Default::default(),
+            input_indices: vec![],
+            diagnostics: None,
+        })
+    }
+    fn intersect_prescreened(
+        &mut self,
+        primary_keys: &[PrimaryKey],
+        rate: f64,
+    ) -> Result<Intersection, Error> {
+        let (nbins, nhashes) = prescreen_parameters(primary_keys.len(), rate)?;
+        {
+            trace_phase!("prescreen", nbins);
+            let mut filter = BloomFilter::from_parameters(nbins, nhashes);
+            for key in primary_keys {
+                filter.insert(key);
+            }
+            self.channel.write_usize(primary_keys.len())?;
+            self.channel.write_lenprefixed(&filter.as_bytes())?;
+            self.channel.flush()?;
+        }
+        self.intersect(primary_keys)
+    }
+    fn intersect_online(
+        &mut self,
+        primary_keys: &[PrimaryKey],
+        precomputed: PrecomputedOts,
+    ) -> Result<Intersection, Error> {
+        self.gb.ot_mut().extend(precomputed)?;
+        self.intersect(primary_keys)
+    }
+    fn intersect_cardinality(
+        &mut self,
+        primary_keys: &[PrimaryKey],
+        hint: NetworkHint,
+    ) -> Result<Option<i64>, Error> {
+        let intersection = self.intersect(primary_keys)?;
+        let existence_bit_vector = &intersection.intersection.existence_bit_vector;
+        let count = match self.dp_cardinality {
+            Some(epsilon) => {
+                let noise_shares = self.dp_noise_shares(epsilon)?;
+                fancy_noisy_cardinality(&mut self.gb, existence_bit_vector, &noise_shares, hint)?
+            }
+            None => fancy_cardinality_with_hint(&mut self.gb, existence_bit_vector, hint)?,
+        };
+        self.gb.bin_output(&count)?;
+        Ok(None)
+    }
+    fn intersect_count_distinct_column(
+        &mut self,
+        primary_keys: &[PrimaryKey],
+        payloads: &[Payload],
+        schema: &PayloadSchema,
+        column: usize,
+    ) -> Result<Option<u128>, Error> {
+        check_column(schema, column)?;
+        let intersection_results = self.intersect_with_payloads(primary_keys, Some(payloads))?;
+        let columns = schema.split_columns(&intersection_results.payloads.sender_payloads)?;
+        let count = fancy_column_count_distinct(
+            &mut self.gb,
+            &intersection_results.intersection.existence_bit_vector,
+            &columns[column],
+        )?;
+        self.gb.bin_output(&count)?;
+        Ok(None)
+    }
+    fn intersect_payload_max(
+        &mut self,
+        primary_keys: &[PrimaryKey],
+        payloads: &[Payload],
+    ) -> Result<Vec<u128>, Error> {
+        let intersection_results = self.intersect_with_payloads(primary_keys, Some(payloads))?;
+        let maxima = fancy_payload_max(
+            &mut self.gb,
+            &intersection_results.intersection.existence_bit_vector,
+            &intersection_results.payloads.sender_payloads,
+            &intersection_results.payloads.receiver_payloads,
+        )?;
+        self.gb.bin_outputs(&maxima)?;
+        Ok(vec![])
+    }
+    fn intersect_top_k(
+        &mut self,
+        primary_keys: &[PrimaryKey],
+        payloads: &[Payload],
+        k: usize,
+        with_keys: bool,
+    ) -> Result<Vec<(u128, Option<PrimaryKey>)>, Error> {
+        let intersection_results = self.intersect_with_payloads(primary_keys, Some(payloads))?;
+        let records = fancy_top_k(
+            &mut self.gb,
+            &intersection_results.intersection.existence_bit_vector,
+            &intersection_results.payloads.sender_payloads,
+            k,
+            with_keys,
+        )?;
+        trace_phase!("reveal", nbits = records.len());
+        self.gb.bin_outputs(&records)?;
+        Ok(vec![])
+    }
+    fn intersect_with_both_payloads(
+        &mut self,
+        primary_keys: &[PrimaryKey],
+        payloads: &[Payload],
+        op: PayloadOp,
+    ) -> Result<Vec<Option<u128>>, Error> {
+        let intersection_results = self.intersect_with_payloads(primary_keys, Some(payloads))?;
+        let existence_bit_vector = &intersection_results.intersection.existence_bit_vector;
+        let combined = fancy_payload_combine(
+            &mut self.gb,
+            existence_bit_vector,
+            &intersection_results.payloads.sender_payloads,
+            &intersection_results.payloads.receiver_payloads,
+            op,
+        )?;
+        trace_phase!("reveal", nbits = existence_bit_vector.len());
+        self.gb.outputs(existence_bit_vector)?;
+        self.gb.bin_outputs(&combined)?;
+        Ok(vec![])
+    }
+    fn intersect_join<T: Clone, U: Clone + Into<Payload>>(
+        &mut self,
+        local: &[(PrimaryKey, T)],
+        payloads: &[U],
+    ) -> Result<Vec<(PrimaryKey, T, Payload)>, Error> {
+        let primary_keys: Vec<PrimaryKey> = local.iter().map(|(key, _)| key.clone()).collect();
+        let payloads: Vec<Payload> = payloads.iter().cloned().map(Into::into).collect();
+        let intersection_results = self.intersect_with_payloads(&primary_keys, Some(&payloads))?;
+        let existence_bit_vector = &intersection_results.intersection.existence_bit_vector;
+        let sender_payloads = fancy_payload_select(
+            &mut self.gb,
+            existence_bit_vector,
+            &intersection_results.payloads.sender_payloads,
+        )?;
+        trace_phase!("reveal", nbits = existence_bit_vector.len());
+        self.gb.outputs(existence_bit_vector)?;
+        self.gb.bin_outputs(&sender_payloads)?;
+        Ok(vec![])
+    }
+    fn intersect_any_column(&mut self, columns: &[Vec<PrimaryKey>]) -> Result<Vec<usize>, Error> {
+        check_key_columns(columns)?;
+        let nreceiver = self.channel.read_usize()?;
+        let tag_bits = key_column_tag_bits(nreceiver);
+        let mut bit_vectors = Vec::with_capacity(columns.len());
+        let mut tags = Vec::with_capacity(columns.len());
+        for column in columns {
+            let intersection_results = self.intersect(column)?;
+            let existence_bit_vector = intersection_results.intersection.existence_bit_vector;
+            tags.push(
+                self.gb
+                    .bin_receive_many(existence_bit_vector.len(), tag_bits)?,
+            );
+            bit_vectors.push(existence_bit_vector);
+        }
+        let bits = fancy_any_column_bit_vector(&mut self.gb, &bit_vectors, &tags, nreceiver)?;
+        trace_phase!("reveal", nbits = bits.len());
+        self.gb.outputs(&bits)?;
+        Ok(vec![])
+    }
+}
@@ -1,18 +1,49 @@
 //! Implementation of the Pinkas-Schneider-Tkachenko-Yanai "extended" private
 //! set intersection protocol (cf. <https://eprint.iacr.org/2019/241>).
 use crate::{
+    cuckoo::compute_nbins,
     errors::Error,
-    psi::circuit_psi::{base_psi::*, circuits::*},
+    psi::circuit_psi::{base_psi::*, circuits::*, utils::PayloadSchema},
 };
 use fancy_garbling::{BinaryBundle, Fancy, FancyBinary, FancyReveal, WireMod2};
+use ocelot::ot::precomputed::PrecomputedOts;
 use rand::{CryptoRng, Rng, RngCore, SeedableRng};
-use scuttlebutt::Block512;
-use std::fmt::Debug;
+use scuttlebutt::{Block512, BloomFilter};
+use std::{fmt::Debug, io::Write};
+
+/// Enter a `tracing` span for a protocol phase until the end of the enclosing
+/// block, if the `tracing` feature is enabled, and time the phase if the `bench`
+/// feature is enabled (cf. `bench::PhaseTimings`).
+///
+/// The phase is also reported to the handle of the protocol, if it runs in the
+/// background, and the enclosing function returns `Error::Cancelled` if the
+/// protocol was cancelled (cf. `progress::ProtocolHandle`).
+macro_rules! trace_phase {
+    ($phase:literal $(, $($fields:tt)+)?) => {
+        $crate::psi::circuit_psi::progress::enter_phase($phase)?;
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!($phase $(, $($fields)+)?).entered();
+        #[cfg(feature = "bench")]
+        let _timer = $crate::psi::circuit_psi::bench::PhaseTimer::start($phase);
+    };
+}
+
+/// Emit a `tracing` event, if the `tracing` feature is enabled.
+macro_rules! trace_event {
+    ($($args:tt)+) => {
+        #[cfg(feature = "tracing")]
+        tracing::info!($($args)+);
+    };
+}
 
 pub mod base_psi;
+#[cfg(feature = "bench")]
+pub mod bench;
 pub mod circuits;
 pub mod evaluator;
 pub mod garbler;
+pub mod progress;
+pub mod psu;
 pub mod tests;
 pub mod utils;
 
@@ -25,6 +56,225 @@ pub type Payload = Block512;
 pub const PRIMARY_KEY_SIZE: usize = 8;
 /// The number of bytes representing a payload value.
 pub const PAYLOAD_SIZE: usize = 8;
+/// The number of bits representing a set size in `compare_set_sizes`.
+pub const SET_SIZE_BITS: usize = 64;
+/// The number of bits of the parties' differential privacy noise shares and of
+/// the noisy cardinality, as two's complement integers (cf.
+/// `fancy_noisy_cardinality`). The noisy cardinality is decoded as an `i64`.
+pub const DP_NOISE_BITS: usize = 64;
+
+/// The security parameters of circuit PSI.
+///
+/// The statistical security parameter `stat_sec` sizes the parts of the
+/// parties' OPPRF outputs that are compared in the circuit: each bin compares
+/// `stat_sec + log2(nbins)` bits, rounded up to bytes, so that a false
+/// positive occurs in any bin with probability at most `2^-stat_sec`. Lowering
+/// it makes the circuit smaller.
+///
+/// The computational security parameter `comp_sec` is an upper bound: the
+/// underlying primitives (AES-based hashing, OT extension and OPPRF) are all
+/// instantiated at 128 bits of security.
+///
+/// The defaults are `stat_sec = 40` and `comp_sec = 128`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SecurityParams {
+    /// The statistical security parameter.
+    pub stat_sec: usize,
+    /// The computational security parameter.
+    pub comp_sec: usize,
+}
+
+impl Default for SecurityParams {
+    fn default() -> Self {
+        SecurityParams {
+            stat_sec: 40,
+            comp_sec: 128,
+        }
+    }
+}
+
+impl SecurityParams {
+    /// The smallest supported statistical security parameter.
+    pub const MIN_STAT_SEC: usize = 20;
+    /// The largest supported statistical security parameter.
+    pub const MAX_STAT_SEC: usize = 128;
+    /// The smallest supported computational security parameter.
+    pub const MIN_COMP_SEC: usize = 80;
+    /// The largest supported computational security parameter.
+    pub const MAX_COMP_SEC: usize = 128;
+
+    /// Estimates the resources used by a run of circuit PSI with these
+    /// parameters, before running it (cf. `CostEstimate`).
+    pub fn estimate_cost(
+        &self,
+        sender_size: usize,
+        receiver_size: usize,
+        with_payloads: bool,
+    ) -> CostEstimate {
+        CostEstimate::new(sender_size, receiver_size, with_payloads, self)
+    }
+
+    /// Check that the parameters are within the supported ranges.
+    pub fn validate(&self) -> Result<(), Error> {
+        if !(Self::MIN_STAT_SEC..=Self::MAX_STAT_SEC).contains(&self.stat_sec)
+            || !(Self::MIN_COMP_SEC..=Self::MAX_COMP_SEC).contains(&self.comp_sec)
+        {
+            return Err(Error::InvalidSecurityParams {
+                stat_sec: self.stat_sec,
+                comp_sec: self.comp_sec,
+            });
+        }
+        Ok(())
+    }
+
+    /// The number of bytes of the parties' OPPRF outputs compared in each of
+    /// `nbins` bins.
+    pub fn hash_size(&self, nbins: usize) -> usize {
+        let log_nbins = nbins.next_power_of_two().trailing_zeros() as usize;
+        (self.stat_sec + log_nbins).div_ceil(8)
+    }
+}
+
+/// The function of the parties' payloads computed for each primary key in the
+/// intersection (cf. `CircuitPsiExt::intersect_with_both_payloads`).
+///
+/// The payloads are read as unsigned integers on their first `PAYLOAD_SIZE`
+/// bytes, and the results wrap around at `PAYLOAD_SIZE * 8` bits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PayloadOp {
+    /// The sum of the parties' payloads.
+    Sum,
+    /// The evaluator's payload minus the garbler's payload.
+    Difference,
+    /// The product of the parties' payloads.
+    Product,
+}
+
+/// A hint on the network the parties run over, for the circuits which come in
+/// variants of different size and multiplicative depth (cf.
+/// `CircuitPsiExt::intersect_cardinality`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NetworkHint {
+    /// The bandwidth is scarce, e.g. on a metered link.
+    Bandwidth,
+    /// The round trips are slow, e.g. across a WAN, so that the evaluation
+    /// benefits from a shallower circuit.
+    Latency,
+}
+
+/// A projection of the resources used by a run of circuit PSI
+/// (cf. `SecurityParams::estimate_cost`).
+///
+/// The estimate covers the base PSI and the computation of the intersection
+/// bit vector, but not the circuits the parties build on top of it. It follows
+/// the protocol's asymptotics: OPPRF communication is linear in the number of
+/// bins and in the sender's programmed points, and the circuit makes one
+/// equality test of `SecurityParams::hash_size` bytes per bin. Garbling uses
+/// half-gates (two ciphertexts per AND gate) and the evaluator's inputs are
+/// transferred with OT extension.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CostEstimate {
+    /// The number of AND gates of the circuit.
+    pub and_gates: usize,
+    /// The number of bytes sent on the channel by both parties.
+    pub communication_bytes: usize,
+    /// The peak memory used by either party, in bytes.
+    pub peak_memory_bytes: usize,
+}
+
+impl CostEstimate {
+    /// Estimate the cost of circuit PSI between a sender with `sender_size`
+    /// primary keys and a receiver with `receiver_size` primary keys.
+    pub fn new(
+        sender_size: usize,
+        receiver_size: usize,
+        with_payloads: bool,
+        security: &SecurityParams,
+    ) -> Self {
+        // The size of a wire label, and of an OPPRF output
+        const LABEL_SIZE: usize = 16;
+        const OPPRF_OUTPUT_SIZE: usize = 64;
+
+        // The estimate assumes the default cuckoo hashing strategy
+        let nhashes = CuckooStrategy::default().nhashes();
+        let nbins = compute_nbins(receiver_size, nhashes).expect("3 hashes are supported");
+        let key_bits = security.hash_size(nbins) * 8;
+        let payload_bits = if with_payloads { PAYLOAD_SIZE * 8 } else { 0 };
+        let nopprfs = if with_payloads { 2 } else { 1 };
+
+        let and_gates = nbins * (key_bits - 1);
+        let garbler_inputs = nbins * (key_bits + payload_bits);
+        let evaluator_inputs = nbins * (key_bits + 2 * payload_bits);
+        let opprf_bytes = nopprfs * (nbins + nhashes * sender_size) * OPPRF_OUTPUT_SIZE;
+        let circuit_bytes = and_gates * 2 * LABEL_SIZE
+            + garbler_inputs * LABEL_SIZE
+            + evaluator_inputs * 3 * LABEL_SIZE;
+        let tables_bytes = nhashes * sender_size * (LABEL_SIZE + nopprfs * OPPRF_OUTPUT_SIZE);
+        let wires_bytes = (garbler_inputs + evaluator_inputs + and_gates) * LABEL_SIZE;
+
+        CostEstimate {
+            and_gates,
+            communication_bytes: opprf_bytes + circuit_bytes,
+            peak_memory_bytes: tables_bytes + wires_bytes,
+        }
+    }
+}
+
+/// Statistics of a party's hashing table in a run of circuit PSI, e.g. to tune
+/// the cuckoo hashing and OPPRF parameters (cf. `PsiGarbler::diagnostics`).
+///
+/// The load of a bin is the number of entries placed in it: at most one of the
+/// receiver's primary keys with cuckoo hashing, and every one of the sender's
+/// primary keys hashed to the bin by any of the hash functions, along with the
+/// dummy entries hiding collisions, with simple hashing.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PsiDiagnostics {
+    /// The number of the party's primary keys hashed into its table.
+    pub nitems: usize,
+    /// The number of bins of the table.
+    pub nbins: usize,
+    /// The number of entries over all bins of the table.
+    pub nentries: usize,
+    /// The largest load of a bin.
+    pub max_bin_load: usize,
+    /// The average load of a bin.
+    pub avg_bin_load: f64,
+    /// The number of primary keys placed in the stash instead of a bin.
+    pub stash_size: usize,
+    /// The number of times the hashing key was resampled because the primary keys
+    /// could not all be placed.
+    pub nrehashes: usize,
+}
+
+impl PsiDiagnostics {
+    /// Computes the statistics of a table of `nitems` primary keys whose bins have
+    /// the given `loads`.
+    pub fn from_loads(
+        nitems: usize,
+        loads: impl ExactSizeIterator<Item = usize>,
+        stash_size: usize,
+        nrehashes: usize,
+    ) -> Self {
+        let nbins = loads.len();
+        let (nentries, max_bin_load) = loads.fold((0, 0), |(sum, max), load| {
+            (sum + load, std::cmp::max(max, load))
+        });
+        let avg_bin_load = if nbins == 0 {
+            0.0
+        } else {
+            nentries as f64 / nbins as f64
+        };
+        PsiDiagnostics {
+            nitems,
+            nbins,
+            nentries,
+            max_bin_load,
+            avg_bin_load,
+            stash_size,
+            nrehashes,
+        }
+    }
+}
 
 /// Encoded Garbled Circuit PsiInputs
 pub struct CircuitInputs<F> {
@@ -41,6 +291,15 @@ pub struct CircuitInputs<F> {
     /// when they share the same key with the sender
     /// and otherwise receive a random mask
     pub masks: Vec<F>,
+    /// The index of the party's own primary key placed in each bin.
+    /// Only the party which cuckoo hashes its inputs knows this mapping,
+    /// and it is empty for the other party.
+    pub input_indices: Vec<Option<usize>>,
+    /// The number of bytes of the parties' primary keys wires per bin
+    /// (cf. `SecurityParams::hash_size`)
+    pub hash_size: usize,
+    /// The statistics of the party's hashing table, if requested
+    pub diagnostics: Option<PsiDiagnostics>,
 }
 
 /// Encoded Garbled Circuit PsiInputs
@@ -81,11 +340,87 @@ impl<F> Default for PrivateIntersection<F> {
 /// A struct defining the intersection results, i.e. the bit vector
 /// that shows whether a primary key is in the intersection and the
 /// unmasked payloads in Circuit Psi
+#[derive(Default)]
 pub struct Intersection {
     /// The set of primary keys and intersection bit vector
     pub intersection: PrivateIntersection<WireMod2>,
     /// The unmasked payloads
     pub payloads: PrivateIntersectionPayloads<WireMod2>,
+    /// The index of the party's own primary key associated with each bit
+    /// of the intersection bit vector (cf. `CircuitInputs::input_indices`)
+    pub input_indices: Vec<Option<usize>>,
+    /// The statistics of the party's hashing table, if requested (cf.
+    /// `PsiDiagnostics`). They are not set when no hashing is done, e.g. by
+    /// `CircuitPsiExt::intersect_sorted`.
+    pub diagnostics: Option<PsiDiagnostics>,
+}
+
+impl Intersection {
+    /// Streams the matched records to `out` as CSV lines, without collecting the records
+    /// first.
+    ///
+    /// opened_bits: The revealed intersection bit vector, with one bit per bin.
+    /// primary_keys: The party's own set primary keys, as passed to the intersection.
+    /// payloads: The party's own payloads, if any.
+    ///
+    /// Every bin whose bit is set is mapped to the party's primary key through
+    /// `input_indices`, and that key is written as `key` or `key,payload`, where the
+    /// first `PRIMARY_KEY_SIZE` (resp. `PAYLOAD_SIZE`) bytes are read as a
+    /// little-endian integer. The records are written in the order of `primary_keys`.
+    ///
+    /// Fails with `Error::PsiProtocolError` if `opened_bits` does not have one bit
+    /// per bin, or if a bin holds a key beyond `primary_keys`.
+    pub fn write_matched(
+        &self,
+        opened_bits: &[bool],
+        primary_keys: &[PrimaryKey],
+        payloads: Option<&[Payload]>,
+        out: &mut impl Write,
+    ) -> Result<(), Error> {
+        if let Some(p) = payloads {
+            if p.len() != primary_keys.len() {
+                return Err(Error::PayloadSetNotComplete {
+                    npayloads: p.len(),
+                    nprimarykeys: primary_keys.len(),
+                });
+            }
+        }
+        if opened_bits.len() != self.input_indices.len() {
+            return Err(Error::PsiProtocolError(format!(
+                "{} opened bits for {} bins",
+                opened_bits.len(),
+                self.input_indices.len()
+            )));
+        }
+        // Empty bins are not in the intersection
+        let mut matched = opened_bits
+            .iter()
+            .zip(self.input_indices.iter())
+            .filter_map(|(bit, index)| if *bit { *index } else { None })
+            .collect::<Vec<usize>>();
+        matched.sort_unstable();
+        if let Some(&i) = matched.last().filter(|&&i| i >= primary_keys.len()) {
+            return Err(Error::PsiProtocolError(format!(
+                "bin holds primary key {} of {}",
+                i,
+                primary_keys.len()
+            )));
+        }
+        for i in matched {
+            let key = &primary_keys[i];
+            let key = utils::le_bytes_to_u128(&key[..key.len().min(PRIMARY_KEY_SIZE)]);
+            match payloads {
+                Some(p) => writeln!(
+                    out,
+                    "{},{}",
+                    key,
+                    utils::le_bytes_to_u128(p[i].prefix(PAYLOAD_SIZE))
+                )?,
+                None => writeln!(out, "{}", key)?,
+            }
+        }
+        Ok(())
+    }
 }
 
 /// A function that takes a `CircuitInputs`` (created by a BasePsi) and groups the wires of
@@ -120,6 +455,32 @@ where
     Ok((sender_payloads, receiver_payloads))
 }
 
+/// The values of primary keys compared in fuzzy PSI (cf. `CircuitPsiExt::intersect_fuzzy`).
+fn fuzzy_key_values(primary_keys: &[PrimaryKey]) -> Vec<u128> {
+    primary_keys
+        .iter()
+        .map(|key| utils::le_bytes_to_u128(&key[..key.len().min(PRIMARY_KEY_SIZE)]))
+        .collect()
+}
+
+/// The values of primary keys compared in sorted PSI (cf. `CircuitPsiExt::intersect_sorted`),
+/// failing if they are not strictly increasing.
+fn sorted_key_values(primary_keys: &[PrimaryKey]) -> Result<Vec<u128>, Error> {
+    let values: Vec<u128> = primary_keys
+        .iter()
+        .map(|key| {
+            let mut bytes = [0u8; PRIMARY_KEY_SIZE];
+            let n = key.len().min(PRIMARY_KEY_SIZE);
+            bytes[..n].copy_from_slice(&key[..n]);
+            bytes.iter().fold(0, |acc, b| (acc << 8) | *b as u128)
+        })
+        .collect();
+    match values.windows(2).position(|pair| pair[0] >= pair[1]) {
+        Some(index) => Err(Error::UnsortedInput(index + 1)),
+        None => Ok(values),
+    }
+}
+
 fn bundle_primary_keys<F, E>(
     circuit_inputs: &CircuitInputs<F::Item>,
 ) -> Result<Vec<BinaryBundle<<F as Fancy>::Item>>, Error>
@@ -130,9 +491,81 @@ where
 {
     Ok(wires_to_bundle::<F>(
         &circuit_inputs.sender_primary_keys,
-        PRIMARY_KEY_SIZE * 8,
+        circuit_inputs.hash_size * 8,
     ))
 }
+/// The number of bins and hash functions of the prescreening Bloom filter of
+/// `n` primary keys with false positive rate `rate` (cf.
+/// `CircuitPsiExt::intersect_prescreened`).
+fn prescreen_parameters(n: usize, rate: f64) -> Result<(usize, usize), Error> {
+    if !(rate > 0.0 && rate < 1.0) {
+        return Err(Error::PsiProtocolError(format!(
+            "invalid prescreen false positive rate {}",
+            rate
+        )));
+    }
+    // An empty filter would have no bins to hash to
+    let n = n.max(1);
+    let nbins = BloomFilter::compute_nbins(n, rate);
+    Ok((nbins, BloomFilter::compute_nhashes(n, nbins)))
+}
+/// Checks that the party has key columns of the same length, before anything is
+/// sent, returning its number of records.
+fn check_key_columns(columns: &[Vec<PrimaryKey>]) -> Result<usize, Error> {
+    let nrecords = match columns.first() {
+        Some(column) => column.len(),
+        None => {
+            return Err(Error::PsiProtocolError(
+                "at least one key column is needed".to_string(),
+            ))
+        }
+    };
+    if let Some(column) = columns.iter().find(|column| column.len() != nrecords) {
+        return Err(Error::PsiProtocolError(format!(
+            "key columns have {} and {} records",
+            nrecords,
+            column.len()
+        )));
+    }
+    Ok(nrecords)
+}
+/// Checks that `column` is a column of `schema`, before anything is sent.
+fn check_column(schema: &PayloadSchema, column: usize) -> Result<(), Error> {
+    if column >= schema.columns().len() {
+        return Err(Error::PayloadEncodingError(format!(
+            "no column {} in a schema of {} columns",
+            column,
+            schema.columns().len()
+        )));
+    }
+    Ok(())
+}
+/// Splits the parties' records into the indices of the valid ones and the errors
+/// of the malformed ones (cf. `CircuitPsi::intersect_lenient`).
+fn validate_records(
+    primary_keys: &[PrimaryKey],
+    payloads: Option<&[Payload]>,
+) -> (Vec<usize>, Vec<(usize, Error)>) {
+    let mut valid = vec![];
+    let mut errors = vec![];
+    for (i, key) in primary_keys.iter().enumerate() {
+        if key.len() != PRIMARY_KEY_SIZE {
+            errors.push((i, Error::InvalidPrimaryKeyLength(key.len())));
+        } else if payloads.is_some_and(|p| p[i].as_ref()[PAYLOAD_SIZE..].iter().any(|b| *b != 0)) {
+            errors.push((
+                i,
+                Error::PayloadEncodingError(format!(
+                    "payload does not fit in {} bytes",
+                    PAYLOAD_SIZE
+                )),
+            ));
+        } else {
+            valid.push(i);
+        }
+    }
+    (valid, errors)
+}
+
 /// A trait which describes the parties participating in the circuit
 /// PSI protocol along with their functionality.
 ///
@@ -150,6 +583,10 @@ pub trait CircuitPsi {
     ///           Payloads are optional, and this function allows computing
     ///           on set primary keys alone (see example below).
     ///
+    /// Either party's set may be empty, in which case no primary key is in the
+    /// intersection. If the evaluator's set is empty, the intersection bit
+    /// vector is empty as well.
+    ///
     /// example:
     /// ---------------------------------------
     // primary key (`primary_keys`) | data (`payloads`)
@@ -165,4 +602,298 @@ pub trait CircuitPsi {
     ) -> Result<Intersection, Error>;
     /// Computes the Circuit PSI on the parties' inputs with no payloads.
     fn intersect(&mut self, keys: &[PrimaryKey]) -> Result<Intersection, Error>;
+    /// Computes the Circuit PSI on the parties' inputs (with payloads), skipping
+    /// malformed records instead of failing the whole run.
+    ///
+    /// A record is malformed if its primary key does not have `PRIMARY_KEY_SIZE`
+    /// bytes, or if its payload has non-zero bytes past the first `PAYLOAD_SIZE`
+    /// bytes, which would otherwise be dropped silently. The intersection is
+    /// computed on the valid records, and the error of each malformed record is
+    /// returned with its index in `primary_keys`. The intersection's
+    /// `input_indices` are indices in `primary_keys` as well.
+    ///
+    /// Since the parties' sets need not have the same size, each party skips its
+    /// own malformed records without telling the other party.
+    fn intersect_lenient(
+        &mut self,
+        primary_keys: &[PrimaryKey],
+        payloads: Option<&[Payload]>,
+    ) -> Result<(Intersection, Vec<(usize, Error)>), Error> {
+        if let Some(p) = payloads {
+            if p.len() != primary_keys.len() {
+                return Err(Error::PayloadSetNotComplete {
+                    npayloads: p.len(),
+                    nprimarykeys: primary_keys.len(),
+                });
+            }
+        }
+        let (valid, errors) = validate_records(primary_keys, payloads);
+        let valid_keys: Vec<PrimaryKey> = valid.iter().map(|i| primary_keys[*i].clone()).collect();
+        let valid_payloads: Option<Vec<Payload>> =
+            payloads.map(|p| valid.iter().map(|i| p[*i]).collect());
+        let mut intersection_results =
+            self.intersect_with_payloads(&valid_keys, valid_payloads.as_deref())?;
+        for index in intersection_results.input_indices.iter_mut().flatten() {
+            *index = valid[*index];
+        }
+        Ok((intersection_results, errors))
+    }
+    /// Computes the Circuit PSI on primary keys that the parties already hashed
+    /// (e.g. SHA-256 digests of emails), with no payloads.
+    ///
+    /// The digests are treated as uniformly random and are not hashed again:
+    /// only their first 16 bytes are used as the parties' compressed inputs.
+    /// Both parties must use the same pre-hashing for their digests to match.
+    ///
+    /// Since the library itself hashes primary keys longer than 16 bytes with
+    /// SHA-256, intersecting the SHA-256 digests of such keys gives the same
+    /// result as intersecting the keys directly.
+    fn intersect_prehashed(&mut self, hashed_keys: &[[u8; 32]]) -> Result<Intersection, Error> {
+        let primary_keys: Vec<PrimaryKey> = hashed_keys
+            .iter()
+            .map(|digest| digest[..16].to_vec())
+            .collect();
+        self.intersect(&primary_keys)
+    }
+}
+
+/// Further computations on the parties' intersection, beyond its bit vector,
+/// e.g. revealing rows or payload statistics, along with alternative ways of
+/// computing the intersection.
+///
+/// This trait is implemented by the two parties participating in the
+/// protocol on top of `CircuitPsi`, i.e the CircuitPsi Garbler and the Evaluator.
+pub trait CircuitPsiExt: CircuitPsi {
+    /// Computes the Circuit PSI on the parties' rows and reveals which of them
+    /// are in the intersection.
+    ///
+    /// rows: The parties' set primary keys along with arbitrary metadata `T`.
+    ///       The metadata stays local to the party and is never sent.
+    ///
+    /// Only the party which knows how its primary keys map to the intersection
+    /// bit vector (i.e. the evaluator) gets the intersecting rows back, the
+    /// other party gets an empty vector.
+    fn intersect_with_rows<T: Clone>(
+        &mut self,
+        rows: &[(PrimaryKey, T)],
+    ) -> Result<Vec<(PrimaryKey, T)>, Error>;
+    /// Computes the set difference of the parties' primary keys: the
+    /// evaluator's keys which are not in the garbler's set.
+    ///
+    /// The complement of the intersection bit vector is revealed to the
+    /// evaluator (cf. `fancy_complement_bit_vector`), which is exactly as much
+    /// as revealing the intersection itself. The evaluator gets its keys absent
+    /// from the garbler's set, in the order they were given. The garbler gets an
+    /// empty vector.
+    fn set_difference(&mut self, primary_keys: &[PrimaryKey]) -> Result<Vec<PrimaryKey>, Error>;
+    /// Computes the Circuit PSI on the parties' inputs (with payloads) and
+    /// XOR-secret-shares the sender's unmasked payloads between the parties,
+    /// e.g. to use them as inputs to a further MPC.
+    ///
+    /// The i-th share corresponds to the i-th bit of the returned intersection
+    /// bit vector. If that bit is set, XORing both parties' shares gives the
+    /// sender's payload associated with the primary key in the bin. Otherwise,
+    /// the sender's payload is unmasked with a wrong mask and XORing the shares
+    /// gives a pseudorandom value. Each share is stored in the first
+    /// `PAYLOAD_SIZE` bytes of a `Payload`.
+    fn intersect_with_payload_shares(
+        &mut self,
+        primary_keys: &[PrimaryKey],
+        payloads: Option<&[Payload]>,
+    ) -> Result<(Intersection, Vec<Payload>), Error>;
+    /// Computes a fuzzy PSI on the parties' primary keys with no payloads, where
+    /// two keys match if their Hamming distance is at most `max_distance`,
+    /// e.g. for noisy biometric identifiers.
+    ///
+    /// Keys are compared on their first `PRIMARY_KEY_SIZE` bytes, padded with
+    /// zeros. Since keys which are close do not hash to the same bin, no hashing
+    /// is done: every key of the evaluator is compared to every key of the
+    /// garbler in the circuit, which costs `O(n * m * PRIMARY_KEY_SIZE * 8)`
+    /// AND gates for sets of sizes `n` and `m`. This is only practical for small
+    /// sets.
+    ///
+    /// The i-th bit of the intersection bit vector indicates whether the
+    /// evaluator's i-th key matches any of the garbler's keys, and the i-th
+    /// primary key of the intersection is the evaluator's i-th key.
+    fn intersect_fuzzy(
+        &mut self,
+        primary_keys: &[PrimaryKey],
+        max_distance: usize,
+    ) -> Result<Intersection, Error>;
+    /// Computes the Circuit PSI on sorted primary keys with no payloads, by
+    /// merging the parties' sets in the circuit instead of hashing them.
+    ///
+    /// The primary keys must be sorted in lexicographic order of their first
+    /// `PRIMARY_KEY_SIZE` bytes, padded with zeros, without duplicates: this is
+    /// checked before anything is sent, failing with `Error::UnsortedInput`. No
+    /// cuckoo table or OPPRF is needed, so the working set is limited to the
+    /// circuit's wires, at the cost of `O(n log n * PRIMARY_KEY_SIZE * 8)` AND
+    /// gates for `n` keys in total (cf. `fancy_sorted_intersection`).
+    ///
+    /// The intersection's primary keys are the parties' merged keys, and the
+    /// i-th bit of the intersection bit vector indicates whether the i-th merged
+    /// key is in the intersection.
+    fn intersect_sorted(&mut self, primary_keys: &[PrimaryKey]) -> Result<Intersection, Error>;
+    /// Computes the Circuit PSI on the parties' inputs with no payloads, after
+    /// an approximate prescreen of the evaluator's primary keys.
+    ///
+    /// The garbler sends a Bloom filter of its primary keys with false positive
+    /// rate `rate`, and the evaluator only runs the exact PSI on its keys which
+    /// the filter contains. Since a Bloom filter has no false negatives, the
+    /// intersection is the same as with `intersect`, and its `input_indices`
+    /// are indices in `primary_keys` as well. Both parties must use the same
+    /// `rate`, which the evaluator checks against the filter it receives.
+    ///
+    /// The prescreen leaks membership to the evaluator: it can query the filter
+    /// with any key, in particular keys outside of its set, and learn whether
+    /// the garbler has it up to the false positive rate, as well as the size of
+    /// the garbler's set. The garbler in turn learns how many of the
+    /// evaluator's keys passed the prescreen. A larger `rate` leaks less, at
+    /// the cost of more candidates for the exact PSI, and only sets for which
+    /// these leaks are acceptable should be prescreened.
+    fn intersect_prescreened(
+        &mut self,
+        primary_keys: &[PrimaryKey],
+        rate: f64,
+    ) -> Result<Intersection, Error>;
+    /// Computes the Circuit PSI on the parties' inputs with no payloads, using
+    /// random OT correlations precomputed in an offline phase for the oblivious
+    /// transfers of the evaluator's circuit inputs.
+    ///
+    /// The correlations are generated by the parties with any random OT (e.g.
+    /// `PrecomputedOts::generate_sender` with ALSZ), before their inputs are
+    /// known: the garbler holds the sender correlations and the evaluator the
+    /// receiver ones. They are consumed first and any remaining transfer falls
+    /// back to OT extension, so both parties must provide the same number of
+    /// correlations. Unused correlations are kept for the next runs.
+    fn intersect_online(
+        &mut self,
+        primary_keys: &[PrimaryKey],
+        precomputed: PrecomputedOts,
+    ) -> Result<Intersection, Error>;
+    /// Computes the Circuit PSI on the parties' inputs and reveals the cardinality
+    /// of the intersection to the evaluator.
+    ///
+    /// The bits of the intersection are counted in the circuit with the popcount
+    /// suited to `hint` (cf. `fancy_cardinality_with_hint`): under
+    /// `NetworkHint::Latency`, the count has the least multiplicative depth. Both
+    /// parties must use the same hint.
+    ///
+    /// If the parties set `dp_cardinality` to some `epsilon`, each of them samples
+    /// discrete Laplace noise with parameter `epsilon` (cf.
+    /// `utils::sample_discrete_laplace`), which is added to the count in the
+    /// circuit before it is revealed (cf. `fancy_noisy_cardinality`). Since a
+    /// party only knows its own noise, the other party's noise alone makes the
+    /// revealed cardinality `epsilon`-differentially private, and it may be
+    /// negative.
+    ///
+    /// Only the evaluator learns the cardinality, the garbler gets `None`.
+    fn intersect_cardinality(
+        &mut self,
+        primary_keys: &[PrimaryKey],
+        hint: NetworkHint,
+    ) -> Result<Option<i64>, Error>;
+    /// Computes the Circuit PSI on the parties' inputs (with payloads) and counts
+    /// the distinct values of the sender's payload `column` over the
+    /// intersection, e.g. the number of distinct products bought by the users
+    /// both parties know.
+    ///
+    /// The sender's payloads are split with `schema` (cf.
+    /// `PayloadSchema::split_columns`), which both parties must agree on. The
+    /// values are sorted obliviously in the circuit, which costs
+    /// `O(n log^2 n * w)` AND gates for `n` bins and a column of `w` bits (cf.
+    /// `fancy_column_count_distinct`), far more than the intersection itself.
+    ///
+    /// Only the evaluator learns the count, the garbler gets `None`.
+    fn intersect_count_distinct_column(
+        &mut self,
+        primary_keys: &[PrimaryKey],
+        payloads: &[Payload],
+        schema: &PayloadSchema,
+        column: usize,
+    ) -> Result<Option<u128>, Error>;
+    /// Computes the Circuit PSI on the parties' inputs (with payloads) and reveals
+    /// to the evaluator the maximum of both parties' payloads for each of its
+    /// primary keys in the intersection, e.g. the latest of two timestamps.
+    ///
+    /// The payloads are compared as unsigned integers on their first
+    /// `PAYLOAD_SIZE` bytes (cf. `fancy_payload_max`). The evaluator gets one
+    /// value per primary key, in the order they were given: the maximum of the
+    /// payloads if the key is in the intersection, and 0 otherwise. The garbler
+    /// gets an empty vector.
+    fn intersect_payload_max(
+        &mut self,
+        primary_keys: &[PrimaryKey],
+        payloads: &[Payload],
+    ) -> Result<Vec<u128>, Error>;
+    /// Computes the Circuit PSI on the parties' inputs (with payloads) and reveals
+    /// to the evaluator the `k` largest of the sender's payloads associated with
+    /// the intersection, e.g. the highest-value matched records, and optionally
+    /// the evaluator's primary keys they are associated with.
+    ///
+    /// The payloads are compared as unsigned integers on their first
+    /// `PAYLOAD_SIZE` bytes, and selected in the circuit with a partial sorting
+    /// network (cf. `fancy_top_k`), which costs `O(n log^2 k)` comparisons for
+    /// `n` bins. Only the selected records are revealed: the evaluator also
+    /// learns whether the intersection holds fewer than `k` records, and how
+    /// many. Tied payloads are selected in an arbitrary order.
+    ///
+    /// The evaluator gets `min(k, |intersection|)` pairs of a payload and, if
+    /// `with_keys` is set, its primary key, in decreasing order of payloads.
+    /// The garbler gets an empty vector. Both parties must use the same `k` and
+    /// `with_keys`.
+    fn intersect_top_k(
+        &mut self,
+        primary_keys: &[PrimaryKey],
+        payloads: &[Payload],
+        k: usize,
+        with_keys: bool,
+    ) -> Result<Vec<(u128, Option<PrimaryKey>)>, Error>;
+    /// Computes the Circuit PSI on the parties' inputs, both parties providing a
+    /// payload per primary key, and reveals to the evaluator `op` applied to the
+    /// parties' payloads for each of its primary keys in the intersection, e.g.
+    /// the sum of two balances (cf. `fancy_payload_combine`).
+    ///
+    /// The evaluator gets one value per primary key, in the order they were
+    /// given: the result of `op` if the key is in the intersection, and `None`
+    /// otherwise. The garbler gets an empty vector. Both parties must use the
+    /// same `op`.
+    fn intersect_with_both_payloads(
+        &mut self,
+        primary_keys: &[PrimaryKey],
+        payloads: &[Payload],
+        op: PayloadOp,
+    ) -> Result<Vec<Option<u128>>, Error>;
+    /// Computes the Circuit PSI on the parties' rows (with payloads) and joins
+    /// them on their primary keys, revealing to the evaluator the sender's
+    /// payload associated with each of its rows in the intersection.
+    ///
+    /// local: The parties' set primary keys along with arbitrary metadata `T`.
+    ///        The metadata stays local to the party and is never sent.
+    /// payloads: The payloads associated with the rows of `local`, in the same
+    ///           order, converted into `Payload`s before being sent.
+    ///
+    /// The evaluator gets one `(primary key, metadata, sender's payload)` triple
+    /// per row in the intersection, in the order they were given. The sender's
+    /// payloads of the other rows are not revealed (cf. `fancy_payload_select`).
+    /// The garbler gets an empty vector.
+    fn intersect_join<T: Clone, U: Clone + Into<Payload>>(
+        &mut self,
+        local: &[(PrimaryKey, T)],
+        payloads: &[U],
+    ) -> Result<Vec<(PrimaryKey, T, Payload)>, Error>;
+    /// Computes the Circuit PSI on each of the parties' key columns and reveals to
+    /// the evaluator which of its records match in any column, e.g. records whose
+    /// email or phone number is known to the garbler.
+    ///
+    /// `columns[c][i]` is the `c`-th key of the party's `i`-th record, and every
+    /// column is intersected with the same column of the other party, so both
+    /// parties must provide the same number of columns, in the same order. The
+    /// per-column bits are combined in the circuit, so that the evaluator does not
+    /// learn which column matched, at the cost of an oblivious sort of the bins of
+    /// all columns (cf. `fancy_any_column_bit_vector`).
+    ///
+    /// The evaluator gets the indices of its records in the intersection, in
+    /// increasing order. The garbler gets an empty vector.
+    fn intersect_any_column(&mut self, columns: &[Vec<PrimaryKey>]) -> Result<Vec<usize>, Error>;
 }
@@ -0,0 +1,168 @@
+//! Running circuit PSI in the background, with progress reports and
+//! cancellation.
+//!
+//! `spawn_garbler` and `spawn_evaluator` create a party on a new thread and run
+//! a protocol on it, returning a `ProtocolHandle` right away. The handle
+//! receives a `Progress` report every time the protocol enters a phase (the
+//! phases traced when the `tracing` feature is enabled), and can cancel the
+//! protocol: it then aborts at the next phase boundary with `Error::Cancelled`.
+//! The channel is closed once the protocol succeeds, so that the other party
+//! reads the end of the stream instead of waiting for more data. If the
+//! protocol fails or is cancelled, the other party fails as well, since the
+//! channel is closed when the thread ends.
+use crate::{
+    errors::Error,
+    psi::circuit_psi::{evaluator::PsiEvaluator, garbler::PsiGarbler},
+};
+use scuttlebutt::{AesRng, Block, CloseChannel};
+use std::{
+    cell::RefCell,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{channel, Receiver, Sender},
+        Arc,
+    },
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+thread_local! {
+    // The progress state of the protocol running on the current thread, if it
+    // was spawned by `ProtocolHandle::spawn`
+    static PROGRESS: RefCell<Option<ProgressState>> = const { RefCell::new(None) };
+}
+
+struct ProgressState {
+    progress: Sender<Progress>,
+    cancelled: Arc<AtomicBool>,
+    start: Instant,
+}
+
+/// A report that the protocol entered a phase.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Progress {
+    /// The phase the protocol entered
+    pub phase: &'static str,
+    /// The time elapsed since the protocol was spawned
+    pub elapsed: Duration,
+}
+
+/// Marks the start of `phase` of the protocol running on the current thread,
+/// failing with `Error::Cancelled` if the protocol was cancelled.
+///
+/// This is a no-op if the protocol was not spawned by `ProtocolHandle::spawn`.
+pub fn enter_phase(phase: &'static str) -> Result<(), Error> {
+    PROGRESS.with(|state| match state.borrow().as_ref() {
+        Some(state) if state.cancelled.load(Ordering::SeqCst) => Err(Error::Cancelled),
+        Some(state) => {
+            // The handle may have been dropped without cancelling
+            let _ = state.progress.send(Progress {
+                phase,
+                elapsed: state.start.elapsed(),
+            });
+            Ok(())
+        }
+        None => Ok(()),
+    })
+}
+
+/// A protocol running on its own thread.
+pub struct ProtocolHandle<T> {
+    progress: Receiver<Progress>,
+    cancelled: Arc<AtomicBool>,
+    thread: JoinHandle<Result<T, Error>>,
+}
+
+impl<T: Send + 'static> ProtocolHandle<T> {
+    /// Runs `f` on a new thread, reporting the phases it enters.
+    pub fn spawn<F>(f: F) -> Self
+    where
+        F: FnOnce() -> Result<T, Error> + Send + 'static,
+    {
+        let (sender, progress) = channel();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let state = ProgressState {
+            progress: sender,
+            cancelled: cancelled.clone(),
+            start: Instant::now(),
+        };
+        let thread = std::thread::spawn(move || {
+            PROGRESS.with(|progress| *progress.borrow_mut() = Some(state));
+            f()
+        });
+        ProtocolHandle {
+            progress,
+            cancelled,
+            thread,
+        }
+    }
+}
+
+impl<T> ProtocolHandle<T> {
+    /// The progress reports of the protocol, in the order of its phases. The
+    /// receiver is disconnected once the protocol ends.
+    pub fn progress(&self) -> &Receiver<Progress> {
+        &self.progress
+    }
+
+    /// Cancels the protocol, which aborts at its next phase boundary.
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Whether the protocol has ended, so that `join` does not block.
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+
+    /// Waits for the protocol to end and returns its result, or
+    /// `Error::Cancelled` if it was cancelled before it ended.
+    ///
+    /// Panics if the protocol panicked.
+    pub fn join(self) -> Result<T, Error> {
+        match self.thread.join() {
+            Ok(result) => result,
+            Err(panic) => std::panic::resume_unwind(panic),
+        }
+    }
+}
+
+/// Creates a `PsiGarbler` on `channel` seeded with `seed` on a new thread and
+/// runs `f` on it (cf. `ProtocolHandle::spawn`).
+///
+/// `f` may configure the garbler before running the protocol, and returns what
+/// `join` returns, e.g. the result of `CircuitPsi::intersect`. The channel is
+/// closed once `f` succeeds, even if a clone of it is still alive.
+pub fn spawn_garbler<C, B, T, F>(mut channel: C, seed: Block, f: F) -> ProtocolHandle<T>
+where
+    C: CloseChannel + Clone + Send + 'static,
+    B: 'static,
+    T: Send + 'static,
+    F: FnOnce(&mut PsiGarbler<C, AesRng, B>) -> Result<T, Error> + Send + 'static,
+{
+    ProtocolHandle::spawn(move || {
+        enter_phase("setup")?;
+        let mut gb = PsiGarbler::new(&mut channel, seed)?;
+        let result = f(&mut gb)?;
+        channel.close()?;
+        Ok(result)
+    })
+}
+
+/// Creates a `PsiEvaluator` on `channel` seeded with `seed` on a new thread and
+/// runs `f` on it (cf. `spawn_garbler`).
+pub fn spawn_evaluator<C, B, T, F>(mut channel: C, seed: Block, f: F) -> ProtocolHandle<T>
+where
+    C: CloseChannel + Clone + Send + 'static,
+    B: 'static,
+    T: Send + 'static,
+    F: FnOnce(&mut PsiEvaluator<C, AesRng, B>) -> Result<T, Error> + Send + 'static,
+{
+    ProtocolHandle::spawn(move || {
+        enter_phase("setup")?;
+        let mut ev = PsiEvaluator::new(&mut channel, seed)?;
+        let result = f(&mut ev)?;
+        channel.close()?;
+        Ok(result)
+    })
+}
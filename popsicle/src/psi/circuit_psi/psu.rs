@@ -0,0 +1,105 @@
+//! Private set union on top of the Circuit Psi parties, revealing the union to
+//! the evaluator in an oblivious random order.
+use crate::{
+    errors::Error,
+    psi::circuit_psi::{circuits::*, evaluator::PsiEvaluator, garbler::PsiGarbler, *},
+};
+use fancy_garbling::{BinaryGadgets, Fancy, FancyInput};
+use rand::{CryptoRng, Rng, RngCore, SeedableRng};
+use scuttlebutt::{AbstractChannel, Block};
+
+/// The number of bits of each party's shuffling tags (cf. `fancy_shuffle`).
+pub const SHUFFLE_TAG_BITS: usize = 64;
+
+/// A trait defining the private set union protocols of Circuit Psi.
+pub trait CircuitPsu {
+    /// Computes the union of the parties' primary keys and reveals it to the
+    /// evaluator, obliviously shuffled so that the order of the union does not
+    /// depend on the order of either party's keys.
+    ///
+    /// Keys are compared on their first `PRIMARY_KEY_SIZE` bytes, padded with
+    /// zeros, and the union is made of these `PRIMARY_KEY_SIZE` bytes. Besides
+    /// the union, the evaluator learns its size, hence the size of the
+    /// intersection, and the garbler learns the size of the evaluator's set.
+    /// The keys are sorted and shuffled in the circuit, which costs
+    /// `O(n log^2 n * PRIMARY_KEY_SIZE * 8)` AND gates for `n` keys in total
+    /// (cf. `fancy_shuffled_union`).
+    ///
+    /// The evaluator gets the union, the garbler an empty vector.
+    fn union_shuffled(&mut self, primary_keys: &[PrimaryKey]) -> Result<Vec<PrimaryKey>, Error>;
+}
+
+impl<C, RNG, B> CircuitPsu for PsiGarbler<C, RNG, B>
+where
+    C: AbstractChannel + Clone,
+    RNG: RngCore + CryptoRng + Rng + SeedableRng<Seed = Block>,
+{
+    fn union_shuffled(&mut self, primary_keys: &[PrimaryKey]) -> Result<Vec<PrimaryKey>, Error> {
+        self.channel.write_usize(primary_keys.len())?;
+        self.channel.flush()?;
+        let nreceiver = self.channel.read_usize()?;
+        let ntags = primary_keys.len() + nreceiver;
+        let tags: Vec<u128> = (0..ntags).map(|_| self.rng.gen::<u64>() as u128).collect();
+
+        let sender_keys = self
+            .gb
+            .bin_encode_many(&fuzzy_key_values(primary_keys), PRIMARY_KEY_SIZE * 8)?;
+        let receiver_keys = self.gb.bin_receive_many(nreceiver, PRIMARY_KEY_SIZE * 8)?;
+        let garbler_tags = self.gb.bin_encode_many(&tags, SHUFFLE_TAG_BITS)?;
+        let evaluator_tags = self.gb.bin_receive_many(ntags, SHUFFLE_TAG_BITS)?;
+        let (keys, duplicates) = fancy_shuffled_union(
+            &mut self.gb,
+            &sender_keys,
+            &receiver_keys,
+            &garbler_tags,
+            &evaluator_tags,
+        )?;
+        trace_phase!("reveal", nbits = keys.len());
+        self.gb.bin_outputs(&keys)?;
+        self.gb.outputs(&duplicates)?;
+        Ok(vec![])
+    }
+}
+
+impl<C, RNG, B> CircuitPsu for PsiEvaluator<C, RNG, B>
+where
+    C: AbstractChannel + Clone,
+    RNG: RngCore + CryptoRng + Rng + SeedableRng<Seed = Block>,
+{
+    fn union_shuffled(&mut self, primary_keys: &[PrimaryKey]) -> Result<Vec<PrimaryKey>, Error> {
+        let nsender = self.channel.read_usize()?;
+        self.channel.write_usize(primary_keys.len())?;
+        self.channel.flush()?;
+        let ntags = nsender + primary_keys.len();
+        let tags: Vec<u128> = (0..ntags).map(|_| self.rng.gen::<u64>() as u128).collect();
+
+        let sender_keys = self.ev.bin_receive_many(nsender, PRIMARY_KEY_SIZE * 8)?;
+        let receiver_keys = self
+            .ev
+            .bin_encode_many(&fuzzy_key_values(primary_keys), PRIMARY_KEY_SIZE * 8)?;
+        let garbler_tags = self.ev.bin_receive_many(ntags, SHUFFLE_TAG_BITS)?;
+        let evaluator_tags = self.ev.bin_encode_many(&tags, SHUFFLE_TAG_BITS)?;
+        let (keys, duplicates) = fancy_shuffled_union(
+            &mut self.ev,
+            &sender_keys,
+            &receiver_keys,
+            &garbler_tags,
+            &evaluator_tags,
+        )?;
+        trace_phase!("reveal", nbits = keys.len());
+        let keys = self
+            .ev
+            .bin_outputs(&keys)?
+            .expect("evaluator should produce outputs");
+        let duplicates = self
+            .ev
+            .outputs(&duplicates)?
+            .expect("evaluator should produce outputs");
+        Ok(keys
+            .into_iter()
+            .zip(duplicates)
+            .filter(|(_, duplicate)| *duplicate == 0)
+            .map(|(key, _)| key.to_le_bytes()[..PRIMARY_KEY_SIZE].to_vec())
+            .collect())
+    }
+}
@@ -1,9 +1,44 @@
 //! Testing Suite for Circuit Psi
+pub mod test_any_column;
 pub mod test_base_psi;
+pub mod test_bench;
+pub mod test_both_payloads;
+pub mod test_cardinality_hint;
 pub mod test_circuit_psi;
+pub mod test_cost_estimate;
+pub mod test_count_distinct;
+pub mod test_cuckoo_strategy;
+pub mod test_diagnostics;
+pub mod test_dp_cardinality;
+pub mod test_dp_output;
+pub mod test_empty;
+pub mod test_fuzzy;
 pub mod test_hashing;
 pub mod test_init;
+pub mod test_intersection;
+pub mod test_join;
+pub mod test_lenient;
+pub mod test_masks;
 pub mod test_opprf;
+pub mod test_oprf_key;
+pub mod test_payload_columns;
+pub mod test_payload_encoding;
+pub mod test_payload_max;
+pub mod test_payload_schema;
+pub mod test_payload_shares;
+pub mod test_precomputed_ot;
+pub mod test_prefix_sum;
+pub mod test_prehashed;
+pub mod test_prescreen;
+pub mod test_progress;
+pub mod test_psu;
+pub mod test_relay;
+pub mod test_security_params;
+pub mod test_set_difference;
+pub mod test_set_sizes;
+pub mod test_sorted;
+pub mod test_top_k;
+pub mod test_tracing;
 pub mod utils;
 
 #[cfg(test)]
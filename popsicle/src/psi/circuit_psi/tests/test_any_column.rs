@@ -0,0 +1,80 @@
+//! Testing the intersection over any of several key columns
+#[cfg(test)]
+mod tests {
+    use crate::{
+        errors::Error,
+        psi::circuit_psi::{
+            evaluator::OpprfPsiEvaluator,
+            garbler::OpprfPsiGarbler,
+            tests::{utils::*, *},
+            *,
+        },
+    };
+    use rand::Rng;
+    use scuttlebutt::{AesRng, Block};
+
+    // The offset of the phone numbers, so that they never collide with emails
+    const PHONE_OFFSET: u64 = 1 << 32;
+
+    // Computes the records of the evaluator matching the garbler's records on any
+    // column, and returns their indices
+    fn psty_any_column(
+        columns_a: &[Vec<PrimaryKey>],
+        columns_b: &[Vec<PrimaryKey>],
+    ) -> (Result<Vec<usize>, Error>, Result<Vec<usize>, Error>) {
+        two_party_test!(
+            |channel, rng| {
+                let mut gb_psi = OpprfPsiGarbler::<_, AesRng>::new(channel, rng.gen::<Block>())?;
+                gb_psi.intersect_any_column(columns_a)
+            },
+            |channel, rng| {
+                let mut ev_psi = OpprfPsiEvaluator::<_, AesRng>::new(channel, rng.gen::<Block>())?;
+                ev_psi.intersect_any_column(columns_b)
+            },
+        )
+    }
+
+    #[test]
+    // Test that records matching on their phone number but not on their email
+    // are in the intersection over the email and phone columns
+    fn test_any_column_phone_only() {
+        let emails_a = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
+        let phones_a = enum_ids(SET_SIZE, PHONE_OFFSET, PRIMARY_KEY_SIZE);
+        // Records 0 to SET_SIZE / 2 match on email, and records 0 to
+        // 3 * SET_SIZE / 4 on phone number
+        let emails_b = enum_ids(SET_SIZE, SET_SIZE as u64 / 2, PRIMARY_KEY_SIZE);
+        let phones_b = enum_ids(
+            SET_SIZE,
+            PHONE_OFFSET + SET_SIZE as u64 / 4,
+            PRIMARY_KEY_SIZE,
+        );
+
+        let (gb, ev) = psty_any_column(
+            &[emails_a.clone(), phones_a.clone()],
+            &[emails_b.clone(), phones_b.clone()],
+        );
+        assert!(gb.unwrap().is_empty());
+        let indices = ev.unwrap();
+        assert_eq!(indices, (0..3 * SET_SIZE / 4).collect::<Vec<usize>>());
+        for i in SET_SIZE / 2..3 * SET_SIZE / 4 {
+            assert!(!emails_a.contains(&emails_b[i]));
+            assert!(indices.contains(&i));
+        }
+
+        // On the email column alone, the records matching only on phone number
+        // are not in the intersection
+        let (_, ev) = psty_any_column(&[emails_a], &[emails_b]);
+        assert_eq!(ev.unwrap(), (0..SET_SIZE / 2).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    // Test that key columns with different numbers of records are rejected
+    // before anything is sent
+    fn test_any_column_uneven_columns() {
+        let emails = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
+        let phones = enum_ids(SET_SIZE - 1, PHONE_OFFSET, PRIMARY_KEY_SIZE);
+        let (gb, ev) = psty_any_column(&[emails.clone(), phones.clone()], &[emails, phones]);
+        assert!(matches!(gb, Err(Error::PsiProtocolError(_))));
+        assert!(matches!(ev, Err(Error::PsiProtocolError(_))));
+    }
+}
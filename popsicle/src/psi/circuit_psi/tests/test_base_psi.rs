@@ -3,7 +3,9 @@
 mod tests {
     use crate::errors::Error;
     use crate::psi::circuit_psi::{
-        base_psi::{receiver::OpprfReceiver, sender::OpprfSender, BasePsi},
+        base_psi::{
+            receiver::OpprfReceiver, sender::OpprfSender, BasePsi, CuckooParams, CuckooStrategy,
+        },
         tests::{utils::*, *},
         utils::*,
         *,
@@ -14,7 +16,7 @@ mod tests {
     };
 
     use ocelot::{ot::AlszReceiver as OtReceiver, ot::AlszSender as OtSender};
-    use scuttlebutt::{AesRng, Block512, Channel};
+    use scuttlebutt::{AesRng, Block, Block512, Channel};
 
     use std::{
         io::{BufReader, BufWriter},
@@ -28,6 +30,8 @@ mod tests {
         payloads: &[Block512],
         seed_sx: u64,
         seed_rx: u64,
+        public_seed_sx: Option<Block>,
+        public_seed_rx: Option<Block>,
     ) -> (
         Result<CircuitInputs<WireMod2>, Error>,
         Result<CircuitInputs<WireMod2>, Error>,
@@ -51,6 +55,12 @@ mod tests {
                     Some(&payloads),
                     &mut channel,
                     &mut rng,
+                    false,
+                    public_seed_sx,
+                    SecurityParams::default(),
+                    CuckooStrategy::default(),
+                    CuckooParams::default(),
+                    false,
                 )
             });
             let mut rng = AesRng::seed_from_u64(seed_rx);
@@ -68,6 +78,12 @@ mod tests {
                 Some(&payloads),
                 &mut channel,
                 &mut rng,
+                false,
+                public_seed_rx,
+                SecurityParams::default(),
+                CuckooStrategy::default(),
+                CuckooParams::default(),
+                false,
             );
             (result_sender.join().unwrap(), result_receiver)
         })
@@ -80,8 +96,14 @@ mod tests {
             let mut rng = AesRng::new();
             let primary_keys = rand_u8_vec_unique(SET_SIZE, ELEMENT_MAX, &mut rng);
             let payloads = int_vec_block512(vec![1u128; SET_SIZE], PAYLOAD_SIZE);
-            let (result_sender, _) =
-                psty_base_psi(&primary_keys, &payloads, DEFAULT_SEED, DEFAULT_SEED);
+            let (result_sender, _) = psty_base_psi(
+                &primary_keys,
+                &payloads,
+                DEFAULT_SEED,
+                DEFAULT_SEED,
+                None,
+                None,
+            );
             assert!(
                 !result_sender.is_err(),
                 "PSTY's Base Psi failed on the sender side"
@@ -96,8 +118,14 @@ mod tests {
             let primary_keys = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
             let payloads =
                 int_vec_block512(rand_u128_vec(SET_SIZE, PAYLOAD_MAX, &mut rng), PAYLOAD_SIZE);
-            let (result_sender, _) =
-                psty_base_psi(&primary_keys, &payloads, DEFAULT_SEED, DEFAULT_SEED);
+            let (result_sender, _) = psty_base_psi(
+                &primary_keys,
+                &payloads,
+                DEFAULT_SEED,
+                DEFAULT_SEED,
+                None,
+                None,
+            );
             assert!(
                 !result_sender.is_err(),
                 "PSTY's Base Psi failed on the sender side"
@@ -111,8 +139,14 @@ mod tests {
             let mut rng = AesRng::new();
             let primary_keys = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
             let payloads = int_vec_block512(vec![1u128; SET_SIZE], PAYLOAD_SIZE);
-            let (result_sender, _) =
-                psty_base_psi(&primary_keys, &payloads, rng.gen(), DEFAULT_SEED);
+            let (result_sender, _) = psty_base_psi(
+                &primary_keys,
+                &payloads,
+                rng.gen(),
+                DEFAULT_SEED,
+                None,
+                None,
+            );
             assert!(
                 !result_sender.is_err(),
                 "PSTY's Base Psi failed on the sender side"
@@ -126,8 +160,14 @@ mod tests {
             let mut rng = AesRng::new();
             let primary_keys = rand_u8_vec_unique(SET_SIZE, ELEMENT_MAX, &mut rng);
             let payloads = int_vec_block512(vec![1u128; SET_SIZE], PAYLOAD_SIZE);
-            let (_, result_receiver) =
-                psty_base_psi(&primary_keys, &payloads, DEFAULT_SEED, DEFAULT_SEED);
+            let (_, result_receiver) = psty_base_psi(
+                &primary_keys,
+                &payloads,
+                DEFAULT_SEED,
+                DEFAULT_SEED,
+                None,
+                None,
+            );
             assert!(
                 !result_receiver.is_err(),
                 "PSTY's Base Psi failed on the receiver side"
@@ -142,8 +182,14 @@ mod tests {
             let primary_keys = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
             let payloads =
                 int_vec_block512(rand_u128_vec(SET_SIZE, PAYLOAD_MAX, &mut rng), PAYLOAD_SIZE);
-            let (_, result_receiver) =
-                psty_base_psi(&primary_keys, &payloads, DEFAULT_SEED, DEFAULT_SEED);
+            let (_, result_receiver) = psty_base_psi(
+                &primary_keys,
+                &payloads,
+                DEFAULT_SEED,
+                DEFAULT_SEED,
+                None,
+                None,
+            );
             assert!(
                 !result_receiver.is_err(),
                 "PSTY's Base Psi failed on the receiver side"
@@ -157,12 +203,64 @@ mod tests {
             let mut rng = AesRng::new();
             let primary_keys = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
             let payloads = int_vec_block512(vec![1u128; SET_SIZE], PAYLOAD_SIZE);
-            let (_, result_receiver) =
-                psty_base_psi(&primary_keys, &payloads, DEFAULT_SEED, rng.gen());
+            let (_, result_receiver) = psty_base_psi(
+                &primary_keys,
+                &payloads,
+                DEFAULT_SEED,
+                rng.gen(),
+                None,
+                None,
+            );
             assert!(
                 !result_receiver.is_err(),
                 "PSTY's Base Psi failed on the receiver side"
             );
         }
     }
+    #[test]
+    // Test that the Base Psi succeeds when both parties use the same public seed
+    fn test_psty_base_psi_matching_public_seeds_succeeded() {
+        for _ in 0..TEST_TRIALS {
+            let mut rng = AesRng::new();
+            let primary_keys = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
+            let payloads = int_vec_block512(vec![1u128; SET_SIZE], PAYLOAD_SIZE);
+            let public_seed = rng.gen::<Block>();
+            let (result_sender, result_receiver) = psty_base_psi(
+                &primary_keys,
+                &payloads,
+                rng.gen(),
+                rng.gen(),
+                Some(public_seed),
+                Some(public_seed),
+            );
+            assert!(
+                result_sender.is_ok() && result_receiver.is_ok(),
+                "PSTY's Base Psi failed with matching public seeds"
+            );
+        }
+    }
+    #[test]
+    // Test that the Base Psi detects parties using different public seeds
+    fn test_psty_base_psi_mismatched_public_seeds_detected() {
+        for _ in 0..TEST_TRIALS {
+            let mut rng = AesRng::new();
+            let primary_keys = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
+            let payloads = int_vec_block512(vec![1u128; SET_SIZE], PAYLOAD_SIZE);
+            let (result_sender, result_receiver) = psty_base_psi(
+                &primary_keys,
+                &payloads,
+                DEFAULT_SEED,
+                DEFAULT_SEED,
+                Some(rng.gen()),
+                Some(rng.gen()),
+            );
+            // The sender rejects the receiver's hashing key, which is not derived
+            // from its seed, and the receiver sees it hang up
+            assert!(
+                matches!(result_sender, Err(Error::ParameterInconsistency))
+                    && result_receiver.is_err(),
+                "PSTY's Base Psi did not detect mismatched public seeds"
+            );
+        }
+    }
 }
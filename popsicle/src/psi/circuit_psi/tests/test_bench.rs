@@ -0,0 +1,131 @@
+//! Testing the benchmarking mode of Circuit Psi
+#[cfg(all(test, feature = "bench"))]
+mod tests {
+    use crate::psi::circuit_psi::{
+        bench::{bench_evaluator, bench_garbler, BenchConfig, PhaseTimings},
+        evaluator::OpprfPsiEvaluator,
+        garbler::OpprfPsiGarbler,
+        tests::{utils::*, *},
+        *,
+    };
+    use rand::{Rng, SeedableRng};
+    use scuttlebutt::{AesRng, Block, Channel};
+    use std::{
+        io::{BufReader, BufWriter, Write},
+        os::unix::net::UnixStream,
+        sync::{Arc, Mutex},
+    };
+
+    // A writer recording all the bytes written through it
+    struct RecordWriter<W> {
+        writer: W,
+        transcript: Arc<Mutex<Vec<u8>>>,
+    }
+
+    impl<W: Write> Write for RecordWriter<W> {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            let n = self.writer.write(buf)?;
+            self.transcript.lock().unwrap().extend_from_slice(&buf[..n]);
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            self.writer.flush()
+        }
+    }
+
+    type TranscriptChannel = Channel<BufReader<UnixStream>, RecordWriter<BufWriter<UnixStream>>>;
+
+    fn record_channel(stream: UnixStream, transcript: &Arc<Mutex<Vec<u8>>>) -> TranscriptChannel {
+        let reader = BufReader::new(stream.try_clone().unwrap());
+        let writer = RecordWriter {
+            writer: BufWriter::new(stream),
+            transcript: transcript.clone(),
+        };
+        Channel::new(reader, writer)
+    }
+
+    // The bytes sent by the garbler and by the evaluator in a benchmark run
+    // seeded with `seed`, along with the evaluator's output and both parties'
+    // timings
+    fn bench_run(
+        seed: Block,
+        rows: &[(PrimaryKey, usize)],
+    ) -> (
+        Vec<u8>,
+        Vec<u8>,
+        Vec<(PrimaryKey, usize)>,
+        PhaseTimings,
+        PhaseTimings,
+    ) {
+        let mut seed_rng = AesRng::from_seed(seed);
+        let gb_config = BenchConfig::new(seed_rng.gen());
+        let ev_config = BenchConfig::new(seed_rng.gen());
+        let gb_transcript = Arc::new(Mutex::new(Vec::new()));
+        let ev_transcript = Arc::new(Mutex::new(Vec::new()));
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let (gb_timings, (ev_output, ev_timings)) = std::thread::scope(|s| {
+            let handle = s.spawn(|| {
+                let mut channel = record_channel(sender, &gb_transcript);
+                let (_, timings) = bench_garbler(
+                    &mut channel,
+                    &gb_config,
+                    |gb: &mut OpprfPsiGarbler<_, _>| gb.intersect_with_rows(rows),
+                )
+                .unwrap();
+                timings
+            });
+            let mut channel = record_channel(receiver, &ev_transcript);
+            let evaluator = bench_evaluator(
+                &mut channel,
+                &ev_config,
+                |ev: &mut OpprfPsiEvaluator<_, _>| ev.intersect_with_rows(rows),
+            )
+            .unwrap();
+            (handle.join().unwrap(), evaluator)
+        });
+        let gb_transcript = gb_transcript.lock().unwrap().clone();
+        let ev_transcript = ev_transcript.lock().unwrap().clone();
+        (
+            gb_transcript,
+            ev_transcript,
+            ev_output,
+            gb_timings,
+            ev_timings,
+        )
+    }
+
+    #[test]
+    // Test that two benchmark runs with the same seed exchange the same messages
+    // and record the same phases
+    fn test_bench_seeded_runs_are_reproducible() {
+        let mut rng = AesRng::seed_from_u64(DEFAULT_SEED);
+        let primary_keys = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
+        let rows: Vec<(PrimaryKey, usize)> = primary_keys.into_iter().zip(0..).collect();
+        let seed = rng.gen::<Block>();
+
+        let (gb_transcript, ev_transcript, ev_output, gb_timings, ev_timings) =
+            bench_run(seed, &rows);
+        let (gb_transcript_, ev_transcript_, ev_output_, gb_timings_, ev_timings_) =
+            bench_run(seed, &rows);
+
+        assert!(!gb_transcript.is_empty() && !ev_transcript.is_empty());
+        assert!(gb_transcript == gb_transcript_);
+        assert!(ev_transcript == ev_transcript_);
+        assert_eq!(ev_output, ev_output_);
+        assert_eq!(ev_output.len(), rows.len());
+
+        assert_eq!(
+            ev_timings.names(),
+            vec!["setup", "init", "hashing", "opprf", "encoding", "garbling", "reveal"]
+        );
+        assert_eq!(gb_timings.names(), ev_timings.names());
+        for timings in [&gb_timings_, &ev_timings_] {
+            assert_eq!(timings.names(), ev_timings.names());
+        }
+        for timings in [&gb_timings, &ev_timings, &gb_timings_, &ev_timings_] {
+            assert!(timings.total() >= timings.get("garbling").unwrap());
+            assert!(timings.get("decoding").is_none());
+        }
+    }
+}
@@ -0,0 +1,91 @@
+//! Testing the functions of both parties' payloads over the intersection
+#[cfg(test)]
+mod tests {
+    use crate::{
+        errors::Error,
+        psi::circuit_psi::{
+            evaluator::OpprfPsiEvaluator,
+            garbler::OpprfPsiGarbler,
+            tests::{utils::*, *},
+            utils::*,
+            *,
+        },
+    };
+    use rand::Rng;
+    use scuttlebutt::{AesRng, Block};
+
+    type CombineResult = Result<Vec<Option<u128>>, Error>;
+
+    // Computes `op` on the parties' payloads over the intersection and returns
+    // the per-record results revealed to the evaluator
+    fn psty_both_payloads(
+        primary_keys_a: &[PrimaryKey],
+        primary_keys_b: &[PrimaryKey],
+        payloads_a: &[Payload],
+        payloads_b: &[Payload],
+        op: PayloadOp,
+    ) -> (CombineResult, CombineResult) {
+        two_party_test!(
+            |channel, rng| {
+                let mut gb_psi = OpprfPsiGarbler::<_, AesRng>::new(channel, rng.gen::<Block>())?;
+                gb_psi.intersect_with_both_payloads(primary_keys_a, payloads_a, op)
+            },
+            |channel, rng| {
+                let mut ev_psi = OpprfPsiEvaluator::<_, AesRng>::new(channel, rng.gen::<Block>())?;
+                ev_psi.intersect_with_both_payloads(primary_keys_b, payloads_b, op)
+            },
+        )
+    }
+
+    // Checks that the evaluator learns `clear_op` of both parties' payloads for
+    // each of its records in the intersection, and nothing for the others
+    fn check_both_payloads(op: PayloadOp, clear_op: impl Fn(u128, u128) -> u128) {
+        let mut rng = AesRng::new();
+        let primary_keys_a = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
+        let primary_keys_b = enum_ids(SET_SIZE, SET_SIZE as u64 / 2, PRIMARY_KEY_SIZE);
+        let payloads_a_u128 = rand_u128_vec(SET_SIZE, PAYLOAD_MAX, &mut rng);
+        let payloads_b_u128 = rand_u128_vec(SET_SIZE, PAYLOAD_MAX, &mut rng);
+        let payloads_a = int_vec_block512(payloads_a_u128.clone(), PAYLOAD_SIZE);
+        let payloads_b = int_vec_block512(payloads_b_u128.clone(), PAYLOAD_SIZE);
+
+        // The first half of the evaluator's keys are the second half of the garbler's
+        let expected: Vec<Option<u128>> = (0..SET_SIZE)
+            .map(|i| {
+                (i < SET_SIZE / 2)
+                    .then(|| clear_op(payloads_a_u128[SET_SIZE / 2 + i], payloads_b_u128[i]))
+            })
+            .collect();
+
+        let (gb, ev) = psty_both_payloads(
+            &primary_keys_a,
+            &primary_keys_b,
+            &payloads_a,
+            &payloads_b,
+            op,
+        );
+        assert!(
+            gb.unwrap().is_empty(),
+            "The garbler should not learn the results"
+        );
+        assert_eq!(
+            ev.unwrap(),
+            expected,
+            "The results of {:?} over the intersection are wrong",
+            op
+        );
+    }
+
+    #[test]
+    // Test that the evaluator learns the sum of both parties' payloads per record
+    fn test_both_payloads_sum() {
+        check_both_payloads(PayloadOp::Sum, |a, b| a + b);
+    }
+
+    #[test]
+    // Test that the evaluator learns the difference of both parties' payloads per
+    // record, wrapping around at the payload width
+    fn test_both_payloads_difference() {
+        let modulus = 1u128 << (PAYLOAD_SIZE * 8);
+        check_both_payloads(PayloadOp::Difference, |a, b| (b + modulus - a) % modulus);
+    }
+}
@@ -0,0 +1,48 @@
+//! Testing the cardinality of the intersection under both network hints
+#[cfg(test)]
+mod tests {
+    use crate::{
+        errors::Error,
+        psi::circuit_psi::{
+            evaluator::OpprfPsiEvaluator,
+            garbler::OpprfPsiGarbler,
+            tests::{utils::*, *},
+            *,
+        },
+    };
+    use rand::Rng;
+    use scuttlebutt::{AesRng, Block};
+
+    // Computes the cardinality of the intersection with `hint` and returns what
+    // each party learns
+    fn psty_cardinality_with_hint(
+        primary_keys_a: &[PrimaryKey],
+        primary_keys_b: &[PrimaryKey],
+        hint: NetworkHint,
+    ) -> (Result<Option<i64>, Error>, Result<Option<i64>, Error>) {
+        two_party_test!(
+            |channel, rng| {
+                let mut gb_psi = OpprfPsiGarbler::<_, AesRng>::new(channel, rng.gen::<Block>())?;
+                gb_psi.intersect_cardinality(primary_keys_a, hint)
+            },
+            |channel, rng| {
+                let mut ev_psi = OpprfPsiEvaluator::<_, AesRng>::new(channel, rng.gen::<Block>())?;
+                ev_psi.intersect_cardinality(primary_keys_b, hint)
+            },
+        )
+    }
+
+    #[test]
+    // Test that only the evaluator learns the cardinality, and that both hints
+    // give the same one
+    fn test_cardinality_hints() {
+        let primary_keys_a = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
+        let primary_keys_b = enum_ids(SET_SIZE, SET_SIZE as u64 / 4, PRIMARY_KEY_SIZE);
+        let expected = (SET_SIZE - SET_SIZE / 4) as i64;
+        for hint in [NetworkHint::Bandwidth, NetworkHint::Latency] {
+            let (gb, ev) = psty_cardinality_with_hint(&primary_keys_a, &primary_keys_b, hint);
+            assert!(gb.unwrap().is_none(), "The garbler should not learn it");
+            assert_eq!(ev.unwrap(), Some(expected), "{:?}", hint);
+        }
+    }
+}
@@ -1,6 +1,7 @@
 //! Testing Circuit Psi on various circuits
 #[cfg(test)]
 mod tests {
+    use crate::errors::Error;
     use crate::psi::circuit_psi::{
         evaluator::OpprfPsiEvaluator,
         garbler::OpprfPsiGarbler,
@@ -9,8 +10,57 @@ mod tests {
         utils::*,
         *,
     };
-    use scuttlebutt::{AesRng, Block};
-    use std::{collections::HashSet, os::unix::net::UnixStream, thread};
+    use scuttlebutt::{AbstractChannel, AesRng, Block, Channel};
+    use std::{
+        collections::HashSet,
+        io::{BufReader, BufWriter},
+        net::Shutdown,
+        os::unix::net::UnixStream,
+        sync::mpsc,
+        thread,
+        time::Duration,
+    };
+
+    type TestChannel = Channel<BufReader<UnixStream>, BufWriter<UnixStream>>;
+
+    // Runs two phases with `sync` called at the boundary, and returns whether both
+    // parties finished within a timeout. The first party ends the first phase with
+    // a message which it does not flush, and then waits for the second party's
+    // message of the second phase, which is only sent once the first message was
+    // received.
+    fn phase_boundary_completes(sync: fn(&mut TestChannel) -> Result<(), Error>) -> bool {
+        let first_phase = move |channel: &mut TestChannel| -> Result<bool, Error> {
+            channel.write_u64(1)?;
+            sync(channel)?;
+            Ok(channel.read_u64()? == 2)
+        };
+        let second_phase = move |channel: &mut TestChannel| -> Result<bool, Error> {
+            let received = channel.read_u64()?;
+            sync(channel)?;
+            channel.write_u64(2)?;
+            channel.flush()?;
+            Ok(received == 1)
+        };
+
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        // Kept to unblock the parties if they deadlock
+        let streams = [sender.try_clone().unwrap(), receiver.try_clone().unwrap()];
+        let (done_tx, done_rx) = mpsc::channel();
+        let first_done = done_tx.clone();
+        thread::spawn(move || {
+            let mut channel = setup_channel(sender);
+            let _ = first_done.send(matches!(first_phase(&mut channel), Ok(true)));
+        });
+        thread::spawn(move || {
+            let mut channel = setup_channel(receiver);
+            let _ = done_tx.send(matches!(second_phase(&mut channel), Ok(true)));
+        });
+        let completed = (0..2).all(|_| done_rx.recv_timeout(Duration::from_secs(1)) == Ok(true));
+        for stream in streams {
+            let _ = stream.shutdown(Shutdown::Both);
+        }
+        completed
+    }
 
     // Computes the cardinality of the intersection in the clear
     pub fn cardinality_in_clear(set_a: &[Vec<u8>], set_b: &[Vec<u8>]) -> usize {
@@ -198,6 +248,76 @@ mod tests {
         );
     }
     #[test]
+    // Test that the barrier synchronizes both parties and that the circuit
+    // psi still succeeds when the channels use tiny buffers.
+    fn test_psty_circuit_cardinality_barrier_small_buffers() {
+        let small_channel = |stream: UnixStream| {
+            let reader = BufReader::with_capacity(1, stream.try_clone().unwrap());
+            let writer = BufWriter::with_capacity(1, stream);
+            Channel::new(reader, writer)
+        };
+        let mut rng = AesRng::new();
+        let set_a = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
+        let set_b = enum_ids(SET_SIZE, 1, PRIMARY_KEY_SIZE);
+        let (seed_sx, seed_rx): (u128, u128) = (rng.gen(), rng.gen());
+
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let cardinality = thread::scope(|s| {
+            let _ = s.spawn(|| {
+                let mut channel = small_channel(sender);
+                let mut gb_psi: _ =
+                    OpprfPsiGarbler::<_, AesRng>::new(&mut channel, Block::from(seed_sx)).unwrap();
+                gb_psi.barrier().unwrap();
+
+                let intersection_results = gb_psi.intersect(&set_a).unwrap();
+                gb_psi.barrier().unwrap();
+                let res = fancy_cardinality(
+                    &mut gb_psi.gb,
+                    &intersection_results.intersection.existence_bit_vector,
+                )
+                .unwrap();
+                gb_psi.gb.outputs(res.wires()).unwrap();
+            });
+            let mut channel = small_channel(receiver);
+            let mut ev_psi =
+                OpprfPsiEvaluator::<_, AesRng>::new(&mut channel, Block::from(seed_rx)).unwrap();
+            ev_psi.barrier().unwrap();
+
+            let intersection_results = ev_psi.intersect(&set_b).unwrap();
+            ev_psi.barrier().unwrap();
+            let res = fancy_cardinality(
+                &mut ev_psi.ev,
+                &intersection_results.intersection.existence_bit_vector,
+            )
+            .unwrap();
+            let res_out = ev_psi
+                .ev
+                .outputs(&res.wires().to_vec())
+                .unwrap()
+                .expect("evaluator should produce outputs");
+            utils::binary_to_u128(res_out) as usize
+        });
+        assert!(
+            cardinality == (SET_SIZE - 1),
+            "The PSI Cardinality with small buffers is wrong! The result was {} and should be {}",
+            cardinality,
+            SET_SIZE - 1
+        );
+    }
+    #[test]
+    // Test that the barrier flushes the data of the previous phase, where the
+    // parties deadlock without it
+    fn test_psty_barrier_flushes_previous_phase() {
+        assert!(
+            !phase_boundary_completes(|_| Ok(())),
+            "The parties did not deadlock without the barrier"
+        );
+        assert!(
+            phase_boundary_completes(utils::barrier),
+            "The parties deadlocked with the barrier"
+        );
+    }
+    #[test]
     // Test the fancy payload sum circuit, where if an intersection happens
     // then the associated payloads are aggregated.
     // This first test checks that the circuit works when we intersect a set of primary_keys with itself
@@ -258,4 +378,49 @@ mod tests {
             sum_in_clear
         );
     }
+    #[test]
+    // Test that both parties reject payloads which do not match their primary
+    // keys in length before running the protocol
+    fn test_psty_circuit_payload_length_mismatch() {
+        let mut rng = AesRng::new();
+        let primary_keys = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
+        let payloads = int_vec_block512(
+            rand_u128_vec(SET_SIZE - 1, PAYLOAD_MAX, &mut rng),
+            PAYLOAD_SIZE,
+        );
+        let (seed_sx, seed_rx): (u128, u128) = (rng.gen(), rng.gen());
+
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let (result_sender, result_receiver) = thread::scope(|s| {
+            let result_sender = s.spawn(|| {
+                let mut channel = setup_channel(sender);
+                let mut gb_psi =
+                    OpprfPsiGarbler::<_, AesRng>::new(&mut channel, Block::from(seed_sx)).unwrap();
+                gb_psi
+                    .intersect_with_payloads(&primary_keys, Some(&payloads))
+                    .err()
+            });
+            let mut channel = setup_channel(receiver);
+            let mut ev_psi =
+                OpprfPsiEvaluator::<_, AesRng>::new(&mut channel, Block::from(seed_rx)).unwrap();
+            let result_receiver = ev_psi
+                .intersect_with_payloads(&primary_keys, Some(&payloads))
+                .err();
+            (result_sender.join().unwrap(), result_receiver)
+        });
+
+        for result in [result_sender, result_receiver] {
+            assert!(
+                matches!(
+                    result,
+                    Some(Error::PayloadSetNotComplete {
+                        npayloads,
+                        nprimarykeys,
+                    }) if npayloads == SET_SIZE - 1 && nprimarykeys == SET_SIZE
+                ),
+                "Mismatched payloads should be rejected, got {:?}",
+                result
+            );
+        }
+    }
 }
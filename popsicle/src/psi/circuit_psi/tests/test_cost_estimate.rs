@@ -0,0 +1,52 @@
+//! Testing the estimation of the resources used by Circuit Psi
+#[cfg(test)]
+mod tests {
+    use crate::psi::circuit_psi::*;
+
+    // Returns the ratio between the estimates of `field` for `2 * n` and `n`
+    fn doubling_ratio(n: usize, with_payloads: bool, field: fn(&CostEstimate) -> usize) -> f64 {
+        let security = SecurityParams::default();
+        let small = CostEstimate::new(n, n, with_payloads, &security);
+        let large = CostEstimate::new(2 * n, 2 * n, with_payloads, &security);
+        field(&large) as f64 / field(&small) as f64
+    }
+
+    #[test]
+    // Test that doubling the set sizes roughly doubles the estimates
+    fn test_cost_estimate_scales_linearly() {
+        for with_payloads in [false, true] {
+            for (name, field) in [
+                ("AND gates", (|c| c.and_gates) as fn(&CostEstimate) -> usize),
+                ("communication", |c| c.communication_bytes),
+                ("memory", |c| c.peak_memory_bytes),
+            ] {
+                let ratio = doubling_ratio(1 << 12, with_payloads, field);
+                assert!(
+                    (1.9..=2.1).contains(&ratio),
+                    "Doubling the set sizes multiplied the {} estimate by {}",
+                    name,
+                    ratio
+                );
+            }
+        }
+    }
+
+    #[test]
+    // Test that payloads and a higher statistical security parameter make
+    // the estimates grow
+    fn test_cost_estimate_grows_with_parameters() {
+        let security = SecurityParams::default();
+        let base = CostEstimate::new(1 << 12, 1 << 12, false, &security);
+        let payloads = CostEstimate::new(1 << 12, 1 << 12, true, &security);
+        assert!(payloads.communication_bytes > base.communication_bytes);
+        assert!(payloads.peak_memory_bytes > base.peak_memory_bytes);
+
+        let security = SecurityParams {
+            stat_sec: 80,
+            ..security
+        };
+        let stat_sec = security.estimate_cost(1 << 12, 1 << 12, false);
+        assert!(stat_sec.and_gates > base.and_gates);
+        assert!(stat_sec.communication_bytes > base.communication_bytes);
+    }
+}
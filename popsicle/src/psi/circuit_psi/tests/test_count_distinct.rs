@@ -0,0 +1,103 @@
+//! Testing the count of distinct payload values over the intersection
+#[cfg(test)]
+mod tests {
+    use crate::{
+        errors::Error,
+        psi::circuit_psi::{
+            evaluator::OpprfPsiEvaluator,
+            garbler::OpprfPsiGarbler,
+            tests::{utils::*, *},
+            utils::*,
+            *,
+        },
+    };
+    use rand::Rng;
+    use scuttlebutt::{AesRng, Block};
+    use std::collections::HashSet;
+
+    fn schema() -> PayloadSchema {
+        PayloadSchema::new(vec![ColumnType::U32, ColumnType::U32]).unwrap()
+    }
+
+    // Counts the distinct values of the garbler's payload `column` over the
+    // intersection, and returns the count opened by the evaluator
+    fn psty_count_distinct(
+        primary_keys_a: &[PrimaryKey],
+        primary_keys_b: &[PrimaryKey],
+        payloads_a: &[Payload],
+        payloads_b: &[Payload],
+        column: usize,
+    ) -> (Result<Option<u128>, Error>, Result<Option<u128>, Error>) {
+        two_party_test!(
+            |channel, rng| {
+                let mut gb_psi = OpprfPsiGarbler::<_, AesRng>::new(channel, rng.gen::<Block>())?;
+                gb_psi.intersect_count_distinct_column(
+                    primary_keys_a,
+                    payloads_a,
+                    &schema(),
+                    column,
+                )
+            },
+            |channel, rng| {
+                let mut ev_psi = OpprfPsiEvaluator::<_, AesRng>::new(channel, rng.gen::<Block>())?;
+                ev_psi.intersect_count_distinct_column(
+                    primary_keys_b,
+                    payloads_b,
+                    &schema(),
+                    column,
+                )
+            },
+        )
+    }
+
+    #[test]
+    // Test that the count of distinct values of a column over the intersection
+    // matches the count in the clear, when values are repeated
+    fn test_count_distinct_column() {
+        let mut rng = AesRng::new();
+        let schema = schema();
+        let primary_keys_a = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
+        let primary_keys_b = enum_ids(SET_SIZE, SET_SIZE as u64 / 2, PRIMARY_KEY_SIZE);
+        // Few distinct values in the first column, so that many are repeated
+        let rows: Vec<(u32, u32)> = (0..SET_SIZE)
+            .map(|_| (rng.gen::<u32>() % 20, rng.gen()))
+            .collect();
+        let payloads_a: Vec<Payload> = rows
+            .iter()
+            .map(|(x, y)| schema.encode_row(&format!("{},{}", x, y)).unwrap())
+            .collect();
+        let payloads_b =
+            int_vec_block512(rand_u128_vec(SET_SIZE, PAYLOAD_MAX, &mut rng), PAYLOAD_SIZE);
+
+        // The second half of the garbler's keys are in the intersection
+        let in_clear = rows[SET_SIZE / 2..]
+            .iter()
+            .map(|(x, _)| *x)
+            .collect::<HashSet<u32>>()
+            .len();
+        let (gb, ev) = psty_count_distinct(
+            &primary_keys_a,
+            &primary_keys_b,
+            &payloads_a,
+            &payloads_b,
+            0,
+        );
+        assert_eq!(gb.unwrap(), None);
+        assert_eq!(
+            ev.unwrap(),
+            Some(in_clear as u128),
+            "The count of distinct values over the intersection is wrong"
+        );
+    }
+
+    #[test]
+    // Test that both parties fail before running the protocol on a column
+    // missing from the schema
+    fn test_count_distinct_unknown_column() {
+        let primary_keys = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
+        let payloads = int_vec_block512(vec![0; SET_SIZE], PAYLOAD_SIZE);
+        let (gb, ev) = psty_count_distinct(&primary_keys, &primary_keys, &payloads, &payloads, 2);
+        assert!(matches!(gb, Err(Error::PayloadEncodingError(_))));
+        assert!(matches!(ev, Err(Error::PayloadEncodingError(_))));
+    }
+}
@@ -0,0 +1,183 @@
+//! Testing the cuckoo hashing strategies of the Base Psi
+#[cfg(test)]
+mod tests {
+    use crate::errors::Error;
+    use crate::psi::circuit_psi::{
+        base_psi::{CuckooParams, CuckooStrategy},
+        evaluator::OpprfPsiEvaluator,
+        garbler::OpprfPsiGarbler,
+        tests::{utils::*, *},
+        *,
+    };
+    use fancy_garbling::Fancy;
+    use rand::Rng;
+    use scuttlebutt::{AesRng, Block};
+
+    // Computes the intersection under `strategy`, with the evaluator's cuckoo
+    // table set up with `cuckoo_params`, returning the indices of the evaluator's
+    // primary keys in the intersection along with the statistics of the parties'
+    // hashing tables
+    fn psty_with_strategy(
+        set_a: &[PrimaryKey],
+        set_b: &[PrimaryKey],
+        strategy: CuckooStrategy,
+        cuckoo_params: CuckooParams,
+    ) -> (Vec<usize>, PsiDiagnostics, PsiDiagnostics) {
+        let (gb, (indices, ev)) = two_party_test!(
+            |channel, rng| {
+                let mut gb_psi =
+                    OpprfPsiGarbler::<_, AesRng>::new(channel, rng.gen::<Block>()).unwrap();
+                gb_psi.cuckoo_strategy = strategy;
+                gb_psi.diagnostics = true;
+                gb_psi.verify = true;
+                let intersection = gb_psi.intersect(set_a).unwrap();
+                gb_psi
+                    .gb
+                    .outputs(&intersection.intersection.existence_bit_vector)
+                    .unwrap();
+                intersection.diagnostics.unwrap()
+            },
+            |channel, rng| {
+                let mut ev_psi =
+                    OpprfPsiEvaluator::<_, AesRng>::new(channel, rng.gen::<Block>()).unwrap();
+                ev_psi.cuckoo_strategy = strategy;
+                ev_psi.cuckoo_params = cuckoo_params;
+                ev_psi.diagnostics = true;
+                ev_psi.verify = true;
+                let intersection = ev_psi.intersect(set_b).unwrap();
+                let opened_bits = ev_psi
+                    .ev
+                    .outputs(&intersection.intersection.existence_bit_vector)
+                    .unwrap()
+                    .unwrap();
+                let mut indices: Vec<usize> = opened_bits
+                    .into_iter()
+                    .zip(intersection.input_indices)
+                    .filter_map(|(bit, index)| if bit == 1 { index } else { None })
+                    .collect();
+                indices.sort_unstable();
+                (indices, intersection.diagnostics.unwrap())
+            },
+        );
+        (indices, gb, ev)
+    }
+
+    #[test]
+    // Test that both strategies compute the same, correct intersection, with the
+    // table shapes of their number of hash functions
+    fn test_cuckoo_strategies_agree() {
+        let set_a = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
+        let set_b = enum_ids(SET_SIZE, SET_SIZE as u64 / 2, PRIMARY_KEY_SIZE);
+        let expected: Vec<usize> = (0..SET_SIZE / 2).collect();
+
+        let mut nbins = vec![];
+        for strategy in [CuckooStrategy::TwoChoice, CuckooStrategy::ThreeChoice] {
+            let (indices, sender, receiver) =
+                psty_with_strategy(&set_a, &set_b, strategy, CuckooParams::default());
+            assert_eq!(indices, expected, "{:?} missed the intersection", strategy);
+
+            // The sender places each of its primary keys in every candidate bin,
+            // plus at most one dummy entry
+            let nhashes = strategy.nhashes();
+            assert!(sender.nentries >= nhashes * sender.nitems);
+            assert!(sender.nentries <= (nhashes + 1) * sender.nitems);
+            assert_eq!(sender.nbins, receiver.nbins);
+            // Without a stash, the base PSI rehashes until every one of the
+            // receiver's primary keys is placed in a bin
+            assert_eq!(receiver.stash_size, 0);
+            assert_eq!(receiver.nentries, SET_SIZE);
+            nbins.push(receiver.nbins);
+        }
+        // Two choices need a larger table than three for the same set
+        assert!(nbins[0] > nbins[1]);
+    }
+
+    #[test]
+    // Test that the primary keys the receiver stashes are still found in the
+    // intersection
+    fn test_cuckoo_stash() {
+        let set_a = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
+        let set_b = enum_ids(SET_SIZE, SET_SIZE as u64 / 2, PRIMARY_KEY_SIZE);
+        let expected: Vec<usize> = (0..SET_SIZE / 2).collect();
+        // With few evictions, the table stashes a fair share of the primary keys
+        let cuckoo_params = CuckooParams {
+            max_evictions: 4,
+            stash_size: 48,
+        };
+
+        let (indices, sender, receiver) =
+            psty_with_strategy(&set_a, &set_b, CuckooStrategy::default(), cuckoo_params);
+        assert_eq!(indices, expected);
+        assert!(receiver.stash_size > 0);
+        assert_eq!(receiver.nentries + receiver.stash_size, SET_SIZE);
+        assert_eq!(sender.nbins, receiver.nbins);
+    }
+
+    // Computes the intersection with an evaluator holding ten copies of the same
+    // primary key, which collide under every hashing key
+    fn psty_with_duplicates(cuckoo_params: CuckooParams) -> (Result<(), Error>, Result<(), Error>) {
+        let set_a = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
+        let mut set_b = enum_ids(SET_SIZE, SET_SIZE as u64 / 2, PRIMARY_KEY_SIZE);
+        let duplicate = set_b[0].clone();
+        set_b[1..10].fill(duplicate);
+        two_party_test!(
+            |channel, rng| {
+                let mut gb_psi =
+                    OpprfPsiGarbler::<_, AesRng>::new(channel, rng.gen::<Block>()).unwrap();
+                gb_psi.intersect(&set_a).map(|_| ())
+            },
+            |channel, rng| {
+                let mut ev_psi =
+                    OpprfPsiEvaluator::<_, AesRng>::new(channel, rng.gen::<Block>()).unwrap();
+                ev_psi.cuckoo_params = cuckoo_params;
+                ev_psi.intersect(&set_b).map(|_| ())
+            },
+        )
+    }
+
+    #[test]
+    // Test that the protocol fails rather than rehashing forever when duplicate
+    // primary keys overflow the stash
+    fn test_cuckoo_stash_overflow() {
+        let (gb, ev) = psty_with_duplicates(CuckooParams {
+            max_evictions: 4,
+            stash_size: 4,
+        });
+        assert!(matches!(ev, Err(Error::StashOverflow)));
+        // The garbler sees the evaluator hang up before sending its table
+        assert!(gb.is_err());
+    }
+
+    #[test]
+    // Test that the protocol fails after a bounded number of rehashes when
+    // duplicate primary keys cannot be placed without a stash
+    fn test_cuckoo_rehashes_bounded() {
+        let (gb, ev) = psty_with_duplicates(CuckooParams::default());
+        assert!(matches!(ev, Err(Error::CuckooHashFull)));
+        assert!(gb.is_err());
+    }
+
+    #[test]
+    // Test that the parties detect that they use different strategies when
+    // verifying their hashing parameters
+    fn test_cuckoo_strategy_mismatch() {
+        let set = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
+        let (gb, ev) = two_party_test!(
+            |channel, rng| {
+                let mut gb_psi =
+                    OpprfPsiGarbler::<_, AesRng>::new(channel, rng.gen::<Block>()).unwrap();
+                gb_psi.cuckoo_strategy = CuckooStrategy::TwoChoice;
+                gb_psi.verify = true;
+                gb_psi.intersect(&set).map(|_| ())
+            },
+            |channel, rng| {
+                let mut ev_psi =
+                    OpprfPsiEvaluator::<_, AesRng>::new(channel, rng.gen::<Block>()).unwrap();
+                ev_psi.verify = true;
+                ev_psi.intersect(&set).map(|_| ())
+            },
+        );
+        assert!(matches!(gb, Err(Error::ParameterInconsistency)));
+        assert!(matches!(ev, Err(Error::ParameterInconsistency)));
+    }
+}
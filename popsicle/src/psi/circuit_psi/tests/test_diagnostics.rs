@@ -0,0 +1,84 @@
+//! Testing the statistics of the parties' hashing tables
+#[cfg(test)]
+mod tests {
+    use crate::psi::circuit_psi::{
+        base_psi::CuckooStrategy,
+        evaluator::OpprfPsiEvaluator,
+        garbler::OpprfPsiGarbler,
+        tests::{utils::*, *},
+        *,
+    };
+    use rand::Rng;
+    use scuttlebutt::{AesRng, Block};
+
+    // Computes the intersection and returns the statistics of the parties'
+    // hashing tables
+    fn psty_diagnostics(
+        set_a: &[PrimaryKey],
+        set_b: &[PrimaryKey],
+        diagnostics: bool,
+    ) -> (Option<PsiDiagnostics>, Option<PsiDiagnostics>) {
+        two_party_test!(
+            |channel, rng| {
+                let mut gb_psi =
+                    OpprfPsiGarbler::<_, AesRng>::new(channel, rng.gen::<Block>()).unwrap();
+                gb_psi.diagnostics = diagnostics;
+                gb_psi.intersect(set_a).unwrap().diagnostics
+            },
+            |channel, rng| {
+                let mut ev_psi =
+                    OpprfPsiEvaluator::<_, AesRng>::new(channel, rng.gen::<Block>()).unwrap();
+                ev_psi.diagnostics = diagnostics;
+                ev_psi.intersect(set_b).unwrap().diagnostics
+            },
+        )
+    }
+
+    // Checks the statistics which hold for both parties' tables
+    fn check_loads(diagnostics: &PsiDiagnostics) {
+        assert!(diagnostics.nbins > 0);
+        assert!(
+            (diagnostics.avg_bin_load - diagnostics.nentries as f64 / diagnostics.nbins as f64)
+                .abs()
+                < 1e-9,
+            "The average bin load is inconsistent with the number of entries"
+        );
+        assert!(diagnostics.max_bin_load as f64 >= diagnostics.avg_bin_load);
+        assert!(diagnostics.max_bin_load <= diagnostics.nentries);
+    }
+
+    #[test]
+    // Test that the diagnostics account for every primary key of both parties
+    fn test_diagnostics_consistent() {
+        let set_a = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
+        let set_b = enum_ids(SET_SIZE, SET_SIZE as u64 / 2, PRIMARY_KEY_SIZE);
+        let (gb, ev) = psty_diagnostics(&set_a, &set_b, true);
+        let (sender, receiver) = (gb.unwrap(), ev.unwrap());
+        check_loads(&sender);
+        check_loads(&receiver);
+        assert_eq!(sender.nbins, receiver.nbins);
+
+        // Cuckoo hashing places every primary key of the receiver in a single bin
+        // or in the stash
+        assert_eq!(receiver.nitems, SET_SIZE);
+        assert_eq!(receiver.nentries + receiver.stash_size, receiver.nitems);
+        assert!(receiver.max_bin_load <= 1);
+
+        // Simple hashing places every primary key of the sender in a bin once per
+        // hash function, plus at most one dummy entry
+        let nhashes = CuckooStrategy::default().nhashes();
+        assert_eq!(sender.nitems, SET_SIZE);
+        assert!(sender.nentries >= nhashes * sender.nitems);
+        assert!(sender.nentries <= (nhashes + 1) * sender.nitems);
+        assert_eq!(sender.stash_size, 0);
+        assert_eq!(sender.nrehashes, 0);
+    }
+
+    #[test]
+    // Test that the diagnostics are only computed when requested
+    fn test_diagnostics_disabled() {
+        let set_a = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
+        let set_b = enum_ids(SET_SIZE, SET_SIZE as u64 / 2, PRIMARY_KEY_SIZE);
+        assert_eq!(psty_diagnostics(&set_a, &set_b, false), (None, None));
+    }
+}
@@ -0,0 +1,114 @@
+//! Testing the differentially private cardinality of the intersection
+#[cfg(test)]
+mod tests {
+    use crate::psi::circuit_psi::{
+        evaluator::OpprfPsiEvaluator, garbler::OpprfPsiGarbler, tests::utils::*, utils::*, *,
+    };
+    use rand::Rng;
+    use scuttlebutt::{AesRng, Block};
+    use std::{os::unix::net::UnixStream, thread};
+
+    const EPSILON: f64 = 1.0;
+    const NSAMPLES: usize = 100_000;
+    const NRUNS: usize = 16;
+    const DP_SET_SIZE: usize = 1 << 4;
+
+    // The variance of the sum of both parties' discrete Laplace noise shares
+    fn noise_variance(epsilon: f64) -> f64 {
+        let q = (-epsilon).exp();
+        2.0 * (2.0 * q / ((1.0 - q) * (1.0 - q)))
+    }
+
+    // Computes the noisy cardinality of the intersection and reveals it to the evaluator
+    fn psty_noisy_cardinality(
+        set_a: &[Vec<u8>],
+        set_b: &[Vec<u8>],
+        epsilon: f64,
+        seed_sx: u128,
+        seed_rx: u128,
+    ) -> Result<i64, Error> {
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        thread::scope(|s| {
+            let _ = s.spawn(|| {
+                let mut channel = setup_channel(sender);
+                let mut gb_psi: _ =
+                    OpprfPsiGarbler::<_, AesRng>::new(&mut channel, Block::from(seed_sx)).unwrap();
+                gb_psi.dp_cardinality = Some(epsilon);
+                gb_psi
+                    .intersect_cardinality(set_a, NetworkHint::Bandwidth)
+                    .unwrap();
+            });
+            let mut channel = setup_channel(receiver);
+
+            let mut ev_psi =
+                OpprfPsiEvaluator::<_, AesRng>::new(&mut channel, Block::from(seed_rx)).unwrap();
+            ev_psi.dp_cardinality = Some(epsilon);
+            let cardinality = ev_psi.intersect_cardinality(set_b, NetworkHint::Bandwidth)?;
+            Ok(cardinality.expect("evaluator should learn the cardinality"))
+        })
+    }
+
+    #[test]
+    // Test that the sum of both parties' noise shares is centered on 0
+    // with the expected variance
+    fn test_dp_noise_distribution() {
+        let mut rng = AesRng::new();
+        let samples: Vec<f64> = (0..NSAMPLES)
+            .map(|_| {
+                (sample_discrete_laplace(EPSILON, &mut rng).unwrap()
+                    + sample_discrete_laplace(EPSILON, &mut rng).unwrap()) as f64
+            })
+            .collect();
+        let mean = samples.iter().sum::<f64>() / NSAMPLES as f64;
+        let variance =
+            samples.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / NSAMPLES as f64;
+        let expected_variance = noise_variance(EPSILON);
+        assert!(
+            mean.abs() < 5.0 * (expected_variance / NSAMPLES as f64).sqrt(),
+            "The DP noise is not centered on 0, its mean was {}",
+            mean
+        );
+        assert!(
+            (variance - expected_variance).abs() < 0.05 * expected_variance,
+            "The DP noise variance was {} and should be {}",
+            variance,
+            expected_variance
+        );
+    }
+    #[test]
+    // Test that invalid privacy parameters are rejected
+    fn test_dp_noise_invalid_epsilon() {
+        let mut rng = AesRng::new();
+        // The smallest positive float cannot be sampled from exactly
+        for epsilon in [0.0, -1.0, f64::NAN, f64::INFINITY, f64::MIN_POSITIVE] {
+            assert!(
+                sample_discrete_laplace(epsilon, &mut rng).is_err(),
+                "The DP noise sampler accepted epsilon = {}",
+                epsilon
+            );
+        }
+    }
+    #[test]
+    // Test that the revealed noisy cardinality is centered on the true
+    // cardinality over many runs
+    fn test_psty_circuit_noisy_cardinality_centered() {
+        let mut rng = AesRng::new();
+        let set_a = enum_ids(DP_SET_SIZE, 0, PRIMARY_KEY_SIZE);
+        let set_b = enum_ids(DP_SET_SIZE, 1, PRIMARY_KEY_SIZE);
+        let cardinality = (DP_SET_SIZE - 1) as f64;
+
+        let results: Vec<f64> = (0..NRUNS)
+            .map(|_| {
+                psty_noisy_cardinality(&set_a, &set_b, EPSILON, rng.gen(), rng.gen()).unwrap()
+                    as f64
+            })
+            .collect();
+        let mean = results.iter().sum::<f64>() / NRUNS as f64;
+        assert!(
+            (mean - cardinality).abs() < 5.0 * (noise_variance(EPSILON) / NRUNS as f64).sqrt(),
+            "The noisy PSI Cardinality is not centered on the true cardinality! The mean was {} and should be close to {}",
+            mean,
+            cardinality
+        );
+    }
+}
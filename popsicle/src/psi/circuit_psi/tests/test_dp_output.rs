@@ -0,0 +1,127 @@
+//! Testing the randomized response output of the intersection bit vector
+#[cfg(test)]
+mod tests {
+    use crate::psi::circuit_psi::{
+        evaluator::OpprfPsiEvaluator,
+        garbler::OpprfPsiGarbler,
+        tests::{utils::*, *},
+        utils::*,
+        *,
+    };
+    use rand::Rng;
+    use scuttlebutt::{AesRng, Block};
+
+    const EPSILON: f64 = 1.0;
+    const NRUNS: usize = 8;
+    const DP_SET_SIZE: usize = 1 << 6;
+
+    // The probability with which each bit of the intersection bit vector is flipped
+    fn flip_probability(epsilon: f64) -> f64 {
+        1.0 / (1.0 + epsilon.exp())
+    }
+
+    // Computes the intersection in randomized response and reveals the bit vector
+    // to the evaluator, along with the evaluator's input index in each bin
+    fn psty_dp_output(
+        set_a: &[PrimaryKey],
+        set_b: &[PrimaryKey],
+        epsilon: f64,
+        seed: Block,
+    ) -> Vec<(u16, Option<usize>)> {
+        let (_, ev) = two_party_test!(
+            seed,
+            |channel, rng| {
+                let mut gb_psi =
+                    OpprfPsiGarbler::<_, AesRng>::new(channel, rng.gen::<Block>()).unwrap();
+                gb_psi.dp_output = Some(epsilon);
+                let intersection_results = gb_psi.intersect(set_a).unwrap();
+                gb_psi
+                    .gb
+                    .outputs(&intersection_results.intersection.existence_bit_vector)
+                    .unwrap();
+            },
+            |channel, rng| {
+                let mut ev_psi =
+                    OpprfPsiEvaluator::<_, AesRng>::new(channel, rng.gen::<Block>()).unwrap();
+                ev_psi.dp_output = Some(epsilon);
+                let intersection_results = ev_psi.intersect(set_b).unwrap();
+                let bits = ev_psi
+                    .ev
+                    .outputs(&intersection_results.intersection.existence_bit_vector)
+                    .unwrap()
+                    .expect("evaluator should produce outputs");
+                bits.into_iter()
+                    .zip(intersection_results.input_indices)
+                    .collect::<Vec<_>>()
+            },
+        );
+        ev
+    }
+
+    #[test]
+    // Test that over many runs the bits are flipped with the configured probability,
+    // and that the membership of the evaluator's keys is recoverable in aggregate
+    fn test_psty_circuit_dp_output() {
+        let mut rng = AesRng::new();
+        let set_a = enum_ids(DP_SET_SIZE, 0, PRIMARY_KEY_SIZE);
+        let set_b = enum_ids(DP_SET_SIZE, DP_SET_SIZE as u64 / 2, PRIMARY_KEY_SIZE);
+        // The first half of the evaluator's keys are in the intersection
+        let is_member = |index: Option<usize>| index.map_or(false, |i| i < DP_SET_SIZE / 2);
+
+        let (mut nbits, mut nflips) = (0, 0);
+        let mut nseen = vec![0usize; DP_SET_SIZE];
+        for _ in 0..NRUNS {
+            for (bit, index) in psty_dp_output(&set_a, &set_b, EPSILON, rng.gen()) {
+                nbits += 1;
+                if (bit == 1) != is_member(index) {
+                    nflips += 1;
+                }
+                if let (1, Some(i)) = (bit, index) {
+                    nseen[i] += 1;
+                }
+            }
+        }
+
+        let p = flip_probability(EPSILON);
+        let rate = nflips as f64 / nbits as f64;
+        assert!(
+            (rate - p).abs() < 5.0 * (p * (1.0 - p) / nbits as f64).sqrt(),
+            "The bits were flipped at a rate of {} and should be flipped with probability {}",
+            rate,
+            p
+        );
+        // Debias the fraction of runs in which each half of the keys was seen
+        let estimate = |seen: &[usize]| {
+            let observed = seen.iter().sum::<usize>() as f64 / (seen.len() * NRUNS) as f64;
+            (observed - p) / (1.0 - 2.0 * p)
+        };
+        let (members, others) = nseen.split_at(DP_SET_SIZE / 2);
+        assert!(
+            estimate(members) > 0.75,
+            "The estimated membership of the keys in the intersection was {}",
+            estimate(members)
+        );
+        assert!(
+            estimate(others) < 0.25,
+            "The estimated membership of the keys outside of the intersection was {}",
+            estimate(others)
+        );
+    }
+
+    #[test]
+    // Test that invalid privacy parameters are rejected
+    fn test_dp_output_invalid_epsilon() {
+        for epsilon in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(
+                dp_flip_threshold(epsilon).is_err(),
+                "The randomized response accepted epsilon = {}",
+                epsilon
+            );
+        }
+        assert_eq!(
+            dp_flip_threshold(f64::MAX.ln()).unwrap(),
+            0,
+            "A large epsilon should never flip a bit"
+        );
+    }
+}
@@ -0,0 +1,75 @@
+//! Testing Circuit Psi on empty sets
+#[cfg(test)]
+mod tests {
+    use crate::psi::circuit_psi::{
+        evaluator::OpprfPsiEvaluator,
+        garbler::OpprfPsiGarbler,
+        tests::{utils::*, *},
+        utils::*,
+        *,
+    };
+    use rand::Rng;
+    use scuttlebutt::{AesRng, Block};
+    use std::{os::unix::net::UnixStream, thread};
+
+    // Runs the Circuit Psi with payloads on sets of the given sizes, and
+    // returns the evaluator's opened intersection bit vector once both parties
+    // have completed
+    fn psty_bits(size_a: usize, size_b: usize) -> Vec<u16> {
+        let mut rng = AesRng::new();
+        let primary_keys_a = enum_ids(size_a, 0, PRIMARY_KEY_SIZE);
+        let primary_keys_b = enum_ids(size_b, 0, PRIMARY_KEY_SIZE);
+        let payloads_a =
+            int_vec_block512(rand_u128_vec(size_a, PAYLOAD_MAX, &mut rng), PAYLOAD_SIZE);
+        let payloads_b =
+            int_vec_block512(rand_u128_vec(size_b, PAYLOAD_MAX, &mut rng), PAYLOAD_SIZE);
+        let (seed_sx, seed_rx): (u128, u128) = (rng.gen(), rng.gen());
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        thread::scope(|s| {
+            s.spawn(|| {
+                let mut channel = setup_channel(sender);
+                let mut gb_psi =
+                    OpprfPsiGarbler::<_, AesRng>::new(&mut channel, Block::from(seed_sx)).unwrap();
+                let intersection_results = gb_psi
+                    .intersect_with_payloads(&primary_keys_a, Some(&payloads_a))
+                    .unwrap();
+                gb_psi
+                    .gb
+                    .outputs(&intersection_results.intersection.existence_bit_vector)
+                    .unwrap();
+            });
+            let mut channel = setup_channel(receiver);
+            let mut ev_psi =
+                OpprfPsiEvaluator::<_, AesRng>::new(&mut channel, Block::from(seed_rx)).unwrap();
+            let intersection_results = ev_psi
+                .intersect_with_payloads(&primary_keys_b, Some(&payloads_b))
+                .unwrap();
+            ev_psi
+                .ev
+                .outputs(&intersection_results.intersection.existence_bit_vector)
+                .unwrap()
+                .expect("evaluator should produce outputs")
+        })
+    }
+
+    #[test]
+    // Test that the intersection of two empty sets is empty
+    fn test_both_empty() {
+        assert!(psty_bits(0, 0).is_empty());
+    }
+
+    #[test]
+    // Test that the intersection with an empty garbler's set has no bit set
+    fn test_sender_empty() {
+        let bits = psty_bits(0, SET_SIZE);
+        assert!(!bits.is_empty());
+        assert!(bits.iter().all(|bit| *bit == 0));
+    }
+
+    #[test]
+    // Test that the intersection with an empty evaluator's set is empty, since
+    // the evaluator's cuckoo table has no bins
+    fn test_receiver_empty() {
+        assert!(psty_bits(SET_SIZE, 0).is_empty());
+    }
+}
@@ -0,0 +1,148 @@
+//! Testing fuzzy Circuit Psi, matching keys within a Hamming distance
+#[cfg(test)]
+mod tests {
+    use crate::psi::circuit_psi::{
+        evaluator::OpprfPsiEvaluator, garbler::OpprfPsiGarbler, utils::*, *,
+    };
+    use fancy_garbling::BinaryGadgets;
+    use rand::Rng;
+    use scuttlebutt::{AesRng, Block};
+    use std::{os::unix::net::UnixStream, thread};
+
+    const FUZZY_SET_SIZE: usize = 8;
+
+    // Runs fuzzy PSI and returns the evaluator's opened intersection bit vector
+    fn fuzzy_intersection(
+        set_a: &[PrimaryKey],
+        set_b: &[PrimaryKey],
+        max_distance: usize,
+    ) -> Vec<u16> {
+        let mut rng = AesRng::new();
+        let (seed_sx, seed_rx): (u128, u128) = (rng.gen(), rng.gen());
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        thread::scope(|s| {
+            s.spawn(|| {
+                let mut channel = setup_channel(sender);
+                let mut gb_psi =
+                    OpprfPsiGarbler::<_, AesRng>::new(&mut channel, Block::from(seed_sx)).unwrap();
+                let intersection_results = gb_psi.intersect_fuzzy(set_a, max_distance).unwrap();
+                gb_psi
+                    .gb
+                    .outputs(&intersection_results.intersection.existence_bit_vector)
+                    .unwrap();
+            });
+            let mut channel = setup_channel(receiver);
+            let mut ev_psi =
+                OpprfPsiEvaluator::<_, AesRng>::new(&mut channel, Block::from(seed_rx)).unwrap();
+            let intersection_results = ev_psi.intersect_fuzzy(set_b, max_distance).unwrap();
+            ev_psi
+                .ev
+                .outputs(&intersection_results.intersection.existence_bit_vector)
+                .unwrap()
+                .expect("evaluator should produce outputs")
+        })
+    }
+
+    // Runs fuzzy PSI and returns the primary keys of the intersection whose bit
+    // is set, as opened by the evaluator
+    fn fuzzy_matched_keys(
+        set_a: &[PrimaryKey],
+        set_b: &[PrimaryKey],
+        max_distance: usize,
+    ) -> Vec<u128> {
+        let mut rng = AesRng::new();
+        let (seed_sx, seed_rx): (u128, u128) = (rng.gen(), rng.gen());
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        thread::scope(|s| {
+            s.spawn(|| {
+                let mut channel = setup_channel(sender);
+                let mut gb_psi =
+                    OpprfPsiGarbler::<_, AesRng>::new(&mut channel, Block::from(seed_sx)).unwrap();
+                let intersection_results = gb_psi.intersect_fuzzy(set_a, max_distance).unwrap();
+                let intersection = intersection_results.intersection;
+                gb_psi
+                    .gb
+                    .outputs(&intersection.existence_bit_vector)
+                    .unwrap();
+                gb_psi.gb.bin_outputs(&intersection.primary_keys).unwrap();
+            });
+            let mut channel = setup_channel(receiver);
+            let mut ev_psi =
+                OpprfPsiEvaluator::<_, AesRng>::new(&mut channel, Block::from(seed_rx)).unwrap();
+            let intersection_results = ev_psi.intersect_fuzzy(set_b, max_distance).unwrap();
+            let intersection = intersection_results.intersection;
+            let bits = ev_psi
+                .ev
+                .outputs(&intersection.existence_bit_vector)
+                .unwrap()
+                .expect("evaluator should produce outputs");
+            let keys = ev_psi
+                .ev
+                .bin_outputs(&intersection.primary_keys)
+                .unwrap()
+                .expect("evaluator should produce outputs");
+            assert_eq!(bits.len(), keys.len());
+            bits.into_iter()
+                .zip(keys)
+                .filter_map(|(bit, key)| if bit == 1 { Some(key) } else { None })
+                .collect()
+        })
+    }
+
+    #[test]
+    // Test that keys differing by one bit match at distance 1 but not at
+    // distance 0, while equal keys always match
+    fn test_fuzzy_one_bit_apart() {
+        let mut rng = AesRng::new();
+        let set_a: Vec<u64> = (0..FUZZY_SET_SIZE).map(|_| rng.gen()).collect();
+        // The evaluator holds one of the garbler's keys, half of them with one
+        // bit flipped, and unrelated keys
+        let set_b: Vec<u64> = (0..FUZZY_SET_SIZE)
+            .map(|i| match i {
+                0 => set_a[0],
+                i if i <= FUZZY_SET_SIZE / 2 => set_a[i] ^ (1 << rng.gen_range(0..64)),
+                _ => rng.gen(),
+            })
+            .collect();
+        let to_keys = |set: &[u64]| -> Vec<PrimaryKey> {
+            set.iter().map(|k| k.to_le_bytes().to_vec()).collect()
+        };
+        let (set_a, set_b) = (to_keys(&set_a), to_keys(&set_b));
+
+        let bits = fuzzy_intersection(&set_a, &set_b, 1);
+        for (i, bit) in bits.iter().enumerate() {
+            assert_eq!(
+                *bit == 1,
+                i <= FUZZY_SET_SIZE / 2,
+                "Key {} is wrongly matched at distance 1",
+                i
+            );
+        }
+        let bits = fuzzy_intersection(&set_a, &set_b, 0);
+        for (i, bit) in bits.iter().enumerate() {
+            assert_eq!(
+                *bit == 1,
+                i == 0,
+                "Key {} is wrongly matched at distance 0",
+                i
+            );
+        }
+    }
+
+    #[test]
+    // Test that the primary keys of the intersection are aligned with its bits,
+    // when the parties hold sets of different sizes
+    fn test_fuzzy_primary_keys() {
+        let mut rng = AesRng::new();
+        let set_a: Vec<u64> = (0..FUZZY_SET_SIZE).map(|_| rng.gen()).collect();
+        // The evaluator holds fewer keys: two close to the garbler's and two
+        // unrelated ones
+        let set_b: Vec<u64> = vec![rng.gen(), set_a[3] ^ 1, rng.gen(), set_a[5]];
+        let to_keys = |set: &[u64]| -> Vec<PrimaryKey> {
+            set.iter().map(|k| k.to_le_bytes().to_vec()).collect()
+        };
+
+        let keys = fuzzy_matched_keys(&to_keys(&set_a), &to_keys(&set_b), 1);
+        assert_eq!(keys, vec![set_b[1] as u128, set_b[3] as u128]);
+    }
+}
@@ -3,12 +3,12 @@
 mod tests {
     use crate::errors::Error;
     use crate::psi::circuit_psi::{
-        base_psi::{receiver::OpprfReceiver, sender::OpprfSender, BasePsi},
+        base_psi::{receiver::OpprfReceiver, sender::OpprfSender, BasePsi, CuckooStrategy},
         tests::{utils::*, *},
         utils::*,
         *,
     };
-    use scuttlebutt::{AesRng, Block512};
+    use scuttlebutt::{AesRng, Block, Block512};
     use std::{collections::HashSet, os::unix::net::UnixStream, thread};
 
     // Run the base psi up to hashing
@@ -29,7 +29,15 @@ mod tests {
             let result_sender = s.spawn(|| {
                 let mut rng = AesRng::seed_from_u64(seed_sx);
                 let mut channel = setup_channel(sender);
-                let mut sender = OpprfSender::init(&mut channel, &mut rng, true).unwrap();
+                let mut sender = OpprfSender::init(
+                    &mut channel,
+                    &mut rng,
+                    true,
+                    None,
+                    SecurityParams::default(),
+                    CuckooStrategy::default(),
+                )
+                .unwrap();
                 let result_hash_sender =
                     sender.hash_data(set, Some(payloads), &mut channel, &mut rng);
                 (sender, result_hash_sender)
@@ -37,7 +45,15 @@ mod tests {
             let mut rng = AesRng::seed_from_u64(seed_rx);
             let mut channel = setup_channel(receiver);
 
-            let mut receiver = OpprfReceiver::init(&mut channel, &mut rng, true).unwrap();
+            let mut receiver = OpprfReceiver::init(
+                &mut channel,
+                &mut rng,
+                true,
+                None,
+                SecurityParams::default(),
+                CuckooStrategy::default(),
+            )
+            .unwrap();
             let result_hash_receiver =
                 receiver.hash_data(set, Some(payloads), &mut channel, &mut rng);
             let (sender, result_hash_sender) = result_sender.join().unwrap();
@@ -79,6 +95,24 @@ mod tests {
         )
     }
 
+    // Run the hashing parameters check between two hashed parties
+    fn psty_verify_params(
+        sender: &OpprfSender,
+        receiver: &OpprfReceiver,
+    ) -> (Result<(), Error>, Result<(), Error>) {
+        let (channel_sender, channel_receiver) = UnixStream::pair().unwrap();
+
+        thread::scope(|s| {
+            let result_sender = s.spawn(|| {
+                let mut channel = setup_channel(channel_sender);
+                sender.verify_params(&mut channel)
+            });
+            let mut channel = setup_channel(channel_receiver);
+            let result_receiver = receiver.verify_params(&mut channel);
+            (result_sender.join().unwrap(), result_receiver)
+        })
+    }
+
     #[test]
     // Test that the OpprfSender produced no errors when
     // set is arbitrary
@@ -370,4 +404,53 @@ sender.state.opprf_payloads_in.len(), sender.state.opprf_payloads_out.len(),
             );
         }
     }
+    #[test]
+    // Test that the parties' hashing parameters are consistent after hashing
+    fn test_psty_hashing_params_consistent() {
+        for _ in 0..TEST_TRIALS {
+            let mut rng = AesRng::new();
+            let set = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
+            let payloads =
+                int_vec_block512(rand_u128_vec(SET_SIZE, PAYLOAD_MAX, &mut rng), PAYLOAD_SIZE);
+            let (sender, receiver, _, _) =
+                psty_up_to_hashing(&set, &payloads, DEFAULT_SEED, DEFAULT_SEED);
+            let (result_sender, result_receiver) = psty_verify_params(&sender, &receiver);
+            assert!(
+                result_sender.is_ok() && result_receiver.is_ok(),
+                "PSTY Hashing: consistent parameters were reported as inconsistent"
+            );
+        }
+    }
+    #[test]
+    // Test that a perturbation of a party's hashing parameters is detected
+    fn test_psty_hashing_params_perturbation_detected() {
+        let perturbations: [fn(&mut OpprfReceiver); 4] = [
+            // The receiver drops a bin, so their table no longer maps to the sender's
+            |receiver| {
+                receiver.state.opprf_primary_keys_in.pop();
+                receiver.state.opprf_payloads_in.pop();
+            },
+            // The receiver hashed with another key than the one it sent
+            |receiver| receiver.key = receiver.key ^ Block::from(1u128),
+            // The receiver uses other security parameters
+            |receiver| receiver.security.stat_sec += 1,
+            // The receiver uses another strategy
+            |receiver| receiver.strategy = CuckooStrategy::TwoChoice,
+        ];
+        for perturb in perturbations {
+            let mut rng = AesRng::new();
+            let set = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
+            let payloads =
+                int_vec_block512(rand_u128_vec(SET_SIZE, PAYLOAD_MAX, &mut rng), PAYLOAD_SIZE);
+            let (sender, mut receiver, _, _) =
+                psty_up_to_hashing(&set, &payloads, DEFAULT_SEED, DEFAULT_SEED);
+            perturb(&mut receiver);
+            let (result_sender, result_receiver) = psty_verify_params(&sender, &receiver);
+            assert!(
+                matches!(result_sender, Err(Error::ParameterInconsistency))
+                    && matches!(result_receiver, Err(Error::ParameterInconsistency)),
+                "PSTY Hashing: the perturbed parameters were not detected"
+            );
+        }
+    }
 }
@@ -2,28 +2,42 @@
 #[cfg(test)]
 mod tests {
 
-    use crate::psi::circuit_psi::{
-        base_psi::{receiver::OpprfReceiver, sender::OpprfSender, BasePsi},
-        tests::*,
-        utils::*,
+    use crate::{
+        errors::Error,
+        psi::circuit_psi::{
+            base_psi::{receiver::OpprfReceiver, sender::OpprfSender, BasePsi, CuckooStrategy},
+            tests::{utils::two_party_test, *},
+            utils::*,
+            SecurityParams,
+        },
     };
-    use scuttlebutt::AesRng;
+    use rand::Rng;
+    use scuttlebutt::{AbstractChannel, AesRng, Block};
     use std::os::unix::net::UnixStream;
 
     #[test]
     fn test_psty_init_receiver_succeeded() {
-        for _ in 0..TEST_TRIALS {
-            let (sender, receiver) = UnixStream::pair().unwrap();
-
-            std::thread::spawn(move || {
-                let mut rng = AesRng::new();
-                let mut channel = setup_channel(sender);
-                let _ = OpprfSender::init(&mut channel, &mut rng, true);
-            });
-            let mut rng = AesRng::new();
-            let mut channel = setup_channel(receiver);
-            let receiver = OpprfReceiver::init(&mut channel, &mut rng, true);
-
+        // Every trial runs with its own seed
+        for trial in 0..TEST_TRIALS {
+            let (_, receiver) = two_party_test!(
+                Block::from(trial as u128),
+                |channel, rng| OpprfSender::init(
+                    channel,
+                    rng,
+                    true,
+                    None,
+                    SecurityParams::default(),
+                    CuckooStrategy::default()
+                ),
+                |channel, rng| OpprfReceiver::init(
+                    channel,
+                    rng,
+                    true,
+                    None,
+                    SecurityParams::default(),
+                    CuckooStrategy::default()
+                ),
+            );
             assert!(
                 !receiver.is_err(),
                 "PSTY Initialization failed on the receiver side"
@@ -32,23 +46,163 @@ mod tests {
     }
     #[test]
     fn test_psty_init_sender_succeeded() {
-        for _ in 0..TEST_TRIALS {
-            let (sender, receiver) = UnixStream::pair().unwrap();
-
-            let sender = std::thread::spawn(move || {
-                let mut rng = AesRng::new();
-                let mut channel = setup_channel(sender);
-
-                OpprfSender::init(&mut channel, &mut rng, true)
-            });
-            let mut rng = AesRng::new();
-            let mut channel = setup_channel(receiver);
-            let _ = OpprfReceiver::init(&mut channel, &mut rng, true);
-
+        // Every trial runs with its own seed
+        for trial in 0..TEST_TRIALS {
+            let (sender, _) = two_party_test!(
+                Block::from(trial as u128),
+                |channel, rng| OpprfSender::init(
+                    channel,
+                    rng,
+                    true,
+                    None,
+                    SecurityParams::default(),
+                    CuckooStrategy::default()
+                ),
+                |channel, rng| OpprfReceiver::init(
+                    channel,
+                    rng,
+                    true,
+                    None,
+                    SecurityParams::default(),
+                    CuckooStrategy::default()
+                ),
+            );
             assert!(
-                !sender.join().unwrap().is_err(),
+                !sender.is_err(),
                 "PSTY Initialization failed on the sender side"
             );
         }
     }
+    #[test]
+    // Test that runs with the same seed sample the same hashing key
+    fn test_psty_init_seeded_is_reproducible() {
+        let seed = AesRng::new().gen::<Block>();
+        let run = || {
+            two_party_test!(
+                seed,
+                |channel, rng| {
+                    OpprfSender::init(
+                        channel,
+                        rng,
+                        true,
+                        None,
+                        SecurityParams::default(),
+                        CuckooStrategy::default(),
+                    )
+                    .unwrap()
+                    .key
+                },
+                |channel, rng| {
+                    OpprfReceiver::init(
+                        channel,
+                        rng,
+                        true,
+                        None,
+                        SecurityParams::default(),
+                        CuckooStrategy::default(),
+                    )
+                    .unwrap()
+                    .key
+                },
+            )
+        };
+        let (sender_key, receiver_key) = run();
+        assert_eq!(sender_key, receiver_key);
+        assert_eq!(run(), (sender_key, receiver_key));
+    }
+    #[test]
+    // Test that both parties fail when the receiver rejects its security parameters
+    fn test_psty_init_invalid_security_params() {
+        let security = SecurityParams {
+            stat_sec: SecurityParams::MIN_STAT_SEC - 1,
+            ..Default::default()
+        };
+        let (sender, receiver) = two_party_test!(
+            |channel, rng| OpprfSender::init(
+                channel,
+                rng,
+                true,
+                None,
+                SecurityParams::default(),
+                CuckooStrategy::default()
+            ),
+            |channel, rng| OpprfReceiver::init(
+                channel,
+                rng,
+                true,
+                None,
+                security,
+                CuckooStrategy::default()
+            ),
+        );
+        assert!(matches!(receiver, Err(Error::InvalidSecurityParams { .. })));
+        // The sender sees the receiver hang up before sending the hashing key
+        assert!(sender.is_err());
+    }
+    #[test]
+    // Test that the sender rejects a hashing key which is not derived from the
+    // public seed
+    fn test_psty_init_public_seed_rejects_other_key() {
+        let public_seed = AesRng::new().gen::<Block>();
+        let (sender, _) = two_party_test!(
+            |channel, rng| OpprfSender::init(
+                channel,
+                rng,
+                true,
+                Some(public_seed),
+                SecurityParams::default(),
+                CuckooStrategy::default()
+            )
+            .map(|_| ()),
+            |channel, rng| {
+                // The receiver sends a key of its own instead of the derived one
+                channel.write_block(&rng.gen::<Block>()).unwrap();
+                channel.flush().unwrap();
+            },
+        );
+        assert!(matches!(sender, Err(Error::ParameterInconsistency)));
+    }
+    #[test]
+    // Test that the hashing key only depends on the public seed when one is set
+    fn test_psty_init_public_seed_derives_key() {
+        for _ in 0..TEST_TRIALS {
+            let public_seed = AesRng::new().gen::<Block>();
+            let keys: Vec<Block> = (0..2)
+                .map(|_| {
+                    let (sender, receiver) = UnixStream::pair().unwrap();
+                    let sender = std::thread::spawn(move || {
+                        let mut rng = AesRng::new();
+                        let mut channel = setup_channel(sender);
+                        OpprfSender::init(
+                            &mut channel,
+                            &mut rng,
+                            true,
+                            Some(public_seed),
+                            SecurityParams::default(),
+                            CuckooStrategy::default(),
+                        )
+                        .unwrap()
+                        .key
+                    });
+                    let mut rng = AesRng::new();
+                    let mut channel = setup_channel(receiver);
+                    let receiver = OpprfReceiver::init(
+                        &mut channel,
+                        &mut rng,
+                        true,
+                        Some(public_seed),
+                        SecurityParams::default(),
+                        CuckooStrategy::default(),
+                    )
+                    .unwrap();
+                    assert_eq!(sender.join().unwrap(), receiver.key);
+                    receiver.key
+                })
+                .collect();
+            assert!(
+                keys[0] == keys[1],
+                "PSTY Initialization: the hashing key was not derived from the public seed"
+            );
+        }
+    }
 }
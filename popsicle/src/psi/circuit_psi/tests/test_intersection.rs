@@ -0,0 +1,194 @@
+//! Testing the helpers operating on Circuit Psi intersection results
+#[cfg(test)]
+mod tests {
+    use crate::errors::Error;
+    use crate::psi::circuit_psi::{
+        evaluator::OpprfPsiEvaluator,
+        garbler::OpprfPsiGarbler,
+        tests::{utils::*, *},
+        utils::*,
+        *,
+    };
+    use scuttlebutt::{AesRng, Block};
+    use std::{os::unix::net::UnixStream, thread};
+
+    // Arbitrary row metadata that stays local to the party
+    #[derive(Clone, Debug, PartialEq)]
+    struct Row {
+        name: String,
+        balance: i64,
+    }
+
+    // An intersection whose bins hold the primary keys in reverse order, with an
+    // empty bin before every fourth key
+    fn reversed_bins(nkeys: usize) -> Intersection {
+        let input_indices = (0..nkeys)
+            .rev()
+            .flat_map(|i| {
+                if i % 4 == 0 {
+                    vec![None, Some(i)]
+                } else {
+                    vec![Some(i)]
+                }
+            })
+            .collect();
+        Intersection {
+            input_indices,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    // Test that streaming the matched records produces one CSV line
+    // per record in the intersection
+    fn test_write_matched_with_payloads() {
+        let mut rng = AesRng::new();
+        let primary_keys = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
+        let payloads_u128 = rand_u128_vec(SET_SIZE, PAYLOAD_MAX, &mut rng);
+        let payloads = int_vec_block512(payloads_u128.clone(), PAYLOAD_SIZE);
+        let intersection = reversed_bins(SET_SIZE);
+        let opened_bits: Vec<bool> = intersection
+            .input_indices
+            .iter()
+            .map(|_| rng.gen())
+            .collect();
+
+        let mut out = Vec::new();
+        intersection
+            .write_matched(&opened_bits, &primary_keys, Some(&payloads), &mut out)
+            .unwrap();
+
+        let matched: Vec<usize> = (0..SET_SIZE)
+            .filter(|&i| {
+                opened_bits
+                    .iter()
+                    .zip(intersection.input_indices.iter())
+                    .any(|(bit, index)| *bit && *index == Some(i))
+            })
+            .collect();
+        let expected: String = matched
+            .into_iter()
+            .map(|i| format!("{},{}\n", i, payloads_u128[i]))
+            .collect();
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+    }
+    #[test]
+    // Test that streaming the matched records without payloads only
+    // writes the primary keys
+    fn test_write_matched_no_payloads() {
+        let primary_keys = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
+        let intersection = Intersection {
+            input_indices: (0..SET_SIZE).map(Some).collect(),
+            ..Default::default()
+        };
+        let opened_bits: Vec<bool> = (0..SET_SIZE).map(|i| i % 3 == 0).collect();
+
+        let mut out = Vec::new();
+        intersection
+            .write_matched(&opened_bits, &primary_keys, None, &mut out)
+            .unwrap();
+
+        let expected: String = (0..SET_SIZE)
+            .filter(|i| i % 3 == 0)
+            .map(|i| format!("{}\n", i))
+            .collect();
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+    }
+    #[test]
+    // Test that the opened bits must be aligned with the bins, and the bins with
+    // the primary keys
+    fn test_write_matched_mismatch() {
+        let primary_keys = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
+        let intersection = reversed_bins(SET_SIZE);
+        let mut out = Vec::new();
+
+        // One bit per primary key rather than per bin
+        let opened_bits = vec![true; SET_SIZE];
+        let result = intersection.write_matched(&opened_bits, &primary_keys, None, &mut out);
+        assert!(matches!(result, Err(Error::PsiProtocolError(_))));
+
+        // The bins hold more primary keys than passed
+        let opened_bits = vec![true; intersection.input_indices.len()];
+        let result = intersection.write_matched(&opened_bits, &primary_keys[1..], None, &mut out);
+        assert!(matches!(result, Err(Error::PsiProtocolError(_))));
+        assert!(out.is_empty());
+    }
+    #[test]
+    // Test that intersecting rows only returns the evaluator's rows whose
+    // primary keys are in the intersection, along with their metadata
+    fn test_intersect_with_rows() {
+        let mut rng = AesRng::new();
+        let set_a = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
+        let set_b = enum_ids(SET_SIZE, (SET_SIZE / 2) as u64, PRIMARY_KEY_SIZE);
+        let rows_a: Vec<(PrimaryKey, ())> = set_a.into_iter().map(|key| (key, ())).collect();
+        let rows_b: Vec<(PrimaryKey, Row)> = set_b
+            .into_iter()
+            .enumerate()
+            .map(|(i, key)| {
+                let row = Row {
+                    name: format!("row {}", i),
+                    balance: rng.gen(),
+                };
+                (key, row)
+            })
+            .collect();
+        let (seed_sx, seed_rx): (u128, u128) = (rng.gen(), rng.gen());
+
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let (result_sender, result_receiver) = thread::scope(|s| {
+            let result_sender = s.spawn(|| {
+                let mut channel = setup_channel(sender);
+                let mut gb_psi =
+                    OpprfPsiGarbler::<_, AesRng>::new(&mut channel, Block::from(seed_sx)).unwrap();
+                gb_psi.intersect_with_rows(&rows_a).unwrap()
+            });
+            let mut channel = setup_channel(receiver);
+            let mut ev_psi =
+                OpprfPsiEvaluator::<_, AesRng>::new(&mut channel, Block::from(seed_rx)).unwrap();
+            let result_receiver = ev_psi.intersect_with_rows(&rows_b).unwrap();
+            (result_sender.join().unwrap(), result_receiver)
+        });
+
+        assert!(
+            result_sender.is_empty(),
+            "The garbler should not learn which rows are in the intersection"
+        );
+        assert_eq!(
+            result_receiver,
+            rows_b[..SET_SIZE / 2].to_vec(),
+            "The intersecting rows are wrong"
+        );
+    }
+    #[test]
+    // Test that the rows the evaluator learns on random, partially overlapping
+    // sets match the intersection computed in the clear
+    fn test_intersect_with_rows_matches_reference() {
+        let mut rng = AesRng::new();
+        let set_a = rand_u8_vec_unique(SET_SIZE, 2 * SET_SIZE as u128, &mut rng);
+        let set_b = rand_u8_vec_unique(SET_SIZE, 2 * SET_SIZE as u128, &mut rng);
+        let rows_a: Vec<(PrimaryKey, ())> = set_a.iter().cloned().map(|key| (key, ())).collect();
+        let rows_b: Vec<(PrimaryKey, ())> = set_b.iter().cloned().map(|key| (key, ())).collect();
+        let (seed_sx, seed_rx): (u128, u128) = (rng.gen(), rng.gen());
+
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let result_receiver = thread::scope(|s| {
+            s.spawn(|| {
+                let mut channel = setup_channel(sender);
+                let mut gb_psi =
+                    OpprfPsiGarbler::<_, AesRng>::new(&mut channel, Block::from(seed_sx)).unwrap();
+                gb_psi.intersect_with_rows(&rows_a).unwrap();
+            });
+            let mut channel = setup_channel(receiver);
+            let mut ev_psi =
+                OpprfPsiEvaluator::<_, AesRng>::new(&mut channel, Block::from(seed_rx)).unwrap();
+            ev_psi.intersect_with_rows(&rows_b).unwrap()
+        });
+
+        let keys: Vec<PrimaryKey> = result_receiver.into_iter().map(|(key, _)| key).collect();
+        assert_eq!(
+            keys,
+            crate::reference_intersection(&set_b, &set_a),
+            "The intersecting rows do not match the reference intersection"
+        );
+    }
+}
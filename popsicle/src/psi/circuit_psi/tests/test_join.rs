@@ -0,0 +1,89 @@
+//! Testing the private join of the parties' rows on their primary keys
+#[cfg(test)]
+mod tests {
+    use crate::{
+        errors::Error,
+        psi::circuit_psi::{
+            evaluator::OpprfPsiEvaluator,
+            garbler::OpprfPsiGarbler,
+            tests::{utils::*, *},
+            utils::*,
+            *,
+        },
+    };
+    use rand::Rng;
+    use scuttlebutt::{AesRng, Block};
+    use std::collections::HashMap;
+
+    type JoinResult = Result<Vec<(PrimaryKey, usize, Payload)>, Error>;
+
+    // Joins the parties' rows on their primary keys and returns the joined rows
+    fn psty_join(
+        rows_a: &[(PrimaryKey, usize)],
+        rows_b: &[(PrimaryKey, usize)],
+        payloads_a: &[Payload],
+        payloads_b: &[Payload],
+    ) -> (JoinResult, JoinResult) {
+        two_party_test!(
+            |channel, rng| {
+                let mut gb_psi = OpprfPsiGarbler::<_, AesRng>::new(channel, rng.gen::<Block>())?;
+                gb_psi.intersect_join(rows_a, payloads_a)
+            },
+            |channel, rng| {
+                let mut ev_psi = OpprfPsiEvaluator::<_, AesRng>::new(channel, rng.gen::<Block>())?;
+                ev_psi.intersect_join(rows_b, payloads_b)
+            },
+        )
+    }
+
+    #[test]
+    // Test that the evaluator gets the same rows as a join in the clear, along
+    // with the garbler's payloads
+    fn test_join() {
+        let mut rng = AesRng::new();
+        let primary_keys_a = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
+        let primary_keys_b = enum_ids(SET_SIZE, SET_SIZE as u64 / 2, PRIMARY_KEY_SIZE);
+        // The metadata of each row is its index, offset per party
+        let rows_a: Vec<(PrimaryKey, usize)> = primary_keys_a
+            .into_iter()
+            .enumerate()
+            .map(|(i, key)| (key, 1000 + i))
+            .collect();
+        let rows_b: Vec<(PrimaryKey, usize)> = primary_keys_b
+            .into_iter()
+            .enumerate()
+            .map(|(i, key)| (key, 2000 + i))
+            .collect();
+        let payloads_a =
+            int_vec_block512(rand_u128_vec(SET_SIZE, PAYLOAD_MAX, &mut rng), PAYLOAD_SIZE);
+        let payloads_b =
+            int_vec_block512(rand_u128_vec(SET_SIZE, PAYLOAD_MAX, &mut rng), PAYLOAD_SIZE);
+
+        // Join the evaluator's rows with the garbler's payloads in the clear
+        let payloads_by_key: HashMap<&PrimaryKey, Payload> = rows_a
+            .iter()
+            .map(|(key, _)| key)
+            .zip(payloads_a.iter().copied())
+            .collect();
+        let expected: Vec<(PrimaryKey, usize, Payload)> = rows_b
+            .iter()
+            .filter_map(|(key, metadata)| {
+                payloads_by_key
+                    .get(key)
+                    .map(|payload| (key.clone(), *metadata, *payload))
+            })
+            .collect();
+        assert_eq!(expected.len(), SET_SIZE / 2);
+
+        let (gb, ev) = psty_join(&rows_a, &rows_b, &payloads_a, &payloads_b);
+        assert!(
+            gb.unwrap().is_empty(),
+            "The garbler should not learn the joined rows"
+        );
+        assert_eq!(
+            ev.unwrap(),
+            expected,
+            "The private join differs from the join in the clear"
+        );
+    }
+}
@@ -0,0 +1,100 @@
+//! Testing Circuit Psi on inputs with malformed records
+#[cfg(test)]
+mod tests {
+    use crate::{
+        errors::Error,
+        psi::circuit_psi::{
+            evaluator::OpprfPsiEvaluator,
+            garbler::OpprfPsiGarbler,
+            tests::{utils::*, *},
+            utils::*,
+            *,
+        },
+    };
+    use rand::Rng;
+    use scuttlebutt::{AesRng, Block};
+    use std::{os::unix::net::UnixStream, thread};
+
+    // Runs the lenient Circuit Psi on the parties' records, and returns the
+    // garbler's errors, the evaluator's errors, and the indices of the
+    // evaluator's records in the intersection
+    #[allow(clippy::type_complexity)]
+    fn psty_lenient(
+        primary_keys_a: &[PrimaryKey],
+        primary_keys_b: &[PrimaryKey],
+        payloads_a: &[Payload],
+        payloads_b: &[Payload],
+    ) -> (Vec<(usize, Error)>, Vec<(usize, Error)>, Vec<usize>) {
+        let mut rng = AesRng::new();
+        let (seed_sx, seed_rx): (u128, u128) = (rng.gen(), rng.gen());
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        thread::scope(|s| {
+            let garbler = s.spawn(|| {
+                let mut channel = setup_channel(sender);
+                let mut gb_psi =
+                    OpprfPsiGarbler::<_, AesRng>::new(&mut channel, Block::from(seed_sx)).unwrap();
+                let (intersection_results, errors) = gb_psi
+                    .intersect_lenient(primary_keys_a, Some(payloads_a))
+                    .unwrap();
+                gb_psi
+                    .gb
+                    .outputs(&intersection_results.intersection.existence_bit_vector)
+                    .unwrap();
+                errors
+            });
+            let mut channel = setup_channel(receiver);
+            let mut ev_psi =
+                OpprfPsiEvaluator::<_, AesRng>::new(&mut channel, Block::from(seed_rx)).unwrap();
+            let (intersection_results, errors) = ev_psi
+                .intersect_lenient(primary_keys_b, Some(payloads_b))
+                .unwrap();
+            let bits = ev_psi
+                .ev
+                .outputs(&intersection_results.intersection.existence_bit_vector)
+                .unwrap()
+                .expect("evaluator should produce outputs");
+            let mut matched: Vec<usize> = bits
+                .into_iter()
+                .zip(intersection_results.input_indices)
+                .filter_map(|(bit, index)| if bit == 1 { index } else { None })
+                .collect();
+            matched.sort_unstable();
+            (garbler.join().unwrap(), errors, matched)
+        })
+    }
+
+    #[test]
+    // Test that the valid records intersect, and that the malformed ones are
+    // reported with their indices without failing the run
+    fn test_lenient_skips_malformed_records() {
+        let mut rng = AesRng::new();
+        let mut primary_keys_a = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
+        let mut primary_keys_b = enum_ids(SET_SIZE, SET_SIZE as u64 / 2, PRIMARY_KEY_SIZE);
+        let mut payloads_a =
+            int_vec_block512(rand_u128_vec(SET_SIZE, PAYLOAD_MAX, &mut rng), PAYLOAD_SIZE);
+        let payloads_b =
+            int_vec_block512(rand_u128_vec(SET_SIZE, PAYLOAD_MAX, &mut rng), PAYLOAD_SIZE);
+
+        // The evaluator's i-th key is the garbler's (i + SET_SIZE / 2)-th key
+        let half = SET_SIZE / 2;
+        primary_keys_a[half + 1].pop();
+        payloads_a[half + 3].as_mut()[PAYLOAD_SIZE] = 1;
+        primary_keys_b[0].push(0);
+
+        let (errors_a, errors_b, matched) =
+            psty_lenient(&primary_keys_a, &primary_keys_b, &payloads_a, &payloads_b);
+        assert!(matches!(
+            errors_a[..],
+            [
+                (i, Error::InvalidPrimaryKeyLength(7)),
+                (j, Error::PayloadEncodingError(_))
+            ] if i == half + 1 && j == half + 3
+        ));
+        assert!(matches!(
+            errors_b[..],
+            [(0, Error::InvalidPrimaryKeyLength(9))]
+        ));
+        let in_clear: Vec<usize> = (0..half).filter(|i| ![0, 1, 3].contains(i)).collect();
+        assert_eq!(matched, in_clear);
+    }
+}
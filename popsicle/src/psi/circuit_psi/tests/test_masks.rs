@@ -0,0 +1,45 @@
+//! Testing the one-time payload masks of Circuit Psi
+#[cfg(test)]
+mod tests {
+    use crate::errors::Error;
+    use crate::psi::circuit_psi::{tests::*, utils::*, *};
+    use scuttlebutt::AesRng;
+
+    #[test]
+    // Test that distinct masks pass the mask reuse check
+    fn test_masks_unique() {
+        let mut rng = AesRng::new();
+        let masks: Vec<Payload> = (0..SET_SIZE).map(|_| rng.gen()).collect();
+        assert!(
+            check_masks_unique(&masks, PAYLOAD_SIZE).is_ok(),
+            "Distinct masks were reported as reused"
+        );
+    }
+    #[test]
+    // Test that feeding duplicate masks triggers the mask reuse error
+    fn test_masks_reused() {
+        let mut rng = AesRng::new();
+        let mut masks: Vec<Payload> = (0..SET_SIZE).map(|_| rng.gen()).collect();
+        masks[SET_SIZE - 1] = masks[0];
+        assert!(
+            matches!(
+                check_masks_unique(&masks, PAYLOAD_SIZE),
+                Err(Error::MaskReuse)
+            ),
+            "Reused masks were not detected"
+        );
+    }
+    #[test]
+    // Test that masks which only differ outside of the payload bytes
+    // are reported as reused
+    fn test_masks_reused_prefix() {
+        let masks = int_vec_block512(vec![42, 42 + (1 << (PAYLOAD_SIZE * 8))], 16);
+        assert!(
+            matches!(
+                check_masks_unique(&masks, PAYLOAD_SIZE),
+                Err(Error::MaskReuse)
+            ),
+            "Masks reused on the payload bytes were not detected"
+        );
+    }
+}
@@ -3,7 +3,7 @@
 mod tests {
     use crate::errors::Error;
     use crate::psi::circuit_psi::{
-        base_psi::{receiver::OpprfReceiver, sender::OpprfSender, BasePsi},
+        base_psi::{receiver::OpprfReceiver, sender::OpprfSender, BasePsi, CuckooStrategy},
         tests::{utils::*, *},
         utils::*,
         *,
@@ -31,7 +31,15 @@ mod tests {
             let result_sender = s.spawn(|| {
                 let mut rng = AesRng::seed_from_u64(seed_sx);
                 let mut channel = setup_channel(sender);
-                let mut sender = OpprfSender::init(&mut channel, &mut rng, true).unwrap();
+                let mut sender = OpprfSender::init(
+                    &mut channel,
+                    &mut rng,
+                    true,
+                    None,
+                    SecurityParams::default(),
+                    CuckooStrategy::default(),
+                )
+                .unwrap();
                 let _ = sender.hash_data(set, Some(payloads), &mut channel, &mut rng);
                 let result_opprf_sender = sender.opprf_exchange(&mut channel, &mut rng);
 
@@ -39,7 +47,15 @@ mod tests {
             });
             let mut rng = AesRng::seed_from_u64(seed_rx);
             let mut channel = setup_channel(receiver);
-            let mut receiver = OpprfReceiver::init(&mut channel, &mut rng, true).unwrap();
+            let mut receiver = OpprfReceiver::init(
+                &mut channel,
+                &mut rng,
+                true,
+                None,
+                SecurityParams::default(),
+                CuckooStrategy::default(),
+            )
+            .unwrap();
             let _ = receiver.hash_data(set, Some(payloads), &mut channel, &mut rng);
             let result_opprf_receiver = receiver.opprf_exchange(&mut channel, &mut rng);
 
@@ -0,0 +1,92 @@
+//! Testing the fixed opprf keys of the Base Psi sender
+#[cfg(test)]
+mod tests {
+    use crate::psi::circuit_psi::{
+        base_psi::{receiver::OpprfReceiver, sender::OpprfSender, BasePsi, CuckooStrategy},
+        tests::{utils::*, *},
+        utils::*,
+        *,
+    };
+
+    use scuttlebutt::{AesRng, Block, Block512};
+    use std::{os::unix::net::UnixStream, thread};
+
+    // The public seed fixing the hashing key, so that the parties' inputs land
+    // in the same bins in every run
+    const PUBLIC_SEED: u128 = 0x5eed;
+
+    // Runs the base psi up to the opprf exchange with the sender's opprf key
+    // set to `key`, returning the receiver's opprf outputs on its primary keys
+    fn opprf_outputs(set: &[PrimaryKey], key: Block, seed_sx: u64, seed_rx: u64) -> Vec<Block512> {
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let public_seed = Some(Block::from(PUBLIC_SEED));
+
+        thread::scope(|s| {
+            s.spawn(|| {
+                let mut rng = AesRng::seed_from_u64(seed_sx);
+                let mut channel = setup_channel(sender);
+                let mut sender = OpprfSender::init_with_key(
+                    &mut channel,
+                    &mut rng,
+                    key,
+                    false,
+                    public_seed,
+                    SecurityParams::default(),
+                    CuckooStrategy::default(),
+                )
+                .unwrap();
+                sender.hash_data(set, None, &mut channel, &mut rng).unwrap();
+                sender.verify_params(&mut channel).unwrap();
+                sender.opprf_exchange(&mut channel, &mut rng).unwrap();
+            });
+            let mut rng = AesRng::seed_from_u64(seed_rx);
+            let mut channel = setup_channel(receiver);
+            let mut receiver = OpprfReceiver::init(
+                &mut channel,
+                &mut rng,
+                false,
+                public_seed,
+                SecurityParams::default(),
+                CuckooStrategy::default(),
+            )
+            .unwrap();
+            receiver
+                .hash_data(set, None, &mut channel, &mut rng)
+                .unwrap();
+            receiver.verify_params(&mut channel).unwrap();
+            receiver.opprf_exchange(&mut channel, &mut rng).unwrap();
+
+            // The outputs of the bins holding a primary key are programmed by
+            // the sender, the others are random
+            receiver
+                .state
+                .opprf_primary_keys_out
+                .into_iter()
+                .zip(receiver.state.input_indices)
+                .filter_map(|(output, index)| index.map(|_| output))
+                .collect()
+        })
+    }
+
+    #[test]
+    // Test that runs with the same key give the same opprf outputs on the same
+    // primary keys, even when the parties' randomness differs
+    fn test_oprf_key_same_outputs() {
+        let set = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
+        let key = Block::from(1u128);
+        let first = opprf_outputs(&set, key, DEFAULT_SEED, DEFAULT_SEED);
+        let second = opprf_outputs(&set, key, DEFAULT_SEED + 1, DEFAULT_SEED + 2);
+        assert_eq!(first.len(), SET_SIZE);
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    // Test that runs with different keys give unrelated opprf outputs
+    fn test_oprf_key_different_outputs() {
+        let set = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
+        let first = opprf_outputs(&set, Block::from(1u128), DEFAULT_SEED, DEFAULT_SEED);
+        let second = opprf_outputs(&set, Block::from(2u128), DEFAULT_SEED, DEFAULT_SEED);
+        assert_eq!(first.len(), second.len());
+        assert!(first.iter().zip(&second).all(|(x, y)| x != y));
+    }
+}
@@ -0,0 +1,117 @@
+//! Testing Circuit Psi on payloads split column-wise
+#[cfg(test)]
+mod tests {
+    use crate::psi::circuit_psi::{
+        evaluator::OpprfPsiEvaluator,
+        garbler::OpprfPsiGarbler,
+        tests::{utils::*, *},
+        utils,
+        utils::*,
+        *,
+    };
+    use rand::Rng;
+    use scuttlebutt::{AesRng, Block};
+    use std::{os::unix::net::UnixStream, thread};
+
+    fn schema() -> PayloadSchema {
+        PayloadSchema::new(vec![ColumnType::U32, ColumnType::U32]).unwrap()
+    }
+
+    // Sums each column of the garbler's payloads over the intersection and
+    // returns the sums opened by the evaluator
+    fn psty_column_sums(
+        primary_keys_a: &[PrimaryKey],
+        primary_keys_b: &[PrimaryKey],
+        payloads_a: &[Payload],
+        payloads_b: &[Payload],
+    ) -> Vec<u128> {
+        let mut rng = AesRng::new();
+        let (seed_sx, seed_rx): (u128, u128) = (rng.gen(), rng.gen());
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        thread::scope(|s| {
+            s.spawn(|| {
+                let mut channel = setup_channel(sender);
+                let mut gb_psi =
+                    OpprfPsiGarbler::<_, AesRng>::new(&mut channel, Block::from(seed_sx)).unwrap();
+                let intersection_results = gb_psi
+                    .intersect_with_payloads(primary_keys_a, Some(payloads_a))
+                    .unwrap();
+                let columns = schema()
+                    .split_columns(&intersection_results.payloads.sender_payloads)
+                    .unwrap();
+                for column in columns.iter() {
+                    let sum = fancy_column_sum(
+                        &mut gb_psi.gb,
+                        &intersection_results.intersection.existence_bit_vector,
+                        column,
+                    )
+                    .unwrap();
+                    gb_psi.gb.outputs(sum.wires()).unwrap();
+                }
+            });
+            let mut channel = setup_channel(receiver);
+            let mut ev_psi =
+                OpprfPsiEvaluator::<_, AesRng>::new(&mut channel, Block::from(seed_rx)).unwrap();
+            let intersection_results = ev_psi
+                .intersect_with_payloads(primary_keys_b, Some(payloads_b))
+                .unwrap();
+            let columns = schema()
+                .split_columns(&intersection_results.payloads.sender_payloads)
+                .unwrap();
+            columns
+                .iter()
+                .map(|column| {
+                    let sum = fancy_column_sum(
+                        &mut ev_psi.ev,
+                        &intersection_results.intersection.existence_bit_vector,
+                        column,
+                    )
+                    .unwrap();
+                    let out = ev_psi
+                        .ev
+                        .outputs(sum.wires())
+                        .unwrap()
+                        .expect("evaluator should produce outputs");
+                    utils::binary_to_u128(out)
+                })
+                .collect()
+        })
+    }
+
+    #[test]
+    // Test that summing each column of the sender's payloads over the
+    // intersection matches the sums of the columns in the clear
+    fn test_payload_column_sum() {
+        let mut rng = AesRng::new();
+        let schema = schema();
+        let primary_keys_a = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
+        let primary_keys_b = enum_ids(SET_SIZE, SET_SIZE as u64 / 2, PRIMARY_KEY_SIZE);
+        let rows: Vec<(u32, u32)> = (0..SET_SIZE).map(|_| rng.gen()).collect();
+        let payloads_a: Vec<Payload> = rows
+            .iter()
+            .map(|(x, y)| schema.encode_row(&format!("{},{}", x, y)).unwrap())
+            .collect();
+        let payloads_b =
+            int_vec_block512(rand_u128_vec(SET_SIZE, PAYLOAD_MAX, &mut rng), PAYLOAD_SIZE);
+
+        // The second half of the garbler's keys are in the intersection
+        let in_clear = rows[SET_SIZE / 2..]
+            .iter()
+            .fold((0u32, 0u32), |(sx, sy), (x, y)| {
+                (sx.wrapping_add(*x), sy.wrapping_add(*y))
+            });
+        let sums = psty_column_sums(&primary_keys_a, &primary_keys_b, &payloads_a, &payloads_b);
+        assert_eq!(
+            sums,
+            vec![in_clear.0 as u128, in_clear.1 as u128],
+            "The column sums over the intersection are wrong"
+        );
+    }
+
+    #[test]
+    // Test that a schema wider than the circuit payloads cannot be split
+    fn test_payload_columns_reject_wide_schema() {
+        let schema = PayloadSchema::new(vec![ColumnType::U64, ColumnType::U32]).unwrap();
+        assert!(schema.split_columns::<WireMod2>(&[]).is_err());
+    }
+}
@@ -0,0 +1,90 @@
+//! Testing the encoding of variable-length payloads
+#[cfg(test)]
+mod tests {
+    use crate::psi::circuit_psi::{tests::*, utils::*, PAYLOAD_SIZE};
+    use rand::Rng;
+    use scuttlebutt::AesRng;
+
+    /// Random payloads whose last bytes are zeros
+    fn payloads_with_trailing_zeros(rng: &mut AesRng, max_len: usize) -> Vec<Vec<u8>> {
+        (0..SET_SIZE)
+            .map(|_| {
+                let len = rng.gen_range(1..=max_len);
+                let nzeros = rng.gen_range(1..=len);
+                let mut payload: Vec<u8> = (0..len).map(|_| rng.gen()).collect();
+                payload[len - nzeros..].iter_mut().for_each(|b| *b = 0);
+                payload
+            })
+            .collect()
+    }
+
+    #[test]
+    // Test that length-prefixed payloads with trailing zeros round-trip exactly
+    fn test_payload_encoding_length_prefixed() {
+        let mut rng = AesRng::new();
+        let encoding = PayloadEncoding::LengthPrefixed;
+        for payload in payloads_with_trailing_zeros(&mut rng, encoding.capacity(PAYLOAD_SIZE)) {
+            let encoded = encoding.encode(&payload, PAYLOAD_SIZE).unwrap();
+            assert_eq!(
+                encoding.decode(&encoded, PAYLOAD_SIZE).unwrap(),
+                payload,
+                "Length-prefixed payload did not round-trip"
+            );
+        }
+    }
+    #[test]
+    // Test that PKCS#7 padded payloads with trailing zeros round-trip exactly
+    fn test_payload_encoding_pkcs7() {
+        let mut rng = AesRng::new();
+        let encoding = PayloadEncoding::Pkcs7;
+        for payload in payloads_with_trailing_zeros(&mut rng, encoding.capacity(PAYLOAD_SIZE)) {
+            let encoded = encoding.encode(&payload, PAYLOAD_SIZE).unwrap();
+            assert_eq!(
+                encoding.decode(&encoded, PAYLOAD_SIZE).unwrap(),
+                payload,
+                "PKCS#7 payload did not round-trip"
+            );
+        }
+    }
+    #[test]
+    // Test that zero padded payloads lose their trailing zeros, which is
+    // why the other encodings exist
+    fn test_payload_encoding_zero_pad() {
+        let mut rng = AesRng::new();
+        let encoding = PayloadEncoding::default();
+        assert_eq!(encoding, PayloadEncoding::ZeroPad);
+        for payload in payloads_with_trailing_zeros(&mut rng, PAYLOAD_SIZE) {
+            let encoded = encoding.encode(&payload, PAYLOAD_SIZE).unwrap();
+            let decoded = encoding.decode(&encoded, PAYLOAD_SIZE).unwrap();
+            let stripped = payload.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+            assert_eq!(
+                decoded,
+                payload[..stripped],
+                "Zero padded payload should decode without its trailing zeros"
+            );
+        }
+    }
+    #[test]
+    // Test that payloads exceeding the capacity of an encoding are rejected
+    fn test_payload_encoding_too_long() {
+        let payload = vec![1u8; PAYLOAD_SIZE];
+        assert!(
+            PayloadEncoding::ZeroPad
+                .encode(&payload, PAYLOAD_SIZE)
+                .is_ok(),
+            "Zero padding should fit a full-width payload"
+        );
+        assert!(
+            PayloadEncoding::LengthPrefixed
+                .encode(&payload, PAYLOAD_SIZE)
+                .is_err(),
+            "Length prefix should not fit a full-width payload"
+        );
+        assert!(
+            PayloadEncoding::Pkcs7
+                .encode(&payload, PAYLOAD_SIZE)
+                .is_err(),
+            "PKCS#7 should not fit a full-width payload"
+        );
+    }
+}
@@ -0,0 +1,76 @@
+//! Testing the maximum of the parties' payloads over the intersection
+#[cfg(test)]
+mod tests {
+    use crate::{
+        errors::Error,
+        psi::circuit_psi::{
+            evaluator::OpprfPsiEvaluator,
+            garbler::OpprfPsiGarbler,
+            tests::{utils::*, *},
+            utils::*,
+            *,
+        },
+    };
+    use rand::Rng;
+    use scuttlebutt::{AesRng, Block};
+
+    // Computes the maximum of the parties' payloads over the intersection and
+    // returns the per-record maxima revealed to the evaluator
+    fn psty_payload_max(
+        primary_keys_a: &[PrimaryKey],
+        primary_keys_b: &[PrimaryKey],
+        payloads_a: &[Payload],
+        payloads_b: &[Payload],
+    ) -> (Result<Vec<u128>, Error>, Result<Vec<u128>, Error>) {
+        two_party_test!(
+            |channel, rng| {
+                let mut gb_psi = OpprfPsiGarbler::<_, AesRng>::new(channel, rng.gen::<Block>())?;
+                gb_psi.intersect_payload_max(primary_keys_a, payloads_a)
+            },
+            |channel, rng| {
+                let mut ev_psi = OpprfPsiEvaluator::<_, AesRng>::new(channel, rng.gen::<Block>())?;
+                ev_psi.intersect_payload_max(primary_keys_b, payloads_b)
+            },
+        )
+    }
+
+    #[test]
+    // Test that the evaluator learns the maximum of both parties' payloads for
+    // each of its records in the intersection, and 0 for the others
+    fn test_payload_max() {
+        let mut rng = AesRng::new();
+        let primary_keys_a = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
+        let primary_keys_b = enum_ids(SET_SIZE, SET_SIZE as u64 / 2, PRIMARY_KEY_SIZE);
+        let payloads_a_u128 = rand_u128_vec(SET_SIZE, PAYLOAD_MAX, &mut rng);
+        let payloads_b_u128 = rand_u128_vec(SET_SIZE, PAYLOAD_MAX, &mut rng);
+        let payloads_a = int_vec_block512(payloads_a_u128.clone(), PAYLOAD_SIZE);
+        let payloads_b = int_vec_block512(payloads_b_u128.clone(), PAYLOAD_SIZE);
+
+        // The first half of the evaluator's keys are the second half of the garbler's
+        let expected: Vec<u128> = (0..SET_SIZE)
+            .map(|i| {
+                if i < SET_SIZE / 2 {
+                    payloads_a_u128[SET_SIZE / 2 + i].max(payloads_b_u128[i])
+                } else {
+                    0
+                }
+            })
+            .collect();
+        // Make sure the maximum comes from either party depending on the record
+        let from_a = (0..SET_SIZE / 2)
+            .filter(|i| payloads_a_u128[SET_SIZE / 2 + i] > payloads_b_u128[*i])
+            .count();
+        assert!(from_a > 0 && from_a < SET_SIZE / 2);
+
+        let (gb, ev) = psty_payload_max(&primary_keys_a, &primary_keys_b, &payloads_a, &payloads_b);
+        assert!(
+            gb.unwrap().is_empty(),
+            "The garbler should not learn the maxima"
+        );
+        assert_eq!(
+            ev.unwrap(),
+            expected,
+            "The payload maxima over the intersection are wrong"
+        );
+    }
+}
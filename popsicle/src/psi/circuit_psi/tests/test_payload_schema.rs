@@ -0,0 +1,82 @@
+//! Testing the schema-driven encoding of CSV payloads
+#[cfg(test)]
+mod tests {
+    use crate::{
+        errors::Error,
+        psi::circuit_psi::{tests::*, utils::*},
+    };
+    use rand::Rng;
+    use scuttlebutt::AesRng;
+
+    fn schema() -> PayloadSchema {
+        PayloadSchema::new(vec![
+            ColumnType::U64,
+            ColumnType::I64,
+            ColumnType::FixedString(12),
+        ])
+        .unwrap()
+    }
+
+    #[test]
+    // Test that rows round-trip through a payload, including strings shorter
+    // than their column's width
+    fn test_payload_schema_roundtrip() {
+        let mut rng = AesRng::new();
+        let schema = schema();
+        for _ in 0..SET_SIZE {
+            let len = rng.gen_range(0..=12);
+            let name: String = (0..len)
+                .map(|_| rng.gen_range(b'a'..=b'z') as char)
+                .collect();
+            let row = format!("{},{},{}", rng.gen::<u64>(), rng.gen::<i64>(), name);
+            let payload = schema.encode_row(&row).unwrap();
+            assert_eq!(
+                schema.decode_row(&payload).unwrap(),
+                row,
+                "Row did not round-trip"
+            );
+        }
+    }
+
+    #[test]
+    // Test that a single unsigned column matches the integer payload encoding
+    fn test_payload_schema_matches_int_encoding() {
+        let schema = PayloadSchema::new(vec![ColumnType::U64]).unwrap();
+        let payload = schema.encode_row("1234").unwrap();
+        assert_eq!(payload, int_vec_block512(vec![1234], 8)[0]);
+    }
+
+    #[test]
+    // Test that malformed rows and oversized schemas are rejected
+    fn test_payload_schema_rejects_invalid_rows() {
+        let schema = schema();
+        for row in [
+            "1,2",
+            "1,2,abc,4",
+            "-1,2,abc",
+            "1,x,abc",
+            "1,2,a string that is too long",
+        ] {
+            assert!(
+                matches!(schema.encode_row(row), Err(Error::PayloadEncodingError(_))),
+                "Row {:?} was accepted",
+                row
+            );
+        }
+        assert!(PayloadSchema::new(vec![ColumnType::FixedString(65)]).is_err());
+    }
+
+    #[test]
+    // Test reading primary keys and payloads from a CSV file
+    fn test_payload_schema_read_csv() {
+        let schema = schema();
+        let csv = "1,10,-10,alice\n\n2,20,-20,bob\n";
+        let (primary_keys, payloads) = schema.read_csv(csv.as_bytes()).unwrap();
+        assert_eq!(
+            primary_keys,
+            vec![1u64.to_le_bytes().to_vec(), 2u64.to_le_bytes().to_vec()]
+        );
+        assert_eq!(schema.decode_row(&payloads[0]).unwrap(), "10,-10,alice");
+        assert_eq!(schema.decode_row(&payloads[1]).unwrap(), "20,-20,bob");
+    }
+}
@@ -0,0 +1,81 @@
+//! Testing XOR-secret-sharing the matched payloads between the parties
+#[cfg(test)]
+mod tests {
+    use crate::psi::circuit_psi::{
+        evaluator::OpprfPsiEvaluator,
+        garbler::OpprfPsiGarbler,
+        tests::{utils::*, *},
+        utils::*,
+        *,
+    };
+    use scuttlebutt::{AesRng, Block};
+    use std::{os::unix::net::UnixStream, thread};
+
+    #[test]
+    // Test that XORing both parties' shares reconstructs the sender's payload
+    // of every primary key in the intersection
+    fn test_payload_shares_reconstruct_payloads() {
+        let mut rng = AesRng::new();
+        let set_a = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
+        let set_b = enum_ids(SET_SIZE, (SET_SIZE / 2) as u64, PRIMARY_KEY_SIZE);
+        let payloads_a_u128 = rand_u128_vec(SET_SIZE, PAYLOAD_MAX, &mut rng);
+        let payloads_a = int_vec_block512(payloads_a_u128.clone(), PAYLOAD_SIZE);
+        let payloads_b =
+            int_vec_block512(rand_u128_vec(SET_SIZE, PAYLOAD_MAX, &mut rng), PAYLOAD_SIZE);
+        let (seed_sx, seed_rx): (u128, u128) = (rng.gen(), rng.gen());
+
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let (shares_sender, (shares_receiver, opened_bits, input_indices)) = thread::scope(|s| {
+            let shares_sender = s.spawn(|| {
+                let mut channel = setup_channel(sender);
+                let mut gb_psi =
+                    OpprfPsiGarbler::<_, AesRng>::new(&mut channel, Block::from(seed_sx)).unwrap();
+                let (intersection_results, shares) = gb_psi
+                    .intersect_with_payload_shares(&set_a, Some(&payloads_a))
+                    .unwrap();
+                gb_psi
+                    .gb
+                    .outputs(&intersection_results.intersection.existence_bit_vector)
+                    .unwrap();
+                shares
+            });
+            let mut channel = setup_channel(receiver);
+            let mut ev_psi =
+                OpprfPsiEvaluator::<_, AesRng>::new(&mut channel, Block::from(seed_rx)).unwrap();
+            let (intersection_results, shares) = ev_psi
+                .intersect_with_payload_shares(&set_b, Some(&payloads_b))
+                .unwrap();
+            let opened_bits = ev_psi
+                .ev
+                .outputs(&intersection_results.intersection.existence_bit_vector)
+                .unwrap()
+                .expect("evaluator should produce outputs");
+            (
+                shares_sender.join().unwrap(),
+                (shares, opened_bits, intersection_results.input_indices),
+            )
+        });
+
+        assert_eq!(shares_sender.len(), opened_bits.len());
+        assert_eq!(shares_receiver.len(), opened_bits.len());
+        let mut nmatched = 0;
+        for (i, bit) in opened_bits.iter().enumerate() {
+            if *bit != 1 {
+                continue;
+            }
+            nmatched += 1;
+            // The evaluator's key `SET_SIZE / 2 + j` is the garbler's key of
+            // the same index
+            let j = input_indices[i].expect("a matched bin should hold a key");
+            let payload =
+                le_bytes_to_u128((shares_sender[i] ^ shares_receiver[i]).prefix(PAYLOAD_SIZE));
+            assert_eq!(
+                payload,
+                payloads_a_u128[SET_SIZE / 2 + j],
+                "The shares of bin {} do not reconstruct the sender's payload",
+                i
+            );
+        }
+        assert_eq!(nmatched, SET_SIZE / 2, "The intersection size is wrong");
+    }
+}
@@ -0,0 +1,94 @@
+//! Testing Circuit Psi with OT correlations precomputed offline
+#[cfg(test)]
+mod tests {
+    use crate::psi::circuit_psi::{
+        evaluator::OpprfPsiEvaluator,
+        garbler::OpprfPsiGarbler,
+        tests::{utils::*, *},
+        utils::*,
+        *,
+    };
+    use ocelot::ot::{AlszReceiver, AlszSender, Receiver as OtReceiver, Sender as OtSender};
+    use rand::Rng;
+    use scuttlebutt::{AesRng, Block};
+    use std::{os::unix::net::UnixStream, thread};
+
+    const NCORRELATIONS: usize = 1 << 10;
+    const PUBLIC_SEED: u128 = 42;
+
+    // Runs Circuit Psi with a fixed public seed, so that the parties' hashing
+    // is the same across runs, using `ncorrelations` precomputed OT
+    // correlations if any. Returns the evaluator's opened intersection bit vector along
+    // with the number of correlations left to each party
+    fn run_psi(
+        set_a: &[PrimaryKey],
+        set_b: &[PrimaryKey],
+        ncorrelations: Option<usize>,
+    ) -> (Vec<u16>, usize, usize) {
+        let mut rng = AesRng::new();
+        let (seed_sx, seed_rx): (u128, u128) = (rng.gen(), rng.gen());
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        thread::scope(|s| {
+            let garbler = s.spawn(|| {
+                let mut channel = setup_channel(sender);
+                let mut rng = AesRng::new();
+                // The offline phase, before the garbler knows its inputs
+                let precomputed = ncorrelations.map(|n| {
+                    let mut ot = AlszSender::init(&mut channel, &mut rng).unwrap();
+                    PrecomputedOts::generate_sender(&mut ot, &mut channel, n, &mut rng).unwrap()
+                });
+                let mut gb_psi =
+                    OpprfPsiGarbler::<_, AesRng>::new(&mut channel, Block::from(seed_sx)).unwrap();
+                gb_psi.public_seed = Some(Block::from(PUBLIC_SEED));
+                let intersection_results = match precomputed {
+                    Some(precomputed) => gb_psi.intersect_online(set_a, precomputed),
+                    None => gb_psi.intersect(set_a),
+                }
+                .unwrap();
+                gb_psi
+                    .gb
+                    .outputs(&intersection_results.intersection.existence_bit_vector)
+                    .unwrap();
+                gb_psi.gb.ot_mut().remaining()
+            });
+            let mut channel = setup_channel(receiver);
+            let precomputed = ncorrelations.map(|n| {
+                let mut ot = AlszReceiver::init(&mut channel, &mut rng).unwrap();
+                PrecomputedOts::generate_receiver(&mut ot, &mut channel, n, &mut rng).unwrap()
+            });
+            let mut ev_psi =
+                OpprfPsiEvaluator::<_, AesRng>::new(&mut channel, Block::from(seed_rx)).unwrap();
+            ev_psi.public_seed = Some(Block::from(PUBLIC_SEED));
+            let intersection_results = match precomputed {
+                Some(precomputed) => ev_psi.intersect_online(set_b, precomputed),
+                None => ev_psi.intersect(set_b),
+            }
+            .unwrap();
+            let bits = ev_psi
+                .ev
+                .outputs(&intersection_results.intersection.existence_bit_vector)
+                .unwrap()
+                .expect("evaluator should produce outputs");
+            let receiver_remaining = ev_psi.ev.ot_mut().remaining();
+            (bits, garbler.join().unwrap(), receiver_remaining)
+        })
+    }
+
+    #[test]
+    // Test that the intersection computed with precomputed OT correlations
+    // matches the one computed fully online, and that the correlations are used
+    fn test_precomputed_ot_matches_online() {
+        let set_a = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
+        let set_b = enum_ids(SET_SIZE, SET_SIZE as u64 / 2, PRIMARY_KEY_SIZE);
+
+        let (online, _, _) = run_psi(&set_a, &set_b, None);
+        let (precomputed, sender_remaining, receiver_remaining) =
+            run_psi(&set_a, &set_b, Some(NCORRELATIONS));
+        assert_eq!(online, precomputed);
+        assert_eq!(
+            precomputed.iter().filter(|bit| **bit == 1).count(),
+            SET_SIZE / 2
+        );
+        assert_eq!((sender_remaining, receiver_remaining), (0, 0));
+    }
+}
@@ -0,0 +1,64 @@
+//! Testing the prefix sums of bundles in a garbled circuit
+#[cfg(test)]
+mod tests {
+    use crate::psi::circuit_psi::{
+        circuits::fancy_prefix_sum,
+        tests::{utils::*, *},
+    };
+    use fancy_garbling::{
+        twopac::semihonest::{Evaluator, Garbler},
+        BinaryGadgets, FancyInput, WireMod2,
+    };
+    use ocelot::ot::{AlszReceiver, AlszSender};
+    use rand::SeedableRng;
+    use scuttlebutt::AesRng;
+
+    const VALUE_BITS: usize = 16;
+
+    // Computes the prefix sums of the garbler's `values` in a garbled circuit and
+    // returns them as opened by the evaluator
+    fn garbled_prefix_sum(values: &[u128]) -> Vec<u128> {
+        let (_, sums) = two_party_test!(
+            |channel, rng| {
+                let mut gb =
+                    Garbler::<_, AesRng, AlszSender, WireMod2>::new(channel.clone(), rng.clone())
+                        .unwrap();
+                let items = gb.bin_encode_many(values, VALUE_BITS).unwrap();
+                let sums = fancy_prefix_sum(&mut gb, &items).unwrap();
+                gb.bin_outputs(&sums).unwrap();
+            },
+            |channel, rng| {
+                let mut ev = Evaluator::<_, AesRng, AlszReceiver, WireMod2>::new(
+                    channel.clone(),
+                    rng.clone(),
+                )
+                .unwrap();
+                let items = ev.bin_receive_many(values.len(), VALUE_BITS).unwrap();
+                let sums = fancy_prefix_sum(&mut ev, &items).unwrap();
+                ev.bin_outputs(&sums).unwrap().unwrap()
+            },
+        );
+        sums
+    }
+
+    #[test]
+    // Test that the prefix sums of random values match the cumulative sums in
+    // the clear, including when they overflow the values' width
+    fn test_prefix_sum() {
+        let mut rng = AesRng::seed_from_u64(DEFAULT_SEED);
+        for n in [0, 1, 2, 5, 8, 100] {
+            let mut values = rand_u128_vec(n, 1 << VALUE_BITS, &mut rng);
+            if let Some(first) = values.first_mut() {
+                *first = (1 << VALUE_BITS) - 1;
+            }
+            let expected: Vec<u128> = values
+                .iter()
+                .scan(0, |acc, x| {
+                    *acc += x;
+                    Some(*acc)
+                })
+                .collect();
+            assert_eq!(garbled_prefix_sum(&values), expected, "n = {}", n);
+        }
+    }
+}
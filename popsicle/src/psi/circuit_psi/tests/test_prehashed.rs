@@ -0,0 +1,87 @@
+//! Testing Circuit Psi on primary keys hashed by the caller
+#[cfg(test)]
+mod tests {
+    use crate::psi::circuit_psi::{
+        evaluator::OpprfPsiEvaluator, garbler::OpprfPsiGarbler, tests::*, utils::*, *,
+    };
+    use fancy_garbling::Fancy;
+    use rand::Rng;
+    use scuttlebutt::{AesRng, Block};
+    use sha2::{Digest, Sha256};
+    use std::{os::unix::net::UnixStream, thread};
+
+    // Email-like identifiers, which are long enough to be hashed by the library
+    fn emails(n: usize, starting_position: usize) -> Vec<PrimaryKey> {
+        (starting_position..n + starting_position)
+            .map(|i| format!("user{:08}@example.com", i).into_bytes())
+            .collect()
+    }
+
+    fn sha256_all(keys: &[PrimaryKey]) -> Vec<[u8; 32]> {
+        keys.iter().map(|key| Sha256::digest(key).into()).collect()
+    }
+
+    // Runs the Circuit Psi on the parties' keys and reveals the intersection
+    // bit vector to the evaluator
+    fn intersection_bits(
+        set_a: &[PrimaryKey],
+        set_b: &[PrimaryKey],
+        seed_sx: u128,
+        seed_rx: u128,
+        prehashed: bool,
+    ) -> Vec<u16> {
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        thread::scope(|s| {
+            s.spawn(|| {
+                let mut channel = setup_channel(sender);
+                let mut gb_psi =
+                    OpprfPsiGarbler::<_, AesRng>::new(&mut channel, Block::from(seed_sx)).unwrap();
+                let intersection_results = if prehashed {
+                    gb_psi.intersect_prehashed(&sha256_all(set_a)).unwrap()
+                } else {
+                    gb_psi.intersect(set_a).unwrap()
+                };
+                gb_psi
+                    .gb
+                    .outputs(&intersection_results.intersection.existence_bit_vector)
+                    .unwrap();
+            });
+            let mut channel = setup_channel(receiver);
+            let mut ev_psi =
+                OpprfPsiEvaluator::<_, AesRng>::new(&mut channel, Block::from(seed_rx)).unwrap();
+            let intersection_results = if prehashed {
+                ev_psi.intersect_prehashed(&sha256_all(set_b)).unwrap()
+            } else {
+                ev_psi.intersect(set_b).unwrap()
+            };
+            ev_psi
+                .ev
+                .outputs(&intersection_results.intersection.existence_bit_vector)
+                .unwrap()
+                .expect("evaluator should produce outputs")
+        })
+    }
+
+    #[test]
+    // Test that intersecting the SHA-256 digests of the parties' identifiers
+    // gives the same intersection as letting the library hash the identifiers
+    fn test_prehashed_matches_library_hashed() {
+        let mut rng = AesRng::new();
+        let set_a = emails(SET_SIZE, 0);
+        let set_b = emails(SET_SIZE, SET_SIZE / 2);
+        let (seed_sx, seed_rx): (u128, u128) = (rng.gen(), rng.gen());
+
+        let bits = intersection_bits(&set_a, &set_b, seed_sx, seed_rx, false);
+        let bits_prehashed = intersection_bits(&set_a, &set_b, seed_sx, seed_rx, true);
+
+        assert_eq!(
+            bits.iter().filter(|&&bit| bit == 1).count(),
+            SET_SIZE / 2,
+            "The library hashed intersection has the wrong size"
+        );
+        assert_eq!(
+            bits, bits_prehashed,
+            "The pre-hashed intersection differs from the library hashed one"
+        );
+    }
+}
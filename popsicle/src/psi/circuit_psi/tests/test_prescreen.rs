@@ -0,0 +1,96 @@
+//! Testing the Bloom filter prescreen of Circuit Psi
+#[cfg(test)]
+mod tests {
+    use crate::{
+        errors::Error,
+        psi::circuit_psi::{
+            evaluator::OpprfPsiEvaluator,
+            garbler::OpprfPsiGarbler,
+            tests::{utils::*, *},
+            *,
+        },
+    };
+    use fancy_garbling::Fancy;
+    use rand::{Rng, SeedableRng};
+    use scuttlebutt::{AesRng, Block};
+
+    // Computes the intersection, prescreened with false positive rate `rate` if
+    // set, and returns the indices of the evaluator's primary keys in it
+    fn psty_indices(set_a: &[PrimaryKey], set_b: &[PrimaryKey], rate: Option<f64>) -> Vec<usize> {
+        let (_, indices) = two_party_test!(
+            |channel, rng| {
+                let mut gb_psi =
+                    OpprfPsiGarbler::<_, AesRng>::new(channel, rng.gen::<Block>()).unwrap();
+                let intersection = match rate {
+                    Some(rate) => gb_psi.intersect_prescreened(set_a, rate).unwrap(),
+                    None => gb_psi.intersect(set_a).unwrap(),
+                };
+                gb_psi
+                    .gb
+                    .outputs(&intersection.intersection.existence_bit_vector)
+                    .unwrap();
+            },
+            |channel, rng| {
+                let mut ev_psi =
+                    OpprfPsiEvaluator::<_, AesRng>::new(channel, rng.gen::<Block>()).unwrap();
+                let intersection = match rate {
+                    Some(rate) => ev_psi.intersect_prescreened(set_b, rate).unwrap(),
+                    None => ev_psi.intersect(set_b).unwrap(),
+                };
+                let opened_bits = ev_psi
+                    .ev
+                    .outputs(&intersection.intersection.existence_bit_vector)
+                    .unwrap()
+                    .unwrap();
+                let mut indices: Vec<usize> = opened_bits
+                    .into_iter()
+                    .zip(intersection.input_indices)
+                    .filter_map(|(bit, index)| if bit == 1 { index } else { None })
+                    .collect();
+                indices.sort_unstable();
+                indices
+            },
+        );
+        indices
+    }
+
+    #[test]
+    // Test that the prescreened intersection equals the full intersection on
+    // random sets, for false positive rates letting few or many keys through
+    fn test_prescreen_no_false_negatives() {
+        let mut rng = AesRng::seed_from_u64(DEFAULT_SEED);
+        // The last half of the garbler's keys are the first half of the
+        // evaluator's
+        let keys = rand_u8_vec_unique(3 * SET_SIZE / 2, ELEMENT_MAX, &mut rng);
+        let set_a = keys[..SET_SIZE].to_vec();
+        let set_b = keys[SET_SIZE / 2..].to_vec();
+
+        let expected: Vec<usize> = (0..SET_SIZE)
+            .filter(|i| set_a.contains(&set_b[*i]))
+            .collect();
+        assert_eq!(expected, (0..SET_SIZE / 2).collect::<Vec<usize>>());
+        let full = psty_indices(&set_a, &set_b, None);
+        assert_eq!(full, expected);
+
+        for rate in [0.01, 0.5] {
+            assert_eq!(psty_indices(&set_a, &set_b, Some(rate)), full);
+        }
+    }
+
+    #[test]
+    // Test that the parties fail when they use different false positive rates
+    fn test_prescreen_rate_mismatch() {
+        let set = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
+        let (_, ev) = two_party_test!(
+            |channel, rng| {
+                let mut gb_psi = OpprfPsiGarbler::<_, AesRng>::new(channel, rng.gen::<Block>())?;
+                gb_psi.intersect_prescreened(&set, 0.01).map(|_| ())
+            },
+            |channel, rng| {
+                let mut ev_psi = OpprfPsiEvaluator::<_, AesRng>::new(channel, rng.gen::<Block>())?;
+                ev_psi.intersect_prescreened(&set, 0.1).map(|_| ())
+            },
+        );
+        assert!(matches!(ev, Err(Error::PsiProtocolError(_))));
+    }
+}
@@ -0,0 +1,149 @@
+//! Testing Circuit Psi running in the background through a protocol handle
+#[cfg(test)]
+mod tests {
+    use crate::{
+        errors::Error,
+        psi::circuit_psi::{
+            evaluator::OpprfPsiEvaluator,
+            garbler::OpprfPsiGarbler,
+            progress::{spawn_evaluator, spawn_garbler},
+            tests::{utils::*, *},
+            *,
+        },
+    };
+    use fancy_garbling::Fancy;
+    use rand::{Rng, SeedableRng};
+    use scuttlebutt::{AesRng, Block, SyncChannel};
+    use std::{
+        io::{BufReader, BufWriter},
+        os::unix::net::UnixStream,
+        time::{Duration, Instant},
+    };
+
+    type BackgroundChannel = SyncChannel<BufReader<UnixStream>, BufWriter<UnixStream>>;
+
+    fn sync_channel(stream: UnixStream) -> BackgroundChannel {
+        let reader = BufReader::new(stream.try_clone().unwrap());
+        let writer = BufWriter::new(stream);
+        SyncChannel::new(reader, writer)
+    }
+
+    #[test]
+    // Test that a protocol run through its handle reports every phase and
+    // completes with the intersection
+    fn test_progress_completion() {
+        let mut rng = AesRng::seed_from_u64(DEFAULT_SEED);
+        let set_a = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
+        let set_b = enum_ids(SET_SIZE, SET_SIZE as u64 / 2, PRIMARY_KEY_SIZE);
+        let (sender, receiver) = UnixStream::pair().unwrap();
+
+        let gb = spawn_garbler(
+            sync_channel(sender),
+            rng.gen::<Block>(),
+            move |gb: &mut OpprfPsiGarbler<_, _>| {
+                let intersection = gb.intersect(&set_a)?;
+                gb.gb
+                    .outputs(&intersection.intersection.existence_bit_vector)?;
+                Ok(intersection)
+            },
+        );
+        let ev = spawn_evaluator(
+            sync_channel(receiver),
+            rng.gen::<Block>(),
+            move |ev: &mut OpprfPsiEvaluator<_, _>| {
+                let intersection = ev.intersect(&set_b)?;
+                let opened_bits = ev
+                    .ev
+                    .outputs(&intersection.intersection.existence_bit_vector)?
+                    .expect("evaluator should produce outputs");
+                Ok((intersection, opened_bits))
+            },
+        );
+
+        // The receivers are disconnected once the protocols end
+        let gb_phases: Vec<&str> = gb.progress().iter().map(|p| p.phase).collect();
+        let ev_phases: Vec<&str> = ev.progress().iter().map(|p| p.phase).collect();
+        assert_eq!(
+            ev_phases,
+            vec!["setup", "init", "hashing", "opprf", "encoding", "garbling"]
+        );
+        assert_eq!(gb_phases, ev_phases);
+
+        let gb_intersection = gb.join().unwrap();
+        let (ev_intersection, opened_bits) = ev.join().unwrap();
+        assert_eq!(
+            gb_intersection.intersection.existence_bit_vector.len(),
+            opened_bits.len()
+        );
+        let mut indices: Vec<usize> = opened_bits
+            .into_iter()
+            .zip(ev_intersection.input_indices)
+            .filter_map(|(bit, index)| if bit == 1 { index } else { None })
+            .collect();
+        indices.sort_unstable();
+        assert_eq!(indices, (0..SET_SIZE / 2).collect::<Vec<usize>>());
+    }
+
+    #[test]
+    // Test that cancelling a protocol mid-run aborts it cleanly at the next
+    // phase, and that the other party fails instead of blocking
+    fn test_progress_cancellation() {
+        let mut rng = AesRng::seed_from_u64(DEFAULT_SEED);
+        let set = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
+        let set_ = set.clone();
+        let (sender, receiver) = UnixStream::pair().unwrap();
+
+        let gb = spawn_garbler(
+            sync_channel(sender),
+            rng.gen::<Block>(),
+            move |gb: &mut OpprfPsiGarbler<_, _>| gb.intersect(&set).map(|_| ()),
+        );
+        // The garbler waits for the evaluator while it is set up, so that it is
+        // cancelled before entering its next phase
+        assert_eq!(gb.progress().recv().unwrap().phase, "setup");
+        gb.cancel();
+        let ev = spawn_evaluator(
+            sync_channel(receiver),
+            rng.gen::<Block>(),
+            move |ev: &mut OpprfPsiEvaluator<_, _>| ev.intersect(&set_).map(|_| ()),
+        );
+
+        let phases: Vec<&str> = gb.progress().iter().map(|p| p.phase).collect();
+        assert!(phases.is_empty());
+        assert!(matches!(gb.join(), Err(Error::Cancelled)));
+        assert!(ev.join().is_err());
+    }
+
+    #[test]
+    // Test that a party closes its channel once its protocol succeeds, so that
+    // the other party fails instead of blocking, even though a clone of the
+    // channel is still alive
+    fn test_progress_closes_channel() {
+        let mut rng = AesRng::seed_from_u64(DEFAULT_SEED);
+        let set = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let channel = sync_channel(sender);
+        let clone = channel.clone();
+
+        // The garbler is only set up, and does not run the protocol
+        let gb = spawn_garbler(
+            channel,
+            rng.gen::<Block>(),
+            |_: &mut OpprfPsiGarbler<_, _>| Ok(()),
+        );
+        let ev = spawn_evaluator(
+            sync_channel(receiver),
+            rng.gen::<Block>(),
+            move |ev: &mut OpprfPsiEvaluator<_, _>| ev.intersect(&set).map(|_| ()),
+        );
+
+        gb.join().unwrap();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !ev.is_finished() && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(ev.is_finished(), "The evaluator is blocked on the channel");
+        assert!(ev.join().is_err());
+        drop(clone);
+    }
+}
@@ -0,0 +1,80 @@
+//! Testing the shuffled private set union
+#[cfg(test)]
+mod tests {
+    use crate::psi::circuit_psi::{
+        evaluator::OpprfPsiEvaluator,
+        garbler::OpprfPsiGarbler,
+        psu::CircuitPsu,
+        tests::{utils::*, *},
+        *,
+    };
+    use rand::Rng;
+    use scuttlebutt::{AesRng, Block};
+    use std::collections::HashSet;
+
+    // The size of the parties' sets, kept small since the keys are sorted twice
+    // in the circuit
+    const PSU_SET_SIZE: usize = 48;
+
+    // Computes the union of the parties' sets with the parties' RNGs derived from
+    // `seed`, and returns it as revealed to the evaluator
+    fn psu(seed: u128, set_a: &[PrimaryKey], set_b: &[PrimaryKey]) -> Vec<PrimaryKey> {
+        let (gb, ev) = two_party_test!(
+            Block::from(seed),
+            |channel, rng| {
+                let mut gb_psi =
+                    OpprfPsiGarbler::<_, AesRng>::new(channel, rng.gen::<Block>()).unwrap();
+                gb_psi.union_shuffled(set_a).unwrap()
+            },
+            |channel, rng| {
+                let mut ev_psi =
+                    OpprfPsiEvaluator::<_, AesRng>::new(channel, rng.gen::<Block>()).unwrap();
+                ev_psi.union_shuffled(set_b).unwrap()
+            },
+        );
+        assert!(gb.is_empty());
+        ev
+    }
+
+    #[test]
+    // Test that the revealed union is the union of the parties' sets, and that
+    // its order only depends on the parties' randomness
+    fn test_union_shuffled() {
+        let set_a = enum_ids(PSU_SET_SIZE, 0, PRIMARY_KEY_SIZE);
+        let set_b = enum_ids(PSU_SET_SIZE, PSU_SET_SIZE as u64 / 2, PRIMARY_KEY_SIZE);
+        let expected: HashSet<PrimaryKey> = set_a.iter().chain(set_b.iter()).cloned().collect();
+
+        let union = psu(DEFAULT_SEED as u128, &set_a, &set_b);
+        assert_eq!(union.len(), expected.len());
+        assert_eq!(union.iter().cloned().collect::<HashSet<_>>(), expected);
+
+        // The order is a random permutation: it is neither the sorted order nor
+        // the parties' order, and changes with the parties' randomness
+        let mut sorted = union.clone();
+        sorted.sort_by_key(|key| u64::from_le_bytes(key[..].try_into().unwrap()));
+        assert_ne!(union, sorted);
+        let union_ = psu(DEFAULT_SEED as u128 + 1, &set_a, &set_b);
+        assert_eq!(union_.iter().cloned().collect::<HashSet<_>>(), expected);
+        assert_ne!(union, union_);
+
+        // Reversing the parties' inputs does not change the order for the same
+        // randomness, which is only determined by the tags
+        let mut reversed_a = set_a.clone();
+        let mut reversed_b = set_b.clone();
+        reversed_a.reverse();
+        reversed_b.reverse();
+        assert_eq!(psu(DEFAULT_SEED as u128, &reversed_a, &reversed_b), union);
+    }
+
+    #[test]
+    // Test the union when either party's set is empty
+    fn test_union_shuffled_empty() {
+        let set = enum_ids(PSU_SET_SIZE, 0, PRIMARY_KEY_SIZE);
+        let expected: HashSet<PrimaryKey> = set.iter().cloned().collect();
+        for (set_a, set_b) in [(&set, &vec![]), (&vec![], &set)] {
+            let union = psu(DEFAULT_SEED as u128, set_a, set_b);
+            assert_eq!(union.iter().cloned().collect::<HashSet<_>>(), expected);
+        }
+        assert!(psu(DEFAULT_SEED as u128, &[], &[]).is_empty());
+    }
+}
@@ -0,0 +1,97 @@
+//! Testing Circuit Psi over a relay with an end-to-end encrypted session
+#[cfg(test)]
+mod tests {
+    use crate::{
+        psi::circuit_psi::{
+            evaluator::OpprfPsiEvaluator,
+            garbler::OpprfPsiGarbler,
+            tests::{utils::*, *},
+            *,
+        },
+        secure_channel::{SecureChannel, KEY_SIZE},
+    };
+    use fancy_garbling::Fancy;
+    use rand::Rng;
+    use scuttlebutt::{AesRng, Block, SyncChannel};
+    use std::{
+        io::{BufReader, BufWriter},
+        net::Shutdown,
+        os::unix::net::UnixStream,
+        thread,
+    };
+
+    fn secure_channel(
+        stream: UnixStream,
+        psk: &[u8; KEY_SIZE],
+        initiator: bool,
+    ) -> SecureChannel<SyncChannel<BufReader<UnixStream>, BufWriter<UnixStream>>> {
+        let reader = BufReader::new(stream.try_clone().unwrap());
+        let writer = BufWriter::new(stream);
+        let channel = SyncChannel::new(reader, writer);
+        let mut rng = AesRng::new();
+        if initiator {
+            SecureChannel::initiate(channel, psk, &mut rng).unwrap()
+        } else {
+            SecureChannel::respond(channel, psk, &mut rng).unwrap()
+        }
+    }
+
+    // A dumb relay forwarding the bytes of `from` to `to` until `from` is closed
+    fn forward(mut from: UnixStream, mut to: UnixStream) -> thread::JoinHandle<()> {
+        thread::spawn(move || {
+            let _ = std::io::copy(&mut from, &mut to);
+            let _ = to.shutdown(Shutdown::Write);
+        })
+    }
+
+    #[test]
+    // Test that the parties compute the intersection through a relay, on top of
+    // a session established with their pre-shared key
+    fn test_intersection_over_relay() {
+        let set_a = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
+        let set_b = enum_ids(SET_SIZE, SET_SIZE as u64 / 2, PRIMARY_KEY_SIZE);
+        let psk = AesRng::new().gen::<[u8; KEY_SIZE]>();
+
+        let (gb_stream, relay_gb) = UnixStream::pair().unwrap();
+        let (ev_stream, relay_ev) = UnixStream::pair().unwrap();
+        let relay = [
+            forward(relay_gb.try_clone().unwrap(), relay_ev.try_clone().unwrap()),
+            forward(relay_ev, relay_gb),
+        ];
+
+        let garbler = thread::spawn(move || {
+            let mut channel = secure_channel(gb_stream, &psk, true);
+            let mut gb_psi =
+                OpprfPsiGarbler::<_, AesRng>::new(&mut channel, AesRng::new().gen::<Block>())
+                    .unwrap();
+            let intersection = gb_psi.intersect(&set_a).unwrap();
+            gb_psi
+                .gb
+                .outputs(&intersection.intersection.existence_bit_vector)
+                .unwrap();
+        });
+        let mut channel = secure_channel(ev_stream, &psk, false);
+        let mut ev_psi =
+            OpprfPsiEvaluator::<_, AesRng>::new(&mut channel, AesRng::new().gen::<Block>())
+                .unwrap();
+        let intersection = ev_psi.intersect(&set_b).unwrap();
+        let opened_bits = ev_psi
+            .ev
+            .outputs(&intersection.intersection.existence_bit_vector)
+            .unwrap()
+            .unwrap();
+        let mut indices: Vec<usize> = opened_bits
+            .into_iter()
+            .zip(intersection.input_indices)
+            .filter_map(|(bit, index)| if bit == 1 { index } else { None })
+            .collect();
+        indices.sort_unstable();
+
+        garbler.join().unwrap();
+        drop((ev_psi, channel));
+        for thread in relay {
+            thread.join().unwrap();
+        }
+        assert_eq!(indices, (0..SET_SIZE / 2).collect::<Vec<usize>>());
+    }
+}
@@ -0,0 +1,158 @@
+//! Testing Circuit Psi with non-default security parameters
+#[cfg(test)]
+mod tests {
+    use crate::errors::Error;
+    use crate::psi::circuit_psi::{
+        base_psi::{receiver::OpprfReceiver, sender::OpprfSender, BasePsi, CuckooStrategy},
+        evaluator::OpprfPsiEvaluator,
+        garbler::OpprfPsiGarbler,
+        tests::{utils::*, *},
+        utils,
+        utils::*,
+        *,
+    };
+    use scuttlebutt::{AesRng, Block};
+    use std::{os::unix::net::UnixStream, thread};
+
+    // Computes the cardinality of the intersection with both parties using
+    // `security`
+    fn cardinality_with_security(
+        set_a: &[Vec<u8>],
+        set_b: &[Vec<u8>],
+        security: SecurityParams,
+    ) -> u128 {
+        let mut rng = AesRng::new();
+        let (seed_sx, seed_rx): (u128, u128) = (rng.gen(), rng.gen());
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        thread::scope(|s| {
+            s.spawn(|| {
+                let mut channel = setup_channel(sender);
+                let mut gb_psi =
+                    OpprfPsiGarbler::<_, AesRng>::new(&mut channel, Block::from(seed_sx)).unwrap();
+                gb_psi.security = security;
+                let intersection_results = gb_psi.intersect(set_a).unwrap();
+                let res = fancy_cardinality(
+                    &mut gb_psi.gb,
+                    &intersection_results.intersection.existence_bit_vector,
+                )
+                .unwrap();
+                gb_psi.gb.outputs(res.wires()).unwrap();
+            });
+            let mut channel = setup_channel(receiver);
+            let mut ev_psi =
+                OpprfPsiEvaluator::<_, AesRng>::new(&mut channel, Block::from(seed_rx)).unwrap();
+            ev_psi.security = security;
+            let intersection_results = ev_psi.intersect(set_b).unwrap();
+            let res = fancy_cardinality(
+                &mut ev_psi.ev,
+                &intersection_results.intersection.existence_bit_vector,
+            )
+            .unwrap();
+            let res_out = ev_psi
+                .ev
+                .outputs(res.wires())
+                .unwrap()
+                .expect("evaluator should produce outputs");
+            utils::binary_to_u128(res_out)
+        })
+    }
+
+    #[test]
+    // Test that the cardinality is correct with a reduced statistical
+    // security parameter
+    fn test_cardinality_reduced_stat_sec() {
+        let set_a = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
+        let set_b = enum_ids(SET_SIZE, (SET_SIZE / 2) as u64, PRIMARY_KEY_SIZE);
+        let security = SecurityParams {
+            stat_sec: SecurityParams::MIN_STAT_SEC,
+            ..SecurityParams::default()
+        };
+        let cardinality = cardinality_with_security(&set_a, &set_b, security);
+        assert_eq!(
+            cardinality as usize,
+            SET_SIZE / 2,
+            "The cardinality with stat_sec = {} is wrong",
+            security.stat_sec
+        );
+    }
+
+    #[test]
+    // Test that the cardinality is correct with an increased statistical
+    // security parameter
+    fn test_cardinality_increased_stat_sec() {
+        let set_a = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
+        let set_b = enum_ids(SET_SIZE, (SET_SIZE / 2) as u64, PRIMARY_KEY_SIZE);
+        let security = SecurityParams {
+            stat_sec: 80,
+            ..SecurityParams::default()
+        };
+        let cardinality = cardinality_with_security(&set_a, &set_b, security);
+        assert_eq!(
+            cardinality as usize,
+            SET_SIZE / 2,
+            "The cardinality with stat_sec = {} is wrong",
+            security.stat_sec
+        );
+    }
+
+    #[test]
+    // Test that the compared hash size grows with the statistical security
+    // parameter and the number of bins
+    fn test_hash_size() {
+        let security = SecurityParams::default();
+        assert_eq!(security.hash_size(1 << 8), 6);
+        assert_eq!(security.hash_size(1 << 20), 8);
+        let security = SecurityParams {
+            stat_sec: 80,
+            ..security
+        };
+        assert_eq!(security.hash_size(1 << 8), 11);
+    }
+
+    #[test]
+    // Test that initialization rejects parameters outside of the supported
+    // ranges
+    fn test_init_rejects_invalid_security_params() {
+        for security in [
+            SecurityParams {
+                stat_sec: SecurityParams::MIN_STAT_SEC - 1,
+                ..SecurityParams::default()
+            },
+            SecurityParams {
+                comp_sec: SecurityParams::MAX_COMP_SEC + 1,
+                ..SecurityParams::default()
+            },
+        ] {
+            let (sender, receiver) = UnixStream::pair().unwrap();
+            let mut rng = AesRng::new();
+            let mut channel = setup_channel(sender);
+            let result = OpprfSender::init(
+                &mut channel,
+                &mut rng,
+                false,
+                None,
+                security,
+                CuckooStrategy::default(),
+            );
+            assert!(
+                matches!(result, Err(Error::InvalidSecurityParams { .. })),
+                "The sender accepted {:?}",
+                security
+            );
+            let mut channel = setup_channel(receiver);
+            let result = OpprfReceiver::init(
+                &mut channel,
+                &mut rng,
+                false,
+                None,
+                security,
+                CuckooStrategy::default(),
+            );
+            assert!(
+                matches!(result, Err(Error::InvalidSecurityParams { .. })),
+                "The receiver accepted {:?}",
+                security
+            );
+        }
+    }
+}
@@ -0,0 +1,56 @@
+//! Testing the set difference of Circuit Psi
+#[cfg(test)]
+mod tests {
+    use crate::psi::circuit_psi::{
+        evaluator::OpprfPsiEvaluator,
+        garbler::OpprfPsiGarbler,
+        tests::{utils::*, *},
+        *,
+    };
+    use rand::{Rng, SeedableRng};
+    use scuttlebutt::{AesRng, Block};
+    use std::collections::HashSet;
+
+    #[test]
+    // Test that the evaluator gets its primary keys which are not in the
+    // garbler's set, on random sets
+    fn test_set_difference() {
+        let mut rng = AesRng::seed_from_u64(DEFAULT_SEED);
+        // Each key is in the garbler's set, in the evaluator's, or in both
+        let keys = rand_u8_vec_unique(2 * SET_SIZE, ELEMENT_MAX, &mut rng);
+        let (mut set_a, mut set_b) = (vec![], vec![]);
+        for key in keys {
+            match rng.gen_range(0..3) {
+                0 => set_a.push(key),
+                1 => set_b.push(key),
+                _ => {
+                    set_a.push(key.clone());
+                    set_b.push(key);
+                }
+            }
+        }
+
+        let (gb, ev) = two_party_test!(
+            |channel, rng| {
+                let mut gb_psi =
+                    OpprfPsiGarbler::<_, AesRng>::new(channel, rng.gen::<Block>()).unwrap();
+                gb_psi.set_difference(&set_a).unwrap()
+            },
+            |channel, rng| {
+                let mut ev_psi =
+                    OpprfPsiEvaluator::<_, AesRng>::new(channel, rng.gen::<Block>()).unwrap();
+                ev_psi.set_difference(&set_b).unwrap()
+            },
+        );
+        assert!(gb.is_empty());
+
+        let set_a: HashSet<PrimaryKey> = set_a.into_iter().collect();
+        let expected: Vec<PrimaryKey> = set_b
+            .iter()
+            .filter(|key| !set_a.contains(*key))
+            .cloned()
+            .collect();
+        assert!(!expected.is_empty());
+        assert_eq!(ev, expected);
+    }
+}
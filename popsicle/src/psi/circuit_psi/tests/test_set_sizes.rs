@@ -0,0 +1,52 @@
+//! Testing the secure comparison of the parties' set sizes
+#[cfg(test)]
+mod tests {
+    use crate::psi::circuit_psi::{
+        evaluator::OpprfPsiEvaluator,
+        garbler::OpprfPsiGarbler,
+        tests::{utils::*, *},
+    };
+    use rand::Rng;
+    use scuttlebutt::{AesRng, Block};
+    use std::cmp::Ordering;
+
+    #[test]
+    // Test that both parties learn the correct ordering of their set sizes, from
+    // their own point of view
+    fn test_compare_set_sizes() {
+        let sizes: Vec<(usize, usize)> = vec![
+            (0, 0),
+            (1, 0),
+            (0, 1),
+            (SET_SIZE, SET_SIZE),
+            (SET_SIZE, SET_SIZE + 1),
+            (SET_SIZE + 1, SET_SIZE),
+            (1 << 40, (1 << 40) - 1),
+            (usize::MAX, usize::MAX - 1),
+            (5, usize::MAX),
+        ];
+        let (gb, ev) = two_party_test!(
+            |channel, rng| {
+                let mut gb_psi =
+                    OpprfPsiGarbler::<_, AesRng>::new(channel, rng.gen::<Block>()).unwrap();
+                sizes
+                    .iter()
+                    .map(|(size, _)| gb_psi.compare_set_sizes(*size).unwrap())
+                    .collect::<Vec<Ordering>>()
+            },
+            |channel, rng| {
+                let mut ev_psi =
+                    OpprfPsiEvaluator::<_, AesRng>::new(channel, rng.gen::<Block>()).unwrap();
+                sizes
+                    .iter()
+                    .map(|(_, size)| ev_psi.compare_set_sizes(*size).unwrap())
+                    .collect::<Vec<Ordering>>()
+            },
+        );
+        for (((gb_size, ev_size), gb), ev) in sizes.iter().zip(gb).zip(ev) {
+            assert_eq!(gb, gb_size.cmp(ev_size));
+            assert_eq!(ev, ev_size.cmp(gb_size));
+            assert_eq!(gb, ev.reverse());
+        }
+    }
+}
@@ -0,0 +1,134 @@
+//! Testing Circuit Psi on sorted inputs, merging the sets instead of hashing them
+#[cfg(test)]
+mod tests {
+    use crate::{
+        errors::Error,
+        psi::circuit_psi::{
+            evaluator::OpprfPsiEvaluator, garbler::OpprfPsiGarbler, utils, utils::*, *,
+        },
+    };
+    use rand::Rng;
+    use scuttlebutt::{AesRng, Block};
+    use std::{collections::BTreeSet, os::unix::net::UnixStream, thread};
+
+    const SORTED_SET_SIZE: usize = 1 << 5;
+
+    // Generates a sorted set of keys, the first `noverlap` of which are in `other`
+    fn sorted_keys(other: &[u64], noverlap: usize, rng: &mut AesRng) -> Vec<PrimaryKey> {
+        let mut set: BTreeSet<u64> = other.iter().take(noverlap).cloned().collect();
+        while set.len() < SORTED_SET_SIZE {
+            set.insert(rng.gen());
+        }
+        set.iter().map(|k| k.to_be_bytes().to_vec()).collect()
+    }
+
+    // Runs the sorted or the standard Circuit Psi, and returns the number of
+    // keys in the intersection, and the keys matched in the sorted intersection
+    // as opened by the evaluator
+    fn psty_intersection(
+        set_a: &[PrimaryKey],
+        set_b: &[PrimaryKey],
+        sorted: bool,
+    ) -> Result<(u128, Vec<u128>), Error> {
+        let mut rng = AesRng::new();
+        let (seed_sx, seed_rx): (u128, u128) = (rng.gen(), rng.gen());
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        thread::scope(|s| {
+            let garbler = s.spawn(|| -> Result<(), Error> {
+                let mut channel = setup_channel(sender);
+                let mut gb_psi =
+                    OpprfPsiGarbler::<_, AesRng>::new(&mut channel, Block::from(seed_sx))?;
+                let intersection_results = if sorted {
+                    gb_psi.intersect_sorted(set_a)?
+                } else {
+                    gb_psi.intersect(set_a)?
+                };
+                let intersection = intersection_results.intersection;
+                let cardinality =
+                    fancy_cardinality(&mut gb_psi.gb, &intersection.existence_bit_vector)?;
+                gb_psi.gb.outputs(cardinality.wires())?;
+                if sorted {
+                    gb_psi.gb.outputs(&intersection.existence_bit_vector)?;
+                    for key in intersection.primary_keys.iter() {
+                        gb_psi.gb.outputs(key.wires())?;
+                    }
+                }
+                Ok(())
+            });
+            let mut channel = setup_channel(receiver);
+            let mut ev_psi =
+                OpprfPsiEvaluator::<_, AesRng>::new(&mut channel, Block::from(seed_rx))?;
+            let intersection_results = if sorted {
+                ev_psi.intersect_sorted(set_b)
+            } else {
+                ev_psi.intersect(set_b)
+            };
+            let intersection_results = match intersection_results {
+                Ok(results) => results,
+                Err(e) => {
+                    garbler.join().unwrap()?;
+                    return Err(e);
+                }
+            };
+            let intersection = intersection_results.intersection;
+            let cardinality =
+                fancy_cardinality(&mut ev_psi.ev, &intersection.existence_bit_vector)?;
+            let cardinality = ev_psi.ev.outputs(cardinality.wires())?.unwrap();
+            let mut matched = vec![];
+            if sorted {
+                let bits = ev_psi
+                    .ev
+                    .outputs(&intersection.existence_bit_vector)?
+                    .unwrap();
+                for (bit, key) in bits.iter().zip(intersection.primary_keys.iter()) {
+                    let key = utils::binary_to_u128(ev_psi.ev.outputs(key.wires())?.unwrap());
+                    if *bit == 1 {
+                        matched.push(key);
+                    }
+                }
+            }
+            garbler.join().unwrap()?;
+            Ok((utils::binary_to_u128(cardinality), matched))
+        })
+    }
+
+    #[test]
+    // Test that the sorted intersection has the same cardinality as the
+    // standard one, and that it matches exactly the keys of the intersection
+    fn test_sorted_matches_standard() {
+        let mut rng = AesRng::new();
+        let set_a = sorted_keys(&[], 0, &mut rng);
+        let values_a: Vec<u64> = set_a
+            .iter()
+            .map(|k| u64::from_be_bytes(k[..].try_into().unwrap()))
+            .collect();
+        let set_b = sorted_keys(&values_a, SORTED_SET_SIZE / 2, &mut rng);
+
+        let (standard, _) = psty_intersection(&set_a, &set_b, false).unwrap();
+        let (sorted, matched) = psty_intersection(&set_a, &set_b, true).unwrap();
+        let in_clear: Vec<u128> = set_a
+            .iter()
+            .filter(|k| set_b.contains(k))
+            .map(|k| u64::from_be_bytes(k[..].try_into().unwrap()) as u128)
+            .collect();
+        assert_eq!(standard, in_clear.len() as u128);
+        assert_eq!(sorted, standard);
+        assert_eq!(matched, in_clear);
+    }
+
+    #[test]
+    // Test that unsorted or duplicated keys are rejected before running the protocol
+    fn test_sorted_rejects_unsorted_input() {
+        let mut rng = AesRng::new();
+        let set = sorted_keys(&[], 0, &mut rng);
+        let mut unsorted = set.clone();
+        unsorted.swap(3, 4);
+        let result = psty_intersection(&unsorted, &unsorted, true);
+        assert!(matches!(result, Err(Error::UnsortedInput(4))));
+
+        let mut duplicated = set.clone();
+        duplicated[5] = duplicated[4].clone();
+        let result = psty_intersection(&duplicated, &duplicated, true);
+        assert!(matches!(result, Err(Error::UnsortedInput(5))));
+    }
+}
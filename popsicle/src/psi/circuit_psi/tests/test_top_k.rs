@@ -0,0 +1,104 @@
+//! Testing the top-k selection of the sender's payloads over the intersection
+#[cfg(test)]
+mod tests {
+    use crate::{
+        errors::Error,
+        psi::circuit_psi::{
+            evaluator::OpprfPsiEvaluator,
+            garbler::OpprfPsiGarbler,
+            tests::{utils::*, *},
+            utils::*,
+            *,
+        },
+    };
+    use rand::Rng;
+    use scuttlebutt::{AesRng, Block};
+    use std::collections::HashSet;
+
+    type TopK = Vec<(u128, Option<PrimaryKey>)>;
+
+    // Selects the `k` largest of the garbler's payloads over the intersection
+    // and returns what each party learns
+    fn psty_top_k(
+        primary_keys_a: &[PrimaryKey],
+        primary_keys_b: &[PrimaryKey],
+        payloads_a: &[Payload],
+        k: usize,
+        with_keys: bool,
+    ) -> (Result<TopK, Error>, Result<TopK, Error>) {
+        let payloads_b = int_vec_block512(vec![0; primary_keys_b.len()], PAYLOAD_SIZE);
+        two_party_test!(
+            |channel, rng| {
+                let mut gb_psi = OpprfPsiGarbler::<_, AesRng>::new(channel, rng.gen::<Block>())?;
+                gb_psi.intersect_top_k(primary_keys_a, payloads_a, k, with_keys)
+            },
+            |channel, rng| {
+                let mut ev_psi = OpprfPsiEvaluator::<_, AesRng>::new(channel, rng.gen::<Block>())?;
+                ev_psi.intersect_top_k(primary_keys_b, &payloads_b, k, with_keys)
+            },
+        )
+    }
+
+    #[test]
+    // Test that the evaluator learns the k largest of the garbler's payloads over
+    // the intersection, with the keys they are associated with, when payloads
+    // are tied
+    fn test_top_k_with_ties() {
+        let k = 10;
+        let primary_keys_a = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
+        let primary_keys_b = enum_ids(SET_SIZE, SET_SIZE as u64 / 2, PRIMARY_KEY_SIZE);
+        // Few distinct values, so that the k-th largest is tied with others
+        let payloads_a_u128: Vec<u128> = (0..SET_SIZE as u128).map(|i| i % 7).collect();
+        let payloads_a = int_vec_block512(payloads_a_u128.clone(), PAYLOAD_SIZE);
+
+        // The first half of the evaluator's keys are the second half of the garbler's
+        let mut expected: Vec<u128> = payloads_a_u128[SET_SIZE / 2..].to_vec();
+        expected.sort_unstable_by(|x, y| y.cmp(x));
+        expected.truncate(k);
+
+        let (gb, ev) = psty_top_k(&primary_keys_a, &primary_keys_b, &payloads_a, k, true);
+        assert!(
+            gb.unwrap().is_empty(),
+            "The garbler should not learn the top k"
+        );
+        let ev = ev.unwrap();
+        let values: Vec<u128> = ev.iter().map(|(value, _)| *value).collect();
+        assert_eq!(values, expected, "The top k payloads are wrong");
+
+        // Tied records may be selected in any order, but every key must be in
+        // the intersection, distinct, and associated with its payload
+        let keys: HashSet<&PrimaryKey> = ev.iter().map(|(_, key)| key.as_ref().unwrap()).collect();
+        assert_eq!(keys.len(), k);
+        for (value, key) in &ev {
+            let i = primary_keys_a
+                .iter()
+                .position(|x| x == key.as_ref().unwrap())
+                .unwrap();
+            assert!(
+                i >= SET_SIZE / 2,
+                "A key outside of the intersection was selected"
+            );
+            assert_eq!(payloads_a_u128[i], *value);
+        }
+    }
+
+    #[test]
+    // Test that only the records in the intersection are revealed when it holds
+    // fewer than k of them, and that keys are only revealed when asked for
+    fn test_top_k_small_intersection() {
+        let k = 16;
+        let nshared = 5;
+        let primary_keys_a = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
+        let primary_keys_b = enum_ids(SET_SIZE, (SET_SIZE - nshared) as u64, PRIMARY_KEY_SIZE);
+        let payloads_a_u128: Vec<u128> = (0..SET_SIZE as u128).map(|i| 1000 - i).collect();
+        let payloads_a = int_vec_block512(payloads_a_u128.clone(), PAYLOAD_SIZE);
+        let expected: Vec<(u128, Option<PrimaryKey>)> = payloads_a_u128[SET_SIZE - nshared..]
+            .iter()
+            .map(|value| (*value, None))
+            .collect();
+
+        let (gb, ev) = psty_top_k(&primary_keys_a, &primary_keys_b, &payloads_a, k, false);
+        assert!(gb.unwrap().is_empty());
+        assert_eq!(ev.unwrap(), expected);
+    }
+}
@@ -0,0 +1,67 @@
+//! Testing the tracing spans emitted by Circuit Psi
+#[cfg(all(test, feature = "tracing"))]
+mod tests {
+    use crate::psi::circuit_psi::{
+        evaluator::OpprfPsiEvaluator,
+        garbler::OpprfPsiGarbler,
+        tests::{utils::*, *},
+        *,
+    };
+    use rand::Rng;
+    use scuttlebutt::{AesRng, Block};
+    use std::sync::{Arc, Mutex};
+    use tracing::{
+        span::{Attributes, Id},
+        Event, Subscriber,
+    };
+    use tracing_subscriber::{
+        layer::{Context, SubscriberExt},
+        Layer,
+    };
+
+    // Records the names of the spans and the number of events emitted
+    #[derive(Clone, Default)]
+    struct Recorder {
+        spans: Arc<Mutex<Vec<&'static str>>>,
+        nevents: Arc<Mutex<usize>>,
+    }
+
+    impl<S: Subscriber> Layer<S> for Recorder {
+        fn on_new_span(&self, attrs: &Attributes<'_>, _id: &Id, _ctx: Context<'_, S>) {
+            self.spans.lock().unwrap().push(attrs.metadata().name());
+        }
+
+        fn on_event(&self, _event: &Event<'_>, _ctx: Context<'_, S>) {
+            *self.nevents.lock().unwrap() += 1;
+        }
+    }
+
+    #[test]
+    // Test that the evaluator enters a span for each phase of the protocol, in order
+    fn test_tracing_spans_in_order() {
+        let primary_keys = enum_ids(SET_SIZE, 0, PRIMARY_KEY_SIZE);
+        let rows: Vec<(PrimaryKey, usize)> = primary_keys.iter().cloned().zip(0..).collect();
+        let recorder = Recorder::default();
+        let subscriber = tracing_subscriber::registry().with(recorder.clone());
+        // Only the evaluator runs on this thread, and thus records its spans
+        tracing::subscriber::with_default(subscriber, || {
+            two_party_test!(
+                |channel, rng| {
+                    let mut gb_psi =
+                        OpprfPsiGarbler::<_, AesRng>::new(channel, rng.gen::<Block>()).unwrap();
+                    gb_psi.intersect_with_rows(&rows).unwrap()
+                },
+                |channel, rng| {
+                    let mut ev_psi =
+                        OpprfPsiEvaluator::<_, AesRng>::new(channel, rng.gen::<Block>()).unwrap();
+                    ev_psi.intersect_with_rows(&rows).unwrap()
+                },
+            )
+        });
+        assert_eq!(
+            *recorder.spans.lock().unwrap(),
+            vec!["init", "hashing", "opprf", "encoding", "garbling", "reveal"]
+        );
+        assert!(*recorder.nevents.lock().unwrap() > 0);
+    }
+}
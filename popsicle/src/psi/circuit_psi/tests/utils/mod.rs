@@ -124,3 +124,83 @@ pub fn rand_u8_vec_unique<RNG: CryptoRng + Rng>(
         .map(|value| value.to_le_bytes().to_vec())
         .collect()
 }
+
+#[cfg(test)]
+use crate::psi::circuit_psi::utils::setup_channel;
+#[cfg(test)]
+use scuttlebutt::{AesRng, Channel};
+#[cfg(test)]
+use std::{
+    io::{BufReader, BufWriter},
+    os::unix::net::UnixStream,
+};
+
+#[cfg(test)]
+/// The channel each party of a `two_party_test!` runs on
+pub type TestChannel = Channel<BufReader<UnixStream>, BufWriter<UnixStream>>;
+
+#[cfg(test)]
+/// Run `garbler` and `evaluator` on both ends of a `UnixStream`, the garbler on its
+/// own thread, with RNGs derived from `seed`, and return both of their results
+/// (cf. `two_party_test!`).
+pub fn run_two_party<G, E, TG, TE>(seed: Block, garbler: G, evaluator: E) -> (TG, TE)
+where
+    G: FnOnce(&mut TestChannel, &mut AesRng) -> TG + Send,
+    E: FnOnce(&mut TestChannel, &mut AesRng) -> TE,
+    TG: Send,
+{
+    use rand::SeedableRng;
+
+    let mut seed_rng = AesRng::from_seed(seed);
+    let (seed_gb, seed_ev): (Block, Block) = (seed_rng.gen(), seed_rng.gen());
+    let (sender, receiver) = UnixStream::pair().unwrap();
+    std::thread::scope(|s| {
+        // Each party's channel is closed once it is done, so that the other party
+        // fails instead of blocking if it returned early. Closing fails if the
+        // other party hung up first, which some tests do on purpose.
+        let handle = s.spawn(move || {
+            let mut channel = setup_channel(sender);
+            let mut rng = AesRng::from_seed(seed_gb);
+            let garbler = garbler(&mut channel, &mut rng);
+            let _ = channel.close();
+            garbler
+        });
+        let evaluator = {
+            let mut channel = setup_channel(receiver);
+            let mut rng = AesRng::from_seed(seed_ev);
+            let evaluator = evaluator(&mut channel, &mut rng);
+            let _ = channel.close();
+            evaluator
+        };
+        (handle.join().unwrap(), evaluator)
+    })
+}
+
+#[cfg(test)]
+/// Run a two-party protocol for tests, taking a garbler and an evaluator closure
+/// which are each given a `&mut TestChannel` and a `&mut AesRng`. Returns the pair
+/// of their results.
+///
+/// The parties' RNGs are derived from a fixed seed, `DEFAULT_SEED` unless one is
+/// given as a first argument, so that runs are reproducible.
+///
+/// ```ignore
+/// let (gb, ev) = two_party_test!(
+///     |channel, rng| OpprfSender::init(channel, rng, true, None, security, strategy),
+///     |channel, rng| OpprfReceiver::init(channel, rng, true, None, security, strategy),
+/// );
+/// ```
+macro_rules! two_party_test {
+    ($garbler:expr, $evaluator:expr $(,)?) => {
+        $crate::psi::circuit_psi::tests::utils::run_two_party(
+            scuttlebutt::Block::from($crate::psi::circuit_psi::tests::DEFAULT_SEED as u128),
+            $garbler,
+            $evaluator,
+        )
+    };
+    ($seed:expr, $garbler:expr, $evaluator:expr $(,)?) => {
+        $crate::psi::circuit_psi::tests::utils::run_two_party($seed, $garbler, $evaluator)
+    };
+}
+#[cfg(test)]
+pub(crate) use two_party_test;
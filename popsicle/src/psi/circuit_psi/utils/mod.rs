@@ -3,9 +3,15 @@ use crate::{cuckoo::CuckooItem, errors::Error};
 use fancy_garbling::{util, FancyInput, WireMod2};
 use itertools::Itertools;
 use rand::{CryptoRng, Rng, RngCore, SeedableRng};
-use scuttlebutt::{Block, Block512, Channel};
+use scuttlebutt::{AbstractChannel, Block, Block512, Channel};
+
+mod payload_encoding;
+mod payload_schema;
+pub use payload_encoding::PayloadEncoding;
+pub use payload_schema::{ColumnType, PayloadSchema};
 
 use std::{
+    collections::HashSet,
     fmt::Debug,
     io::{BufReader, BufWriter},
     os::unix::net::UnixStream,
@@ -22,6 +28,20 @@ pub fn binary_to_u128(bin: Vec<u16>) -> u128 {
     acc
 }
 
+/// Turn a vector of bits represented as u16 into a signed value, reading
+/// them as a 64-bit two's complement integer.
+pub fn binary_to_i64(bin: Vec<u16>) -> i64 {
+    binary_to_u128(bin) as u64 as i64
+}
+
+/// Turn up to 16 little-endian bytes into a decimal value represented as a u128.
+pub fn le_bytes_to_u128(bytes: &[u8]) -> u128 {
+    let mut b_128 = [0_u8; 16];
+    let n = bytes.len().min(16);
+    b_128[..n].copy_from_slice(&bytes[..n]);
+    u128::from_le_bytes(b_128)
+}
+
 fn block512_to_crt(b: Block512, size: usize) -> Vec<u16> {
     let size_b = size / 8;
     let b_val = b.prefix(size_b);
@@ -62,6 +82,19 @@ pub fn mask_payload_binary(x: Block512, y: Block512) -> Block512 {
     Block512::from(x ^ y)
 }
 
+/// Check that no one-time payload mask is used for more than one record.
+///
+/// Only the first `size` bytes of the masks are compared, since these are the
+/// bytes that hide the payloads in the circuit.
+pub fn check_masks_unique(masks: &[Block512], size: usize) -> Result<(), Error> {
+    let mut seen = HashSet::with_capacity(masks.len());
+    if masks.iter().all(|mask| seen.insert(mask.prefix(size))) {
+        Ok(())
+    } else {
+        Err(Error::MaskReuse)
+    }
+}
+
 /// Create a vector of Block512, from a vector of u64s
 pub fn int_vec_block512(values: Vec<u128>, size: usize) -> Vec<Block512> {
     values
@@ -197,6 +230,134 @@ where
     gc_party.receive_many(&moduli)
 }
 
+/// The largest number of bits of the numerator and denominator of `epsilon`
+/// accepted by `sample_discrete_laplace`.
+const DP_EPSILON_BITS: i32 = 96;
+
+/// Returns `epsilon` as an exact fraction `(numerator, denominator)`.
+fn dp_epsilon_ratio(epsilon: f64) -> Result<(u128, u128), Error> {
+    if !epsilon.is_finite() || epsilon <= 0.0 {
+        return Err(Error::InvalidDpEpsilon(epsilon));
+    }
+    // A finite float is exactly `mantissa * 2^exponent`
+    let bits = epsilon.to_bits();
+    let biased_exponent = ((bits >> 52) & 0x7ff) as i32;
+    let fraction = (bits & ((1 << 52) - 1)) as u128;
+    let (mantissa, exponent) = if biased_exponent == 0 {
+        (fraction, -1074)
+    } else {
+        (fraction | (1 << 52), biased_exponent - 1075)
+    };
+    let shift = mantissa.trailing_zeros() as i32;
+    let (mantissa, exponent) = (mantissa >> shift, exponent + shift);
+    if exponent >= 0 && exponent <= DP_EPSILON_BITS - 53 {
+        Ok((mantissa << exponent, 1))
+    } else if exponent < 0 && -exponent <= DP_EPSILON_BITS {
+        Ok((mantissa, 1 << -exponent))
+    } else {
+        Err(Error::InvalidDpEpsilon(epsilon))
+    }
+}
+
+// Samples a bit set with probability `exp(-n / d)` for `n <= d`, with integer
+// arithmetic only: the first `k` at which a bit set with probability `n / (d k)`
+// is not set is odd with probability `exp(-n / d)`
+fn sample_bernoulli_exp_fraction<RNG: Rng>(n: u128, d: u128, rng: &mut RNG) -> bool {
+    let mut k = 1;
+    while rng.gen_range(0..d * k) < n {
+        k += 1;
+    }
+    k % 2 == 1
+}
+
+// Samples a bit set with probability `exp(-n / d)`, with integer arithmetic only
+fn sample_bernoulli_exp<RNG: Rng>(n: u128, d: u128, rng: &mut RNG) -> bool {
+    // exp(-n / d) = exp(-1)^floor(n / d) * exp(-(n mod d) / d)
+    for _ in 0..n / d {
+        if !sample_bernoulli_exp_fraction(1, 1, rng) {
+            return false;
+        }
+    }
+    sample_bernoulli_exp_fraction(n % d, d, rng)
+}
+
+/// Samples noise from the discrete Laplace distribution with parameter `epsilon`,
+/// i.e. `Pr[x] ∝ exp(-epsilon * |x|)`, which makes a count with sensitivity 1
+/// `epsilon`-differentially private. Its variance is `2q / (1 - q)^2` for
+/// `q = exp(-epsilon)`.
+///
+/// The noise is sampled exactly, with the rejection sampler of Canonne, Kamath
+/// and Steinke ("The Discrete Gaussian for Differential Privacy", 2020) on the
+/// exact value of `epsilon` as a fraction. It only uses integer arithmetic,
+/// since floating-point samplers leak through the gaps in the distribution
+/// they actually sample from (Mironov, 2012). Fails with
+/// `Error::InvalidDpEpsilon` if `epsilon` is not positive and finite, or if its
+/// numerator or denominator does not fit in 96 bits.
+pub fn sample_discrete_laplace<RNG: Rng>(epsilon: f64, rng: &mut RNG) -> Result<i64, Error> {
+    // The noise is `Y = floor(X / s)` for `X` geometric with parameter
+    // `exp(-1 / t)`, itself sampled as `U + t * V` for `U` in `0..t` and `V`
+    // geometric with parameter `exp(-1)`
+    let (s, t) = dp_epsilon_ratio(epsilon)?;
+    loop {
+        let u = rng.gen_range(0..t);
+        if !sample_bernoulli_exp(u, t, rng) {
+            continue;
+        }
+        let mut v = 0;
+        while sample_bernoulli_exp_fraction(1, 1, rng) {
+            v += 1;
+        }
+        let y = (u + t * v) / s;
+        let negative = rng.gen::<bool>();
+        // Otherwise, 0 would be sampled twice as often
+        if negative && y == 0 {
+            continue;
+        }
+        let y = i64::try_from(y).map_err(|_| Error::InvalidDpEpsilon(epsilon))?;
+        return Ok(if negative { -y } else { y });
+    }
+}
+
+/// The number of bits of the parties' shares of a randomized response flip
+pub const DP_FLIP_BITS: usize = 32;
+
+/// Returns the threshold below which a uniform `DP_FLIP_BITS`-bit value flips a bit
+/// of the intersection bit vector in randomized response with parameter `epsilon`.
+///
+/// Flipping each bit with probability `p = 1 / (1 + exp(epsilon))` makes the
+/// presence of every primary key `epsilon`-differentially private, since the
+/// ratio of the probabilities of any output bit with and without the key is
+/// at most `(1 - p) / p = exp(epsilon)`. The probability is rounded to a
+/// multiple of `2^-DP_FLIP_BITS`.
+pub fn dp_flip_threshold(epsilon: f64) -> Result<u128, Error> {
+    if !epsilon.is_finite() || epsilon <= 0.0 {
+        return Err(Error::InvalidDpEpsilon(epsilon));
+    }
+    let p = 1.0 / (1.0 + epsilon.exp());
+    Ok((p * (1u128 << DP_FLIP_BITS) as f64).round() as u128)
+}
+
+/// The byte exchanged by the parties to acknowledge a barrier
+const BARRIER_ACK: u8 = 0xAC;
+
+/// Synchronizes both parties at a protocol phase boundary.
+///
+/// The write buffer is flushed and a one-byte ack is exchanged, so that neither
+/// party can start the next phase while data of the previous phase is still
+/// sitting in a buffer.
+pub fn barrier<C: AbstractChannel>(channel: &mut C) -> Result<(), Error> {
+    channel.write_u8(BARRIER_ACK)?;
+    channel.flush()?;
+    let ack = channel.read_u8()?;
+    if ack != BARRIER_ACK {
+        return Err(Error::PsiProtocolError(format!(
+            "barrier expected ack {:#x} but received {:#x}",
+            BARRIER_ACK, ack
+        )));
+    }
+    Ok(())
+}
+
 /// Turns a Unixstream into a scuttlebutt channel
 pub fn setup_channel(stream: UnixStream) -> Channel<BufReader<UnixStream>, BufWriter<UnixStream>> {
     let reader = BufReader::new(stream.try_clone().unwrap());
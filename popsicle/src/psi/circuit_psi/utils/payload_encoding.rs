@@ -0,0 +1,103 @@
+//! Encoding of variable-length payloads into fixed-size `Payload`s
+use crate::{errors::Error, psi::circuit_psi::Payload};
+
+/// The maximum number of bytes a `Payload` can hold.
+const PAYLOAD_MAX_BYTES: usize = 64;
+
+/// The scheme used to pad a variable-length payload to a fixed width.
+///
+/// - `ZeroPad` fills the remaining bytes with zeros. It is not reversible when a
+///   payload legitimately ends with zero bytes, since decoding strips them.
+/// - `LengthPrefixed` stores the length of the payload in the first byte, followed
+///   by the payload and zeros. It round-trips exactly.
+/// - `Pkcs7` fills the remaining `n` bytes with the value `n` (cf. RFC 5652). It
+///   round-trips exactly, and always adds at least one byte of padding.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PayloadEncoding {
+    /// Pad with zeros.
+    #[default]
+    ZeroPad,
+    /// Prefix with a one byte length.
+    LengthPrefixed,
+    /// Pad according to PKCS#7.
+    Pkcs7,
+}
+
+impl PayloadEncoding {
+    /// Encodes `bytes` into a payload whose first `width` bytes are used.
+    pub fn encode(&self, bytes: &[u8], width: usize) -> Result<Payload, Error> {
+        if width > PAYLOAD_MAX_BYTES || bytes.len() > self.capacity(width) {
+            return Err(Error::PayloadEncodingError(format!(
+                "{} bytes do not fit in a payload of width {} using {:?}",
+                bytes.len(),
+                width,
+                self
+            )));
+        }
+        let mut payload = Payload::default();
+        let out = payload.prefix_mut(width);
+        match self {
+            PayloadEncoding::ZeroPad => out[..bytes.len()].copy_from_slice(bytes),
+            PayloadEncoding::LengthPrefixed => {
+                out[0] = bytes.len() as u8;
+                out[1..=bytes.len()].copy_from_slice(bytes);
+            }
+            PayloadEncoding::Pkcs7 => {
+                let npad = width - bytes.len();
+                out[..bytes.len()].copy_from_slice(bytes);
+                out[bytes.len()..].iter_mut().for_each(|b| *b = npad as u8);
+            }
+        }
+        Ok(payload)
+    }
+
+    /// Decodes the first `width` bytes of `payload` back into the original bytes.
+    pub fn decode(&self, payload: &Payload, width: usize) -> Result<Vec<u8>, Error> {
+        if width > PAYLOAD_MAX_BYTES {
+            return Err(Error::PayloadEncodingError(format!(
+                "invalid payload width {}",
+                width
+            )));
+        }
+        let bytes = payload.prefix(width);
+        match self {
+            PayloadEncoding::ZeroPad => {
+                let len = bytes.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+                Ok(bytes[..len].to_vec())
+            }
+            PayloadEncoding::LengthPrefixed => {
+                let len = bytes.first().map_or(0, |&b| b as usize);
+                if width == 0 || len > self.capacity(width) {
+                    return Err(Error::PayloadEncodingError(format!(
+                        "invalid length prefix {} for width {}",
+                        len, width
+                    )));
+                }
+                Ok(bytes[1..=len].to_vec())
+            }
+            PayloadEncoding::Pkcs7 => {
+                let npad = bytes.last().map_or(0, |&b| b as usize);
+                if npad == 0
+                    || npad > width
+                    || !bytes[width - npad..].iter().all(|&b| b as usize == npad)
+                {
+                    return Err(Error::PayloadEncodingError(
+                        "invalid PKCS#7 padding".to_string(),
+                    ));
+                }
+                Ok(bytes[..width - npad].to_vec())
+            }
+        }
+    }
+
+    /// The maximum number of bytes that can be encoded in a payload of `width` bytes.
+    pub fn capacity(&self, width: usize) -> usize {
+        match self {
+            PayloadEncoding::ZeroPad => width,
+            PayloadEncoding::LengthPrefixed => {
+                std::cmp::min(width.saturating_sub(1), u8::MAX as usize)
+            }
+            PayloadEncoding::Pkcs7 => width.saturating_sub(1),
+        }
+    }
+}
@@ -0,0 +1,205 @@
+//! Encoding of typed CSV columns into `Payload`s according to a schema
+use crate::{
+    errors::Error,
+    psi::circuit_psi::{Payload, PrimaryKey, PAYLOAD_SIZE, PRIMARY_KEY_SIZE},
+};
+use fancy_garbling::{BinaryBundle, HasModulus};
+use std::io::BufRead;
+
+/// The maximum number of bytes a `Payload` can hold.
+const PAYLOAD_MAX_BYTES: usize = 64;
+
+/// The type of a payload column.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColumnType {
+    /// A signed integer, stored as 8 little-endian bytes in two's complement.
+    I64,
+    /// An unsigned integer, stored as 8 little-endian bytes.
+    U64,
+    /// An unsigned integer, stored as 4 little-endian bytes.
+    U32,
+    /// A string of at most `n` bytes, padded with zeros. Since decoding strips
+    /// the padding, the string should not end with a zero byte.
+    FixedString(usize),
+}
+
+impl ColumnType {
+    /// The number of bytes taken by a column of this type.
+    pub fn width(&self) -> usize {
+        match self {
+            ColumnType::I64 | ColumnType::U64 => 8,
+            ColumnType::U32 => 4,
+            ColumnType::FixedString(n) => *n,
+        }
+    }
+
+    fn encode(&self, field: &str, out: &mut [u8]) -> Result<(), Error> {
+        let parse_error = |e: &dyn std::fmt::Display| {
+            Error::PayloadEncodingError(format!("cannot parse {:?} as {:?}: {}", field, self, e))
+        };
+        match self {
+            ColumnType::I64 => {
+                let value = field.trim().parse::<i64>().map_err(|e| parse_error(&e))?;
+                out.copy_from_slice(&value.to_le_bytes());
+            }
+            ColumnType::U64 => {
+                let value = field.trim().parse::<u64>().map_err(|e| parse_error(&e))?;
+                out.copy_from_slice(&value.to_le_bytes());
+            }
+            ColumnType::U32 => {
+                let value = field.trim().parse::<u32>().map_err(|e| parse_error(&e))?;
+                out.copy_from_slice(&value.to_le_bytes());
+            }
+            ColumnType::FixedString(n) => {
+                if field.len() > *n {
+                    return Err(parse_error(&format!("longer than {} bytes", n)));
+                }
+                out[..field.len()].copy_from_slice(field.as_bytes());
+                out[field.len()..].iter_mut().for_each(|b| *b = 0);
+            }
+        }
+        Ok(())
+    }
+
+    fn decode(&self, bytes: &[u8]) -> Result<String, Error> {
+        match self {
+            ColumnType::I64 => Ok(i64::from_le_bytes(bytes.try_into().unwrap()).to_string()),
+            ColumnType::U64 => Ok(u64::from_le_bytes(bytes.try_into().unwrap()).to_string()),
+            ColumnType::U32 => Ok(u32::from_le_bytes(bytes.try_into().unwrap()).to_string()),
+            ColumnType::FixedString(_) => {
+                let len = bytes.iter().rposition(|&b| b != 0).map_or(0, |i| i + 1);
+                String::from_utf8(bytes[..len].to_vec()).map_err(|e| {
+                    Error::PayloadEncodingError(format!("invalid string column: {}", e))
+                })
+            }
+        }
+    }
+}
+
+/// The layout of the columns packed into a `Payload`.
+///
+/// Columns are stored one after the other from the first byte of the payload,
+/// so that a schema with a single `U64` column is compatible with payloads built
+/// by `int_vec_block512`. Fields are separated by commas and quoting is not
+/// supported, so string columns cannot contain commas.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PayloadSchema {
+    columns: Vec<ColumnType>,
+}
+
+impl PayloadSchema {
+    /// Create a schema from its `columns`, failing if they do not fit in a payload.
+    pub fn new(columns: Vec<ColumnType>) -> Result<Self, Error> {
+        let schema = PayloadSchema { columns };
+        if schema.width() > PAYLOAD_MAX_BYTES {
+            return Err(Error::PayloadEncodingError(format!(
+                "a schema of {} bytes does not fit in a payload",
+                schema.width()
+            )));
+        }
+        Ok(schema)
+    }
+
+    /// The columns of the schema.
+    pub fn columns(&self) -> &[ColumnType] {
+        &self.columns
+    }
+
+    /// The number of bytes of a payload used by the schema.
+    pub fn width(&self) -> usize {
+        self.columns.iter().map(ColumnType::width).sum()
+    }
+
+    /// Encodes a CSV `row` with one field per column into a payload.
+    pub fn encode_row(&self, row: &str) -> Result<Payload, Error> {
+        let fields: Vec<&str> = if row.is_empty() && self.columns.is_empty() {
+            vec![]
+        } else {
+            row.split(',').collect()
+        };
+        if fields.len() != self.columns.len() {
+            return Err(Error::PayloadEncodingError(format!(
+                "expected {} fields, got {}",
+                self.columns.len(),
+                fields.len()
+            )));
+        }
+        let mut payload = Payload::default();
+        let mut bytes = payload.prefix_mut(self.width());
+        for (column, field) in self.columns.iter().zip(fields) {
+            let (out, rest) = bytes.split_at_mut(column.width());
+            column.encode(field, out)?;
+            bytes = rest;
+        }
+        Ok(payload)
+    }
+
+    /// Decodes a payload back into a CSV row.
+    pub fn decode_row(&self, payload: &Payload) -> Result<String, Error> {
+        let mut bytes = payload.prefix(self.width());
+        let mut fields = Vec::with_capacity(self.columns.len());
+        for column in self.columns.iter() {
+            let (field, rest) = bytes.split_at(column.width());
+            fields.push(column.decode(field)?);
+            bytes = rest;
+        }
+        Ok(fields.join(","))
+    }
+
+    /// Splits the payload bundles of a circuit (e.g. `PrivateIntersectionPayloads`)
+    /// column-wise: the `c`-th output holds the bundle of column `c` of each payload,
+    /// in order, so that gadgets aggregating a column (cf. `fancy_column_sum`) work
+    /// on bundles of the column's width instead of whole payloads.
+    ///
+    /// Only the first `PAYLOAD_SIZE` bytes of a payload enter the circuit, so the
+    /// schema must fit in them.
+    pub fn split_columns<W: Clone + HasModulus>(
+        &self,
+        payloads: &[BinaryBundle<W>],
+    ) -> Result<Vec<Vec<BinaryBundle<W>>>, Error> {
+        if self.width() > PAYLOAD_SIZE {
+            return Err(Error::PayloadEncodingError(format!(
+                "a schema of {} bytes does not fit in the {} bytes of a circuit payload",
+                self.width(),
+                PAYLOAD_SIZE
+            )));
+        }
+        let mut columns = Vec::with_capacity(self.columns.len());
+        let mut offset = 0;
+        for column in self.columns.iter() {
+            let bits = offset * 8..(offset + column.width()) * 8;
+            columns.push(
+                payloads
+                    .iter()
+                    .map(|payload| BinaryBundle::new(payload.wires()[bits.clone()].to_vec()))
+                    .collect(),
+            );
+            offset += column.width();
+        }
+        Ok(columns)
+    }
+
+    /// Reads the primary keys and payloads of a CSV file with no header, whose
+    /// lines are made of an unsigned integer primary key followed by one field per
+    /// column. Empty lines are skipped.
+    pub fn read_csv<R: BufRead>(
+        &self,
+        reader: R,
+    ) -> Result<(Vec<PrimaryKey>, Vec<Payload>), Error> {
+        let mut primary_keys = Vec::new();
+        let mut payloads = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let (key, row) = line.split_once(',').unwrap_or((&line, ""));
+            let key = key.trim().parse::<u64>().map_err(|e| {
+                Error::PayloadEncodingError(format!("invalid primary key {:?}: {}", key, e))
+            })?;
+            primary_keys.push(key.to_le_bytes()[..PRIMARY_KEY_SIZE].to_vec());
+            payloads.push(self.encode_row(row)?);
+        }
+        Ok((primary_keys, payloads))
+    }
+}
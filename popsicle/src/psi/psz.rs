@@ -11,11 +11,32 @@ use crate::{
 use itertools::Itertools;
 use ocelot::oprf::{self, Receiver as OprfReceiver, Sender as OprfSender};
 use rand::{seq::SliceRandom, CryptoRng, Rng, RngCore};
-use scuttlebutt::{cointoss, AbstractChannel, Block, Block512, SemiHonest};
+use scuttlebutt::{cointoss, AbstractChannel, AesPrf, Block, Block512, BlockPrf, SemiHonest};
+use sha2::{Digest, Sha256};
 use std::collections::{HashMap, HashSet};
 
 const NHASHES: usize = 3;
 
+/// Derive the key of the record `item` for the purpose `domain` (e.g.
+/// `b"payload-encryption"`) from the payload it was assigned by
+/// `Sender::send_payloads` or obtained by `Receiver::receive_payloads`.
+///
+/// The payloads should not be used as keys directly: the same payload would
+/// then key every use the application makes of it, and it is one-time padded
+/// with the OPRF outputs during the protocol, so that any structure in how a
+/// key is used may interact with the protocol's own use of these outputs. The
+/// payload instead keys an AES-based PRF evaluated on a hash of `domain` and
+/// `item`, so that keys for different purposes or records are independent.
+pub fn derive_record_key(payload: Block, item: &[u8], domain: &[u8]) -> Block {
+    let mut hasher = Sha256::new();
+    hasher.update((domain.len() as u64).to_le_bytes());
+    hasher.update(domain);
+    hasher.update(item);
+    let digest = hasher.finalize();
+    let tweak = Block::from(<[u8; 16]>::try_from(&digest[..16]).expect("it is 16 bytes long"));
+    AesPrf::new(payload).eval(tweak)
+}
+
 /// Private set intersection sender.
 pub struct Sender {
     oprf: oprf::KkrtSender,
@@ -116,6 +137,24 @@ impl Sender {
         channel.flush()?;
         Ok(payloads)
     }
+
+    /// Run the PSI protocol over `inputs`. Returns a key for each input, derived
+    /// for `domain` with `derive_record_key`, which the receiver gets as well
+    /// for the inputs in the intersection.
+    pub fn send_payload_keys<C: AbstractChannel, RNG: CryptoRng + RngCore>(
+        &mut self,
+        inputs: &[Vec<u8>],
+        domain: &[u8],
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<Vec<Block>, Error> {
+        let payloads = self.send_payloads(inputs, channel, rng)?;
+        Ok(inputs
+            .iter()
+            .zip(payloads)
+            .map(|(item, payload)| derive_record_key(payload, item, domain))
+            .collect())
+    }
 }
 
 impl Receiver {
@@ -221,6 +260,26 @@ impl Receiver {
         Ok(intersection)
     }
 
+    /// Run the PSI protocol over `inputs`, receiving the key of each item in the
+    /// intersection, derived for `domain` with `derive_record_key` (cf.
+    /// `Sender::send_payload_keys`).
+    pub fn receive_payload_keys<C: AbstractChannel, RNG: CryptoRng + RngCore>(
+        &mut self,
+        inputs: &[Vec<u8>],
+        domain: &[u8],
+        channel: &mut C,
+        rng: &mut RNG,
+    ) -> Result<HashMap<Vec<u8>, Block>, Error> {
+        let payloads = self.receive_payloads(inputs, channel, rng)?;
+        Ok(payloads
+            .into_iter()
+            .map(|(item, payload)| {
+                let key = derive_record_key(payload, &item, domain);
+                (item, key)
+            })
+            .collect())
+    }
+
     // Helper to do computation common to both receive and receive_payloads
     fn perform_oprfs<C: AbstractChannel, RNG: CryptoRng + RngCore>(
         &mut self,
@@ -272,7 +331,8 @@ impl SemiHonest for Receiver {}
 mod tests {
     use super::*;
     use crate::utils::rand_vec_vec;
-    use scuttlebutt::{AesRng, Channel};
+    use rand::SeedableRng;
+    use scuttlebutt::{AesRng, Channel, RecordChannel, ReplayChannel};
     use std::{
         io::{BufReader, BufWriter},
         os::unix::net::UnixStream,
@@ -307,6 +367,49 @@ mod tests {
         assert_eq!(intersection.len(), SET_SIZE);
     }
 
+    #[test]
+    fn test_psi_replay_sender_transcript() {
+        let mut rng = AesRng::new();
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let sender_inputs = rand_vec_vec(1 << 10, ITEM_SIZE, &mut rng);
+        let mut receiver_inputs = sender_inputs[..1 << 9].to_vec();
+        receiver_inputs.extend(rand_vec_vec(1 << 9, ITEM_SIZE, &mut rng));
+        let receiver_seed = rng.gen::<Block>();
+        let handle = std::thread::spawn(move || {
+            let mut rng = AesRng::new();
+            let reader = BufReader::new(sender.try_clone().unwrap());
+            let writer = BufWriter::new(sender);
+            let mut channel = RecordChannel::new(Channel::new(reader, writer));
+            let mut psi = Sender::init(&mut channel, &mut rng).unwrap();
+            psi.send(&sender_inputs, &mut channel, &mut rng).unwrap();
+            channel.finish().1
+        });
+        let mut rng = AesRng::from_seed(receiver_seed);
+        let reader = BufReader::new(receiver.try_clone().unwrap());
+        let writer = BufWriter::new(receiver);
+        let mut channel = Channel::new(reader, writer);
+        let mut psi = Receiver::init(&mut channel, &mut rng).unwrap();
+        let intersection = psi
+            .receive(&receiver_inputs, &mut channel, &mut rng)
+            .unwrap();
+        let transcript = handle.join().unwrap();
+
+        // Running the receiver again with the same seed against the recorded
+        // sender gives the same result, without a live sender
+        let mut rng = AesRng::from_seed(receiver_seed);
+        let mut channel = ReplayChannel::new(&transcript);
+        let mut psi = Receiver::init(&mut channel, &mut rng).unwrap();
+        let replayed = psi
+            .receive(&receiver_inputs, &mut channel, &mut rng)
+            .unwrap();
+        assert!(
+            channel.is_finished(),
+            "The transcript was not fully replayed"
+        );
+        assert_eq!(replayed, intersection);
+        assert_eq!(replayed.len(), 1 << 9);
+    }
+
     #[test]
     fn test_payloads() {
         let mut rng = AesRng::new();
@@ -352,4 +455,59 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_payload_keys() {
+        let mut rng = AesRng::new();
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let domain = b"test-payload-keys";
+
+        let sender_inputs = rand_vec_vec(1 << 10, ITEM_SIZE, &mut rng);
+        let mut receiver_inputs = sender_inputs[..1 << 9].to_vec();
+        receiver_inputs.extend(rand_vec_vec(1 << 9, ITEM_SIZE, &mut rng));
+
+        let thread_sender_inputs = sender_inputs.clone();
+        let handle = std::thread::spawn(move || {
+            let mut rng = AesRng::new();
+            let reader = BufReader::new(sender.try_clone().unwrap());
+            let writer = BufWriter::new(sender);
+            let mut channel = Channel::new(reader, writer);
+            let mut psi = Sender::init(&mut channel, &mut rng).unwrap();
+            psi.send_payload_keys(&thread_sender_inputs, domain, &mut channel, &mut rng)
+                .unwrap()
+        });
+
+        let mut rng = AesRng::new();
+        let reader = BufReader::new(receiver.try_clone().unwrap());
+        let writer = BufWriter::new(receiver);
+        let mut channel = Channel::new(reader, writer);
+        let mut psi = Receiver::init(&mut channel, &mut rng).unwrap();
+        let receiver_keys = psi
+            .receive_payload_keys(&receiver_inputs, domain, &mut channel, &mut rng)
+            .unwrap();
+        let sender_keys = handle.join().unwrap();
+
+        assert_eq!(receiver_keys.len(), 1 << 9);
+        for (item, key) in sender_inputs.iter().zip(sender_keys.iter()) {
+            if let Some(other_key) = receiver_keys.get(item) {
+                assert_eq!(key, other_key);
+            }
+        }
+        // Keys differ across records
+        assert_eq!(sender_keys.iter().collect::<HashSet<_>>().len(), 1 << 10);
+    }
+
+    #[test]
+    fn test_derive_record_key() {
+        let mut rng = AesRng::new();
+        let payload = rng.gen::<Block>();
+        let (item, item_) = (b"record".as_slice(), b"record_".as_slice());
+        let key = derive_record_key(payload, item, b"domain");
+        // Deterministic per record, and separated across records, domains and payloads
+        assert_eq!(derive_record_key(payload, item, b"domain"), key);
+        assert_ne!(derive_record_key(payload, item_, b"domain"), key);
+        assert_ne!(derive_record_key(payload, item, b"domain_"), key);
+        assert_ne!(derive_record_key(rng.gen(), item, b"domain"), key);
+        assert_ne!(key, payload);
+    }
 }
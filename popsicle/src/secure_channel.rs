@@ -0,0 +1,367 @@
+//! An authenticated, encrypted session over an untrusted transport, e.g. a
+//! connection routed through a relay.
+//!
+//! Both parties hold a pre-shared key of `KEY_SIZE` bytes, agreed upon out of
+//! band. During the handshake, each party sends a fresh random nonce, and the
+//! keys of both directions are derived from the pre-shared key and both nonces,
+//! so that every session uses fresh keys. Each party then sends an encrypted
+//! confirmation, so that parties with different pre-shared keys fail the
+//! handshake rather than the protocol run on top of it.
+//!
+//! Data is sent in records of at most `RECORD_SIZE` bytes encrypted with
+//! AES-256-GCM, each written as its plaintext length followed by its
+//! ciphertext, the nonce of a record being its index in its direction. The
+//! transport thus only sees the length and timing of the records, and cannot
+//! modify, reorder, drop or replay them without the other party failing to
+//! decrypt them.
+//!
+//! A `SecureChannel` is an `AbstractChannel`, so that the existing protocols
+//! run unchanged on top of it. Written data is buffered until the channel is
+//! flushed or a record is full, as the protocols already flush the channel
+//! before waiting for the other party.
+
+use crate::errors::Error;
+use aes_gcm::{
+    aead::{Aead, KeyInit},
+    Aes256Gcm, Key, Nonce,
+};
+use rand::{CryptoRng, RngCore};
+use scuttlebutt::AbstractChannel;
+use sha2::{Digest, Sha256};
+use std::{
+    io::{ErrorKind, Result},
+    sync::{Arc, Mutex},
+};
+
+/// The size of the pre-shared keys
+pub const KEY_SIZE: usize = 32;
+/// The maximum number of plaintext bytes in a record
+pub const RECORD_SIZE: usize = 1 << 16;
+
+const HANDSHAKE_NONCE_SIZE: usize = 32;
+const NONCE_SIZE: usize = 12;
+const TAG_SIZE: usize = 16;
+const CONFIRMATION: &[u8] = b"popsicle secure channel confirmation";
+
+/// A channel encrypting and authenticating everything sent over another
+/// channel, e.g. one routed through an untrusted relay.
+///
+/// Clones share the same session, as the circuit PSI parties require. Like a
+/// `BufWriter`, the buffered data is flushed when the last clone is dropped,
+/// ignoring errors.
+pub struct SecureChannel<C: AbstractChannel>(Arc<Mutex<InternalSecureChannel<C>>>);
+
+impl<C: AbstractChannel> Clone for SecureChannel<C> {
+    fn clone(&self) -> Self {
+        Self(self.0.clone())
+    }
+}
+
+struct InternalSecureChannel<C: AbstractChannel> {
+    channel: C,
+    send_cipher: Aes256Gcm,
+    receive_cipher: Aes256Gcm,
+    nsent: u64,
+    nreceived: u64,
+    write_buf: Vec<u8>,
+    read_buf: Vec<u8>,
+    read_pos: usize,
+}
+
+// The nonce of the `counter`-th record of a direction
+fn record_nonce(counter: u64) -> [u8; NONCE_SIZE] {
+    let mut nonce = [0u8; NONCE_SIZE];
+    nonce[NONCE_SIZE - 8..].copy_from_slice(&counter.to_be_bytes());
+    nonce
+}
+
+// The key of a direction of the session, derived from the pre-shared key and
+// both parties' handshake nonces
+fn direction_key(
+    psk: &[u8; KEY_SIZE],
+    label: &[u8],
+    initiator_nonce: &[u8],
+    responder_nonce: &[u8],
+) -> Aes256Gcm {
+    let mut hasher = Sha256::new();
+    hasher.update(label);
+    hasher.update(psk);
+    hasher.update(initiator_nonce);
+    hasher.update(responder_nonce);
+    Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(&hasher.finalize()))
+}
+
+impl<C: AbstractChannel> InternalSecureChannel<C> {
+    // Encrypt and send the buffered data as a record, if there is any
+    fn write_record(&mut self) -> Result<()> {
+        if self.write_buf.is_empty() {
+            return Ok(());
+        }
+        let nonce = record_nonce(self.nsent);
+        let ciphertext = self
+            .send_cipher
+            .encrypt(Nonce::from_slice(&nonce), self.write_buf.as_ref())
+            .map_err(|_| std::io::Error::new(ErrorKind::Other, "record encryption failed"))?;
+        self.channel.write_u32(self.write_buf.len() as u32)?;
+        self.channel.write_bytes(&ciphertext)?;
+        self.nsent += 1;
+        self.write_buf.clear();
+        Ok(())
+    }
+
+    // Receive and decrypt the next record, failing with `ErrorKind::InvalidData`
+    // if it does not authenticate
+    fn read_record(&mut self) -> Result<()> {
+        let invalid = || std::io::Error::new(ErrorKind::InvalidData, "invalid record");
+        let len = self.channel.read_u32()? as usize;
+        if len == 0 || len > RECORD_SIZE {
+            return Err(invalid());
+        }
+        let ciphertext = self.channel.read_vec(len + TAG_SIZE)?;
+        let nonce = record_nonce(self.nreceived);
+        self.read_buf = self
+            .receive_cipher
+            .decrypt(Nonce::from_slice(&nonce), ciphertext.as_ref())
+            .map_err(|_| invalid())?;
+        self.read_pos = 0;
+        self.nreceived += 1;
+        Ok(())
+    }
+}
+
+impl<C: AbstractChannel> Drop for InternalSecureChannel<C> {
+    fn drop(&mut self) {
+        if self.write_record().is_ok() {
+            let _ = self.channel.flush();
+        }
+    }
+}
+
+impl<C: AbstractChannel> SecureChannel<C> {
+    /// Establish a session over `channel` with the party calling
+    /// `SecureChannel::respond` with the same pre-shared key `psk`.
+    ///
+    /// Fails with `Error::AESGCMError` if the parties' pre-shared keys differ.
+    pub fn initiate<RNG: RngCore + CryptoRng>(
+        channel: C,
+        psk: &[u8; KEY_SIZE],
+        rng: &mut RNG,
+    ) -> std::result::Result<Self, Error> {
+        Self::handshake(channel, psk, rng, true)
+    }
+
+    /// Establish a session over `channel` with the party calling
+    /// `SecureChannel::initiate` (cf. `SecureChannel::initiate`).
+    pub fn respond<RNG: RngCore + CryptoRng>(
+        channel: C,
+        psk: &[u8; KEY_SIZE],
+        rng: &mut RNG,
+    ) -> std::result::Result<Self, Error> {
+        Self::handshake(channel, psk, rng, false)
+    }
+
+    fn handshake<RNG: RngCore + CryptoRng>(
+        mut channel: C,
+        psk: &[u8; KEY_SIZE],
+        rng: &mut RNG,
+        initiator: bool,
+    ) -> std::result::Result<Self, Error> {
+        let mut nonce = [0u8; HANDSHAKE_NONCE_SIZE];
+        rng.fill_bytes(&mut nonce);
+        channel.write_bytes(&nonce)?;
+        channel.flush()?;
+        let mut other = [0u8; HANDSHAKE_NONCE_SIZE];
+        channel.read_bytes(&mut other)?;
+
+        let (initiator_nonce, responder_nonce) = if initiator {
+            (&nonce, &other)
+        } else {
+            (&other, &nonce)
+        };
+        let to_responder = direction_key(psk, b"initiator", initiator_nonce, responder_nonce);
+        let to_initiator = direction_key(psk, b"responder", initiator_nonce, responder_nonce);
+        let (send_cipher, receive_cipher) = if initiator {
+            (to_responder, to_initiator)
+        } else {
+            (to_initiator, to_responder)
+        };
+        let mut secure = SecureChannel(Arc::new(Mutex::new(InternalSecureChannel {
+            channel,
+            send_cipher,
+            receive_cipher,
+            nsent: 0,
+            nreceived: 0,
+            write_buf: Vec::new(),
+            read_buf: Vec::new(),
+            read_pos: 0,
+        })));
+
+        secure.write_bytes(CONFIRMATION)?;
+        secure.flush()?;
+        let mut confirmation = [0u8; CONFIRMATION.len()];
+        match secure.read_bytes(&mut confirmation) {
+            Ok(()) if confirmation == CONFIRMATION => Ok(secure),
+            Ok(()) => Err(Error::AESGCMError(aes_gcm::Error)),
+            Err(e) if e.kind() == ErrorKind::InvalidData => Err(Error::AESGCMError(aes_gcm::Error)),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+impl<C: AbstractChannel> AbstractChannel for SecureChannel<C> {
+    fn write_bytes(&mut self, mut bytes: &[u8]) -> Result<()> {
+        let mut int = self.0.lock().unwrap();
+        while !bytes.is_empty() {
+            let n = (RECORD_SIZE - int.write_buf.len()).min(bytes.len());
+            int.write_buf.extend_from_slice(&bytes[..n]);
+            bytes = &bytes[n..];
+            if int.write_buf.len() == RECORD_SIZE {
+                int.write_record()?;
+            }
+        }
+        Ok(())
+    }
+
+    fn read_bytes(&mut self, bytes: &mut [u8]) -> Result<()> {
+        let mut int = self.0.lock().unwrap();
+        let mut nread = 0;
+        while nread < bytes.len() {
+            if int.read_pos == int.read_buf.len() {
+                int.read_record()?;
+            }
+            let n = (int.read_buf.len() - int.read_pos).min(bytes.len() - nread);
+            let start = int.read_pos;
+            bytes[nread..nread + n].copy_from_slice(&int.read_buf[start..start + n]);
+            int.read_pos += n;
+            nread += n;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        let mut int = self.0.lock().unwrap();
+        int.write_record()?;
+        int.channel.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+    use scuttlebutt::{AesRng, SyncChannel};
+    use std::{
+        io::{BufReader, BufWriter, Read, Write},
+        net::Shutdown,
+        os::unix::net::UnixStream,
+        thread,
+    };
+
+    type UnixSyncChannel = SyncChannel<BufReader<UnixStream>, BufWriter<UnixStream>>;
+
+    fn sync_channel(stream: UnixStream) -> UnixSyncChannel {
+        let reader = BufReader::new(stream.try_clone().unwrap());
+        let writer = BufWriter::new(stream);
+        SyncChannel::new(reader, writer)
+    }
+
+    // Forward everything read from `from` to `to`, flipping the byte at offset
+    // `tamper` if any, and return the forwarded bytes
+    fn forward(
+        mut from: UnixStream,
+        mut to: UnixStream,
+        tamper: Option<usize>,
+    ) -> thread::JoinHandle<Vec<u8>> {
+        thread::spawn(move || {
+            let mut seen = Vec::new();
+            let mut buf = [0u8; 4096];
+            loop {
+                let n = match from.read(&mut buf) {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => n,
+                };
+                if let Some(i) = tamper.filter(|i| (seen.len()..seen.len() + n).contains(i)) {
+                    buf[i - seen.len()] ^= 1;
+                }
+                seen.extend_from_slice(&buf[..n]);
+                if to.write_all(&buf[..n]).is_err() {
+                    break;
+                }
+            }
+            let _ = to.shutdown(Shutdown::Write);
+            seen
+        })
+    }
+
+    // Establish a session between two parties with pre-shared keys `psk_a` and
+    // `psk_b` through a relay, the responder sending `message` and the initiator
+    // echoing it. Returns the echo received by the responder and everything the
+    // relay forwarded.
+    fn exchange(
+        psk_a: [u8; KEY_SIZE],
+        psk_b: [u8; KEY_SIZE],
+        message: &[u8],
+        tamper: Option<usize>,
+    ) -> (std::result::Result<Vec<u8>, Error>, Vec<u8>) {
+        let (a, relay_a) = UnixStream::pair().unwrap();
+        let (b, relay_b) = UnixStream::pair().unwrap();
+        let a_to_b = forward(
+            relay_a.try_clone().unwrap(),
+            relay_b.try_clone().unwrap(),
+            tamper,
+        );
+        let b_to_a = forward(relay_b, relay_a, None);
+
+        let len = message.len();
+        let initiator = thread::spawn(move || -> std::result::Result<(), Error> {
+            let mut channel = SecureChannel::initiate(sync_channel(a), &psk_a, &mut AesRng::new())?;
+            let received = channel.read_vec(len)?;
+            channel.write_bytes(&received)?;
+            channel.flush()?;
+            Ok(())
+        });
+        let echo = SecureChannel::respond(sync_channel(b), &psk_b, &mut AesRng::new()).and_then(
+            |mut channel| {
+                channel.write_bytes(message)?;
+                channel.flush()?;
+                Ok(channel.read_vec(len)?)
+            },
+        );
+        let _ = initiator.join().unwrap();
+        let mut seen = a_to_b.join().unwrap();
+        seen.extend(b_to_a.join().unwrap());
+        (echo, seen)
+    }
+
+    #[test]
+    fn test_relay_sees_ciphertext() {
+        let psk = AesRng::new().gen::<[u8; KEY_SIZE]>();
+        // A message spanning several records
+        let mut message = b"a secret the relay must not see".repeat(5000);
+        message.truncate(3 * RECORD_SIZE / 2);
+        let (echo, seen) = exchange(psk, psk, &message, None);
+        assert_eq!(echo.unwrap(), message);
+        assert!(!seen.windows(31).any(|w| w == &message[..31]));
+        assert!(!seen.windows(CONFIRMATION.len()).any(|w| w == CONFIRMATION));
+    }
+
+    #[test]
+    fn test_different_keys_fail() {
+        let mut rng = AesRng::new();
+        let (echo, _) = exchange(rng.gen(), rng.gen(), b"hello", None);
+        assert!(matches!(echo, Err(Error::AESGCMError(_))));
+    }
+
+    #[test]
+    fn test_tampered_record_fails() {
+        let psk = AesRng::new().gen::<[u8; KEY_SIZE]>();
+        // Past the initiator's handshake nonce and confirmation record, i.e. in
+        // the echo
+        let offset = HANDSHAKE_NONCE_SIZE + 4 + CONFIRMATION.len() + TAG_SIZE + 10;
+        let (echo, _) = exchange(psk, psk, b"hello, world", Some(offset));
+        match echo {
+            Err(Error::IoError(e)) => assert_eq!(e.kind(), ErrorKind::InvalidData),
+            other => panic!("the tampered record was accepted: {:?}", other),
+        }
+    }
+}
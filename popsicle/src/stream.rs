@@ -0,0 +1,178 @@
+//! Streaming authenticated encryption of arbitrary-length payloads over a
+//! channel.
+//!
+//! The payload is split into chunks of `CHUNK_SIZE` bytes which are encrypted
+//! with AES-256-GCM one at a time, so that neither party needs to hold the whole
+//! payload in memory. Each chunk is sent as its plaintext length followed by its
+//! ciphertext, and the final chunk is the first one shorter than `CHUNK_SIZE`
+//! bytes, possibly empty.
+//!
+//! The nonce of each chunk is its index in the stream followed by a byte set
+//! only for the final chunk (as in the STREAM construction of Hoang, Reyhanitabar,
+//! Rogaway and Vizár), so that chunks cannot be reordered, dropped or truncated
+//! without failing authentication. Since the nonces are deterministic, a key
+//! must only be used to encrypt a single stream.
+
+use crate::errors::Error;
+use aes_gcm::{
+    aead::{Aead, KeyInit},
+    Aes256Gcm, Key, Nonce,
+};
+use scuttlebutt::AbstractChannel;
+use std::io::{ErrorKind, Read, Write};
+
+/// The size of the AES-256-GCM keys encrypting a stream
+pub const KEY_SIZE: usize = 32;
+/// The number of plaintext bytes in every chunk but the final one
+pub const CHUNK_SIZE: usize = 1 << 16;
+
+const NONCE_SIZE: usize = 12;
+const TAG_SIZE: usize = 16;
+
+// The nonce of the `counter`-th chunk of the stream
+fn chunk_nonce(counter: u64, last: bool) -> [u8; NONCE_SIZE] {
+    let mut nonce = [0u8; NONCE_SIZE];
+    nonce[3..NONCE_SIZE - 1].copy_from_slice(&counter.to_be_bytes());
+    nonce[NONCE_SIZE - 1] = last as u8;
+    nonce
+}
+
+// Read from `reader` until `buf` is full or the end of the stream is reached,
+// returning the number of bytes read
+fn read_chunk<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<usize, Error> {
+    let mut n = 0;
+    while n < buf.len() {
+        match reader.read(&mut buf[n..]) {
+            Ok(0) => break,
+            Ok(m) => n += m,
+            Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(n)
+}
+
+/// Encrypt everything read from `reader` under `key` and send it over `channel`
+/// chunk by chunk, returning the number of plaintext bytes sent.
+pub fn channel_encrypt_stream<R: Read, C: AbstractChannel>(
+    key: &[u8; KEY_SIZE],
+    reader: &mut R,
+    channel: &mut C,
+) -> Result<u64, Error> {
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+    let mut buf = vec![0u8; CHUNK_SIZE];
+    let mut nbytes = 0;
+    for counter in 0.. {
+        let len = read_chunk(reader, &mut buf)?;
+        let last = len < CHUNK_SIZE;
+        let nonce = chunk_nonce(counter, last);
+        let ciphertext = cipher.encrypt(Nonce::from_slice(&nonce), &buf[..len])?;
+        channel.write_u32(len as u32)?;
+        channel.write_bytes(&ciphertext)?;
+        nbytes += len as u64;
+        if last {
+            break;
+        }
+    }
+    channel.flush()?;
+    Ok(nbytes)
+}
+
+/// Receive a stream sent by `channel_encrypt_stream` over `channel`, decrypt it
+/// under `key` and write it to `writer` chunk by chunk, returning the number of
+/// plaintext bytes written.
+///
+/// Fails with `Error::AESGCMError` if a chunk does not authenticate, in which
+/// case the chunks written so far must be discarded.
+pub fn channel_decrypt_stream<C: AbstractChannel, W: Write>(
+    key: &[u8; KEY_SIZE],
+    channel: &mut C,
+    writer: &mut W,
+) -> Result<u64, Error> {
+    let cipher = Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key));
+    let mut nbytes = 0;
+    for counter in 0.. {
+        let len = channel.read_u32()? as usize;
+        if len > CHUNK_SIZE {
+            return Err(Error::AESGCMError(aes_gcm::Error));
+        }
+        let last = len < CHUNK_SIZE;
+        let ciphertext = channel.read_vec(len + TAG_SIZE)?;
+        let nonce = chunk_nonce(counter, last);
+        let plaintext = cipher.decrypt(Nonce::from_slice(&nonce), ciphertext.as_ref())?;
+        writer.write_all(&plaintext)?;
+        nbytes += len as u64;
+        if last {
+            break;
+        }
+    }
+    writer.flush()?;
+    Ok(nbytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, RngCore};
+    use scuttlebutt::{AesRng, Channel};
+    use std::{io::Cursor, thread};
+
+    // Round trip `payload` through an encrypted stream over an in-memory channel,
+    // flipping the ciphertext byte at `flip` if any
+    fn round_trip(payload: &[u8], flip: Option<usize>) -> Result<Vec<u8>, Error> {
+        let key = AesRng::new().gen::<[u8; KEY_SIZE]>();
+        let mut sent = Vec::new();
+        let mut channel = Channel::new(Cursor::new(vec![]), &mut sent);
+        channel_encrypt_stream(&key, &mut Cursor::new(payload), &mut channel)?;
+        drop(channel);
+        if let Some(i) = flip {
+            sent[i] ^= 1;
+        }
+        let mut received = Vec::new();
+        let mut channel = Channel::new(Cursor::new(sent), std::io::sink());
+        channel_decrypt_stream(&key, &mut channel, &mut received)?;
+        Ok(received)
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut rng = AesRng::new();
+        // A multi-megabyte payload ending with a partial chunk, and one ending
+        // exactly on a chunk boundary
+        for len in [3 * (1 << 20) + 12345, 2 * CHUNK_SIZE, 0] {
+            let mut payload = vec![0u8; len];
+            rng.fill_bytes(&mut payload);
+            assert_eq!(round_trip(&payload, None).unwrap(), payload);
+        }
+    }
+
+    #[test]
+    fn test_round_trip_over_unix_channel() {
+        let mut rng = AesRng::new();
+        let key = rng.gen::<[u8; KEY_SIZE]>();
+        let mut payload = vec![0u8; 5 * CHUNK_SIZE / 2];
+        rng.fill_bytes(&mut payload);
+        let (mut sender, mut receiver) = scuttlebutt::unix_channel_pair();
+        let handle = thread::spawn(move || {
+            let mut received = Vec::new();
+            channel_decrypt_stream(&key, &mut receiver, &mut received).unwrap();
+            received
+        });
+        let nbytes = channel_encrypt_stream(&key, &mut payload.as_slice(), &mut sender).unwrap();
+        assert_eq!(nbytes, payload.len() as u64);
+        assert_eq!(handle.join().unwrap(), payload);
+    }
+
+    #[test]
+    fn test_flipped_byte_fails() {
+        let mut rng = AesRng::new();
+        let mut payload = vec![0u8; 3 * CHUNK_SIZE + 100];
+        rng.fill_bytes(&mut payload);
+        // Flip a byte in the ciphertext of the second chunk
+        let flip = 2 * 4 + CHUNK_SIZE + TAG_SIZE + 17;
+        assert!(matches!(
+            round_trip(&payload, Some(flip)),
+            Err(Error::AESGCMError(_))
+        ));
+    }
+}
@@ -1,8 +1,10 @@
 //! Util mostly in support of cuckoo hashing.
 
+use crate::psi::circuit_psi::PrimaryKey;
 use rand::{CryptoRng, Rng};
 use scuttlebutt::{AesHash, Block};
 use sha2::{Digest, Sha256};
+use std::collections::HashSet;
 
 /// Compress an arbitrary vector into a 128-bit chunk, leaving the final 8-bits
 /// as zero. We need to leave 8 bits free in order to add in the hash index when
@@ -31,6 +33,16 @@ pub fn compress_and_hash_inputs(inputs: &[Vec<u8>], key: Block) -> Vec<Block> {
         .collect::<Vec<Block>>()
 }
 
+/// Compute the intersection of `a` and `b` in the clear, returning the keys of
+/// `a` which also appear in `b`, in the order they appear in `a`.
+///
+/// This is meant for testing and for validating the output of a PSI protocol
+/// against a known reference.
+pub fn reference_intersection(a: &[PrimaryKey], b: &[PrimaryKey]) -> Vec<PrimaryKey> {
+    let b: HashSet<&PrimaryKey> = b.iter().collect();
+    a.iter().filter(|key| b.contains(key)).cloned().collect()
+}
+
 #[allow(dead_code)]
 /// used in tests
 pub fn rand_vec<RNG: CryptoRng + Rng>(n: usize, rng: &mut RNG) -> Vec<u8> {
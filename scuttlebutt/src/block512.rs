@@ -1,5 +1,5 @@
 //! Defines a 512-bit value.
-use crate::Block;
+use crate::{AesRng, Block};
 use std::hash::Hash;
 
 /// A 512-bit value.
@@ -19,6 +19,15 @@ use std::hash::Hash;
 pub struct Block512(pub(crate) [Block; 4]);
 
 impl Block512 {
+    /// Sample a uniformly random value from `rng`.
+    ///
+    /// This encrypts the four counter blocks of the value in a single call to AES,
+    /// whereas `rng.gen::<Block512>()` draws four `Block`s one at a time.
+    #[inline]
+    pub fn random(rng: &mut AesRng) -> Self {
+        Block512(rng.random_bits_custom_size::<4>())
+    }
+
     /// Return the first `n` bytes, where `n` must be `<= 64`.
     #[inline]
     pub fn prefix(&self, n: usize) -> &[u8] {
@@ -30,6 +39,51 @@ impl Block512 {
     pub fn prefix_mut(&mut self, n: usize) -> &mut [u8] {
         &mut self.as_mut()[0..n]
     }
+
+    /// Split into four `Block`s, e.g. to transfer the value with a `Block`-based OT.
+    ///
+    /// Chunk `i` holds bytes `16 * i..16 * (i + 1)` of `as_ref`, so that the
+    /// first chunk holds the least significant bytes when the value is read as a
+    /// little-endian integer. `from_chunks` puts the chunks back together.
+    #[inline]
+    pub fn chunks(&self) -> [Block; 4] {
+        self.0
+    }
+
+    /// Recombine four `Block`s, as output by `chunks`.
+    #[inline]
+    pub fn from_chunks(chunks: [Block; 4]) -> Self {
+        Self(chunks)
+    }
+}
+
+impl PartialOrd for Block512 {
+    #[inline]
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Block512 {
+    /// Compare the little-endian byte representations lexicographically.
+    #[inline]
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.as_ref().cmp(other.as_ref())
+    }
+}
+
+impl subtle::ConstantTimeEq for Block512 {
+    /// Compare in constant time, e.g. to check MACs or commitment openings. The
+    /// derived `PartialEq` may return as soon as a difference is found.
+    #[inline]
+    fn ct_eq(&self, other: &Self) -> subtle::Choice {
+        self.0
+            .iter()
+            .zip(other.0.iter())
+            .fold(subtle::Choice::from(1), |acc, (x, y)| {
+                acc & subtle::ConstantTimeEq::ct_eq(x, y)
+            })
+    }
 }
 
 impl AsMut<[u8]> for Block512 {
@@ -171,3 +225,57 @@ impl<'de> Deserialize<'de> for Block512 {
         Ok(Block512::from(helper.blocks))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, SeedableRng};
+    use subtle::ConstantTimeEq;
+
+    #[test]
+    fn test_random() {
+        let seed = rand::random::<Block>();
+        let mut rng = AesRng::from_seed(seed);
+        let mut rng_ = AesRng::from_seed(seed);
+        let xs = (0..16)
+            .map(|_| Block512::random(&mut rng))
+            .collect::<Vec<_>>();
+        let ys = (0..16)
+            .map(|_| Block512::random(&mut rng_))
+            .collect::<Vec<_>>();
+        assert_eq!(xs, ys);
+        // Successive draws differ
+        for (i, x) in xs.iter().enumerate() {
+            assert!(xs[i + 1..].iter().all(|y| y != x));
+        }
+    }
+
+    #[test]
+    fn test_chunks_roundtrip() {
+        let mut rng = AesRng::new();
+        let x = rng.gen::<Block512>();
+        let chunks = x.chunks();
+        assert_eq!(Block512::from_chunks(chunks), x);
+        for (i, chunk) in chunks.iter().enumerate() {
+            assert_eq!(chunk.as_ref(), &x.as_ref()[16 * i..16 * (i + 1)]);
+        }
+    }
+
+    #[test]
+    fn test_ct_eq() {
+        let mut rng = AesRng::new();
+        for _ in 0..16 {
+            let (x, y) = (rng.gen::<Block>(), rng.gen::<Block>());
+            assert_eq!(bool::from(x.ct_eq(&y)), x == y);
+            assert!(bool::from(x.ct_eq(&x)));
+
+            let (x, mut y) = (rng.gen::<Block512>(), rng.gen::<Block512>());
+            assert_eq!(bool::from(x.ct_eq(&y)), x == y);
+            assert!(bool::from(x.ct_eq(&x)));
+            // Differ in a single byte of the last chunk
+            y = x;
+            y.as_mut()[63] ^= 1;
+            assert!(!bool::from(x.ct_eq(&y)));
+        }
+    }
+}
@@ -0,0 +1,532 @@
+//! Implementation of a bloom filter.
+
+use sha2::{Digest, Sha256};
+use siphasher::{
+    sip::SipHasher24,
+    sip128::{Hasher128, SipHasher24 as SipHasher128},
+};
+use std::hash::Hasher;
+
+/// The number of bins stored in each word of the filter.
+const BINS_PER_WORD: usize = 64;
+/// The default number of bytes of the hash used to compute a bin.
+const DEFAULT_BIN_BYTES: usize = 8;
+/// The maximum number of bytes of the hash used to compute a bin.
+const MAX_BIN_BYTES: usize = 16;
+
+/// The hash function used to map items to bins.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BloomHashKind {
+    /// SHA-256, which is safe to use when the inserted items or queries may be
+    /// chosen adversarially.
+    #[default]
+    Sha256,
+    /// SipHash-2-4 keyed with `key`, which is much faster than SHA-256.
+    ///
+    /// SipHash is only a PRF: anyone who learns `key` can craft items which
+    /// collide on the same bins. Only use this for filters whose contents and
+    /// key never leave the party, e.g. for local deduplication.
+    SipHash {
+        /// The SipHash key.
+        key: [u8; 16],
+    },
+}
+
+/// Simple implementation of a Bloom Filter, which is guaranteed to report that an
+/// item is in the set if it was inserted, but reports that an item is in the set with
+/// probability `rate` (settable) if it was not inserted.
+///
+/// The bins are packed into 64-bit words, so that counting the set bins only takes
+/// one hardware popcount per word.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BloomFilter {
+    words: Vec<u64>,
+    nbins: usize,
+    nhashes: usize,
+    hasher: BloomHashKind,
+    bin_bytes: usize,
+}
+
+impl BloomFilter {
+    /// Create a new BloomFilter with `size` entries, using `rate` to determine
+    /// the false positive rate.
+    pub fn new(size: usize, rate: f64) -> Self {
+        let nbins = Self::compute_nbins(size, rate);
+        let nhashes = Self::compute_nhashes(size, nbins);
+        Self::from_parameters(nbins, nhashes)
+    }
+
+    /// Create a new BloomFilter with `nbins` bins and `nhashes` hash functions.
+    pub fn from_parameters(nbins: usize, nhashes: usize) -> Self {
+        BloomFilter {
+            words: vec![0; Self::nwords(nbins)],
+            nbins,
+            nhashes,
+            hasher: BloomHashKind::default(),
+            bin_bytes: DEFAULT_BIN_BYTES,
+        }
+    }
+
+    /// Use `hasher` to map items to bins. This should be set before inserting
+    /// any item, as items already inserted are not rehashed.
+    pub fn with_hasher(mut self, hasher: BloomHashKind) -> Self {
+        self.hasher = hasher;
+        self
+    }
+
+    /// Get the hash function used to map items to bins.
+    pub fn hasher(&self) -> BloomHashKind {
+        self.hasher
+    }
+
+    /// Use the first `nbytes` bytes of the hash of an item, between 8 and 16, to
+    /// compute its bins. This should be set before inserting any item, as items
+    /// already inserted are not rehashed.
+    ///
+    /// Reducing an `8 * nbytes`-bit hash modulo the number of bins favours the
+    /// lower bins with a bias of about `len() / 2^(8 * nbytes)`, which is only
+    /// negligible with the default of 8 bytes for filters much smaller than 2^64
+    /// bins.
+    pub fn with_bin_bytes(mut self, nbytes: usize) -> Self {
+        assert!(
+            (DEFAULT_BIN_BYTES..=MAX_BIN_BYTES).contains(&nbytes),
+            "the number of hash bytes per bin must be between {} and {}",
+            DEFAULT_BIN_BYTES,
+            MAX_BIN_BYTES
+        );
+        self.bin_bytes = nbytes;
+        self
+    }
+
+    /// Get the number of bytes of the hash of an item used to compute its bins.
+    pub fn bin_bytes(&self) -> usize {
+        self.bin_bytes
+    }
+
+    /// Compute the number of bins needed for `size` entries and false positive
+    /// rate `rate`.
+    pub fn compute_nbins(size: usize, rate: f64) -> usize {
+        ((-(size as f64) * rate.ln()) / std::f64::consts::LN_2.powi(2)).ceil() as usize
+    }
+
+    /// Compute the optimal number of hash functions for `size` entries and `nbins` bins.
+    pub fn compute_nhashes(size: usize, nbins: usize) -> usize {
+        ((nbins as f64 / size as f64) * std::f64::consts::LN_2).ceil() as usize
+    }
+
+    fn nwords(nbins: usize) -> usize {
+        nbins.div_ceil(BINS_PER_WORD)
+    }
+
+    /// Get the number of bins in this BloomFilter.
+    pub fn len(&self) -> usize {
+        self.nbins
+    }
+
+    /// Check whether the BloomFilter has no bins.
+    pub fn is_empty(&self) -> bool {
+        self.nbins == 0
+    }
+
+    /// Get the number of hash functions in this BloomFilter.
+    pub fn nhashes(&self) -> usize {
+        self.nhashes
+    }
+
+    /// Get the value of bin `bin`.
+    pub fn get(&self, bin: usize) -> bool {
+        (self.words[bin / BINS_PER_WORD] >> (bin % BINS_PER_WORD)) & 1 == 1
+    }
+
+    /// Set the value of bin `bin`.
+    pub fn set(&mut self, bin: usize) {
+        self.words[bin / BINS_PER_WORD] |= 1 << (bin % BINS_PER_WORD);
+    }
+
+    /// Compute the bin that this value would go to in a BloomFilter using
+    /// the hash function indexed by `hash_index`.
+    pub fn bin<V: AsRef<[u8]>>(&self, value: &V, hash_index: usize) -> usize {
+        let mut hbytes = [0u8; MAX_BIN_BYTES];
+        match self.hasher {
+            BloomHashKind::Sha256 => {
+                let mut h = Sha256::new();
+                h.update((hash_index as u64).to_le_bytes());
+                h.update(value);
+                hbytes.copy_from_slice(&h.finalize()[0..MAX_BIN_BYTES]);
+            }
+            BloomHashKind::SipHash { key } if self.bin_bytes <= 8 => {
+                let mut h = SipHasher24::new_with_key(&key);
+                h.write(&(hash_index as u64).to_le_bytes());
+                h.write(value.as_ref());
+                hbytes[0..8].copy_from_slice(&h.finish().to_le_bytes());
+            }
+            BloomHashKind::SipHash { key } => {
+                let mut h = SipHasher128::new_with_key(&key);
+                h.write(&(hash_index as u64).to_le_bytes());
+                h.write(value.as_ref());
+                hbytes = h.finish128().as_bytes();
+            }
+        }
+        reduce_bin(&hbytes[0..self.bin_bytes], self.nbins as u128) as usize
+    }
+
+    /// Insert an item into the BloomFilter.
+    pub fn insert<V: AsRef<[u8]>>(&mut self, value: &V) {
+        for hash_index in 0..self.nhashes {
+            let bin = self.bin(value, hash_index);
+            self.set(bin);
+        }
+    }
+
+    /// Insert an item into the BloomFilter, returning whether it was probably
+    /// already present, i.e. whether all its bins were already set.
+    ///
+    /// This hashes the item once per hash function, against twice for a
+    /// `contains` followed by an `insert`.
+    pub fn insert_checked<V: AsRef<[u8]>>(&mut self, value: &V) -> bool {
+        let mut present = true;
+        for hash_index in 0..self.nhashes {
+            let bin = self.bin(value, hash_index);
+            present &= self.get(bin);
+            self.set(bin);
+        }
+        present
+    }
+
+    /// Check whether an item exists in the BloomFilter.
+    pub fn contains<V: AsRef<[u8]>>(&self, value: &V) -> bool {
+        (0..self.nhashes).all(|hash_index| self.get(self.bin(value, hash_index)))
+    }
+
+    /// Count the number of set bins in the BloomFilter.
+    pub fn count_ones(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    /// Get the bins of the BloomFilter packed into bytes, where bin `i` is bit
+    /// `i % 8` of byte `i / 8`.
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut bytes = self
+            .words
+            .iter()
+            .flat_map(|w| w.to_le_bytes())
+            .collect::<Vec<u8>>();
+        bytes.truncate(self.nbins.div_ceil(8));
+        bytes
+    }
+
+    /// Create a BloomFilter with `nhashes` hash functions from bytes produced by
+    /// `as_bytes`, containing `nbins` bins. The filter uses the default hasher,
+    /// which can be changed with `with_hasher`.
+    pub fn from_bytes(bytes: &[u8], nbins: usize, nhashes: usize) -> Self {
+        assert_eq!(bytes.len(), nbins.div_ceil(8));
+        let mut words = bytes
+            .chunks(8)
+            .map(|chunk| {
+                let mut word = [0u8; 8];
+                word[..chunk.len()].copy_from_slice(chunk);
+                u64::from_le_bytes(word)
+            })
+            .collect::<Vec<u64>>();
+        // Clear any bit set beyond the last bin
+        if nbins % BINS_PER_WORD != 0 {
+            if let Some(last) = words.last_mut() {
+                *last &= (1 << (nbins % BINS_PER_WORD)) - 1;
+            }
+        }
+        BloomFilter {
+            words,
+            nbins,
+            nhashes,
+            hasher: BloomHashKind::default(),
+            bin_bytes: DEFAULT_BIN_BYTES,
+        }
+    }
+}
+
+// Map the little-endian integer `hbytes`, of at most 16 bytes, to one of `nbins`
+// bins.
+fn reduce_bin(hbytes: &[u8], nbins: u128) -> u128 {
+    let mut word = [0u8; MAX_BIN_BYTES];
+    word[0..hbytes.len()].copy_from_slice(hbytes);
+    u128::from_le_bytes(word) % nbins
+}
+
+/// The ratio between the capacities of consecutive layers of a `ScalableBloomFilter`.
+const GROWTH_FACTOR: usize = 2;
+/// The ratio between the false positive rates of consecutive layers of a
+/// `ScalableBloomFilter`.
+const TIGHTENING_RATIO: f64 = 0.5;
+
+/// A Bloom Filter which grows as items are inserted, for when the number of items
+/// is not known up front (cf. Almeida et al., "Scalable Bloom Filters").
+///
+/// Items are inserted in the last of a list of `BloomFilter` layers. Once it holds
+/// as many items as its capacity, a new layer is added with `GROWTH_FACTOR` times
+/// the capacity and `TIGHTENING_RATIO` times the false positive rate. The false
+/// positive rate of the `i`-th layer being `rate * (1 - TIGHTENING_RATIO) *
+/// TIGHTENING_RATIO^i`, the overall false positive rate stays below `rate`
+/// however many layers are added.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScalableBloomFilter {
+    layers: Vec<BloomFilter>,
+    capacity: usize,
+    nitems: usize,
+    rate: f64,
+    hasher: BloomHashKind,
+}
+
+impl ScalableBloomFilter {
+    /// Create a new ScalableBloomFilter whose first layer holds `capacity` items,
+    /// using `rate` as the overall false positive rate.
+    pub fn new(capacity: usize, rate: f64) -> Self {
+        let capacity = capacity.max(1);
+        ScalableBloomFilter {
+            layers: vec![BloomFilter::new(capacity, rate * (1.0 - TIGHTENING_RATIO))],
+            capacity,
+            nitems: 0,
+            rate,
+            hasher: BloomHashKind::default(),
+        }
+    }
+
+    /// Use `hasher` to map items to bins in every layer. This should be set before
+    /// inserting any item, as items already inserted are not rehashed.
+    pub fn with_hasher(mut self, hasher: BloomHashKind) -> Self {
+        self.layers = self
+            .layers
+            .into_iter()
+            .map(|layer| layer.with_hasher(hasher))
+            .collect();
+        self.hasher = hasher;
+        self
+    }
+
+    /// Get the overall false positive rate targeted by the filter.
+    pub fn rate(&self) -> f64 {
+        self.rate
+    }
+
+    /// Get the number of layers of the filter.
+    pub fn nlayers(&self) -> usize {
+        self.layers.len()
+    }
+
+    /// Get the layers of the filter, from the oldest to the newest.
+    pub fn layers(&self) -> &[BloomFilter] {
+        &self.layers
+    }
+
+    /// Insert an item into the ScalableBloomFilter, adding a layer if the last one
+    /// is full. Items which are probably already present are not inserted again,
+    /// so that they do not count towards the capacity.
+    pub fn insert<V: AsRef<[u8]>>(&mut self, value: &V) {
+        if self.contains(value) {
+            return;
+        }
+        if self.nitems >= self.capacity {
+            self.capacity *= GROWTH_FACTOR;
+            self.nitems = 0;
+            let rate = self.rate
+                * (1.0 - TIGHTENING_RATIO)
+                * TIGHTENING_RATIO.powi(self.layers.len() as i32);
+            self.layers
+                .push(BloomFilter::new(self.capacity, rate).with_hasher(self.hasher));
+        }
+        // There is always at least one layer
+        self.layers.last_mut().unwrap().insert(value);
+        self.nitems += 1;
+    }
+
+    /// Check whether an item exists in any layer of the ScalableBloomFilter.
+    pub fn contains<V: AsRef<[u8]>>(&self, value: &V) -> bool {
+        self.layers.iter().any(|layer| layer.contains(value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AesRng;
+    use rand::Rng;
+
+    const SIZE: usize = 1000;
+    const RATE: f64 = 0.01;
+
+    fn rand_items(n: usize, rng: &mut AesRng) -> Vec<[u8; 16]> {
+        (0..n).map(|_| rng.gen()).collect()
+    }
+
+    #[test]
+    fn test_bloomfilter_membership() {
+        let mut rng = AesRng::new();
+        let items = rand_items(SIZE, &mut rng);
+        let mut filter = BloomFilter::new(SIZE, RATE);
+        for item in items.iter() {
+            filter.insert(item);
+        }
+        for item in items.iter() {
+            assert!(filter.contains(item));
+        }
+        let false_positives = rand_items(SIZE, &mut rng)
+            .iter()
+            .filter(|item| filter.contains(*item))
+            .count();
+        assert!(false_positives < SIZE / 10);
+    }
+
+    #[test]
+    fn test_bloomfilter_insert_checked() {
+        let mut rng = AesRng::new();
+        let items = rand_items(SIZE, &mut rng);
+        let mut filter = BloomFilter::new(SIZE, RATE);
+        assert!(!filter.insert_checked(&items[0]));
+        assert!(filter.insert_checked(&items[0]));
+        for item in items[1..].iter() {
+            let present = filter.contains(item);
+            assert_eq!(filter.insert_checked(item), present);
+            assert!(filter.insert_checked(item));
+        }
+    }
+
+    #[test]
+    fn test_bloomfilter_count_ones() {
+        let mut rng = AesRng::new();
+        let mut filter = BloomFilter::new(SIZE, RATE);
+        for item in rand_items(SIZE, &mut rng).iter() {
+            filter.insert(item);
+        }
+        let count = (0..filter.len()).filter(|&bin| filter.get(bin)).count();
+        assert_eq!(filter.count_ones(), count);
+    }
+
+    #[test]
+    fn test_bloomfilter_bytes_roundtrip() {
+        let mut rng = AesRng::new();
+        let items = rand_items(SIZE, &mut rng);
+        let mut filter = BloomFilter::new(SIZE, RATE);
+        for item in items.iter() {
+            filter.insert(item);
+        }
+        let bytes = filter.as_bytes();
+        assert_eq!(bytes.len(), filter.len().div_ceil(8));
+        let filter_ = BloomFilter::from_bytes(&bytes, filter.len(), filter.nhashes());
+        assert_eq!(filter, filter_);
+        for item in items.iter() {
+            assert!(filter_.contains(item));
+        }
+    }
+
+    #[test]
+    fn test_bloomfilter_siphash_membership() {
+        let mut rng = AesRng::new();
+        let items = rand_items(SIZE, &mut rng);
+        let mut filter =
+            BloomFilter::new(SIZE, RATE).with_hasher(BloomHashKind::SipHash { key: rng.gen() });
+        for item in items.iter() {
+            filter.insert(item);
+        }
+        for item in items.iter() {
+            assert!(filter.contains(item));
+        }
+        let false_positives = rand_items(SIZE, &mut rng)
+            .iter()
+            .filter(|item| filter.contains(*item))
+            .count();
+        assert!(false_positives < SIZE / 10);
+    }
+
+    #[test]
+    fn test_bloomfilter_siphash_keys_differ() {
+        let mut rng = AesRng::new();
+        let items = rand_items(SIZE, &mut rng);
+        let key: [u8; 16] = rng.gen();
+        let mut other_key = key;
+        other_key[0] ^= 1;
+        let mut filter = BloomFilter::new(SIZE, RATE).with_hasher(BloomHashKind::SipHash { key });
+        let mut other_filter =
+            BloomFilter::new(SIZE, RATE).with_hasher(BloomHashKind::SipHash { key: other_key });
+        for item in items.iter() {
+            filter.insert(item);
+            other_filter.insert(item);
+        }
+        assert_ne!(filter.as_bytes(), other_filter.as_bytes());
+    }
+
+    #[test]
+    fn test_bloomfilter_bin_bytes() {
+        let mut rng = AesRng::new();
+        let items = rand_items(SIZE, &mut rng);
+        for hasher in [
+            BloomHashKind::Sha256,
+            BloomHashKind::SipHash { key: rng.gen() },
+        ] {
+            let mut filter = BloomFilter::new(SIZE, RATE)
+                .with_hasher(hasher)
+                .with_bin_bytes(16);
+            assert_eq!(filter.bin_bytes(), 16);
+            for item in items.iter() {
+                filter.insert(item);
+            }
+            for item in items.iter() {
+                assert!(filter.contains(item));
+            }
+        }
+    }
+
+    #[test]
+    fn test_bloomfilter_wide_bins_reduce_bias() {
+        // With 3 * 2^62 bins, reducing a 64-bit hash maps two hashes to each of the
+        // first third of the bins, but only one to the others
+        let nbins = 3u128 << 62;
+        let nbuckets = 12;
+        let ntrials = 12000;
+        let mut rng = AesRng::new();
+        let mut chi_squared = |nbytes: usize| {
+            let mut counts = vec![0usize; nbuckets];
+            for item in rand_items(ntrials, &mut rng).iter() {
+                let hbytes = Sha256::digest(item);
+                let bin = reduce_bin(&hbytes[0..nbytes], nbins);
+                counts[(bin * nbuckets as u128 / nbins) as usize] += 1;
+            }
+            let expected = (ntrials / nbuckets) as f64;
+            counts
+                .iter()
+                .map(|&count| (count as f64 - expected).powi(2) / expected)
+                .sum::<f64>()
+        };
+        let narrow = chi_squared(8);
+        let wide = chi_squared(16);
+        // The 99.9th percentile of the chi-squared distribution with 11 degrees
+        // of freedom is about 31.3
+        assert!(wide < 31.3, "chi-squared statistic {} is too large", wide);
+        assert!(narrow > wide);
+    }
+
+    #[test]
+    fn test_scalable_bloomfilter_grows() {
+        let mut rng = AesRng::new();
+        // Insert far more items than the capacity of the first layer
+        let items = rand_items(100 * SIZE / 10, &mut rng);
+        let mut filter = ScalableBloomFilter::new(SIZE / 10, RATE);
+        for item in items.iter() {
+            filter.insert(item);
+        }
+        assert!(filter.nlayers() > 1);
+        for item in items.iter() {
+            assert!(filter.contains(item));
+        }
+        let ntrials = 10 * SIZE;
+        let false_positives = rand_items(ntrials, &mut rng)
+            .iter()
+            .filter(|item| filter.contains(*item))
+            .count();
+        let measured = false_positives as f64 / ntrials as f64;
+        assert!(
+            measured < 1.5 * RATE,
+            "The false positive rate was {} and should be close to {}",
+            measured,
+            RATE
+        );
+    }
+}
@@ -1,12 +1,20 @@
+mod budget_channel;
 mod hash_channel;
+mod mux_channel;
+mod record_channel;
 mod sync_channel;
 mod track_channel;
 #[cfg(unix)]
 mod unix_channel;
 
+pub use budget_channel::{BudgetChannel, BudgetExceeded};
 pub use hash_channel::HashChannel;
+pub use mux_channel::{MuxChannel, SubChannel};
+pub use record_channel::{RecordChannel, ReplayChannel, Transcript};
 pub use sync_channel::SyncChannel;
-pub use track_channel::TrackChannel;
+pub use track_channel::{
+    assert_comm_within, check_comm_within, ChannelReport, PhaseReport, TrackChannel,
+};
 
 #[cfg(unix)]
 pub use unix_channel::{track_unix_channel_pair, unix_channel_pair, TrackUnixChannel, UnixChannel};
@@ -20,6 +28,10 @@ use std::{
     rc::Rc,
 };
 
+/// The largest length of a byte vector accepted by
+/// `AbstractChannel::read_lenprefixed`, i.e. 64 MiB.
+pub const MAX_LENPREFIXED_LEN: usize = 1 << 26;
+
 /// A trait for managing I/O. `AbstractChannel`s are clonable, and provide basic
 /// read/write capabilities for both common and scuttlebutt-specific types.
 pub trait AbstractChannel {
@@ -29,6 +41,20 @@ pub trait AbstractChannel {
     fn write_bytes(&mut self, bytes: &[u8]) -> Result<()>;
     /// Flush the channel.
     fn flush(&mut self) -> Result<()>;
+    /// Return a number of bytes which can be read from the channel without
+    /// blocking, e.g. to check whether the peer sent a message before waiting
+    /// for it.
+    ///
+    /// This may be less than the number of bytes sent by the peer so far, e.g.
+    /// for a buffered channel, which only counts the bytes it already buffered.
+    /// Channels which cannot tell, such as a `Channel` or `SyncChannel` made
+    /// with `new` from an arbitrary reader, fail with `ErrorKind::Unsupported`.
+    fn bytes_available(&mut self) -> Result<usize> {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "the channel cannot tell how many bytes are available",
+        ))
+    }
     /// Read `nbytes` from the channel, and return it as a `Vec`.
     fn read_vec(&mut self, nbytes: usize) -> Result<Vec<u8>> {
         let mut data = vec![0; nbytes];
@@ -36,6 +62,32 @@ pub trait AbstractChannel {
         Ok(data)
     }
 
+    /// Write `data` to the channel preceded by its length, to be read with
+    /// `read_lenprefixed`.
+    fn write_lenprefixed(&mut self, data: &[u8]) -> Result<()> {
+        self.write_usize(data.len())?;
+        self.write_bytes(data)
+    }
+
+    /// Read a byte vector written with `write_lenprefixed`.
+    ///
+    /// Fails with `ErrorKind::InvalidData` if the declared length exceeds
+    /// `MAX_LENPREFIXED_LEN`, before allocating any memory for the data, so that
+    /// the other party cannot exhaust the memory with a bogus length.
+    fn read_lenprefixed(&mut self) -> Result<Vec<u8>> {
+        let len = self.read_usize()?;
+        if len > MAX_LENPREFIXED_LEN {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "declared length {} exceeds the maximum of {} bytes",
+                    len, MAX_LENPREFIXED_LEN
+                ),
+            ));
+        }
+        self.read_vec(len)
+    }
+
     /// Write a `bool` to the channel.
     #[inline(always)]
     fn write_bool(&mut self, b: bool) -> Result<()> {
@@ -222,6 +274,10 @@ impl<'a, C: AbstractChannel> AbstractChannel for &'a mut C {
     fn flush(&mut self) -> Result<()> {
         C::flush(self)
     }
+
+    fn bytes_available(&mut self) -> Result<usize> {
+        C::bytes_available(self)
+    }
 }
 
 impl AbstractChannel for swanky_channel::Channel<'_> {
@@ -242,12 +298,100 @@ impl AbstractChannel for swanky_channel::Channel<'_> {
         self.force_flush()
             .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))
     }
+
+    #[inline]
+    fn bytes_available(&mut self) -> Result<usize> {
+        Ok(self.buffered_len())
+    }
+}
+
+/// A writer whose write half can be shut down, so that the reader on the other
+/// end sees the end of the stream (cf. `Channel::close`).
+pub trait ShutdownWrite {
+    /// Shut down the write half of the underlying stream.
+    fn shutdown_write(&mut self) -> Result<()>;
+}
+
+impl ShutdownWrite for std::net::TcpStream {
+    fn shutdown_write(&mut self) -> Result<()> {
+        self.shutdown(std::net::Shutdown::Write)
+    }
+}
+
+#[cfg(unix)]
+impl ShutdownWrite for std::os::unix::net::UnixStream {
+    fn shutdown_write(&mut self) -> Result<()> {
+        self.shutdown(std::net::Shutdown::Write)
+    }
+}
+
+impl<W: Write + ShutdownWrite> ShutdownWrite for std::io::BufWriter<W> {
+    /// Flush the buffered data before shutting down the write half.
+    fn shutdown_write(&mut self) -> Result<()> {
+        self.flush()?;
+        self.get_mut().shutdown_write()
+    }
+}
+
+/// A channel which can be closed, so that the other party reads the end of the
+/// stream instead of waiting for more data (cf. `Channel::close`).
+pub trait CloseChannel: AbstractChannel {
+    /// Flush the channel and shut down its write half.
+    fn close(self) -> Result<()>;
+}
+
+/// A reader which can tell how many bytes it can return without blocking
+/// (cf. `AbstractChannel::bytes_available`).
+pub trait BytesAvailable {
+    /// Return the number of bytes which can be read without blocking.
+    fn bytes_available(&mut self) -> Result<usize>;
+}
+
+/// Return the number of bytes received on a socket and not read yet.
+#[cfg(unix)]
+fn socket_bytes_available(fd: std::os::unix::io::RawFd) -> Result<usize> {
+    let mut nbytes: libc::c_int = 0;
+    // Safety: `FIONREAD` only writes a `c_int` to the given pointer.
+    if unsafe { libc::ioctl(fd, libc::FIONREAD, &mut nbytes) } < 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+    Ok(nbytes as usize)
+}
+
+#[cfg(unix)]
+impl BytesAvailable for std::net::TcpStream {
+    fn bytes_available(&mut self) -> Result<usize> {
+        socket_bytes_available(std::os::unix::io::AsRawFd::as_raw_fd(self))
+    }
+}
+
+#[cfg(unix)]
+impl BytesAvailable for std::os::unix::net::UnixStream {
+    fn bytes_available(&mut self) -> Result<usize> {
+        socket_bytes_available(std::os::unix::io::AsRawFd::as_raw_fd(self))
+    }
+}
+
+impl<R: Read + BytesAvailable> BytesAvailable for std::io::BufReader<R> {
+    /// Count the buffered bytes as well as those available on the reader.
+    fn bytes_available(&mut self) -> Result<usize> {
+        Ok(self.buffer().len() + self.get_mut().bytes_available()?)
+    }
+}
+
+/// The `bytes_available` of a channel made from a reader which cannot tell.
+fn bytes_available_unsupported<R>(_reader: &mut R) -> Result<usize> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "the channel cannot tell how many bytes are available",
+    ))
 }
 
 /// A standard read/write channel that implements `AbstractChannel`.
 pub struct Channel<R, W> {
     reader: Rc<RefCell<R>>,
     writer: Rc<RefCell<W>>,
+    available: fn(&mut R) -> Result<usize>,
 }
 
 /// DO NOT USE THIS IMPL EXCEPT IN LEGACY CODE!
@@ -257,16 +401,24 @@ impl<R, W> Clone for Channel<R, W> {
         Channel {
             reader: self.reader.clone(),
             writer: self.writer.clone(),
+            available: self.available,
         }
     }
 }
 
 impl<R: Read, W: Write> Channel<R, W> {
     /// Make a new `Channel` from a `reader` and a `writer`.
+    ///
+    /// Its `bytes_available` fails with `ErrorKind::Unsupported`, cf.
+    /// `Channel::with_bytes_available` for readers which can tell.
     pub fn new(reader: R, writer: W) -> Self {
         let reader = Rc::new(RefCell::new(reader));
         let writer = Rc::new(RefCell::new(writer));
-        Self { reader, writer }
+        Self {
+            reader,
+            writer,
+            available: bytes_available_unsupported,
+        }
     }
 
     /// Return a reader object wrapped in `Rc<RefCell>`.
@@ -280,6 +432,36 @@ impl<R: Read, W: Write> Channel<R, W> {
     }
 }
 
+impl<R: Read + BytesAvailable, W: Write> Channel<R, W> {
+    /// Make a new `Channel` from a `reader` and a `writer`, whose
+    /// `bytes_available` asks the reader, e.g. a buffered socket.
+    pub fn with_bytes_available(reader: R, writer: W) -> Self {
+        Self {
+            available: R::bytes_available,
+            ..Self::new(reader, writer)
+        }
+    }
+}
+
+impl<R, W: Write + ShutdownWrite> Channel<R, W> {
+    /// Flush the channel and shut down its write half, so that the other party
+    /// reads the end of the stream instead of waiting for more data.
+    ///
+    /// Unlike dropping the channel, this reports errors, and it also closes the
+    /// stream for any clone of the channel still alive.
+    pub fn close(self) -> Result<()> {
+        let mut writer = self.writer.borrow_mut();
+        writer.flush()?;
+        writer.shutdown_write()
+    }
+}
+
+impl<R: Read, W: Write + ShutdownWrite> CloseChannel for Channel<R, W> {
+    fn close(self) -> Result<()> {
+        Channel::close(self)
+    }
+}
+
 impl<R: Read, W: Write> AbstractChannel for Channel<R, W> {
     #[inline(always)]
     fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
@@ -296,6 +478,10 @@ impl<R: Read, W: Write> AbstractChannel for Channel<R, W> {
     fn flush(&mut self) -> Result<()> {
         self.writer.borrow_mut().flush()
     }
+
+    fn bytes_available(&mut self) -> Result<usize> {
+        (self.available)(&mut self.reader.borrow_mut())
+    }
 }
 
 /// Standard Read/Write channel built from a symmetric stream.
@@ -328,3 +514,53 @@ impl<S: Read + Write> AbstractChannel for SymChannel<S> {
         self.stream.borrow_mut().flush()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{Rng, RngCore};
+    use std::io::{Cursor, ErrorKind};
+
+    #[test]
+    fn test_lenprefixed_round_trip() {
+        let mut rng = rand::thread_rng();
+        let buffers: Vec<Vec<u8>> = [0, 1, 17, 4096, 100_000]
+            .iter()
+            .map(|&len| {
+                let mut data = vec![0u8; len];
+                rng.fill_bytes(&mut data);
+                data
+            })
+            .collect();
+        let mut sent = Vec::new();
+        let mut channel = Channel::new(Cursor::new(vec![]), &mut sent);
+        for data in buffers.iter() {
+            channel.write_lenprefixed(data).unwrap();
+        }
+        // A trailing value must not be consumed as part of the last buffer
+        channel.write_u32(rng.gen()).unwrap();
+        drop(channel);
+        let mut channel = Channel::new(Cursor::new(sent), std::io::sink());
+        for data in buffers.iter() {
+            assert_eq!(&channel.read_lenprefixed().unwrap(), data);
+        }
+        channel.read_u32().unwrap();
+        assert_eq!(
+            channel.read_u8().unwrap_err().kind(),
+            ErrorKind::UnexpectedEof
+        );
+    }
+
+    #[test]
+    fn test_lenprefixed_oversized_length() {
+        let mut sent = Vec::new();
+        let mut channel = Channel::new(Cursor::new(vec![]), &mut sent);
+        // Declare a length just above the maximum, followed by a few bytes
+        channel.write_usize(MAX_LENPREFIXED_LEN + 1).unwrap();
+        channel.write_bytes(&[0; 16]).unwrap();
+        drop(channel);
+        let mut channel = Channel::new(Cursor::new(sent), std::io::sink());
+        let err = channel.read_lenprefixed().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
@@ -0,0 +1,153 @@
+use crate::AbstractChannel;
+use std::io::{Error, ErrorKind, Result};
+
+/// The error returned by a `BudgetChannel` once its byte budget is exhausted.
+///
+/// It is wrapped in an `std::io::Error` of kind `ErrorKind::Other`, so that it
+/// propagates through the protocols' errors like any other I/O error, and can
+/// be recovered with `std::io::Error::get_ref` and `downcast_ref`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BudgetExceeded {
+    /// The number of bytes the channel was allowed to read and write.
+    pub budget: usize,
+    /// The number of bytes read and written had the operation gone through.
+    pub nbytes: usize,
+}
+
+impl std::fmt::Display for BudgetExceeded {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "channel budget exceeded: {} bytes needed, {} allowed",
+            self.nbytes, self.budget
+        )
+    }
+}
+
+impl std::error::Error for BudgetExceeded {}
+
+/// A channel wrapping another channel which aborts once the total number of
+/// bytes read and written exceeds a budget.
+///
+/// An operation which would exceed the budget fails with a `BudgetExceeded`
+/// error before touching the inner channel, so that nothing is sent past the
+/// budget.
+pub struct BudgetChannel<C> {
+    channel: C,
+    budget: usize,
+    nbytes: usize,
+}
+
+impl<C: AbstractChannel> BudgetChannel<C> {
+    /// Make a new `BudgetChannel` from a `channel`, allowing at most `budget`
+    /// bytes to be read and written in total.
+    pub fn new(channel: C, budget: usize) -> Self {
+        Self {
+            channel,
+            budget,
+            nbytes: 0,
+        }
+    }
+
+    /// Return the number of bytes read and written so far.
+    pub fn bytes_used(&self) -> usize {
+        self.nbytes
+    }
+
+    /// Return the number of bytes which can still be read or written.
+    pub fn bytes_remaining(&self) -> usize {
+        self.budget - self.nbytes
+    }
+
+    /// Consume the channel and output the inner channel.
+    pub fn into_inner(self) -> C {
+        self.channel
+    }
+
+    // Account for `len` more bytes, failing if this exceeds the budget.
+    fn spend(&mut self, len: usize) -> Result<()> {
+        let nbytes = self.nbytes.saturating_add(len);
+        if nbytes > self.budget {
+            return Err(Error::new(
+                ErrorKind::Other,
+                BudgetExceeded {
+                    budget: self.budget,
+                    nbytes,
+                },
+            ));
+        }
+        self.nbytes = nbytes;
+        Ok(())
+    }
+}
+
+impl<C: AbstractChannel> AbstractChannel for BudgetChannel<C> {
+    #[inline]
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        self.spend(bytes.len())?;
+        self.channel.write_bytes(bytes)
+    }
+
+    #[inline]
+    fn read_bytes(&mut self, bytes: &mut [u8]) -> Result<()> {
+        self.spend(bytes.len())?;
+        self.channel.read_bytes(bytes)
+    }
+
+    #[inline]
+    fn flush(&mut self) -> Result<()> {
+        self.channel.flush()
+    }
+
+    #[inline]
+    fn bytes_available(&mut self) -> Result<usize> {
+        self.channel.bytes_available()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{cointoss, unix_channel_pair, Block};
+    use std::thread;
+
+    #[test]
+    fn test_within_budget() {
+        let (sender, mut receiver) = unix_channel_pair();
+        let seeds = [Block::from(1u128), Block::from(2u128)];
+        let handle = thread::spawn(move || cointoss::receive(&mut receiver, &seeds).unwrap());
+        // Each seed costs a commitment, a seed received and a seed opened
+        let mut sender = BudgetChannel::new(sender, 3 * 16 * seeds.len());
+        let out = cointoss::send(&mut sender, &seeds).unwrap();
+        assert_eq!(out, handle.join().unwrap());
+        assert_eq!(sender.bytes_remaining(), 0);
+    }
+
+    #[test]
+    fn test_budget_exceeded() {
+        let (sender, mut receiver) = unix_channel_pair();
+        let seeds = [Block::from(1u128)];
+        let handle = thread::spawn(move || cointoss::receive(&mut receiver, &seeds));
+        let mut sender = BudgetChannel::new(sender, 32);
+        let err = cointoss::send(&mut sender, &seeds).unwrap_err();
+        let err = match err {
+            cointoss::Error::IoError(err) => err,
+            err => panic!("expected an I/O error, got {}", err),
+        };
+        let err = err
+            .get_ref()
+            .and_then(|e| e.downcast_ref::<BudgetExceeded>())
+            .unwrap();
+        assert_eq!(
+            *err,
+            BudgetExceeded {
+                budget: 32,
+                nbytes: 48
+            }
+        );
+        assert_eq!(sender.bytes_used(), 32);
+        // The receiver sees the sender hang up instead of opening its commitment
+        drop(sender);
+        assert!(handle.join().unwrap().is_err());
+    }
+}
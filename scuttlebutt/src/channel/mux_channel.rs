@@ -0,0 +1,231 @@
+use crate::AbstractChannel;
+use std::{
+    collections::VecDeque,
+    io::{Error, ErrorKind, Read, Result, Write},
+    sync::{Arc, Condvar, Mutex},
+};
+
+/// The number of buffered bytes after which a sub-channel sends a frame, even
+/// if it has not been flushed.
+const MAX_FRAME_SIZE: usize = 1 << 16;
+
+struct MuxQueues {
+    // The bytes received for each sub-channel which were not read yet.
+    queues: Vec<VecDeque<u8>>,
+    // Whether a sub-channel is currently reading a frame.
+    reading: bool,
+}
+
+struct MuxShared<R, W> {
+    reader: Mutex<R>,
+    queues: Mutex<MuxQueues>,
+    // Signalled whenever a frame was read.
+    ready: Condvar,
+    writer: Mutex<W>,
+}
+
+// Read one frame from `reader`, returning the index of its sub-channel and its
+// payload.
+fn read_frame<R: Read>(reader: &mut R, nchannels: usize) -> Result<(usize, Vec<u8>)> {
+    let mut header = [0u8; 8];
+    reader.read_exact(&mut header)?;
+    let id = u32::from_le_bytes(header[0..4].try_into().unwrap()) as usize;
+    let len = u32::from_le_bytes(header[4..8].try_into().unwrap()) as usize;
+    if id >= nchannels {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("frame for unknown sub-channel {}", id),
+        ));
+    }
+    let mut payload = vec![0u8; len];
+    reader.read_exact(&mut payload)?;
+    Ok((id, payload))
+}
+
+/// A channel which multiplexes a fixed number of independent sub-channels over
+/// a single reader and writer.
+///
+/// Each sub-channel is `Send`, so that independent sub-protocols can run on
+/// their own thread. Data is sent in frames tagged with the sub-channel index,
+/// so both parties must create the same number of sub-channels.
+pub struct MuxChannel<R, W> {
+    shared: Arc<MuxShared<R, W>>,
+    nchannels: usize,
+}
+
+impl<R: Read, W: Write> MuxChannel<R, W> {
+    /// Make a new `MuxChannel` with `nchannels` sub-channels from a `reader`
+    /// and a `writer`.
+    pub fn new(reader: R, writer: W, nchannels: usize) -> Self {
+        assert!(nchannels <= u32::MAX as usize);
+        let queues = MuxQueues {
+            queues: vec![VecDeque::new(); nchannels],
+            reading: false,
+        };
+        let shared = Arc::new(MuxShared {
+            reader: Mutex::new(reader),
+            queues: Mutex::new(queues),
+            ready: Condvar::new(),
+            writer: Mutex::new(writer),
+        });
+        Self { shared, nchannels }
+    }
+
+    /// Get the number of sub-channels.
+    pub fn nchannels(&self) -> usize {
+        self.nchannels
+    }
+
+    /// Get the sub-channel with index `id`. At most one sub-channel object per
+    /// index should be used at a time.
+    pub fn subchannel(&self, id: usize) -> SubChannel<R, W> {
+        assert!(id < self.nchannels);
+        SubChannel {
+            shared: self.shared.clone(),
+            id,
+            buffer: Vec::new(),
+        }
+    }
+}
+
+/// One of the sub-channels of a `MuxChannel`.
+///
+/// Written bytes are buffered until the sub-channel is flushed, which also
+/// happens before every read.
+pub struct SubChannel<R, W> {
+    shared: Arc<MuxShared<R, W>>,
+    id: usize,
+    buffer: Vec<u8>,
+}
+
+impl<R, W> SubChannel<R, W> {
+    /// Get the index of this sub-channel.
+    pub fn id(&self) -> usize {
+        self.id
+    }
+}
+
+impl<R: Read, W: Write> SubChannel<R, W> {
+    fn write_frame(&mut self) -> Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+        let mut writer = self.shared.writer.lock().unwrap();
+        writer.write_all(&(self.id as u32).to_le_bytes())?;
+        writer.write_all(&(self.buffer.len() as u32).to_le_bytes())?;
+        writer.write_all(&self.buffer)?;
+        self.buffer.clear();
+        Ok(())
+    }
+}
+
+impl<R: Read, W: Write> AbstractChannel for SubChannel<R, W> {
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        self.buffer.extend_from_slice(bytes);
+        if self.buffer.len() >= MAX_FRAME_SIZE {
+            self.write_frame()?;
+        }
+        Ok(())
+    }
+
+    fn read_bytes(&mut self, bytes: &mut [u8]) -> Result<()> {
+        // Make sure the other party has everything it needs to answer
+        self.flush()?;
+        let shared = &self.shared;
+        let mut filled = 0;
+        let mut queues = shared.queues.lock().unwrap();
+        while filled < bytes.len() {
+            let queue = &mut queues.queues[self.id];
+            if !queue.is_empty() {
+                let n = queue.len().min(bytes.len() - filled);
+                for (b, q) in bytes[filled..filled + n].iter_mut().zip(queue.drain(..n)) {
+                    *b = q;
+                }
+                filled += n;
+                continue;
+            }
+            if queues.reading {
+                // Another sub-channel is reading a frame, which may be ours
+                queues = shared.ready.wait(queues).unwrap();
+                continue;
+            }
+            // Read the next frame without blocking the sub-channels which
+            // already have data to read
+            let nchannels = queues.queues.len();
+            queues.reading = true;
+            drop(queues);
+            let frame = read_frame(&mut *shared.reader.lock().unwrap(), nchannels);
+            queues = shared.queues.lock().unwrap();
+            queues.reading = false;
+            shared.ready.notify_all();
+            let (id, payload) = frame?;
+            queues.queues[id].extend(payload);
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.write_frame()?;
+        self.shared.writer.lock().unwrap().flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AesRng, Block};
+    use rand::Rng;
+    use std::{
+        io::{BufReader, BufWriter},
+        os::unix::net::UnixStream,
+        thread,
+    };
+
+    const NCHANNELS: usize = 4;
+    const NBLOCKS: usize = 1000;
+
+    fn mux(stream: UnixStream) -> MuxChannel<BufReader<UnixStream>, BufWriter<UnixStream>> {
+        let reader = BufReader::new(stream.try_clone().unwrap());
+        let writer = BufWriter::new(stream);
+        MuxChannel::new(reader, writer, NCHANNELS)
+    }
+
+    #[test]
+    fn test_subchannels_are_independent() {
+        let mut rng = AesRng::new();
+        let data = (0..NCHANNELS)
+            .map(|_| (0..NBLOCKS).map(|_| rng.gen()).collect::<Vec<Block>>())
+            .collect::<Vec<_>>();
+        let (sender, receiver) = UnixStream::pair().unwrap();
+        let (mux_sx, mux_rx) = (mux(sender), mux(receiver));
+        thread::scope(|s| {
+            for id in 0..NCHANNELS {
+                let mut channel = mux_sx.subchannel(id);
+                let data = &data[id];
+                // Each sub-channel echoes back what it receives
+                s.spawn(move || {
+                    for _ in 0..NBLOCKS {
+                        let block = channel.read_block().unwrap();
+                        channel.write_block(&block).unwrap();
+                    }
+                    channel.flush().unwrap();
+                    assert_eq!(channel.read_blocks(NBLOCKS).unwrap(), *data);
+                });
+            }
+            for id in 0..NCHANNELS {
+                let mut channel = mux_rx.subchannel(id);
+                let data = &data[id];
+                s.spawn(move || {
+                    for block in data.iter() {
+                        channel.write_block(block).unwrap();
+                        assert_eq!(channel.read_block().unwrap(), *block);
+                    }
+                    for block in data.iter() {
+                        channel.write_block(block).unwrap();
+                    }
+                    channel.flush().unwrap();
+                });
+            }
+        });
+    }
+}
@@ -0,0 +1,202 @@
+use crate::AbstractChannel;
+use std::io::{Error, ErrorKind, Result};
+
+/// The bytes read from and written to a channel, in order.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Transcript {
+    /// The bytes read from the channel.
+    pub read: Vec<u8>,
+    /// The bytes written to the channel.
+    pub written: Vec<u8>,
+}
+
+/// A channel wrapping another channel which records a `Transcript` of all the
+/// bytes read and written.
+pub struct RecordChannel<C> {
+    channel: C,
+    transcript: Transcript,
+}
+
+impl<C: AbstractChannel> RecordChannel<C> {
+    /// Make a new `RecordChannel` from a `channel`.
+    pub fn new(channel: C) -> Self {
+        Self {
+            channel,
+            transcript: Transcript::default(),
+        }
+    }
+
+    /// Get the transcript recorded so far.
+    pub fn transcript(&self) -> &Transcript {
+        &self.transcript
+    }
+
+    /// Consume the channel and output the inner channel and the recorded
+    /// transcript.
+    pub fn finish(self) -> (C, Transcript) {
+        (self.channel, self.transcript)
+    }
+}
+
+impl<C: AbstractChannel> AbstractChannel for RecordChannel<C> {
+    #[inline]
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        self.transcript.written.extend_from_slice(bytes);
+        self.channel.write_bytes(bytes)
+    }
+
+    #[inline]
+    fn read_bytes(&mut self, bytes: &mut [u8]) -> Result<()> {
+        self.channel.read_bytes(bytes)?;
+        self.transcript.read.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    #[inline]
+    fn flush(&mut self) -> Result<()> {
+        self.channel.flush()
+    }
+
+    #[inline]
+    fn bytes_available(&mut self) -> Result<usize> {
+        self.channel.bytes_available()
+    }
+}
+
+/// A channel which replays a recorded `Transcript` without a live peer.
+///
+/// Reads are served from the bytes the peer sent, and writes are checked
+/// against the bytes the peer received, failing with `ErrorKind::InvalidData`
+/// as soon as they diverge. This allows running one party of a deterministic
+/// protocol (e.g. with a seeded RNG) in isolation.
+pub struct ReplayChannel {
+    to_read: Vec<u8>,
+    nread: usize,
+    expected: Vec<u8>,
+    nwritten: usize,
+}
+
+impl ReplayChannel {
+    /// Make a new `ReplayChannel` taking the place of the party which recorded
+    /// `transcript`'s peer.
+    pub fn new(transcript: &Transcript) -> Self {
+        Self {
+            to_read: transcript.written.clone(),
+            nread: 0,
+            expected: transcript.read.clone(),
+            nwritten: 0,
+        }
+    }
+
+    /// Return whether the whole transcript was replayed.
+    pub fn is_finished(&self) -> bool {
+        self.nread == self.to_read.len() && self.nwritten == self.expected.len()
+    }
+}
+
+impl AbstractChannel for ReplayChannel {
+    fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        let end = self.nwritten + bytes.len();
+        if self.expected.get(self.nwritten..end) != Some(bytes) {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "write of {} bytes at offset {} diverges from the transcript",
+                    bytes.len(),
+                    self.nwritten
+                ),
+            ));
+        }
+        self.nwritten = end;
+        Ok(())
+    }
+
+    fn read_bytes(&mut self, bytes: &mut [u8]) -> Result<()> {
+        let end = self.nread + bytes.len();
+        match self.to_read.get(self.nread..end) {
+            Some(recorded) => bytes.copy_from_slice(recorded),
+            None => {
+                return Err(Error::new(
+                    ErrorKind::UnexpectedEof,
+                    "read past the end of the transcript",
+                ))
+            }
+        }
+        self.nread = end;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Return the number of bytes of the transcript left to read.
+    fn bytes_available(&mut self) -> Result<usize> {
+        Ok(self.to_read.len() - self.nread)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{unix_channel_pair, Block};
+    use std::thread;
+
+    // Echo `nblocks` blocks back to the peer
+    fn echo<C: AbstractChannel>(channel: &mut C, nblocks: usize) -> Result<()> {
+        for _ in 0..nblocks {
+            let block = channel.read_block()?;
+            channel.write_block(&block)?;
+            channel.flush()?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_record_and_replay() {
+        let blocks = (0..16u128).map(Block::from).collect::<Vec<_>>();
+        let (sender, mut receiver) = unix_channel_pair();
+        let handle = thread::spawn(move || {
+            let mut channel = RecordChannel::new(sender);
+            echo(&mut channel, 16).unwrap();
+            channel.finish().1
+        });
+        for block in blocks.iter() {
+            receiver.write_block(block).unwrap();
+            receiver.flush().unwrap();
+            assert_eq!(receiver.read_block().unwrap(), *block);
+        }
+        let transcript = handle.join().unwrap();
+        assert_eq!(transcript.read, transcript.written);
+
+        // Replay the receiver's side, then check that a diverging write fails
+        let mut channel = ReplayChannel::new(&transcript);
+        for block in blocks.iter() {
+            channel.write_block(block).unwrap();
+            assert_eq!(channel.read_block().unwrap(), *block);
+        }
+        assert!(channel.is_finished());
+        let mut channel = ReplayChannel::new(&transcript);
+        let err = channel.write_block(&Block::from(42)).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_bytes_available() {
+        let transcript = Transcript {
+            read: vec![],
+            written: vec![1, 2, 3, 4, 5],
+        };
+        let mut channel = RecordChannel::new(ReplayChannel::new(&transcript));
+        assert_eq!(channel.bytes_available().unwrap(), 5);
+        channel.read_vec(2).unwrap();
+        assert_eq!(channel.bytes_available().unwrap(), 3);
+        channel.read_vec(3).unwrap();
+        assert_eq!(channel.bytes_available().unwrap(), 0);
+
+        // Channels wrapping arbitrary readers cannot tell
+        let (mut sender, _) = unix_channel_pair();
+        let err = sender.bytes_available().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Unsupported);
+    }
+}
@@ -1,4 +1,7 @@
-use crate::AbstractChannel;
+use crate::{
+    channel::{bytes_available_unsupported, BytesAvailable, CloseChannel, ShutdownWrite},
+    AbstractChannel,
+};
 use std::{
     io::{Read, Result, Write},
     sync::{Arc, Mutex},
@@ -8,6 +11,7 @@ use std::{
 pub struct SyncChannel<R, W> {
     reader: Arc<Mutex<R>>,
     writer: Arc<Mutex<W>>,
+    available: fn(&mut R) -> Result<usize>,
 }
 
 /// DO NOT USE THIS IMPL EXCEPT IN LEGACY CODE!
@@ -17,16 +21,24 @@ impl<R, W> Clone for SyncChannel<R, W> {
         SyncChannel {
             reader: self.reader.clone(),
             writer: self.writer.clone(),
+            available: self.available,
         }
     }
 }
 
 impl<R: Read, W: Write> SyncChannel<R, W> {
     /// Make a new `Channel` from a `reader` and a `writer`.
+    ///
+    /// Its `bytes_available` fails with `ErrorKind::Unsupported`, cf.
+    /// `SyncChannel::with_bytes_available` for readers which can tell.
     pub fn new(reader: R, writer: W) -> Self {
         let reader = Arc::new(Mutex::new(reader));
         let writer = Arc::new(Mutex::new(writer));
-        Self { reader, writer }
+        Self {
+            reader,
+            writer,
+            available: bytes_available_unsupported,
+        }
     }
 
     /// Return a reader object wrapped in `Arc<Mutex<R>>`.
@@ -40,6 +52,36 @@ impl<R: Read, W: Write> SyncChannel<R, W> {
     }
 }
 
+impl<R: Read + BytesAvailable, W: Write> SyncChannel<R, W> {
+    /// Make a new `SyncChannel` from a `reader` and a `writer`, whose
+    /// `bytes_available` asks the reader, e.g. a buffered socket.
+    pub fn with_bytes_available(reader: R, writer: W) -> Self {
+        Self {
+            available: R::bytes_available,
+            ..Self::new(reader, writer)
+        }
+    }
+}
+
+impl<R, W: Write + ShutdownWrite> SyncChannel<R, W> {
+    /// Flush the channel and shut down its write half, so that the other party
+    /// reads the end of the stream instead of waiting for more data.
+    ///
+    /// Unlike dropping the channel, this reports errors, and it also closes the
+    /// stream for any clone of the channel still alive.
+    pub fn close(self) -> Result<()> {
+        let mut writer = self.writer.lock().unwrap();
+        writer.flush()?;
+        writer.shutdown_write()
+    }
+}
+
+impl<R: Read, W: Write + ShutdownWrite> CloseChannel for SyncChannel<R, W> {
+    fn close(self) -> Result<()> {
+        SyncChannel::close(self)
+    }
+}
+
 impl<R: Read, W: Write> AbstractChannel for SyncChannel<R, W> {
     #[inline(always)]
     fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
@@ -56,4 +98,8 @@ impl<R: Read, W: Write> AbstractChannel for SyncChannel<R, W> {
     fn flush(&mut self) -> Result<()> {
         self.writer.lock().unwrap().flush()
     }
+
+    fn bytes_available(&mut self) -> Result<usize> {
+        (self.available)(&mut self.reader.lock().unwrap())
+    }
 }
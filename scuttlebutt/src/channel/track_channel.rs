@@ -1,5 +1,6 @@
 use crate::AbstractChannel;
 use std::{
+    fmt,
     io::Result,
     sync::{Arc, Mutex},
 };
@@ -19,6 +20,78 @@ struct InternalTrackChannel<C> {
     channel: C,
     nbits_read: usize,
     nbits_written: usize,
+    // The communication of each phase, the last one being the current phase
+    phases: Vec<PhaseReport>,
+}
+
+/// The communication on a `TrackChannel` during a labelled phase of a protocol.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PhaseReport {
+    /// The label of the phase.
+    pub label: String,
+    /// The number of bytes read from the channel during the phase.
+    pub bytes_read: usize,
+    /// The number of bytes written to the channel during the phase.
+    pub bytes_written: usize,
+}
+
+/// A summary of the communication on a `TrackChannel` (cf.
+/// `TrackChannel::report`).
+///
+/// The `Display` impl gives a human-readable summary, and the report can be
+/// serialized with the `serde` feature, e.g. to JSON so that communication
+/// metrics can be compared over time.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ChannelReport {
+    /// The number of bytes read from the channel.
+    pub bytes_read: usize,
+    /// The number of bytes written to the channel.
+    pub bytes_written: usize,
+    /// The communication of each phase, in order, if phases were started (cf.
+    /// `TrackChannel::start_phase`).
+    pub phases: Vec<PhaseReport>,
+}
+
+impl ChannelReport {
+    /// Return the total number of bytes read from and written to the channel.
+    pub fn total_bytes(&self) -> usize {
+        self.bytes_read + self.bytes_written
+    }
+}
+
+// Format a number of bytes in B, KB or MB, with 1 KB = 1024 B.
+fn format_bytes(nbytes: usize) -> String {
+    if nbytes < 1 << 10 {
+        format!("{} B", nbytes)
+    } else if nbytes < 1 << 20 {
+        format!("{:.2} KB", nbytes as f64 / 1024.0)
+    } else {
+        format!("{:.2} MB", nbytes as f64 / (1024.0 * 1024.0))
+    }
+}
+
+impl fmt::Display for ChannelReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "read {}, written {}, total {}",
+            format_bytes(self.bytes_read),
+            format_bytes(self.bytes_written),
+            format_bytes(self.total_bytes())
+        )?;
+        for phase in &self.phases {
+            write!(
+                f,
+                "\n  {}: read {}, written {}",
+                phase.label,
+                format_bytes(phase.bytes_read),
+                format_bytes(phase.bytes_written)
+            )?;
+        }
+        Ok(())
+    }
 }
 
 impl<C: AbstractChannel> TrackChannel<C> {
@@ -28,15 +101,37 @@ impl<C: AbstractChannel> TrackChannel<C> {
             channel,
             nbits_read: 0,
             nbits_written: 0,
+            phases: Vec::new(),
         };
         Self(Arc::new(Mutex::new(internal)))
     }
 
-    /// Clear the number of bits read/written.
+    /// Clear the number of bits read/written, along with the phases.
     pub fn clear(&mut self) {
         let mut int = self.0.lock().unwrap();
         int.nbits_read = 0;
         int.nbits_written = 0;
+        int.phases.clear();
+    }
+
+    /// Start a phase labelled `label`: the communication is attributed to it
+    /// in the `report` until the next phase starts.
+    pub fn start_phase(&mut self, label: impl Into<String>) {
+        self.0.lock().unwrap().phases.push(PhaseReport {
+            label: label.into(),
+            ..Default::default()
+        });
+    }
+
+    /// Return a summary of the communication on the channel, including the
+    /// communication of each phase.
+    pub fn report(&self) -> ChannelReport {
+        let int = self.0.lock().unwrap();
+        ChannelReport {
+            bytes_read: int.nbits_read / 8,
+            bytes_written: int.nbits_written / 8,
+            phases: int.phases.clone(),
+        }
     }
 
     /// Return the number of kilobits written to the channel.
@@ -69,22 +164,206 @@ impl<C: AbstractChannel> TrackChannel<C> {
     pub fn total_kilobytes(&self) -> f64 {
         self.kilobytes_written() + self.kilobytes_read()
     }
+
+    /// Return the number of bytes read from the channel.
+    pub fn bytes_read(&self) -> usize {
+        self.0.lock().unwrap().nbits_read / 8
+    }
+
+    /// Return the number of bytes written to the channel.
+    pub fn bytes_written(&self) -> usize {
+        self.0.lock().unwrap().nbits_written / 8
+    }
+}
+
+/// Assert that at most `max_read` bytes were read from and at most `max_write` bytes
+/// were written to `channel`.
+///
+/// This is meant for tests guarding against communication regressions. To only
+/// bound the communication of a single protocol call, use the `assert_comm_within!`
+/// macro instead.
+#[track_caller]
+pub fn assert_comm_within<C: AbstractChannel>(
+    channel: &TrackChannel<C>,
+    max_read: usize,
+    max_write: usize,
+) {
+    check_comm_within(
+        channel.bytes_read(),
+        channel.bytes_written(),
+        max_read,
+        max_write,
+    );
+}
+
+#[doc(hidden)]
+#[track_caller]
+pub fn check_comm_within(nread: usize, nwritten: usize, max_read: usize, max_write: usize) {
+    assert!(
+        nread <= max_read,
+        "read {} bytes from the channel, expected at most {}",
+        nread,
+        max_read
+    );
+    assert!(
+        nwritten <= max_write,
+        "wrote {} bytes to the channel, expected at most {}",
+        nwritten,
+        max_write
+    );
+}
+
+/// Evaluate an expression, asserting that it reads at most `max_read` bytes from and
+/// writes at most `max_write` bytes to a `TrackChannel`. Returns the value of the
+/// expression.
+///
+/// ```ignore
+/// let output = assert_comm_within!(channel, 1 << 10, 1 << 10, {
+///     protocol.run(&mut channel)
+/// });
+/// ```
+#[macro_export]
+macro_rules! assert_comm_within {
+    ($channel:expr, $max_read:expr, $max_write:expr, $e:expr) => {{
+        let (nread, nwritten) = ($channel.bytes_read(), $channel.bytes_written());
+        let result = $e;
+        $crate::channel::check_comm_within(
+            $channel.bytes_read() - nread,
+            $channel.bytes_written() - nwritten,
+            $max_read,
+            $max_write,
+        );
+        result
+    }};
 }
 
 impl<C: AbstractChannel> AbstractChannel for TrackChannel<C> {
     fn write_bytes(&mut self, bytes: &[u8]) -> Result<()> {
         let mut int = self.0.lock().unwrap();
         int.nbits_written += bytes.len() * 8;
+        if let Some(phase) = int.phases.last_mut() {
+            phase.bytes_written += bytes.len();
+        }
         int.channel.write_bytes(bytes)
     }
 
     fn read_bytes(&mut self, mut bytes: &mut [u8]) -> Result<()> {
         let mut int = self.0.lock().unwrap();
         int.nbits_read += bytes.len() * 8;
+        if let Some(phase) = int.phases.last_mut() {
+            phase.bytes_read += bytes.len();
+        }
         int.channel.read_bytes(&mut bytes)
     }
 
     fn flush(&mut self) -> Result<()> {
         self.0.lock().unwrap().channel.flush()
     }
+
+    fn bytes_available(&mut self) -> Result<usize> {
+        self.0.lock().unwrap().channel.bytes_available()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{track_unix_channel_pair, AbstractChannel, Block};
+    use rand::Rng;
+    use std::thread;
+
+    // Send `nblocks` blocks from one side of the channel to the other
+    fn send_blocks(nblocks: usize) -> crate::TrackUnixChannel {
+        let (mut sender, mut receiver) = track_unix_channel_pair();
+        let handle = thread::spawn(move || {
+            let mut rng = rand::thread_rng();
+            for _ in 0..nblocks {
+                sender.write_block(&rng.gen::<Block>()).unwrap();
+            }
+            sender.flush().unwrap();
+        });
+        assert_comm_within!(receiver, 16 * nblocks, 0, {
+            for _ in 0..nblocks {
+                receiver.read_block().unwrap();
+            }
+        });
+        handle.join().unwrap();
+        receiver
+    }
+
+    #[test]
+    fn test_assert_comm_within() {
+        let receiver = send_blocks(8);
+        super::assert_comm_within(&receiver, 128, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "read 128 bytes from the channel, expected at most 127")]
+    fn test_assert_comm_within_too_tight() {
+        let receiver = send_blocks(8);
+        super::assert_comm_within(&receiver, 127, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "read 16 bytes from the channel, expected at most 0")]
+    fn test_assert_comm_within_macro_too_tight() {
+        let (mut sender, mut receiver) = track_unix_channel_pair();
+        sender.write_block(&Block::default()).unwrap();
+        sender.flush().unwrap();
+        assert_comm_within!(receiver, 0, 0, receiver.read_block().unwrap());
+    }
+
+    // Exchange blocks over two phases, returning the receiving side
+    fn exchange_in_phases() -> crate::TrackUnixChannel {
+        let (mut sender, mut receiver) = track_unix_channel_pair();
+        let handle = thread::spawn(move || {
+            for nblocks in [4, 99] {
+                for _ in 0..nblocks {
+                    sender.write_block(&Block::default()).unwrap();
+                }
+                sender.flush().unwrap();
+                sender.read_block().unwrap();
+            }
+        });
+        for (label, nblocks) in [("setup", 4), ("online", 99)] {
+            receiver.start_phase(label);
+            for _ in 0..nblocks {
+                receiver.read_block().unwrap();
+            }
+            receiver.write_block(&Block::default()).unwrap();
+            receiver.flush().unwrap();
+        }
+        handle.join().unwrap();
+        receiver
+    }
+
+    #[test]
+    fn test_report() {
+        let receiver = exchange_in_phases();
+        let report = receiver.report();
+        assert_eq!(report.bytes_read, receiver.bytes_read());
+        assert_eq!(report.bytes_written, receiver.bytes_written());
+        assert_eq!(report.total_bytes(), 16 * 105);
+        let labels: Vec<&str> = report.phases.iter().map(|p| p.label.as_str()).collect();
+        assert_eq!(labels, ["setup", "online"]);
+        assert_eq!(
+            report.phases.iter().map(|p| p.bytes_read).sum::<usize>(),
+            report.bytes_read
+        );
+        assert_eq!(report.phases[1].bytes_read, 1584);
+        assert_eq!(
+            report.to_string(),
+            "read 1.61 KB, written 32 B, total 1.64 KB\n  \
+             setup: read 64 B, written 16 B\n  \
+             online: read 1.55 KB, written 16 B"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_report_json() {
+        let report = exchange_in_phases().report();
+        let json = serde_json::to_string(&report).unwrap();
+        let decoded: super::ChannelReport = serde_json::from_str(&json).unwrap();
+        assert_eq!(decoded, report);
+    }
 }
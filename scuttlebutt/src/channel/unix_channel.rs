@@ -13,21 +13,107 @@ pub type TrackUnixChannel = TrackChannel<UnixChannel>;
 /// Convenience function to create a pair of UnixChannels for local tests in `swanky`.
 pub fn unix_channel_pair() -> (UnixChannel, UnixChannel) {
     let (tx, rx) = UnixStream::pair().unwrap();
-    let sender = SyncChannel::new(BufReader::new(tx.try_clone().unwrap()), BufWriter::new(tx));
-    let receiver = SyncChannel::new(BufReader::new(rx.try_clone().unwrap()), BufWriter::new(rx));
+    let sender = SyncChannel::with_bytes_available(
+        BufReader::new(tx.try_clone().unwrap()),
+        BufWriter::new(tx),
+    );
+    let receiver = SyncChannel::with_bytes_available(
+        BufReader::new(rx.try_clone().unwrap()),
+        BufWriter::new(rx),
+    );
     (sender, receiver)
 }
 
 /// Convenience function to create a pair of TrackUnixChannels for local tests in `swanky`.
 pub fn track_unix_channel_pair() -> (TrackUnixChannel, TrackUnixChannel) {
     let (tx, rx) = UnixStream::pair().unwrap();
-    let sender = TrackChannel::new(SyncChannel::new(
+    let sender = TrackChannel::new(SyncChannel::with_bytes_available(
         BufReader::new(tx.try_clone().unwrap()),
         BufWriter::new(tx),
     ));
-    let receiver = TrackChannel::new(SyncChannel::new(
+    let receiver = TrackChannel::new(SyncChannel::with_bytes_available(
         BufReader::new(rx.try_clone().unwrap()),
         BufWriter::new(rx),
     ));
     (sender, receiver)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AbstractChannel, Channel};
+    use std::io::ErrorKind;
+
+    #[test]
+    fn test_close_sends_eof() {
+        let (mut sender, mut receiver) = unix_channel_pair();
+        // The byte is still buffered, and must be flushed by `close`
+        sender.write_u8(42).unwrap();
+        sender.close().unwrap();
+        assert_eq!(receiver.read_u8().unwrap(), 42);
+        let err = receiver.read_u8().unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn test_close_channel_with_clone() {
+        let (tx, rx) = UnixStream::pair().unwrap();
+        let mut sender = Channel::new(BufReader::new(tx.try_clone().unwrap()), BufWriter::new(tx));
+        let mut receiver =
+            Channel::new(BufReader::new(rx.try_clone().unwrap()), BufWriter::new(rx));
+        let clone = sender.clone();
+        sender.write_u8(42).unwrap();
+        sender.close().unwrap();
+        assert_eq!(receiver.read_u8().unwrap(), 42);
+        // The clone does not keep the stream open
+        assert_eq!(
+            receiver.read_u8().unwrap_err().kind(),
+            ErrorKind::UnexpectedEof
+        );
+        drop(clone);
+    }
+
+    #[test]
+    fn test_bytes_available() {
+        let (mut sender, mut receiver) = unix_channel_pair();
+        assert_eq!(receiver.bytes_available().unwrap(), 0);
+        sender.write_bytes(&[1, 2, 3, 4, 5]).unwrap();
+        // Unflushed bytes are not available yet
+        assert_eq!(receiver.bytes_available().unwrap(), 0);
+        sender.flush().unwrap();
+        assert_eq!(receiver.bytes_available().unwrap(), 5);
+        // The bytes buffered by the reader still count
+        receiver.read_u8().unwrap();
+        assert_eq!(receiver.bytes_available().unwrap(), 4);
+        sender.write_bytes(&[6, 7]).unwrap();
+        sender.flush().unwrap();
+        assert_eq!(receiver.bytes_available().unwrap(), 6);
+        receiver.read_vec(6).unwrap();
+        assert_eq!(receiver.bytes_available().unwrap(), 0);
+    }
+
+    #[test]
+    fn test_bytes_available_tcp_channel() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let tx = std::net::TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (rx, _) = listener.accept().unwrap();
+        let mut sender = Channel::new(BufReader::new(tx.try_clone().unwrap()), BufWriter::new(tx));
+        let mut receiver =
+            Channel::with_bytes_available(BufReader::new(rx.try_clone().unwrap()), rx);
+        assert_eq!(receiver.bytes_available().unwrap(), 0);
+        sender.write_u64(42).unwrap();
+        sender.flush().unwrap();
+        // Loopback delivery is not synchronous with the write
+        while receiver.bytes_available().unwrap() < 8 {
+            std::thread::yield_now();
+        }
+        assert_eq!(receiver.bytes_available().unwrap(), 8);
+        assert_eq!(receiver.read_u64().unwrap(), 42);
+        assert_eq!(receiver.bytes_available().unwrap(), 0);
+        // A channel made with `new` cannot tell
+        assert_eq!(
+            sender.bytes_available().unwrap_err().kind(),
+            ErrorKind::Unsupported
+        );
+    }
+}
@@ -5,9 +5,16 @@
 //! receiver. It then receives `seed_` from the receiver and outputs `seed ⊕
 //! seed_`. Likewise, on input `seed`, the receiver gets `r`, sends `seed` to
 //! the sender, and then receives `seed_`, checking that `PRG(seed_) = r`.
+//!
+//! `coin_flip` is a symmetric variant in which both parties commit to a
+//! random block with a hash-based commitment (cf. `crate::commitment`) before
+//! either opens it.
 
-use crate::{AbstractChannel, AesRng, Block};
-use rand_core::{RngCore, SeedableRng};
+use crate::{
+    commitment::{self, Commitment, Opening},
+    AbstractChannel, AesRng, Block,
+};
+use rand_core::{CryptoRng, RngCore, SeedableRng};
 
 /// Errors produced by the coin tossing protocol.
 #[derive(Debug)]
@@ -88,6 +95,43 @@ pub fn receive<C: AbstractChannel>(channel: &mut C, seeds: &[Block]) -> Result<V
     Ok(out)
 }
 
+/// Jointly sample a uniformly random block with the other party, e.g. to seed
+/// the public randomness of a protocol.
+///
+/// Each party commits to a random block, then both open their commitments and
+/// output the XOR of the two blocks, so that neither party can bias the result
+/// on its own. Fails with `Error::CommitmentCheckFailed` if the other party's
+/// opening does not match its commitment, or if its commitment is a copy of
+/// ours: reflecting our commitment and then our opening would otherwise pass the
+/// check and force the result to zero.
+pub fn coin_flip<C: AbstractChannel, RNG: CryptoRng + RngCore>(
+    channel: &mut C,
+    rng: &mut RNG,
+) -> Result<Block, Error> {
+    let mut value = Block::default();
+    rng.fill_bytes(value.as_mut());
+    let (com, opening) = Commitment::commit(rng, value.as_ref());
+    channel.write_bytes(com.as_ref())?;
+    channel.flush()?;
+    let mut com_ = [0u8; 32];
+    channel.read_bytes(&mut com_)?;
+    let com_ = Commitment::from(com_);
+    if com_ == com {
+        return Err(Error::CommitmentCheckFailed);
+    }
+    channel.write_block(&value)?;
+    channel.write_block(&opening.nonce)?;
+    channel.flush()?;
+    let value_ = channel.read_block()?;
+    let opening_ = Opening {
+        nonce: channel.read_block()?,
+    };
+    if !commitment::verify(&com_, &opening_, value_.as_ref()) {
+        return Err(Error::CommitmentCheckFailed);
+    }
+    Ok(value ^ value_)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -116,4 +160,57 @@ mod tests {
         assert_eq!(output_[0], seed ^ seed_);
         handle.join().unwrap();
     }
+
+    #[test]
+    fn test_coin_flip() {
+        let (mut sender, mut receiver) = crate::unix_channel_pair();
+        let handle =
+            std::thread::spawn(move || coin_flip(&mut sender, &mut AesRng::new()).unwrap());
+        let output_ = coin_flip(&mut receiver, &mut AesRng::new()).unwrap();
+        assert_eq!(handle.join().unwrap(), output_);
+    }
+
+    #[test]
+    fn test_coin_flip_bad_opening() {
+        let (mut sender, mut receiver) = crate::unix_channel_pair();
+        let handle = std::thread::spawn(move || {
+            // Commit to one block, then open another one
+            let value = rand::random::<Block>();
+            let (com, opening) = Commitment::commit(&mut AesRng::new(), value.as_ref());
+            sender.write_bytes(com.as_ref()).unwrap();
+            sender.flush().unwrap();
+            let mut com = [0u8; 32];
+            sender.read_bytes(&mut com).unwrap();
+            sender.write_block(&(value ^ Block::from(1))).unwrap();
+            sender.write_block(&opening.nonce).unwrap();
+            sender.flush().unwrap();
+            sender.read_blocks(2).unwrap();
+        });
+        let result = coin_flip(&mut receiver, &mut AesRng::new());
+        assert!(matches!(result, Err(Error::CommitmentCheckFailed)));
+        handle.join().unwrap();
+    }
+
+    #[test]
+    fn test_coin_flip_reflected() {
+        let (mut sender, mut receiver) = crate::unix_channel_pair();
+        let handle = std::thread::spawn(move || {
+            // Send back the other party's commitment, then its opening, which
+            // would make the result zero
+            let mut com = [0u8; 32];
+            sender.read_bytes(&mut com)?;
+            sender.write_bytes(&com)?;
+            sender.flush()?;
+            for block in sender.read_blocks(2)? {
+                sender.write_block(&block)?;
+            }
+            sender.flush()
+        });
+        let result = coin_flip(&mut receiver, &mut AesRng::new());
+        assert!(matches!(result, Err(Error::CommitmentCheckFailed)));
+        drop(receiver);
+        // The protocol stops before the opening, which the reflecting party
+        // never gets
+        assert!(handle.join().unwrap().is_err());
+    }
 }
@@ -0,0 +1,94 @@
+//! A hash-based commitment scheme.
+//!
+//! To commit to `msg`, the committer samples a random `nonce` and publishes
+//! `SHA-256(nonce || msg)`. Opening the commitment consists of revealing `nonce`
+//! (and `msg`), so that the other party can recompute the hash. The scheme is
+//! hiding since the nonce is uniform and unknown to the other party, and binding
+//! by the collision resistance of SHA-256.
+
+use crate::Block;
+use rand_core::{CryptoRng, RngCore};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+/// A commitment to a message.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Commitment([u8; 32]);
+
+/// The randomness needed to open a `Commitment`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Opening {
+    /// The nonce the message was committed with.
+    pub nonce: Block,
+}
+
+impl Commitment {
+    /// Commit to `msg`, returning the commitment along with its opening.
+    pub fn commit<RNG: CryptoRng + RngCore>(rng: &mut RNG, msg: &[u8]) -> (Commitment, Opening) {
+        let mut nonce = Block::default();
+        rng.fill_bytes(nonce.as_mut());
+        (Self::hash(&nonce, msg), Opening { nonce })
+    }
+
+    fn hash(nonce: &Block, msg: &[u8]) -> Commitment {
+        let mut hasher = Sha256::new();
+        hasher.update(nonce.as_ref());
+        hasher.update(msg);
+        Commitment(hasher.finalize().into())
+    }
+}
+
+impl AsRef<[u8]> for Commitment {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<[u8; 32]> for Commitment {
+    fn from(bytes: [u8; 32]) -> Self {
+        Commitment(bytes)
+    }
+}
+
+/// Check that `opening` opens `commitment` to `msg`.
+pub fn verify(commitment: &Commitment, opening: &Opening, msg: &[u8]) -> bool {
+    Commitment::hash(&opening.nonce, msg)
+        .0
+        .ct_eq(&commitment.0)
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AesRng;
+
+    #[test]
+    fn test_valid_opening() {
+        let mut rng = AesRng::new();
+        let (commitment, opening) = Commitment::commit(&mut rng, b"hello");
+        assert!(verify(&commitment, &opening, b"hello"));
+        // The commitment can be sent over and rebuilt from its bytes
+        let mut bytes = [0u8; 32];
+        bytes.copy_from_slice(commitment.as_ref());
+        assert!(verify(&Commitment::from(bytes), &opening, b"hello"));
+    }
+
+    #[test]
+    fn test_wrong_message() {
+        let mut rng = AesRng::new();
+        let (commitment, opening) = Commitment::commit(&mut rng, b"hello");
+        assert!(!verify(&commitment, &opening, b"hellp"));
+        assert!(!verify(&commitment, &opening, b""));
+    }
+
+    #[test]
+    fn test_wrong_nonce() {
+        let mut rng = AesRng::new();
+        let (commitment, opening) = Commitment::commit(&mut rng, b"hello");
+        let opening = Opening {
+            nonce: opening.nonce ^ Block::from(1u128),
+        };
+        assert!(!verify(&commitment, &opening, b"hello"));
+    }
+}
@@ -7,11 +7,13 @@ use vectoreyes::{
 };
 
 use crate::Block;
+use std::collections::HashMap;
 
 /// AES-based correlation-robust hash function.
 ///
 /// This hash function supports the correlation-robust variants given in
 /// <https://eprint.iacr.org/2019/074>.
+#[derive(Clone)]
 pub struct AesHash {
     aes: Aes128EncryptOnly,
 }
@@ -29,6 +31,13 @@ impl AesHash {
         AesHash { aes }
     }
 
+    /// Initialize one hash function per key of `keys`, running the AES key
+    /// schedule only once for keys which are repeated (cf. `AesHashCache`).
+    pub fn new_many(keys: &[Block]) -> Vec<Self> {
+        let mut cache = AesHashCache::new(keys.len());
+        keys.iter().map(|key| cache.get(*key)).collect()
+    }
+
     /// Correlation-robust hash function for 128-bit inputs (cf.
     /// <https://eprint.iacr.org/2019/074>, §7.2).
     ///
@@ -67,3 +76,89 @@ impl AesHash {
         )
     }
 }
+
+/// A cache of AES key schedules, to create `AesHash` instances for repeated
+/// keys without running the key schedule again.
+///
+/// At most `capacity` key schedules are kept: once the cache is full, the hash
+/// functions of new keys are still created, but their key schedules are not
+/// cached.
+pub struct AesHashCache {
+    schedules: HashMap<u128, Aes128EncryptOnly>,
+    capacity: usize,
+}
+
+impl AesHashCache {
+    /// Create an empty cache holding at most `capacity` key schedules.
+    pub fn new(capacity: usize) -> Self {
+        AesHashCache {
+            schedules: HashMap::new(),
+            capacity,
+        }
+    }
+
+    /// Get the hash function with key `key`, from the cache if its key schedule
+    /// was already computed.
+    pub fn get(&mut self, key: Block) -> AesHash {
+        let id = u128::from(key);
+        if let Some(aes) = self.schedules.get(&id) {
+            return AesHash { aes: aes.clone() };
+        }
+        let hash = AesHash::new(key);
+        if self.schedules.len() < self.capacity {
+            self.schedules.insert(id, hash.aes.clone());
+        }
+        hash
+    }
+
+    /// The number of key schedules in the cache.
+    pub fn len(&self) -> usize {
+        self.schedules.len()
+    }
+
+    /// Whether the cache holds no key schedule.
+    pub fn is_empty(&self) -> bool {
+        self.schedules.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AesRng;
+    use rand::Rng;
+
+    #[test]
+    fn new_many_matches_new() {
+        let mut rng = AesRng::new();
+        let distinct: Vec<Block> = (0..16).map(|_| rng.gen()).collect();
+        // Repeat some of the keys
+        let keys: Vec<Block> = (0..64).map(|i| distinct[(i * 7) % 16]).collect();
+        let hashes = AesHash::new_many(&keys);
+        assert_eq!(hashes.len(), keys.len());
+        for (key, hash) in keys.iter().zip(hashes.iter()) {
+            let expected = AesHash::new(*key);
+            for _ in 0..8 {
+                let (i, x) = (rng.gen::<Block>(), rng.gen::<Block>());
+                assert_eq!(hash.cr_hash(i, x), expected.cr_hash(i, x));
+                assert_eq!(hash.tccr_hash(i, x), expected.tccr_hash(i, x));
+            }
+        }
+    }
+
+    #[test]
+    fn cache_capacity() {
+        let mut rng = AesRng::new();
+        let mut cache = AesHashCache::new(2);
+        let keys: Vec<Block> = (0..4).map(|_| rng.gen()).collect();
+        for key in keys.iter().chain(keys.iter()) {
+            let x = rng.gen::<Block>();
+            assert_eq!(
+                cache.get(*key).cr_hash(Block::default(), x),
+                AesHash::new(*key).cr_hash(Block::default(), x)
+            );
+        }
+        assert_eq!(cache.len(), 2);
+        assert!(AesHashCache::new(0).is_empty());
+    }
+}
@@ -6,13 +6,19 @@
 //! Scuttlebutt provides many utility functions for cryptographic applications.
 
 /// This is a legacy alias for `U8x16`
+///
+/// Use `subtle::ConstantTimeEq::ct_eq` rather than `==` to compare secret
+/// values, e.g. MACs or commitment openings, in constant time.
 pub type Block = vectoreyes::U8x16;
 
 mod block512;
+mod bloomfilter;
 /// Module for encapsulating communication channels for `swanky`.
 pub mod channel;
 pub mod cointoss;
+pub mod commitment;
 mod hash_aes;
+mod prf;
 pub use swanky_serialization as serialization;
 pub mod utils;
 
@@ -33,8 +39,14 @@ pub mod ring {
 
 pub use crate::{
     block512::Block512,
-    channel::{AbstractChannel, Channel, HashChannel, SymChannel, SyncChannel, TrackChannel},
-    hash_aes::{AesHash, AES_HASH},
+    bloomfilter::{BloomFilter, BloomHashKind, ScalableBloomFilter},
+    channel::{
+        AbstractChannel, BudgetChannel, BytesAvailable, Channel, ChannelReport, CloseChannel,
+        HashChannel, MuxChannel, PhaseReport, RecordChannel, ReplayChannel, ShutdownWrite,
+        SubChannel, SymChannel, SyncChannel, TrackChannel, Transcript,
+    },
+    hash_aes::{AesHash, AesHashCache, AES_HASH},
+    prf::{AesPrf, BlockPrf},
 };
 pub use swanky_aes_rng::{AesRng, UniformIntegersUnderBound};
 
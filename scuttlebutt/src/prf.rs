@@ -0,0 +1,94 @@
+//! Keyed pseudorandom functions on `Block`s.
+
+use vectoreyes::{Aes128EncryptOnly, AesBlockCipher};
+
+use crate::Block;
+
+/// The number of blocks encrypted at once by `AesPrf::eval_many`.
+const AES_PRF_CHUNK_SIZE: usize = 8;
+
+/// A keyed pseudorandom function mapping `Block`s to `Block`s.
+pub trait BlockPrf {
+    /// Evaluate the PRF on `x`.
+    fn eval(&self, x: Block) -> Block;
+
+    /// Evaluate the PRF on each of `xs`, writing the results to `out`.
+    ///
+    /// Implementations may override this to evaluate many blocks at once.
+    fn eval_many(&self, xs: &[Block], out: &mut [Block]) {
+        assert_eq!(xs.len(), out.len());
+        for (x, y) in xs.iter().zip(out.iter_mut()) {
+            *y = self.eval(*x);
+        }
+    }
+}
+
+/// AES-based PRF, computing `AES_k(x)`.
+#[derive(Clone)]
+pub struct AesPrf {
+    aes: Aes128EncryptOnly,
+}
+
+impl AesPrf {
+    /// Initialize the PRF using `key`.
+    #[inline]
+    pub fn new(key: Block) -> Self {
+        let aes = Aes128EncryptOnly::new_with_key(key);
+        AesPrf { aes }
+    }
+}
+
+impl BlockPrf for AesPrf {
+    #[inline]
+    fn eval(&self, x: Block) -> Block {
+        self.aes.encrypt(x)
+    }
+
+    fn eval_many(&self, xs: &[Block], out: &mut [Block]) {
+        assert_eq!(xs.len(), out.len());
+        let xs_chunks = xs.chunks_exact(AES_PRF_CHUNK_SIZE);
+        let xs_remainder = xs_chunks.remainder();
+        let mut out_chunks = out.chunks_exact_mut(AES_PRF_CHUNK_SIZE);
+        for (x, y) in xs_chunks.zip(&mut out_chunks) {
+            let x: [Block; AES_PRF_CHUNK_SIZE] = x
+                .try_into()
+                .expect("Chunks ought to be the size we've specified.");
+            y.copy_from_slice(&self.aes.encrypt_many(x));
+        }
+        for (x, y) in xs_remainder
+            .iter()
+            .zip(out_chunks.into_remainder().iter_mut())
+        {
+            *y = self.aes.encrypt(*x);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_aes_prf_deterministic() {
+        let key = rand::random::<Block>();
+        let xs = (0..21)
+            .map(|_| rand::random::<Block>())
+            .collect::<Vec<Block>>();
+        let prf = AesPrf::new(key);
+        let prf_ = AesPrf::new(key);
+        let mut ys = vec![Block::default(); xs.len()];
+        prf.eval_many(&xs, &mut ys);
+        for (x, y) in xs.iter().zip(ys.iter()) {
+            assert_eq!(prf.eval(*x), *y);
+            assert_eq!(prf_.eval(*x), *y);
+        }
+    }
+
+    #[test]
+    fn test_aes_prf_keys_differ() {
+        let key = rand::random::<Block>();
+        let key_ = rand::random::<Block>();
+        let x = rand::random::<Block>();
+        assert_ne!(AesPrf::new(key).eval(x), AesPrf::new(key_).eval(x));
+    }
+}
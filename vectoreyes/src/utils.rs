@@ -15,6 +15,38 @@ impl From<U8x16> for u128 {
 }
 
 impl U8x16 {
+    /// Create a vector from the little-endian bytes of a 128-bit integer.
+    ///
+    /// The integer is the one given by `u128::from` on the vector, so
+    /// `U8x16::from_le_bytes(x.to_le_bytes()) == U8x16::from(x)` for any `x: u128`.
+    /// Unlike the raw layout given by `as_ref`, this doesn't depend on the
+    /// endianness of the machine.
+    #[inline(always)]
+    pub fn from_le_bytes(bytes: [u8; 16]) -> Self {
+        Self::from(u128::from_le_bytes(bytes))
+    }
+
+    /// Create a vector from the big-endian bytes of a 128-bit integer.
+    ///
+    /// This is the reverse byte order of `from_le_bytes`, e.g. for values coming
+    /// from a network protocol.
+    #[inline(always)]
+    pub fn from_be_bytes(bytes: [u8; 16]) -> Self {
+        Self::from(u128::from_be_bytes(bytes))
+    }
+
+    /// Return the little-endian bytes of the 128-bit integer given by `u128::from`.
+    #[inline(always)]
+    pub fn to_le_bytes(self) -> [u8; 16] {
+        u128::from(self).to_le_bytes()
+    }
+
+    /// Return the big-endian bytes of the 128-bit integer given by `u128::from`.
+    #[inline(always)]
+    pub fn to_be_bytes(self) -> [u8; 16] {
+        u128::from(self).to_be_bytes()
+    }
+
     /// Perform a (full) 128-bit wide carryless multiply
     ///
     /// The result of the 128-bit wide carryless multiply is 256-bits. This is returned as
@@ -90,3 +122,23 @@ fn test_carryless_mul_wide() {
         ]
     );
 }
+
+#[test]
+fn test_bytes_roundtrip() {
+    let bytes: [u8; 16] = std::array::from_fn(|i| i as u8 + 1);
+    assert_eq!(U8x16::from_le_bytes(bytes).to_le_bytes(), bytes);
+    assert_eq!(U8x16::from_be_bytes(bytes).to_be_bytes(), bytes);
+    let x = 0x0102_0304_0506_0708_090a_0b0c_0d0e_0f10_u128;
+    assert_eq!(U8x16::from_le_bytes(x.to_le_bytes()), U8x16::from(x));
+    assert_eq!(U8x16::from_be_bytes(x.to_be_bytes()), U8x16::from(x));
+    assert_eq!(U8x16::from(x).to_be_bytes(), x.to_be_bytes());
+}
+
+#[test]
+fn test_bytes_orderings_differ() {
+    let bytes: [u8; 16] = std::array::from_fn(|i| i as u8 + 1);
+    assert_ne!(U8x16::from_le_bytes(bytes), U8x16::from_be_bytes(bytes));
+    let mut reversed = bytes;
+    reversed.reverse();
+    assert_eq!(U8x16::from_le_bytes(bytes), U8x16::from_be_bytes(reversed));
+}